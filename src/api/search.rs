@@ -1,42 +1,200 @@
 use crate::config::Config;
 #[cfg(feature = "embedding")]
-use crate::embedding::Embedder;
-use crate::error::Result;
-use crate::normalize::Package;
+use crate::config::{EmbeddingTextMode, ModelType};
+#[cfg(feature = "embedding")]
+use crate::embedding::{Embedder, ModelCache};
+use crate::error::{Result, RpmSearchError};
+use crate::installed::{InstalledPackage, Upgrade};
+use crate::normalize::{canonical_arch, expand_arch_aliases, AppstreamInfo, Package};
+use crate::repomd::appstream::AppstreamParser;
 use crate::repomd::fetch::RepoFetcher;
 use crate::repomd::filelists_parser::FilelistsXmlParser;
-use crate::repomd::model::RpmPackage;
+use crate::repomd::model::{RpmFileEntry, RpmFileType, RpmPackage};
 use crate::repomd::parser::PrimaryXmlParser;
+use crate::repomd::rpm_header::RpmHeaderParser;
+use crate::repomd::writer::{write_repomd, ChecksumWriter, PrimaryXmlWriter, RepomdRecord};
+use crate::resolve::{DependencyResolver, Resolution};
 #[cfg(feature = "embedding")]
 use crate::search::{
-    QueryPlanner, SearchFilters, SearchQuery, SearchResult, SemanticSearch, StructuredSearch,
+    LazyEmbedder, QueryPlanner, SearchFilters, SearchQuery, SearchResult, SemanticSearch,
+    StructuredSearch,
 };
-use crate::storage::FindFilter;
-use crate::storage::PackageStore;
-#[cfg(feature = "embedding")]
-use crate::storage::VectorStore;
+use crate::storage::{finish_savepoint, PackageStore};
 #[cfg(feature = "embedding")]
-use rusqlite::Connection;
+use crate::storage::{vector_tables, VectorStore, DEFAULT_VECTOR_TABLE};
+use crate::storage::{DuplicateProvide, DuplicateVersions, FileConflict, RepoDiff};
+use crate::storage::{FindFilter, SortField};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
 use std::path::Path;
+#[cfg(feature = "embedding")]
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{debug, info, instrument, warn};
 
+/// Packages inserted per transaction when streaming a full repository index
+pub const INDEX_BATCH_SIZE: usize = 1000;
+
 pub struct RpmSearchApi {
     #[cfg_attr(not(feature = "embedding"), allow(dead_code))]
     config: Config,
     package_store: PackageStore,
+    /// Models loaded by searches (see [`RpmSearchApi::with_model_cache`])
+    #[cfg(feature = "embedding")]
+    models: Arc<ModelCache>,
+}
+
+/// Database health report (see [`RpmSearchApi::diagnostics`])
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostics {
+    pub db_path: String,
+    pub schema_version: i32,
+    /// Version of the loaded sqlite-vec extension (None if not loaded)
+    pub sqlite_vec_version: Option<String>,
+    pub total_packages: usize,
+    pub embedded_packages: usize,
+    /// Default embedding model recorded in the DB
+    pub embedding_model: Option<String>,
+    /// Package fields the embeddings were built from (e.g. "full")
+    pub embedding_text_mode: Option<String>,
+    pub repos: Vec<RepoDiagnostics>,
+}
+
+/// Per-repository part of [`Diagnostics`]
+#[derive(Debug, Clone, Serialize)]
+pub struct RepoDiagnostics {
+    pub name: String,
+    pub packages: usize,
+    pub embedded_packages: usize,
+    pub has_filelists: bool,
+    /// Model the repository's embeddings use (its override or the DB default)
+    pub embedding_model: Option<String>,
+}
+
+/// Embedding coverage of one repository (see [`RpmSearchApi::embedding_coverage`])
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbeddingCoverage {
+    pub repo: String,
+    pub packages: usize,
+    pub embedded_packages: usize,
+}
+
+impl EmbeddingCoverage {
+    /// Percentage of packages with an embedding (100 for an empty repository)
+    pub fn percent(&self) -> f64 {
+        if self.packages == 0 {
+            100.0
+        } else {
+            self.embedded_packages as f64 * 100.0 / self.packages as f64
+        }
+    }
+}
+
+/// One line of a repository snapshot (see [`RpmSearchApi::export_repository`])
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageSnapshot {
+    #[serde(flatten)]
+    pub package: Package,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<RpmFileEntry>,
+    /// Model the embedding was built with (as stored in the DB, e.g. "minilm")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
+}
+
+/// A package owning a searched file (see [`RpmSearchApi::search_file`])
+#[derive(Debug, Clone, Serialize)]
+pub struct FileMatch {
+    #[serde(flatten)]
+    pub package: Package,
+    /// Every matching file of the package, in the order they were found
+    pub files: Vec<MatchedFile>,
+}
+
+/// A file of a [`FileMatch`]
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchedFile {
+    pub path: String,
+    /// `file`, `dir` or `ghost`
+    pub file_type: String,
+}
+
+/// How package text is built for embedding, recorded with the embeddings so
+/// later builds can match them
+#[cfg(feature = "embedding")]
+#[derive(Debug, Clone)]
+struct EmbeddingText {
+    mode: EmbeddingTextMode,
+    boilerplate: Vec<String>,
+}
+
+/// Changes applied by an incremental update (see [`RpmSearchApi::update_repository`])
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UpdateStats {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    /// Unchanged packages whose dependencies were rewritten after a schema upgrade
+    pub refreshed: usize,
+    /// Reading, decompressing and parsing primary.xml
+    pub parse_time: Duration,
+    /// Comparing against the stored packages and writing the changes
+    pub insert_time: Duration,
+}
+
+/// Pending changes for an incremental repository update
+struct RepoChanges {
+    inserts: Vec<Package>,
+    updates: Vec<(i64, Package)>,
+    deletes: Vec<(String, String, String)>,
+    /// Unchanged packages to rewrite dependencies of, when the repository has
+    /// a pending dependency refresh
+    refreshes: Option<Vec<(i64, Package)>>,
 }
 
 impl RpmSearchApi {
     /// Create a new API instance
     pub fn new(config: Config) -> Result<Self> {
-        let package_store = PackageStore::new(&config.db_path)?;
+        let package_store = PackageStore::open(&config.db_path, &config.sqlite)?
+            .with_slow_query_ms(config.slow_query_ms);
+        Ok(Self {
+            config,
+            package_store,
+            #[cfg(feature = "embedding")]
+            models: Arc::default(),
+        })
+    }
+
+    /// Share loaded search models with other API instances, so a model is
+    /// loaded once however many connections serve searches
+    #[cfg(feature = "embedding")]
+    pub fn with_model_cache(mut self, models: Arc<ModelCache>) -> Self {
+        self.models = models;
+        self
+    }
+
+    /// Open an existing database read-only (see [`PackageStore::open_read_only`]),
+    /// for previews that must not write
+    pub fn open_read_only(config: Config) -> Result<Self> {
+        let package_store =
+            PackageStore::open_read_only(&config.db_path)?.with_slow_query_ms(config.slow_query_ms);
         Ok(Self {
             config,
             package_store,
+            #[cfg(feature = "embedding")]
+            models: Arc::default(),
         })
     }
 
     /// Index a repository from primary.xml file
+    ///
+    /// A full index streams the file and inserts packages in batches of
+    /// [`INDEX_BATCH_SIZE`], so memory stays flat for very large repositories.
+    /// The whole import is one transaction: a parse error part way through
+    /// stores nothing. `update` needs the complete package list to find
+    /// removals and loads it up front.
     #[instrument(skip(self, primary_xml_path), fields(path = %primary_xml_path.as_ref().display(), repo = %repo_name, update))]
     pub fn index_repository<P: AsRef<Path>>(
         &mut self,
@@ -44,51 +202,234 @@ impl RpmSearchApi {
         repo_name: &str,
         update: bool,
     ) -> Result<usize> {
-        debug!("Fetching local file");
-        // Fetch and parse primary.xml
-        let data = RepoFetcher::fetch_local(&primary_xml_path)?;
+        if update {
+            let stats = self.update_repository(&primary_xml_path, repo_name)?;
+            return Ok(stats.added + stats.updated);
+        }
 
-        debug!("Decompressing data");
-        // Auto-detect and decompress (supports .gz, .zst)
-        let xml_data = RepoFetcher::auto_decompress(&primary_xml_path, &data)?;
+        let reader = RepoFetcher::open_local(&primary_xml_path)?;
+        let config = &self.config;
+        let count = self.package_store.in_transaction(|store| {
+            let mut batch: Vec<Package> = Vec::with_capacity(INDEX_BATCH_SIZE);
+            let count = PrimaryXmlParser::parse_streaming(reader, |rpm_pkg| {
+                batch.push(Self::to_package(config, rpm_pkg, repo_name));
+                if batch.len() >= INDEX_BATCH_SIZE {
+                    store.insert_packages_batch(&batch)?;
+                    debug!(inserted = batch.len(), "Stored package batch");
+                    batch.clear();
+                }
+                Ok(())
+            })?;
+            store.insert_packages_batch(&batch)?;
+            Ok(count)
+        })?;
 
-        debug!("Parsing XML");
-        let rpm_packages = PrimaryXmlParser::parse(&xml_data[..])?;
+        info!(package_count = count, "Stored packages in database");
 
-        info!(
-            package_count = rpm_packages.len(),
-            update, "Parsed RPM packages"
-        );
+        Ok(count)
+    }
 
-        if update {
-            self.update_repository_packages(rpm_packages, repo_name)
-        } else {
-            // Convert all packages first, then batch insert
-            let packages: Vec<Package> = rpm_packages
+    /// Incrementally update a repository from primary.xml, reporting the
+    /// changes applied and how long parsing and storing took
+    #[instrument(skip(self, primary_xml_path), fields(path = %primary_xml_path.as_ref().display(), repo = %repo_name))]
+    pub fn update_repository<P: AsRef<Path>>(
+        &mut self,
+        primary_xml_path: P,
+        repo_name: &str,
+    ) -> Result<UpdateStats> {
+        let started = Instant::now();
+        let rpm_packages = Self::load_primary(&primary_xml_path)?;
+        let parse_time = started.elapsed();
+        info!(package_count = rpm_packages.len(), "Parsed RPM packages");
+
+        let started = Instant::now();
+        let mut stats = self.update_repository_packages(rpm_packages, repo_name)?;
+        stats.parse_time = parse_time;
+        stats.insert_time = started.elapsed();
+        Ok(stats)
+    }
+
+    /// Index a directory of `.rpm` files (searched recursively) by reading each
+    /// package header, for packages that have no repodata. Source RPMs and
+    /// unreadable files are skipped. Returns (packages, file entries) indexed.
+    #[instrument(skip(self, dir), fields(dir = %dir.as_ref().display(), repo = %repo_name))]
+    pub fn index_rpm_directory<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        repo_name: &str,
+    ) -> Result<(usize, usize)> {
+        let dir = dir.as_ref();
+        let mut rpm_paths = Vec::new();
+        collect_rpm_files(dir, &mut rpm_paths)?;
+        rpm_paths.sort();
+
+        let mut packages = Vec::with_capacity(rpm_paths.len());
+        let mut file_lists = Vec::with_capacity(rpm_paths.len());
+        for path in &rpm_paths {
+            if path.to_string_lossy().ends_with(".src.rpm") {
+                debug!(path = %path.display(), "Skipping source RPM");
+                continue;
+            }
+            let parsed = match std::fs::File::open(path)
+                .map_err(RpmSearchError::from)
+                .and_then(|f| RpmHeaderParser::parse(std::io::BufReader::new(f)))
+            {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    warn!(path = %path.display(), error = %e, "Skipping unreadable RPM");
+                    continue;
+                }
+            };
+            let mut rpm_pkg = parsed.package;
+            // Relative to the directory, like a createrepo location
+            let href = path.strip_prefix(dir).unwrap_or(path);
+            rpm_pkg.location_href = Some(href.to_string_lossy().into_owned());
+            packages.push(Self::to_package(&self.config, rpm_pkg, repo_name));
+            file_lists.push(parsed.files);
+        }
+        info!(package_count = packages.len(), "Read RPM headers");
+
+        // Packages and their file lists go in together, so a failure leaves neither
+        let total_files = self.package_store.in_transaction(|store| {
+            let pkg_ids = store.insert_packages_batch(&packages)?;
+            let entries: Vec<(i64, Vec<(String, i32)>)> = pkg_ids
                 .into_iter()
-                .map(|rpm_pkg| Package::from_rpm_package(rpm_pkg, repo_name.to_string()))
+                .zip(file_lists)
+                .map(|(id, files)| {
+                    let files = files
+                        .into_iter()
+                        .map(|f| (f.path, f.file_type.as_i32()))
+                        .collect();
+                    (id, files)
+                })
                 .collect();
+            let mut total_files = 0;
+            for chunk in entries.chunks(500) {
+                total_files += store.insert_filelists_batch(chunk)?;
+            }
+            Ok(total_files)
+        })?;
 
-            let count = packages.len();
-            self.package_store.insert_packages_batch(&packages)?;
+        Ok((packages.len(), total_files))
+    }
 
-            debug!(inserted = count, "Stored packages in database");
+    /// Compute the changes an incremental update would apply, without writing.
+    /// Returns (added, updated, removed) package counts.
+    #[instrument(skip(self, primary_xml_path), fields(path = %primary_xml_path.as_ref().display(), repo = %repo_name))]
+    pub fn preview_repository_update<P: AsRef<Path>>(
+        &self,
+        primary_xml_path: P,
+        repo_name: &str,
+    ) -> Result<(usize, usize, usize)> {
+        let rpm_packages = Self::load_primary(&primary_xml_path)?;
+        let changes = self.classify_repository_changes(rpm_packages, repo_name)?;
 
-            Ok(count)
+        info!(
+            added = changes.inserts.len(),
+            updated = changes.updates.len(),
+            removed = changes.deletes.len(),
+            "Computed incremental update preview (dry run)"
+        );
+
+        Ok((
+            changes.inserts.len(),
+            changes.updates.len(),
+            changes.deletes.len(),
+        ))
+    }
+
+    /// Normalize a parsed package, storing it under the canonical arch name
+    /// when `normalize_arch` is set
+    fn to_package(config: &Config, rpm_pkg: RpmPackage, repo_name: &str) -> Package {
+        let mut package = Package::from_rpm_package(rpm_pkg, repo_name.to_string());
+        let arch = Self::stored_arch(config, &package.arch);
+        if arch != package.arch {
+            package.arch = arch.to_string();
+        }
+        package
+    }
+
+    /// Arch name as stored in the database (see `Config::normalize_arch`)
+    fn stored_arch<'s>(config: &Config, arch: &'s str) -> &'s str {
+        if config.normalize_arch {
+            canonical_arch(arch)
+        } else {
+            arch
+        }
+    }
+
+    /// Expand an arch filter with equivalent arch names (see `Config::arch_alias`)
+    fn expand_arches(&self, arches: Vec<String>) -> Vec<String> {
+        if self.config.arch_alias {
+            expand_arch_aliases(&arches)
+        } else {
+            arches
         }
     }
 
+    /// Read, decompress (.gz, .zst), and parse a primary.xml file
+    fn load_primary<P: AsRef<Path>>(primary_xml_path: P) -> Result<Vec<RpmPackage>> {
+        debug!("Parsing XML");
+        PrimaryXmlParser::parse(RepoFetcher::open_local(&primary_xml_path)?)
+    }
+
     /// Update repository with incremental changes (single transaction)
     #[instrument(skip(self, rpm_packages), fields(repo = %repo_name, package_count = rpm_packages.len()))]
     fn update_repository_packages(
         &mut self,
         rpm_packages: Vec<RpmPackage>,
         repo_name: &str,
-    ) -> Result<usize> {
-        use std::collections::{HashMap, HashSet};
-
+    ) -> Result<UpdateStats> {
         info!("Starting incremental update");
 
+        let RepoChanges {
+            inserts,
+            updates,
+            deletes,
+            refreshes,
+        } = self.classify_repository_changes(rpm_packages, repo_name)?;
+
+        let added = inserts.len();
+        let updated = updates.len();
+        let removed = deletes.len();
+        let refreshed = refreshes.as_ref().map_or(0, Vec::len);
+
+        // Execute all changes in a single transaction
+        self.package_store.in_transaction(|store| {
+            store.batch_incremental_update(&inserts, &updates, &deletes)?;
+            match refreshes {
+                Some(refreshes) => store.refresh_dependencies(repo_name, &refreshes),
+                None => Ok(()),
+            }
+        })?;
+
+        info!(
+            added,
+            updated,
+            removed,
+            refreshed,
+            total = added + updated,
+            "Incremental update completed"
+        );
+
+        Ok(UpdateStats {
+            added,
+            updated,
+            removed,
+            refreshed,
+            ..Default::default()
+        })
+    }
+
+    /// Classify parsed packages against the stored repository into
+    /// inserts, updates, and deletes
+    fn classify_repository_changes(
+        &self,
+        rpm_packages: Vec<RpmPackage>,
+        repo_name: &str,
+    ) -> Result<RepoChanges> {
+        use std::collections::{HashMap, HashSet};
+
         // Get existing packages in the repository
         let existing = self.package_store.get_packages_in_repo(repo_name)?;
         let mut existing_map: HashMap<(String, String), (String, String, String)> = HashMap::new();
@@ -105,10 +446,16 @@ impl RpmSearchApi {
         let mut new_package_set = HashSet::new();
         let mut inserts: Vec<Package> = Vec::new();
         let mut updates: Vec<(i64, Package)> = Vec::new();
+        let mut refreshes = if self.package_store.dependency_refresh_pending(repo_name)? {
+            info!(repo = %repo_name, "Refreshing dependencies stored by an older version");
+            Some(Vec::new())
+        } else {
+            None
+        };
 
         // Classify packages: insert vs update vs skip
         for rpm_pkg in rpm_packages {
-            let package = Package::from_rpm_package(rpm_pkg.clone(), repo_name.to_string());
+            let package = Self::to_package(&self.config, rpm_pkg.clone(), repo_name);
             let key = (package.name.clone(), package.arch.clone());
 
             new_package_set.insert(key.clone());
@@ -133,6 +480,12 @@ impl RpmSearchApi {
                         );
                         updates.push((old_pkg.pkg_id.unwrap(), package));
                     }
+                } else if let Some(ref mut refreshes) = refreshes {
+                    if let Some(pkg_id) =
+                        self.package_store.find_pkg_id(&key.0, &key.1, repo_name)?
+                    {
+                        refreshes.push((pkg_id, package));
+                    }
                 }
             } else {
                 debug!(package = %package.name, arch = %package.arch, "Adding new package");
@@ -150,23 +503,72 @@ impl RpmSearchApi {
             })
             .collect();
 
-        let added = inserts.len();
-        let updated = updates.len();
-        let removed = deletes.len();
+        Ok(RepoChanges {
+            inserts,
+            updates,
+            deletes,
+            refreshes,
+        })
+    }
 
-        // Execute all changes in a single transaction
-        self.package_store
-            .batch_incremental_update(&inserts, &updates, &deletes)?;
+    /// Rebuild derived structures after an upgrade or an external import:
+    /// SQLite indexes, the trigram name index and, with `rebuild_embeddings`,
+    /// all embeddings of the default table. Embeddings are rebuilt with the
+    /// model, revision and text mode recorded in the database (like search
+    /// uses), falling back to the config when none is recorded. Returns the
+    /// number of embeddings built.
+    pub fn reindex(&mut self, rebuild_embeddings: bool) -> Result<usize> {
+        self.package_store.reindex()?;
+        info!("Rebuilt SQLite indexes");
+        if !rebuild_embeddings {
+            return Ok(0);
+        }
 
-        info!(
-            added,
-            updated,
-            removed,
-            total = added + updated,
-            "Incremental update completed"
-        );
+        #[cfg(feature = "embedding")]
+        {
+            let vector_store = self.vector_store()?;
+            let recorded = vector_store
+                .get_embedding_model_type()?
+                .as_deref()
+                .and_then(ModelType::from_db_str);
+            let (config, text) = match recorded {
+                Some(model_type) => {
+                    let mut config = self.config.clone();
+                    if config.model_type != model_type {
+                        config = config.with_model_type(model_type);
+                    }
+                    config.model_revision = vector_store.get_embedding_model_revision()?;
+                    (config, Self::stored_text(&vector_store)?)
+                }
+                None => (self.config.clone(), self.configured_text()),
+            };
+            info!(
+                model = %config.model_type,
+                revision = config.model_revision.as_deref().unwrap_or("default branch"),
+                text_mode = %text.mode,
+                "Rebuilding embeddings"
+            );
+            let embedder = Embedder::for_config(&config, &config.model_type)?;
+            self.build_embeddings_with_text(&embedder, false, true, &text)
+        }
+        #[cfg(not(feature = "embedding"))]
+        Err(RpmSearchError::Config(
+            "Rebuilding embeddings requires the embedding feature".to_string(),
+        ))
+    }
 
-        Ok(added + updated)
+    /// Forget an interrupted embedding rebuild of `repo` (of the default table
+    /// with `None`), so the next rebuild starts over instead of resuming
+    #[cfg(feature = "embedding")]
+    pub fn discard_pending_rebuild(&self, repo: Option<&str>) -> Result<()> {
+        let vector_store = self.vector_store()?;
+        if vector_store.pending_rebuild(repo)?.is_some() {
+            info!(
+                repo = repo.unwrap_or("all"),
+                "Discarding interrupted embedding rebuild"
+            );
+        }
+        vector_store.clear_rebuild_pending(repo)
     }
 
     /// Build embeddings for packages
@@ -175,7 +577,12 @@ impl RpmSearchApi {
     /// - `rebuild = true`: full rebuild — drops all embeddings and regenerates from scratch
     ///
     /// Model mismatch protection: if the DB was built with a different model type,
-    /// incremental builds are rejected (must use `--rebuild`).
+    /// incremental builds are rejected (must use `--rebuild`). Incremental
+    /// builds also keep the text mode recorded in the DB; only a rebuild
+    /// switches to the configured one.
+    ///
+    /// Repositories assigned their own model via [`Self::build_repo_embeddings`]
+    /// are skipped.
     #[cfg(feature = "embedding")]
     #[instrument(skip(self, embedder), fields(verbose, rebuild))]
     pub fn build_embeddings(
@@ -183,11 +590,27 @@ impl RpmSearchApi {
         embedder: &Embedder,
         verbose: bool,
         rebuild: bool,
+    ) -> Result<usize> {
+        let text = if rebuild {
+            self.configured_text()
+        } else {
+            self.recorded_text(&self.vector_store()?)?
+        };
+        self.build_embeddings_with_text(embedder, verbose, rebuild, &text)
+    }
+
+    /// [`Self::build_embeddings`] from package text built as `text` describes
+    #[cfg(feature = "embedding")]
+    fn build_embeddings_with_text(
+        &self,
+        embedder: &Embedder,
+        verbose: bool,
+        rebuild: bool,
+        text: &EmbeddingText,
     ) -> Result<usize> {
         use std::collections::HashSet;
 
-        let conn = Connection::open(&self.config.db_path)?;
-        let vector_store = VectorStore::new(conn)?;
+        let vector_store = self.vector_store()?;
 
         // Check model mismatch (only for incremental builds)
         let requested_type = embedder.model_type();
@@ -197,40 +620,331 @@ impl RpmSearchApi {
                     return Err(crate::error::RpmSearchError::Embedding(format!(
                         "Model mismatch: existing embeddings were built with '{}', \
                          but '{}' was requested.\n\
-                         Use --rebuild to drop existing embeddings and regenerate with the new model.",
+                         Use --rebuild to drop existing embeddings and regenerate with the new model, \
+                         or --repo to use a different model for a single repository.",
                         db_type_str,
                         requested_type.as_db_str()
                     )));
                 }
+                let db_revision = vector_store.get_embedding_model_revision()?;
+                if db_revision.as_deref() != embedder.revision() {
+                    warn!(
+                        built_with = db_revision.as_deref().unwrap_or("default branch"),
+                        requested = embedder.revision().unwrap_or("default branch"),
+                        "Model revision differs from the existing embeddings; \
+                         use --rebuild to regenerate them with one revision"
+                    );
+                }
+            }
+        }
+
+        // Repos with their own model keep their embeddings in a separate table.
+        // A rebuild that makes their model the default folds them back in.
+        let mut override_repos = Vec::new();
+        for (repo, model) in vector_store.get_repo_embedding_models()? {
+            if rebuild && model == requested_type.as_db_str() {
+                let table = vector_store.table_name_for(requested_type)?;
+                if table != DEFAULT_VECTOR_TABLE {
+                    self.vector_store()?.with_table(table).drop_table()?;
+                }
+                vector_store.clear_repo_embedding_model(&repo)?;
+            } else {
+                override_repos.push(repo);
             }
         }
 
-        let (pkg_ids, label) = if rebuild {
+        let excluded: HashSet<i64> = if override_repos.is_empty() {
+            HashSet::new()
+        } else {
+            info!(repos = ?override_repos, "Skipping repositories with their own embedding model");
+            self.package_store
+                .get_filtered_pkg_ids(&[], &override_repos)?
+                .into_iter()
+                .collect()
+        };
+        let pkg_ids: Vec<i64> = self
+            .package_store
+            .get_all_pkg_ids()?
+            .into_iter()
+            .filter(|id| !excluded.contains(id))
+            .collect();
+
+        // A rebuild with the same model that was interrupted keeps what it
+        // already embedded and only fills in the missing packages
+        let resume = rebuild
+            && vector_store.pending_rebuild(None)?.as_deref() == Some(requested_type.as_db_str());
+        if rebuild && !resume {
             // Full rebuild: drop + recreate
             if verbose {
                 println!("✓ Full rebuild mode — dropping existing embeddings");
             }
-            vector_store.reinitialize(self.config.embedding_dim)?;
+            vector_store.mark_rebuild_pending(None, requested_type)?;
+            vector_store.reinitialize(self.embedding_dim_for(embedder))?;
+            // Record the new model right away so an incremental run can also resume
+            vector_store.set_embedding_model_info(requested_type, embedder.revision())?;
+        } else {
+            if resume {
+                info!("Resuming interrupted embedding rebuild");
+                if verbose {
+                    println!("✓ Resuming interrupted rebuild — keeping embeddings built so far");
+                }
+            }
+            vector_store.ensure_table(self.embedding_dim_for(embedder))?;
+        }
 
-            let ids = self.package_store.get_all_pkg_ids()?;
-            let total = ids.len();
+        let count = self.embed_packages(
+            embedder,
+            &vector_store,
+            pkg_ids,
+            rebuild && !resume,
+            text,
+            verbose,
+        )?;
+        vector_store.clear_rebuild_pending(None)?;
+
+        if rebuild || count > 0 {
+            // Record model info in DB metadata
+            vector_store.set_embedding_model_info(requested_type, embedder.revision())?;
+            Self::record_text(&vector_store, text)?;
+            info!(model = %requested_type, "Saved embedding model info to DB");
+        }
+
+        Ok(count)
+    }
+
+    /// Build embeddings for a single repository.
+    ///
+    /// If the requested model differs from the DB default, the repository is
+    /// assigned that model and its embeddings are stored in a separate table;
+    /// other repositories keep their embeddings. Switching a repository to a
+    /// different model always regenerates all of its embeddings.
+    #[cfg(feature = "embedding")]
+    #[instrument(skip(self, embedder), fields(repo = %repo, verbose, rebuild))]
+    pub fn build_repo_embeddings(
+        &self,
+        embedder: &Embedder,
+        repo: &str,
+        verbose: bool,
+        rebuild: bool,
+    ) -> Result<usize> {
+        let requested_type = embedder.model_type();
+
+        let pkg_ids = self
+            .package_store
+            .get_filtered_pkg_ids(&[], &[repo.to_string()])?;
+        if pkg_ids.is_empty() {
+            return Err(crate::error::RpmSearchError::Config(format!(
+                "Repository '{}' not found or has no packages",
+                repo
+            )));
+        }
+
+        let default_store = self.vector_store()?;
+
+        // The first model used in a DB becomes its default. The text settings
+        // are shared by all repositories, so later builds keep the recorded ones.
+        let text = if default_store.get_embedding_model_type()?.is_none() {
+            default_store.set_embedding_model_info(requested_type, embedder.revision())?;
+            let text = self.configured_text();
+            Self::record_text(&default_store, &text)?;
+            text
+        } else {
+            self.recorded_text(&default_store)?
+        };
+
+        let current = default_store.get_repo_embedding_model_type(repo)?;
+        let switching = current.as_deref() != Some(requested_type.as_db_str());
+        if switching {
+            info!(
+                from = ?current,
+                to = %requested_type.as_db_str(),
+                "Switching repository embedding model"
+            );
+        }
+
+        let table = default_store.table_name_for(requested_type)?;
+        if table == DEFAULT_VECTOR_TABLE {
+            default_store.clear_repo_embedding_model(repo)?;
+        } else {
+            default_store.set_repo_embedding_model(repo, requested_type)?;
+        }
+
+        let vector_store = self.vector_store()?.with_table(table);
+        vector_store.ensure_table(self.embedding_dim_for(embedder))?;
+        vector_store.remove_from_other_tables(&pkg_ids)?;
+
+        // See build_embeddings: an interrupted rebuild resumes where it stopped
+        let resume = default_store.pending_rebuild(Some(repo))?.as_deref()
+            == Some(requested_type.as_db_str());
+        let rebuild = (rebuild || switching) && !resume;
+        if rebuild {
+            if verbose {
+                println!("✓ Rebuilding embeddings for repository '{}'", repo);
+            }
+            default_store.mark_rebuild_pending(Some(repo), requested_type)?;
+            vector_store.delete_embeddings(&pkg_ids)?;
+        } else if resume && verbose {
+            println!("✓ Resuming interrupted rebuild for repository '{}'", repo);
+        }
+
+        let count =
+            self.embed_packages(embedder, &vector_store, pkg_ids, rebuild, &text, verbose)?;
+        default_store.clear_rebuild_pending(Some(repo))?;
+        Ok(count)
+    }
+
+    /// Incrementally build embeddings for the given repositories only, each with
+    /// the model assigned to it (e.g. after syncing those repositories).
+    /// `load_embedder` is called once per distinct model type.
+    #[cfg(feature = "embedding")]
+    pub fn build_embeddings_for_repos<F>(
+        &self,
+        repos: &[String],
+        mut load_embedder: F,
+        verbose: bool,
+    ) -> Result<usize>
+    where
+        F: FnMut(&ModelType) -> Result<Embedder>,
+    {
+        let vector_store = self.vector_store()?;
+
+        let mut embedders: Vec<Embedder> = Vec::new();
+        let mut count = 0;
+        for repo in repos {
+            let has_packages = !self
+                .package_store
+                .get_filtered_pkg_ids(&[], std::slice::from_ref(repo))?
+                .is_empty();
+            if !has_packages {
+                debug!(repo = %repo, "Repository has no packages, skipping embeddings");
+                continue;
+            }
+
+            let model_type = vector_store
+                .get_repo_embedding_model_type(repo)?
+                .and_then(|m| ModelType::from_db_str(&m))
+                .unwrap_or_else(|| self.config.model_type.clone());
+            let idx = match embedders.iter().position(|e| *e.model_type() == model_type) {
+                Some(idx) => idx,
+                None => {
+                    embedders.push(load_embedder(&model_type)?);
+                    embedders.len() - 1
+                }
+            };
+
+            count += self.build_repo_embeddings(&embedders[idx], repo, verbose, false)?;
+        }
+
+        Ok(count)
+    }
+
+    /// Package text settings from the config
+    #[cfg(feature = "embedding")]
+    fn configured_text(&self) -> EmbeddingText {
+        EmbeddingText {
+            mode: self.config.embedding_text_mode,
+            boilerplate: self.config.embedding_boilerplate.clone(),
+        }
+    }
+
+    /// Package text settings recorded with the embeddings. Databases from
+    /// before a setting was recorded get what they were built with: the
+    /// default mode and no boilerplate stripping.
+    #[cfg(feature = "embedding")]
+    fn stored_text(vector_store: &VectorStore) -> Result<EmbeddingText> {
+        Ok(EmbeddingText {
+            mode: vector_store.get_embedding_text_mode()?.unwrap_or_default(),
+            boilerplate: vector_store
+                .get_embedding_boilerplate()?
+                .unwrap_or_default(),
+        })
+    }
+
+    #[cfg(feature = "embedding")]
+    fn record_text(vector_store: &VectorStore, text: &EmbeddingText) -> Result<()> {
+        vector_store.set_embedding_text_mode(text.mode)?;
+        vector_store.set_embedding_boilerplate(&text.boilerplate)
+    }
+
+    /// Package text settings for adding to existing embeddings: the ones they
+    /// were built with, so a database never mixes them. The configured
+    /// settings only take effect on a rebuild or in a database without
+    /// embeddings.
+    #[cfg(feature = "embedding")]
+    fn recorded_text(&self, vector_store: &VectorStore) -> Result<EmbeddingText> {
+        if vector_store.get_embedding_model_type()?.is_none() {
+            return Ok(self.configured_text());
+        }
+        let built_with = Self::stored_text(vector_store)?;
+        if built_with.mode != self.config.embedding_text_mode {
+            warn!(
+                built_with = %built_with.mode,
+                configured = %self.config.embedding_text_mode,
+                "Keeping the text mode of the existing embeddings; \
+                 use --rebuild to switch to the configured one"
+            );
+        }
+        if built_with.boilerplate != self.config.embedding_boilerplate {
+            warn!(
+                built_with = ?built_with.boilerplate,
+                configured = ?self.config.embedding_boilerplate,
+                "Keeping the boilerplate list of the existing embeddings; \
+                 use --rebuild to switch to the configured one"
+            );
+        }
+        Ok(built_with)
+    }
+
+    /// Vector table dimension for `embedder`: the loaded model's hidden size,
+    /// which wins over a stale `Config::embedding_dim`
+    #[cfg(feature = "embedding")]
+    fn embedding_dim_for(&self, embedder: &Embedder) -> usize {
+        let dim = embedder.embedding_dim();
+        if dim != self.config.embedding_dim {
+            warn!(
+                configured = self.config.embedding_dim,
+                model = dim,
+                model_type = %embedder.model_type(),
+                "Configured embedding_dim does not match the model; using the model's dimension"
+            );
+        }
+        dim
+    }
+
+    /// Generate and store embeddings for the given packages, from their text
+    /// built as `text` describes. Unless `all` is set, packages that already
+    /// have an embedding are skipped.
+    #[cfg(feature = "embedding")]
+    fn embed_packages(
+        &self,
+        embedder: &Embedder,
+        vector_store: &VectorStore,
+        pkg_ids: Vec<i64>,
+        all: bool,
+        text: &EmbeddingText,
+        verbose: bool,
+    ) -> Result<usize> {
+        use std::collections::HashSet;
+
+        let (pkg_ids, label) = if all {
+            let total = pkg_ids.len();
             info!(total, "Starting full embedding rebuild");
             if verbose {
                 println!("Total packages to process: {}", total);
             }
-            (ids, "packages")
+            (pkg_ids, "packages")
         } else {
             // Incremental: only missing
-            vector_store.ensure_table(self.config.embedding_dim)?;
-
-            let all_ids: HashSet<i64> = self.package_store.get_all_pkg_ids()?.into_iter().collect();
             let existing_ids: HashSet<i64> = vector_store
                 .get_embedded_pkg_ids()
                 .unwrap_or_default()
                 .into_iter()
                 .collect();
 
-            let missing: Vec<i64> = all_ids.difference(&existing_ids).copied().collect();
+            let missing: Vec<i64> = pkg_ids
+                .into_iter()
+                .filter(|id| !existing_ids.contains(id))
+                .collect();
 
             if missing.is_empty() {
                 info!("All packages already have embeddings, nothing to do");
@@ -257,7 +971,15 @@ impl RpmSearchApi {
         };
 
         let total = pkg_ids.len();
-        let batch_size = self.config.batch_size;
+        let batch_size = self
+            .config
+            .batch_size
+            .unwrap_or_else(|| embedder.recommended_batch_size());
+        info!(
+            batch_size,
+            auto = self.config.batch_size.is_none(),
+            "Effective embedding batch size"
+        );
         let mut count = 0;
         let total_batches = total.div_ceil(batch_size);
 
@@ -266,56 +988,113 @@ impl RpmSearchApi {
             println!();
         }
 
-        for (batch_idx, chunk) in pkg_ids.chunks(batch_size).enumerate() {
-            let mut texts = Vec::new();
-            let mut ids = Vec::new();
+        // Store one embedded batch. Never store NaN or zero vectors: they
+        // break distance computations for every query.
+        let mut store_batch =
+            |batch_idx: usize, ids: Vec<i64>, embeddings: Vec<Vec<f32>>| -> Result<()> {
+                let batch_len = ids.len();
+                let batch_items: Vec<(i64, Vec<f32>)> = ids
+                    .into_iter()
+                    .zip(embeddings)
+                    .map(|(id, emb)| {
+                        if crate::embedding::is_degenerate(&emb) {
+                            warn!(
+                                pkg_id = id,
+                                "Degenerate embedding (zero norm or NaN), \
+                                 storing placeholder vector"
+                            );
+                            (
+                                id,
+                                crate::embedding::placeholder_embedding(embedder.embedding_dim()),
+                            )
+                        } else {
+                            (id, emb)
+                        }
+                    })
+                    .collect();
+                // Batch insert embeddings in a single transaction
+                vector_store.insert_embeddings_batch(&batch_items)?;
+                count += batch_items.len();
+
+                debug!(batch = batch_idx + 1, total = count, "Stored embeddings");
 
-            for &pkg_id in chunk {
-                if let Some(pkg) = self.package_store.get_package(pkg_id)? {
-                    texts.push(pkg.build_embedding_text());
-                    ids.push(pkg_id);
+                if verbose {
+                    println!(
+                        "Batch {}/{}: Processed {} packages → Total: {}/{} ({:.1}%)",
+                        batch_idx + 1,
+                        total_batches,
+                        batch_len,
+                        count,
+                        total,
+                        (count as f64 / total as f64) * 100.0
+                    );
+                } else {
+                    print!(
+                        "\rProcessing: {}/{} {} ({:.1}%)...",
+                        count,
+                        total,
+                        label,
+                        (count as f64 / total as f64) * 100.0
+                    );
+                    std::io::Write::flush(&mut std::io::stdout()).ok();
                 }
-            }
+                Ok(())
+            };
+
+        // Pipeline: a worker thread runs the model on one batch while this
+        // thread stores the previous batch and loads the next one (the DB
+        // connection stays on this thread). Inserts are keyed by pkg_id, so
+        // the order batches finish in doesn't matter.
+        std::thread::scope(|scope| -> Result<()> {
+            let (job_tx, job_rx) = std::sync::mpsc::channel::<(usize, Vec<i64>, Vec<String>)>();
+            let (result_tx, result_rx) = std::sync::mpsc::channel();
+            scope.spawn(move || {
+                for (batch_idx, ids, texts) in job_rx {
+                    debug!(
+                        batch = batch_idx + 1,
+                        packages = texts.len(),
+                        "Generating embeddings for batch"
+                    );
+                    let embeddings = embedder.embed_passages(&texts);
+                    if result_tx.send((batch_idx, ids, embeddings)).is_err() {
+                        break;
+                    }
+                }
+            });
 
-            debug!(
-                batch = batch_idx + 1,
-                packages = texts.len(),
-                "Generating embeddings for batch"
-            );
-            let embeddings = embedder.embed_passages(&texts)?;
+            let mut in_flight = 0;
+            for (batch_idx, chunk) in pkg_ids.chunks(batch_size).enumerate() {
+                let mut texts = Vec::new();
+                let mut ids = Vec::new();
 
-            // Batch insert embeddings in a single transaction
-            let batch_items: Vec<(i64, Vec<f32>)> = ids
-                .iter()
-                .zip(embeddings.iter())
-                .map(|(&id, emb)| (id, emb.clone()))
-                .collect();
-            vector_store.insert_embeddings_batch(&batch_items)?;
-            count += batch_items.len();
+                for &pkg_id in chunk {
+                    if let Some(pkg) = self.package_store.get_package(pkg_id)? {
+                        texts.push(pkg.build_embedding_text(text.mode, &text.boilerplate));
+                        ids.push(pkg_id);
+                    }
+                }
 
-            debug!(batch = batch_idx + 1, total = count, "Stored embeddings");
+                if job_tx.send((batch_idx, ids, texts)).is_err() {
+                    break;
+                }
+                in_flight += 1;
+
+                // Keep at most one batch queued behind the one being embedded
+                if in_flight > 1 {
+                    let Ok((done_idx, ids, embeddings)) = result_rx.recv() else {
+                        break;
+                    };
+                    in_flight -= 1;
+                    store_batch(done_idx, ids, embeddings?)?;
+                }
+            }
+            drop(job_tx);
 
-            if verbose {
-                println!(
-                    "Batch {}/{}: Processed {} packages → Total: {}/{} ({:.1}%)",
-                    batch_idx + 1,
-                    total_batches,
-                    texts.len(),
-                    count,
-                    total,
-                    (count as f64 / total as f64) * 100.0
-                );
-            } else {
-                print!(
-                    "\rProcessing: {}/{} {} ({:.1}%)...",
-                    count,
-                    total,
-                    label,
-                    (count as f64 / total as f64) * 100.0
-                );
-                std::io::Write::flush(&mut std::io::stdout()).ok();
+            for (done_idx, ids, embeddings) in result_rx {
+                store_batch(done_idx, ids, embeddings?)?;
             }
-        }
+            Ok(())
+        })?;
 
         if !verbose {
             println!();
@@ -323,32 +1102,111 @@ impl RpmSearchApi {
 
         info!(total_embeddings = count, "Completed embedding generation");
 
-        // Record model info in DB metadata
-        vector_store.set_embedding_model_info(requested_type)?;
-        info!(model = %requested_type, "Saved embedding model info to DB");
+        Ok(count)
+    }
+
+    /// Search packages
+    #[cfg(feature = "embedding")]
+    #[instrument(skip(self, query, filters), fields(query = %query, top_k = self.config.top_k))]
+    pub fn search(&self, query: &str, filters: SearchFilters) -> Result<Vec<Package>> {
+        let result = self.search_with_scores(query, filters)?;
+        Ok(result.packages)
+    }
+
+    /// Search packages with scores
+    ///
+    /// Auto-detects the embedding model type from DB metadata if available,
+    /// falling back to the config default.
+    #[cfg(feature = "embedding")]
+    #[instrument(skip(self, query, filters), fields(query = %query, top_k = self.config.top_k))]
+    pub fn search_with_scores(&self, query: &str, filters: SearchFilters) -> Result<SearchResult> {
+        self.search_with_top_k(query, filters, self.config.top_k)
+    }
+
+    /// Search packages with scores, returning up to `top_k` results instead
+    /// of the configured `top_k`
+    #[cfg(feature = "embedding")]
+    #[instrument(skip(self, query, filters), fields(query = %query, top_k = top_k))]
+    pub fn search_with_top_k(
+        &self,
+        query: &str,
+        filters: SearchFilters,
+        top_k: usize,
+    ) -> Result<SearchResult> {
+        let planner = self.query_planner(!self.is_short_query(query))?;
+        self.run_search(&planner, query, filters, top_k)
+    }
+
+    /// Run several searches with the same filters, loading the embedding
+    /// model only once. Results are returned in query order.
+    #[cfg(feature = "embedding")]
+    #[instrument(skip(self, queries, filters), fields(queries = queries.len()))]
+    pub fn search_batch(
+        &self,
+        queries: &[String],
+        filters: SearchFilters,
+    ) -> Result<Vec<SearchResult>> {
+        let planner = self.query_planner(queries.iter().any(|q| !self.is_short_query(q)))?;
+        queries
+            .iter()
+            .map(|query| self.run_search(&planner, query, filters.clone(), self.config.top_k))
+            .collect()
+    }
+
+    #[cfg(feature = "embedding")]
+    fn run_search(
+        &self,
+        planner: &QueryPlanner<'_>,
+        query: &str,
+        mut filters: SearchFilters,
+        top_k: usize,
+    ) -> Result<SearchResult> {
+        filters.arches = self.expand_arches(filters.arches);
+        let search_query = SearchQuery {
+            query_text: query.to_string(),
+            filters,
+            top_k: Some(top_k),
+        };
+
+        debug!("Executing hybrid search");
+        let result = planner.search(search_query)?;
+
+        info!(results = result.packages.len(), "Search completed");
 
-        Ok(count)
+        Ok(result)
     }
 
-    /// Search packages
+    /// Whether `query` is below `min_semantic_query_len` and only matches names
     #[cfg(feature = "embedding")]
-    #[instrument(skip(self, query, filters), fields(query = %query, top_k = self.config.top_k))]
-    pub fn search(&self, query: &str, filters: SearchFilters) -> Result<Vec<Package>> {
-        let result = self.search_with_scores(query, filters)?;
-        Ok(result.packages)
+    fn is_short_query(&self, query: &str) -> bool {
+        QueryPlanner::is_short_query(query, self.config.min_semantic_query_len)
     }
 
-    /// Search packages with scores
-    ///
-    /// Auto-detects the embedding model type from DB metadata if available,
-    /// falling back to the config default.
+    /// Build the hybrid search planner, loading the embedding model(s) the
+    /// database was built with (structured-only when there are no embeddings
+    /// or `semantic` is false, e.g. because every query is too short)
     #[cfg(feature = "embedding")]
-    #[instrument(skip(self, query, filters), fields(query = %query, top_k = self.config.top_k))]
-    pub fn search_with_scores(&self, query: &str, filters: SearchFilters) -> Result<SearchResult> {
+    fn query_planner(&self, semantic: bool) -> Result<QueryPlanner<'_>> {
+        let structured_search =
+            StructuredSearch::new(&self.package_store).with_weights(self.config.structured_weights);
+
+        // Nothing to compare against: skip loading the embedding model
+        if !semantic || !self.package_store.has_embeddings() {
+            if semantic {
+                info!("No embeddings in the database, running structured-only search");
+            }
+            return Ok(QueryPlanner::new(
+                SemanticSearch::empty(),
+                structured_search,
+                self.config.top_k,
+                self.config.scoring_weights,
+            )
+            .with_min_semantic_query_len(self.config.min_semantic_query_len));
+        }
+
         debug!("Creating embedder and vector store");
 
-        let conn = Connection::open(&self.config.db_path)?;
-        let vector_store = VectorStore::new(conn)?;
+        let vector_store = self.vector_store()?;
 
         // Auto-detect model type from DB metadata
         let model_type = if let Some(db_type_str) = vector_store.get_embedding_model_type()? {
@@ -358,27 +1216,88 @@ impl RpmSearchApi {
             self.config.model_type.clone()
         };
 
-        // Resolve model files: local dir > hf-hub cache > download
-        let model_files = crate::embedding::hub::resolve_model_files(&model_type, None, None)?;
-        let embedder = Embedder::from_model_files(&model_files, model_type)?;
-
-        debug!("Initializing search components");
-        let semantic_search = SemanticSearch::new(vector_store, embedder);
-        let structured_search = StructuredSearch::new(&self.package_store);
-        let planner = QueryPlanner::new(semantic_search, structured_search, self.config.top_k);
+        // Repositories with their own model are searched in that model's table
+        let mut extra_models: Vec<crate::config::ModelType> = Vec::new();
+        for (_, model_str) in vector_store.get_repo_embedding_models()? {
+            if let Some(model) = crate::config::ModelType::from_db_str(&model_str) {
+                if model != model_type && !extra_models.contains(&model) {
+                    extra_models.push(model);
+                }
+            }
+        }
+        let extra_tables = extra_models
+            .iter()
+            .map(|model| vector_store.table_name_for(model))
+            .collect::<Result<Vec<_>>>()?;
+
+        // The configured revision pin applies to the configured model only
+        let revision = self
+            .config
+            .model_revision
+            .as_deref()
+            .filter(|_| model_type == self.config.model_type);
+        let db_revision = vector_store.get_embedding_model_revision()?;
+        if db_revision.as_deref() != revision {
+            warn!(
+                built_with = db_revision.as_deref().unwrap_or("default branch"),
+                searching_with = revision.unwrap_or("default branch"),
+                "Embeddings were built with a different model revision; \
+                 set model_revision in the config to match"
+            );
+        }
 
-        let search_query = SearchQuery {
-            query_text: query.to_string(),
-            filters,
-            top_k: Some(self.config.top_k),
-        };
+        debug!(
+            text_mode = %vector_store.get_embedding_text_mode()?.unwrap_or_default(),
+            "Embeddings were built from this package text"
+        );
 
-        debug!("Executing hybrid search");
-        let result = planner.search(search_query)?;
+        // Models are loaded on the first query embedding missing from the
+        // query cache, and kept in the model cache for later searches
+        let models = &self.models;
+        let revision = revision.map(str::to_string);
+        let embedder = LazyEmbedder::new(model_type.clone(), revision.clone(), move || {
+            models.get_or_load(&model_type, revision.as_deref(), || {
+                // Resolve model files: local dir > hf-hub cache > download
+                let model_files = crate::embedding::hub::resolve_model_files(
+                    &model_type,
+                    None,
+                    None,
+                    revision.as_deref(),
+                )?;
+                Ok(
+                    Embedder::from_model_files(&model_files, model_type.clone())?
+                        .with_revision(revision.clone()),
+                )
+            })
+        });
 
-        info!(results = result.packages.len(), "Search completed");
+        debug!("Initializing search components");
+        let mut semantic_search = SemanticSearch::new(vector_store, embedder);
+        for (model, table) in extra_models.into_iter().zip(extra_tables) {
+            debug!(model = %model, table = %table, "Adding per-repo embedding model");
+            let config = &self.config;
+            let revision = Embedder::config_revision(config, &model);
+            let embedder = LazyEmbedder::new(model.clone(), revision.clone(), move || {
+                models.get_or_load(&model, revision.as_deref(), || {
+                    Embedder::for_config(config, &model)
+                })
+            });
+            let store = self.vector_store()?.with_table(table);
+            semantic_search.add_backend(store, embedder);
+        }
+        Ok(QueryPlanner::new(
+            semantic_search,
+            structured_search,
+            self.config.top_k,
+            self.config.scoring_weights,
+        )
+        .with_min_semantic_query_len(self.config.min_semantic_query_len))
+    }
 
-        Ok(result)
+    /// Handle for aborting the SQL statement this API is running, from
+    /// another thread (the interrupted call fails with a database error)
+    pub fn interrupt_handle(&self) -> rusqlite::InterruptHandle {
+        self.package_store.connection().get_interrupt_handle()
     }
 
     /// Get package count
@@ -396,11 +1315,338 @@ impl RpmSearchApi {
         self.package_store.count_packages_by_repo(repo)
     }
 
-    /// Delete a repository and all its packages
+    /// Compare two repositories (only in A, only in B, differing versions)
+    pub fn compare_repositories(&self, repo_a: &str, repo_b: &str) -> Result<RepoDiff> {
+        self.package_store.repo_diff(repo_a, repo_b)
+    }
+
+    /// Capabilities provided by packages in more than one repository
+    pub fn duplicate_provides(&self) -> Result<Vec<DuplicateProvide>> {
+        self.package_store.duplicate_provides()
+    }
+
+    /// Files of a repository owned by more than one package
+    pub fn conflicting_files(&self, repo: &str) -> Result<Vec<FileConflict>> {
+        self.package_store.conflicting_files(repo)
+    }
+
+    /// Packages indexed with more than one version of the same (name, arch)
+    pub fn duplicate_versions(
+        &self,
+        arches: &[String],
+        repos: &[String],
+    ) -> Result<Vec<DuplicateVersions>> {
+        let arches = self.expand_arches(arches.to_vec());
+        self.package_store.find_duplicate_versions(&arches, repos)
+    }
+
+    /// Indexed packages newer than the installed version of the same package.
+    /// For each installed package the newest matching candidate is reported;
+    /// candidates are limited to `arches` and `repos` when given.
+    pub fn find_upgrades(
+        &self,
+        installed: &[InstalledPackage],
+        arches: &[String],
+        repos: &[String],
+    ) -> Result<Vec<Upgrade>> {
+        let arches = self.expand_arches(arches.to_vec());
+        let mut names: Vec<String> = installed.iter().map(|inst| inst.name.clone()).collect();
+        names.sort();
+        names.dedup();
+        let mut candidates: std::collections::HashMap<String, Vec<Package>> =
+            std::collections::HashMap::new();
+        for pkg in self.package_store.get_bare_packages_by_names(&names)? {
+            if pkg.matches_arch_repo(&arches, repos) {
+                candidates.entry(pkg.name.clone()).or_default().push(pkg);
+            }
+        }
+
+        let mut upgrades = Vec::new();
+        for inst in installed {
+            let current = inst.to_rpm_version();
+            let newest = candidates
+                .get(&inst.name)
+                .into_iter()
+                .flatten()
+                .filter(|pkg| inst.is_replaced_by(pkg))
+                .filter(|pkg| pkg.to_rpm_version() > current)
+                .max_by(|a, b| a.to_rpm_version().cmp(&b.to_rpm_version()));
+            // Only the reported packages are loaded in full
+            let available = match newest.and_then(|pkg| pkg.pkg_id) {
+                Some(pkg_id) => self.package_store.get_package(pkg_id)?,
+                None => None,
+            };
+            if let Some(available) = available {
+                upgrades.push(Upgrade {
+                    installed: inst.to_string(),
+                    available,
+                });
+            }
+        }
+        upgrades.sort_by(|a, b| a.available.cmp(&b.available));
+        Ok(upgrades)
+    }
+
+    /// Delete a repository with all its packages and tags
     pub fn delete_repository(&mut self, repo: &str) -> Result<usize> {
         self.package_store.delete_repository(repo)
     }
 
+    /// Write every package of a repository (with requires/provides/files) as
+    /// JSONL, one [`PackageSnapshot`] per line. Returns the package count.
+    #[instrument(skip(self, writer), fields(repo = %repo, with_embeddings))]
+    pub fn export_repository<W: Write>(
+        &self,
+        repo: &str,
+        mut writer: W,
+        with_embeddings: bool,
+    ) -> Result<usize> {
+        let pkg_ids = self
+            .package_store
+            .get_filtered_pkg_ids(&[], &[repo.to_string()])?;
+        if pkg_ids.is_empty() {
+            return Err(RpmSearchError::Config(format!(
+                "Repository '{}' not found or has no packages",
+                repo
+            )));
+        }
+
+        #[cfg(feature = "embedding")]
+        let vectors = if with_embeddings {
+            Some(self.repo_vector_store(repo)?)
+        } else {
+            None
+        };
+        #[cfg(not(feature = "embedding"))]
+        if with_embeddings {
+            return Err(RpmSearchError::Config(
+                "Exporting embeddings requires the 'embedding' feature".to_string(),
+            ));
+        }
+
+        let mut count = 0;
+        for pkg_id in pkg_ids {
+            let Some(package) = self.package_store.get_package(pkg_id)? else {
+                continue;
+            };
+            let files = self
+                .package_store
+                .get_files_for_package(pkg_id)?
+                .into_iter()
+                .map(|(path, ft)| RpmFileEntry {
+                    path,
+                    file_type: RpmFileType::from_i32(ft),
+                })
+                .collect();
+
+            #[allow(unused_mut)]
+            let mut snapshot = PackageSnapshot {
+                package,
+                files,
+                embedding_model: None,
+                embedding: None,
+            };
+
+            #[cfg(feature = "embedding")]
+            if let Some((model_type, store)) = &vectors {
+                snapshot.embedding = store.get_embedding(pkg_id)?;
+                if snapshot.embedding.is_some() {
+                    snapshot.embedding_model = Some(model_type.as_db_str().to_string());
+                }
+            }
+
+            serde_json::to_writer(&mut writer, &snapshot).map_err(|e| {
+                RpmSearchError::Storage(format!("Failed to serialize package: {}", e))
+            })?;
+            writer.write_all(b"\n")?;
+            count += 1;
+        }
+
+        writer.flush()?;
+        info!(count, "Exported repository snapshot");
+        Ok(count)
+    }
+
+    /// Vector store on the package store's connection, so every table access
+    /// reuses one SQLite connection (with sqlite-vec already loaded)
+    #[cfg(feature = "embedding")]
+    fn vector_store(&self) -> Result<VectorStore<'_>> {
+        Ok(VectorStore::new(self.package_store.connection())?
+            .with_slow_query_ms(self.config.slow_query_ms)
+            .with_score_transform(self.config.score_transform))
+    }
+
+    /// Run `f` atomically on the shared connection: package and vector
+    /// writes inside it are kept only if it returns `Ok`
+    fn in_transaction<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let conn = self.package_store.connection();
+        conn.execute_batch("SAVEPOINT api_transaction")?;
+        let result = f(self);
+        finish_savepoint(self.package_store.connection(), "api_transaction", result)
+    }
+
+    /// Vector store (and its model) holding a repository's embeddings
+    #[cfg(feature = "embedding")]
+    fn repo_vector_store(&self, repo: &str) -> Result<(ModelType, VectorStore<'_>)> {
+        let default_store = self.vector_store()?;
+        let model = match default_store.get_repo_embedding_model_type(repo)? {
+            Some(model) => Some(model),
+            None => default_store.get_embedding_model_type()?,
+        };
+        let model_type = model
+            .as_deref()
+            .and_then(ModelType::from_db_str)
+            .ok_or_else(|| {
+                RpmSearchError::Config(format!("Repository '{}' has no embeddings", repo))
+            })?;
+        let table = default_store.table_name_for(&model_type)?;
+
+        let store = self.vector_store()?.with_table(table);
+        Ok((model_type, store))
+    }
+
+    /// Load a JSONL snapshot written by [`RpmSearchApi::export_repository`]
+    /// into `repo`, which must not exist yet. Embeddings in the snapshot are
+    /// restored as well. Returns (packages, files, embeddings) counts.
+    #[instrument(skip(self, reader), fields(repo = %repo))]
+    pub fn import_repository<R: BufRead>(
+        &mut self,
+        reader: R,
+        repo: &str,
+    ) -> Result<(usize, usize, usize)> {
+        if self.package_store.count_packages_by_repo(repo)? > 0 {
+            return Err(RpmSearchError::Config(format!(
+                "Repository '{}' already exists; delete it before importing",
+                repo
+            )));
+        }
+
+        let mut snapshots = Vec::new();
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut snapshot: PackageSnapshot = serde_json::from_str(&line)
+                .map_err(|e| RpmSearchError::Parse(format!("line {}: {}", idx + 1, e)))?;
+            snapshot.package.pkg_id = None;
+            snapshot.package.repo = repo.to_string();
+            snapshots.push(snapshot);
+        }
+
+        // Packages, files and embeddings land together or not at all
+        self.in_transaction(|api| api.import_snapshots(repo, &snapshots))
+    }
+
+    fn import_snapshots(
+        &mut self,
+        repo: &str,
+        snapshots: &[PackageSnapshot],
+    ) -> Result<(usize, usize, usize)> {
+        let packages: Vec<Package> = snapshots.iter().map(|s| s.package.clone()).collect();
+        let pkg_ids = self.package_store.insert_packages_batch(&packages)?;
+
+        let entries: Vec<(i64, Vec<(String, i32)>)> = pkg_ids
+            .iter()
+            .zip(snapshots)
+            .filter(|(_, s)| !s.files.is_empty())
+            .map(|(&id, s)| {
+                let files = s
+                    .files
+                    .iter()
+                    .map(|f| (f.path.clone(), f.file_type.as_i32()))
+                    .collect();
+                (id, files)
+            })
+            .collect();
+        let mut file_count = 0;
+        for chunk in entries.chunks(500) {
+            file_count += self.package_store.insert_filelists_batch(chunk)?;
+        }
+
+        // Stored per package name; before the embeddings, which it would drop
+        let mut appstream: Vec<(String, AppstreamInfo)> = Vec::new();
+        for snapshot in snapshots {
+            if let Some(info) = &snapshot.package.appstream {
+                if !appstream
+                    .iter()
+                    .any(|(name, _)| *name == snapshot.package.name)
+                {
+                    appstream.push((snapshot.package.name.clone(), info.clone()));
+                }
+            }
+        }
+        if !appstream.is_empty() {
+            self.package_store
+                .insert_appstream_batch(repo, &appstream)?;
+        }
+
+        let embeddings: Vec<(i64, &PackageSnapshot)> = pkg_ids
+            .iter()
+            .copied()
+            .zip(snapshots)
+            .filter(|(_, s)| s.embedding.is_some())
+            .collect();
+        let embedding_count = if embeddings.is_empty() {
+            0
+        } else {
+            self.import_embeddings(repo, &embeddings)?
+        };
+
+        info!(
+            packages = pkg_ids.len(),
+            files = file_count,
+            embeddings = embedding_count,
+            "Imported repository snapshot"
+        );
+        Ok((pkg_ids.len(), file_count, embedding_count))
+    }
+
+    #[cfg(feature = "embedding")]
+    fn import_embeddings(&self, repo: &str, items: &[(i64, &PackageSnapshot)]) -> Result<usize> {
+        let model = items[0].1.embedding_model.as_deref().unwrap_or_default();
+        if items
+            .iter()
+            .any(|(_, s)| s.embedding_model.as_deref() != Some(model))
+        {
+            return Err(RpmSearchError::Parse(
+                "Snapshot embeddings were built with different models".to_string(),
+            ));
+        }
+        let model_type = ModelType::from_db_str(model).ok_or_else(|| {
+            RpmSearchError::Parse(format!("Unknown embedding model '{}' in snapshot", model))
+        })?;
+
+        let default_store = self.vector_store()?;
+        if default_store.get_embedding_model_type()?.is_none() {
+            default_store.set_embedding_model_info(&model_type, None)?;
+        }
+        let table = default_store.table_name_for(&model_type)?;
+        if table == DEFAULT_VECTOR_TABLE {
+            default_store.clear_repo_embedding_model(repo)?;
+        } else {
+            default_store.set_repo_embedding_model(repo, &model_type)?;
+        }
+
+        let vectors: Vec<(i64, Vec<f32>)> = items
+            .iter()
+            .filter_map(|(id, s)| s.embedding.clone().map(|e| (*id, e)))
+            .collect();
+        let store = self.vector_store()?.with_table(table);
+        store.ensure_table(vectors[0].1.len())?;
+        store.insert_embeddings_batch(&vectors)?;
+        Ok(vectors.len())
+    }
+
+    #[cfg(not(feature = "embedding"))]
+    fn import_embeddings(&self, _repo: &str, items: &[(i64, &PackageSnapshot)]) -> Result<usize> {
+        warn!(
+            count = items.len(),
+            "Skipping snapshot embeddings: built without the 'embedding' feature"
+        );
+        Ok(0)
+    }
+
     /// Search by name only
     #[allow(dead_code)]
     pub fn search_by_name(&self, name: &str) -> Result<Vec<Package>> {
@@ -439,7 +1685,7 @@ impl RpmSearchApi {
         for fl_pkg in &fl_packages {
             let pkg_id = self.package_store.find_package_by_nevra(
                 &fl_pkg.name,
-                &fl_pkg.arch,
+                Self::stored_arch(&self.config, &fl_pkg.arch),
                 fl_pkg.epoch,
                 &fl_pkg.version,
                 &fl_pkg.release,
@@ -485,24 +1731,137 @@ impl RpmSearchApi {
         Ok(total_files)
     }
 
-    /// Search for packages providing a specific file
-    pub fn search_file(&self, path: &str) -> Result<Vec<(Package, String, String)>> {
+    // ── AppStream methods ───────────────────────────────────────────────
+
+    /// Index AppStream metadata (components.xml) for an existing repository.
+    /// Components are matched to packages by `<pkgname>`; their embeddings are
+    /// invalidated so the next embedding build uses the richer description.
+    /// Returns the number of packages matched.
+    #[instrument(skip(self, appstream_path), fields(path = %appstream_path.as_ref().display(), repo = %repo_name))]
+    pub fn index_appstream<P: AsRef<Path>>(
+        &mut self,
+        appstream_path: P,
+        repo_name: &str,
+    ) -> Result<usize> {
+        debug!("Fetching AppStream file");
+        let data = RepoFetcher::fetch_local(&appstream_path)?;
+        let xml_data = RepoFetcher::auto_decompress(&appstream_path, &data)?;
+
+        debug!("Parsing AppStream XML");
+        let components = AppstreamParser::parse(&xml_data[..])?;
+        info!(components = components.len(), "Parsed AppStream components");
+
+        let entries: Vec<(String, AppstreamInfo)> = components
+            .into_iter()
+            .map(|c| (c.pkgname.clone(), AppstreamInfo::from(c)))
+            .collect();
+
+        let matched = self
+            .package_store
+            .insert_appstream_batch(repo_name, &entries)?;
+        if matched == 0 {
+            warn!("No AppStream components matched existing indexed packages");
+        }
+        info!(matched, "AppStream component matching completed");
+        Ok(matched)
+    }
+
+    // ── Export methods ──────────────────────────────────────────────────
+
+    /// Write `packages` (with their dependencies and indexed files) as rpm-md
+    /// metadata: `primary.xml.gz` and a `repomd.xml` referencing it, both in
+    /// `output_dir`, which is taken to be the repository's metadata directory
+    /// (`<repo>/repodata`). The result can be indexed again with `index repo`.
+    ///
+    /// The index keeps neither package checksums nor sizes, so `<package>`
+    /// entries have no `<checksum pkgid>` or `<size>` and dnf/createrepo cannot
+    /// consume the output as a real repository.
+    #[instrument(skip(self, packages, output_dir), fields(packages = packages.len(), dir = %output_dir.as_ref().display()))]
+    pub fn export_repomd<P: AsRef<Path>>(&self, packages: &[Package], output_dir: P) -> Result<()> {
+        const PRIMARY_FILE: &str = "primary.xml.gz";
+
+        let output_dir = output_dir.as_ref();
+        std::fs::create_dir_all(output_dir)?;
+
+        let file = std::fs::File::create(output_dir.join(PRIMARY_FILE))?;
+        let stored = ChecksumWriter::new(std::io::BufWriter::new(file));
+        let encoder = flate2::write::GzEncoder::new(stored, flate2::Compression::default());
+        let mut primary = PrimaryXmlWriter::new(ChecksumWriter::new(encoder), packages.len())?;
+        for package in packages {
+            let files: Vec<RpmFileEntry> = match package.pkg_id {
+                Some(pkg_id) => self
+                    .package_store
+                    .get_files_for_package(pkg_id)?
+                    .into_iter()
+                    .map(|(path, ft)| RpmFileEntry {
+                        path,
+                        file_type: RpmFileType::from_i32(ft),
+                    })
+                    .collect(),
+                None => Vec::new(),
+            };
+            primary.write_package(&package.to_rpm_package(), &files)?;
+        }
+        let (encoder, open_checksum, open_size) = primary.finish()?.finish();
+        let (mut file, checksum, size) = encoder.finish()?.finish();
+        file.flush()?;
+
+        // repomd.xml locations are relative to the repository root, the parent
+        // of the metadata directory
+        let href = match std::fs::canonicalize(output_dir)?.file_name() {
+            Some(dir) => format!("{}/{}", dir.to_string_lossy(), PRIMARY_FILE),
+            None => PRIMARY_FILE.to_string(),
+        };
+        let timestamp = chrono::Utc::now().timestamp();
+        let record = RepomdRecord {
+            data_type: "primary".to_string(),
+            href,
+            checksum,
+            open_checksum,
+            size,
+            open_size,
+            timestamp,
+        };
+        let repomd = std::fs::File::create(output_dir.join("repomd.xml"))?;
+        write_repomd(std::io::BufWriter::new(repomd), timestamp, &[record])?;
+
+        info!("Exported rpm-md metadata");
+        Ok(())
+    }
+
+    /// All indexed versions of the package with exactly this name
+    pub fn get_packages_by_name(&self, name: &str) -> Result<Vec<Package>> {
+        self.package_store.get_packages_by_name(name)
+    }
+
+    /// Search for packages providing a specific file, each with all of its
+    /// matching files
+    pub fn search_file(&self, path: &str) -> Result<Vec<FileMatch>> {
         let results = self.package_store.search_by_file_path(path)?;
 
-        let mut output = Vec::new();
-        let mut seen_pkg_ids = std::collections::HashSet::new();
+        let mut output: Vec<FileMatch> = Vec::new();
+        let mut index_by_pkg_id: std::collections::HashMap<i64, usize> =
+            std::collections::HashMap::new();
 
         for (pkg_id, full_path, file_type) in results {
-            if !seen_pkg_ids.insert(pkg_id) {
-                continue;
-            }
-            if let Some(pkg) = self.package_store.get_package(pkg_id)? {
-                let type_str = match file_type {
+            let file = MatchedFile {
+                path: full_path,
+                file_type: match file_type {
                     1 => "dir".to_string(),
                     2 => "ghost".to_string(),
                     _ => "file".to_string(),
-                };
-                output.push((pkg, full_path, type_str));
+                },
+            };
+            if let Some(&i) = index_by_pkg_id.get(&pkg_id) {
+                output[i].files.push(file);
+                continue;
+            }
+            if let Some(package) = self.package_store.get_package(pkg_id)? {
+                index_by_pkg_id.insert(pkg_id, output.len());
+                output.push(FileMatch {
+                    package,
+                    files: vec![file],
+                });
             }
         }
 
@@ -521,12 +1880,8 @@ impl RpmSearchApi {
 
         let mut results = Vec::new();
         for pkg in packages {
-            if let Some(a) = arch {
-                if pkg.arch != a {
-                    continue;
-                }
-            }
-            if !repos.is_empty() && !repos.contains(&pkg.repo) {
+            let arches: Vec<String> = arch.into_iter().map(String::from).collect();
+            if !pkg.matches_arch_repo(&arches, repos) {
                 continue;
             }
 
@@ -551,7 +1906,6 @@ impl RpmSearchApi {
     }
 
     /// Check if filelists have been indexed for a repository
-    #[allow(dead_code)]
     pub fn has_filelists(&self, repo: &str) -> Result<bool> {
         self.package_store.has_filelists(repo)
     }
@@ -566,20 +1920,501 @@ impl RpmSearchApi {
         self.package_store.count_directories()
     }
 
+    /// Collect schema, extension, embedding and filelists status in one report
+    pub fn diagnostics(&self) -> Result<Diagnostics> {
+        let embedded = self.package_store.embedded_pkg_ids();
+
+        #[cfg(feature = "embedding")]
+        let vector_store = self.vector_store()?;
+        #[cfg(feature = "embedding")]
+        let embedding_model = vector_store.get_embedding_model_type()?;
+        #[cfg(not(feature = "embedding"))]
+        let embedding_model: Option<String> = None;
+        // Databases built before the mode was recorded used the full text
+        #[cfg(feature = "embedding")]
+        let embedding_text_mode = embedding_model.as_ref().map(|_| {
+            vector_store
+                .get_embedding_text_mode()
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+                .to_string()
+        });
+        #[cfg(not(feature = "embedding"))]
+        let embedding_text_mode: Option<String> = None;
+
+        let mut repos = Vec::new();
+        for (name, packages) in self.package_store.list_repositories()? {
+            let embedded_packages = self
+                .package_store
+                .pkg_ids_by_repo(&name)?
+                .iter()
+                .filter(|id| embedded.contains(id))
+                .count();
+
+            #[cfg(feature = "embedding")]
+            let repo_model = if embedded_packages == 0 {
+                None
+            } else {
+                vector_store
+                    .get_repo_embedding_model_type(&name)?
+                    .or_else(|| embedding_model.clone())
+            };
+            #[cfg(not(feature = "embedding"))]
+            let repo_model = None;
+
+            repos.push(RepoDiagnostics {
+                has_filelists: self.package_store.has_filelists(&name)?,
+                name,
+                packages,
+                embedded_packages,
+                embedding_model: repo_model,
+            });
+        }
+
+        Ok(Diagnostics {
+            db_path: self.config.db_path.display().to_string(),
+            schema_version: self.package_store.schema_version()?,
+            sqlite_vec_version: self.package_store.sqlite_vec_version(),
+            total_packages: self.package_store.count_packages()?,
+            embedded_packages: embedded.len(),
+            embedding_model,
+            embedding_text_mode,
+            repos,
+        })
+    }
+
+    /// Embeddings whose package no longer exists, as (vec table, pkg_ids) pairs
+    /// for every model's table that has any
+    #[cfg(feature = "embedding")]
+    pub fn orphan_embeddings(&self) -> Result<Vec<(String, Vec<i64>)>> {
+        let mut orphans = Vec::new();
+        for table in vector_tables(self.package_store.connection()) {
+            let store = self.vector_store()?.with_table(table.clone());
+            let ids = store.orphan_pkg_ids(&self.package_store)?;
+            if !ids.is_empty() {
+                orphans.push((table, ids));
+            }
+        }
+        Ok(orphans)
+    }
+
+    /// Delete embeddings whose package no longer exists from every model's
+    /// vec table, returning the number removed
+    #[cfg(feature = "embedding")]
+    pub fn purge_orphan_embeddings(&self) -> Result<usize> {
+        let mut deleted = 0;
+        for (table, ids) in self.orphan_embeddings()? {
+            deleted += self
+                .vector_store()?
+                .with_table(table)
+                .delete_embeddings(&ids)?;
+        }
+        info!(deleted, "Purged orphan embeddings");
+        Ok(deleted)
+    }
+
+    /// Packages with an embedding per repository, counted in the table of the
+    /// model that repository is embedded with: its own model if it has one,
+    /// otherwise the configured model. Embeddings left in other models' tables
+    /// are not counted, since neither search nor builds use them.
+    pub fn embedding_coverage(&self) -> Result<Vec<EmbeddingCoverage>> {
+        use std::collections::{HashMap, HashSet};
+
+        #[cfg(feature = "embedding")]
+        let (default_store, repo_models) = {
+            let store = self.vector_store()?;
+            let repo_models: HashMap<String, String> =
+                store.get_repo_embedding_models()?.into_iter().collect();
+            (store, repo_models)
+        };
+        let mut embedded_by_table: HashMap<String, HashSet<i64>> = HashMap::new();
+
+        self.package_store
+            .list_repositories()?
+            .into_iter()
+            .map(|(repo, packages)| {
+                #[cfg(feature = "embedding")]
+                let embedded = {
+                    use std::collections::hash_map::Entry;
+
+                    let model = repo_models
+                        .get(&repo)
+                        .and_then(|m| ModelType::from_db_str(m))
+                        .unwrap_or_else(|| self.config.model_type.clone());
+                    let table = default_store.table_name_for(&model)?;
+                    match embedded_by_table.entry(table) {
+                        Entry::Occupied(entry) => entry.into_mut(),
+                        Entry::Vacant(entry) => {
+                            let store = self.vector_store()?.with_table(entry.key().clone());
+                            let ids = if store.has_table() {
+                                store.get_embedded_pkg_ids()?.into_iter().collect()
+                            } else {
+                                HashSet::new()
+                            };
+                            entry.insert(ids)
+                        }
+                    }
+                };
+                // Without the embedding feature no model is configured to search with
+                #[cfg(not(feature = "embedding"))]
+                let embedded = embedded_by_table
+                    .entry(String::new())
+                    .or_insert_with(|| self.package_store.embedded_pkg_ids());
+
+                let embedded_packages = self
+                    .package_store
+                    .pkg_ids_by_repo(&repo)?
+                    .iter()
+                    .filter(|id| embedded.contains(id))
+                    .count();
+                Ok(EmbeddingCoverage {
+                    repo,
+                    packages,
+                    embedded_packages,
+                })
+            })
+            .collect()
+    }
+
+    /// Compute the transitive runtime dependency closure of a package within
+    /// the indexed repositories.
+    ///
+    /// `nevra` is a package name or a full `name-[epoch:]version-release[.arch]`;
+    /// for a bare name the latest version is used. Providers are restricted to
+    /// `arch` (or the package's own arch) plus `noarch`.
+    pub fn resolve_dependencies(&self, nevra: &str, arch: Option<&str>) -> Result<Resolution> {
+        // A noarch package is installable on any architecture
+        let arches: Vec<String> = match arch {
+            Some(arch) => vec![arch.to_string(), "noarch".to_string()],
+            None => Vec::new(),
+        };
+
+        // Try the whole string as a name, then each prefix ending before a '-'
+        let mut candidates = Vec::new();
+        let names = std::iter::once(nevra)
+            .chain(nevra.rmatch_indices('-').map(|(i, _)| &nevra[..i]))
+            .filter(|name| !name.is_empty());
+        for name in names {
+            candidates = self
+                .package_store
+                .get_packages_by_name(name)?
+                .into_iter()
+                .filter(|pkg| pkg.matches_arch_repo(&arches, &[]))
+                .filter(|pkg| {
+                    // Accept the EVR with or without its epoch
+                    name == nevra
+                        || [
+                            format!("{}-{}", pkg.name, pkg.full_version()),
+                            format!("{}-{}-{}", pkg.name, pkg.version, pkg.release),
+                        ]
+                        .iter()
+                        .any(|nv| nv == nevra || format!("{}.{}", nv, pkg.arch) == nevra)
+                })
+                .collect();
+            if !candidates.is_empty() {
+                break;
+            }
+        }
+
+        let root = candidates
+            .into_iter()
+            .max_by(|a, b| a.to_rpm_version().cmp(&b.to_rpm_version()))
+            .ok_or_else(|| RpmSearchError::Storage(format!("Package '{}' not found", nevra)))?;
+
+        let provider_arches = match arch {
+            Some(arch) => vec![arch.to_string()],
+            None if root.arch != "noarch" => vec![root.arch.clone()],
+            None => Vec::new(),
+        };
+        info!(package = %root.name, "Resolving dependencies");
+        DependencyResolver::new(&self.package_store, &provider_arches).resolve(root)
+    }
+
     // ── General search ──────────────────────────────────────────────────
 
     /// General-purpose structured search with multiple filters and wildcard support.
-    /// Returns matching packages ordered by name.
+    /// Returns matching packages in `filter.sort` order.
     pub fn find(&self, filter: &FindFilter) -> Result<Vec<Package>> {
-        let pkg_ids = self.package_store.general_search(filter)?;
-
         let mut packages = Vec::new();
+        self.find_each(filter, |pkg| {
+            packages.push(pkg);
+            Ok(())
+        })?;
+        Ok(packages)
+    }
+
+    /// Like [`find`](Self::find), but hands each package to `f` as soon as it
+    /// is loaded instead of collecting them (for streaming output)
+    pub fn find_each<F>(&self, filter: &FindFilter, mut f: F) -> Result<()>
+    where
+        F: FnMut(Package) -> Result<()>,
+    {
+        let filter = FindFilter {
+            arches: self.expand_arches(filter.arches.clone()),
+            ..filter.clone()
+        };
+        let pkg_ids = self.package_store.general_search(&filter)?;
+        if filter.sort == SortField::Version {
+            // SQL can't compare RPM versions: load every match, sort, then limit
+            let mut packages = Vec::with_capacity(pkg_ids.len());
+            for pkg_id in pkg_ids {
+                packages.extend(self.package_store.get_package(pkg_id)?);
+            }
+            SortField::Version.sort_packages(&mut packages, filter.reverse);
+            if filter.limit > 0 {
+                packages.truncate(filter.limit);
+            }
+            return packages.into_iter().try_for_each(f);
+        }
         for pkg_id in pkg_ids {
             if let Some(pkg) = self.package_store.get_package(pkg_id)? {
-                packages.push(pkg);
+                f(pkg)?;
             }
         }
+        Ok(())
+    }
+}
+
+/// Recursively collect `*.rpm` files under `dir`
+fn collect_rpm_files(dir: &Path, out: &mut Vec<std::path::PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_rpm_files(&path, out)?;
+        } else if path.extension().is_some_and(|ext| ext == "rpm") {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
 
-        Ok(packages)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalize::test_package;
+
+    fn test_api() -> (tempfile::TempDir, RpmSearchApi) {
+        let dir = tempfile::tempdir().unwrap();
+        let api = RpmSearchApi::new(Config::new(dir.path().join("test.db"))).unwrap();
+        (dir, api)
+    }
+
+    fn snapshots(api: &RpmSearchApi, repo: &str) -> Vec<serde_json::Value> {
+        let mut out = Vec::new();
+        api.export_repository(repo, &mut out, false).unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let mut value: serde_json::Value = serde_json::from_str(line).unwrap();
+                value["pkg_id"] = serde_json::Value::Null;
+                value["repo"] = serde_json::Value::Null;
+                value
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "embedding")]
+    #[test]
+    fn test_recorded_text_without_boilerplate_is_empty() {
+        let (_dir, api) = test_api();
+        let vector_store = api.vector_store().unwrap();
+        vector_store
+            .set_embedding_model_info(&api.config.model_type, None)
+            .unwrap();
+
+        // Embeddings from before the boilerplate list was recorded kept every phrase
+        assert!(!api.config.embedding_boilerplate.is_empty());
+        let text = api.recorded_text(&vector_store).unwrap();
+        assert!(text.boilerplate.is_empty());
+    }
+
+    #[cfg(feature = "embedding")]
+    #[test]
+    fn test_discard_pending_rebuild() {
+        let (_dir, api) = test_api();
+        let vector_store = api.vector_store().unwrap();
+        vector_store
+            .mark_rebuild_pending(None, &ModelType::Minilm)
+            .unwrap();
+        vector_store
+            .mark_rebuild_pending(Some("updates"), &ModelType::Minilm)
+            .unwrap();
+
+        api.discard_pending_rebuild(None).unwrap();
+        assert!(vector_store.pending_rebuild(None).unwrap().is_none());
+        // Other repositories' rebuilds still resume
+        assert!(vector_store
+            .pending_rebuild(Some("updates"))
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_export_repomd_references_written_primary() {
+        let (dir, mut api) = test_api();
+        api.package_store
+            .insert_packages_batch(&[test_package("bash", "5.2")])
+            .unwrap();
+        let packages = api.get_packages_by_name("bash").unwrap();
+
+        let output = dir.path().join("subset").join("meta");
+        api.export_repomd(&packages, &output).unwrap();
+
+        let primary_gz = std::fs::read(output.join("primary.xml.gz")).unwrap();
+        let primary_xml = RepoFetcher::auto_decompress("primary.xml.gz", &primary_gz).unwrap();
+        let expected = RepomdRecord::new("primary", "", &primary_gz, &primary_xml, 0);
+        let repomd = std::fs::read_to_string(output.join("repomd.xml")).unwrap();
+        assert!(repomd.contains(r#"<location href="meta/primary.xml.gz"/>"#));
+        assert!(repomd.contains(&expected.checksum));
+        assert!(repomd.contains(&expected.open_checksum));
+        assert!(repomd.contains(&format!("<size>{}</size>", expected.size)));
+
+        let parsed = PrimaryXmlParser::parse(&primary_xml[..]).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].name, "bash");
+    }
+
+    #[test]
+    fn test_find_upgrades_compares_version_and_arch() {
+        let (_dir, mut api) = test_api();
+        let pkg = |name: &str, epoch: Option<i64>, version: &str, arch: &str| Package {
+            epoch,
+            arch: arch.to_string(),
+            provides: vec![crate::normalize::Dependency::from_evr(name, None, None)],
+            ..test_package(name, version)
+        };
+        api.package_store
+            .insert_packages_batch(&[
+                pkg("bash", None, "5.2", "x86_64"),
+                pkg("bash", None, "5.10", "x86_64"),
+                pkg("bash", None, "6.0", "aarch64"),
+                pkg("zlib", None, "1.3", "x86_64"),
+                pkg("perl-Carp", Some(1), "1.50", "noarch"),
+                pkg("perl-Carp", None, "2.0", "noarch"),
+            ])
+            .unwrap();
+        let installed = crate::installed::parse_installed_list(
+            "bash-5.9-1.x86_64\nzlib-1.3-1.x86_64\nperl-Carp-1:1.40-1.noarch\nmissing-1.0-1.x86_64\n",
+        );
+
+        let upgrades = api.find_upgrades(&installed, &[], &[]).unwrap();
+        let found: Vec<(&str, &str, &str)> = upgrades
+            .iter()
+            .map(|u| {
+                let pkg = &u.available;
+                (
+                    u.installed.as_str(),
+                    pkg.version.as_str(),
+                    pkg.arch.as_str(),
+                )
+            })
+            .collect();
+        // 5.10 > 5.9 numerically; the aarch64 build doesn't replace x86_64;
+        // same EVR is no upgrade; the epoch outranks a higher version
+        assert_eq!(
+            found,
+            [
+                ("bash-5.9-1.x86_64", "5.10", "x86_64"),
+                ("perl-Carp-1:1.40-1.noarch", "1.50", "noarch"),
+            ]
+        );
+        // Reported packages are loaded in full
+        assert!(upgrades.iter().all(|u| !u.available.provides.is_empty()));
+
+        let upgrades = api
+            .find_upgrades(&installed, &[], &["other".to_string()])
+            .unwrap();
+        assert!(upgrades.is_empty());
+    }
+
+    #[test]
+    fn test_update_refreshes_dependencies_after_upgrade() {
+        let (_dir, mut api) = test_api();
+        let mut bash = test_package("bash", "5.2");
+        bash.requires = vec![crate::normalize::Dependency::from_evr(
+            "/bin/sh", None, None,
+        )];
+        let ids = api
+            .package_store
+            .insert_packages_batch(std::slice::from_ref(&bash))
+            .unwrap();
+        // As left by an upgrade from a version that stored no `pre` flags
+        api.package_store
+            .connection()
+            .execute(
+                "INSERT INTO repo_dependency_refresh (repo) VALUES ('base')",
+                [],
+            )
+            .unwrap();
+
+        bash.requires[0].pre = true;
+        let stats = api
+            .update_repository_packages(vec![bash.to_rpm_package()], "base")
+            .unwrap();
+        assert_eq!((stats.added, stats.updated, stats.refreshed), (0, 0, 1));
+
+        let stored = api.package_store.get_package(ids[0]).unwrap().unwrap();
+        assert!(stored.requires[0].pre);
+        assert!(!api
+            .package_store
+            .dependency_refresh_pending("base")
+            .unwrap());
+        let stats = api
+            .update_repository_packages(vec![bash.to_rpm_package()], "base")
+            .unwrap();
+        assert_eq!(stats.refreshed, 0);
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let (_dir, mut api) = test_api();
+        let bash = Package {
+            summary: "The GNU Bourne Again shell".to_string(),
+            requires: vec![crate::normalize::Dependency::from_evr(
+                "glibc",
+                Some("GE"),
+                Some("2.34"),
+            )],
+            ..test_package("bash", "5.2")
+        };
+        let ids = api
+            .package_store
+            .insert_packages_batch(&[bash, test_package("zsh", "5.9")])
+            .unwrap();
+        api.package_store
+            .insert_filelists_batch(&[(
+                ids[0],
+                vec![
+                    ("/usr/bin/bash".to_string(), 0),
+                    ("/etc/skel".to_string(), 1),
+                ],
+            )])
+            .unwrap();
+        api.package_store
+            .insert_appstream_batch(
+                "base",
+                &[(
+                    "bash".to_string(),
+                    AppstreamInfo {
+                        description: "A shell".to_string(),
+                        categories: vec!["System".to_string()],
+                        keywords: vec!["terminal".to_string()],
+                    },
+                )],
+            )
+            .unwrap();
+
+        let mut exported = Vec::new();
+        api.export_repository("base", &mut exported, false).unwrap();
+        let (packages, files, embeddings) =
+            api.import_repository(exported.as_slice(), "copy").unwrap();
+        assert_eq!((packages, files, embeddings), (2, 2, 0));
+
+        let original = snapshots(&api, "base");
+        assert_eq!(snapshots(&api, "copy"), original);
+        assert_eq!(original[0]["appstream"]["description"], "A shell");
+        assert_eq!(original[0]["files"].as_array().unwrap().len(), 2);
     }
 }
@@ -2,13 +2,15 @@ use rpm_repo_search::api;
 use rpm_repo_search::config::Config;
 use rpm_repo_search::error::{Result, RpmSearchError};
 use rpm_repo_search::gbs;
-use rpm_repo_search::normalize::Package;
-use rpm_repo_search::storage::FindFilter;
+use rpm_repo_search::normalize::{filter_latest_n, sort_by_arch_order, Package};
+use rpm_repo_search::output::{self, OutputFormat};
+use rpm_repo_search::repomd::fetch::RepoFetcher;
+use rpm_repo_search::repomd::model::RpmFileType;
+use rpm_repo_search::storage::{FindFilter, SortField};
 use rpm_repo_search::sync;
 
 use clap::Parser;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tracing::info;
 
 const CACHE_DIR: &str = ".cache/dpa";
@@ -21,6 +23,15 @@ struct Cli {
     /// Package name or glob pattern (e.g., "bash", "lib*ssl*")
     package: Option<String>,
 
+    /// Match the package name exactly instead of as a substring (e.g., "bash" but not "bash-completion")
+    #[arg(long, requires = "package")]
+    name_exact: bool,
+
+    /// Treat the package name, --summary and --description as regular expressions
+    /// instead of globs (full table scan; at most 1000 results)
+    #[arg(long, conflicts_with = "name_exact")]
+    regex: bool,
+
     // -- GBS config --
     /// GBS configuration file path (default: ~/.gbs.conf)
     #[arg(long, value_name = "PATH")]
@@ -30,6 +41,10 @@ struct Cli {
     #[arg(long)]
     gbs_profile: Option<String>,
 
+    /// Directory for the package database (default: ~/.cache/dpa)
+    #[arg(long, env = "RPM_SEARCH_CACHE_DIR", value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+
     // -- Query mode --
     /// Find packages that provide a capability (e.g., "libssl.so*")
     #[arg(long)]
@@ -39,10 +54,22 @@ struct Cli {
     #[arg(long)]
     whatrequires: Option<String>,
 
+    /// Find packages that conflict with a capability
+    #[arg(long)]
+    whatconflicts: Option<String>,
+
+    /// Find packages that obsolete a capability (e.g., what replaces an old package)
+    #[arg(long)]
+    whatobsoletes: Option<String>,
+
     /// Find packages that own a specific file (e.g., "/usr/bin/python*", "*.so")
     #[arg(long)]
     file: Option<String>,
 
+    /// With --file, only match entries of this type (e.g., "dir" for owned directories)
+    #[arg(long, value_enum, requires = "file")]
+    file_type: Option<RpmFileType>,
+
     // -- Additional filters --
     /// Summary keyword pattern
     #[arg(short, long)]
@@ -69,29 +96,78 @@ struct Cli {
     #[arg(long)]
     provides: bool,
 
+    /// Show the source RPM (name-version-release.src.rpm) of matched packages
+    #[arg(long)]
+    source: bool,
+
     /// Custom output format (supports %{name}, %{version}, %{release}, %{epoch}, %{arch},
     /// %{summary}, %{description}, %{license}, %{repo}, %{vcs}, %{nevra},
-    /// %{location}, %{download_url})
+    /// %{sourcerpm}, %{location}, %{download_url})
     #[arg(long)]
     queryformat: Option<String>,
 
+    /// Machine-readable output: csv/tsv with a header row
+    /// (name, epoch, version, release, arch, repo, summary, license),
+    /// a JSON array, NDJSON (one package per line, streamed) or a YAML sequence
+    #[arg(long, value_enum, conflicts_with_all = ["info", "list", "requires", "provides", "source", "queryformat"])]
+    format: Option<OutputFormat>,
+
+    /// Indent --format json output for reading
+    #[arg(long, requires = "format")]
+    pretty: bool,
+
+    /// Download the matched RPMs (into --dest) instead of listing them;
+    /// files already present there are skipped
+    #[arg(long, conflicts_with_all = ["info", "list", "requires", "provides", "source", "queryformat", "format"])]
+    download: bool,
+
+    /// Directory for --download (created if missing)
+    #[arg(long, value_name = "DIR", default_value = ".", requires = "download")]
+    dest: PathBuf,
+
     // -- Filters --
-    /// Filter by architecture
+    /// Filter by architecture (can be specified multiple times)
     #[arg(short, long)]
-    arch: Option<String>,
+    arch: Vec<String>,
 
     /// Filter by repository (can be specified multiple times)
     #[arg(long)]
     repo: Vec<String>,
 
-    /// Show only the latest version per package name+arch
-    #[arg(long)]
+    /// Show only the latest version per package name+arch (same as --latest-n 1)
+    #[arg(long, group = "latest_filter")]
     latest: bool,
 
-    /// Maximum results
+    /// Show the K newest versions per package name+arch
+    #[arg(long, value_name = "K", group = "latest_filter", value_parser = clap::value_parser!(u32).range(1..))]
+    latest_n: Option<u32>,
+
+    /// With --latest/--latest-n, prefer packages from earlier repos when versions
+    /// are equal (e.g., "updates,base"); unlisted repos come last
+    #[arg(long, value_delimiter = ',', requires = "latest_filter")]
+    repo_priority: Vec<String>,
+
+    /// Show localized summary/description for this language where available (e.g., "de")
+    #[arg(long)]
+    lang: Option<String>,
+
+    /// Maximum results (0 = no limit)
     #[arg(long, default_value = "200")]
     limit: usize,
 
+    /// Result ordering (version sorting loads every match into memory first)
+    #[arg(long, value_enum, default_value = "name")]
+    sort: SortField,
+
+    /// Reverse the --sort ordering
+    #[arg(long)]
+    reverse: bool,
+
+    /// List each package's versions newest first and each version's arches
+    /// in this order (e.g., "x86_64,i686,noarch"); unlisted arches follow
+    #[arg(long, value_delimiter = ',', value_name = "ARCHES")]
+    arch_order: Vec<String>,
+
     /// Skip repository sync (use cached database only)
     #[arg(long)]
     no_sync: bool,
@@ -104,11 +180,14 @@ fn default_gbs_conf_path() -> Result<PathBuf> {
     Ok(home.join(".gbs.conf"))
 }
 
-/// Get DB path at ~/.cache/dpa/packages.db
-fn get_db_path() -> Result<PathBuf> {
-    let home = dirs::home_dir()
-        .ok_or_else(|| RpmSearchError::Config("Cannot determine home directory".to_string()))?;
-    let cache_dir = home.join(CACHE_DIR);
+/// Get DB path at <cache_dir>/packages.db (default: ~/.cache/dpa/packages.db)
+fn get_db_path(cache_dir: Option<&Path>) -> Result<PathBuf> {
+    let cache_dir = match cache_dir {
+        Some(dir) => dir.to_path_buf(),
+        None => dirs::home_dir()
+            .ok_or_else(|| RpmSearchError::Config("Cannot determine home directory".to_string()))?
+            .join(CACHE_DIR),
+    };
     std::fs::create_dir_all(&cache_dir).map_err(RpmSearchError::Io)?;
     Ok(cache_dir.join(DB_FILENAME))
 }
@@ -138,7 +217,8 @@ fn sync_repos(gbs_config: &gbs::GbsConfig, profile: Option<&str>, config: &Confi
         let state_conn = rusqlite::Connection::open(&config.db_path)?;
         let state_store = sync::SyncStateStore::new(state_conn)?;
 
-        let mut syncer = sync::syncer::RepoSyncer::new(api, state_store, work_dir.clone())?;
+        let mut syncer =
+            sync::syncer::RepoSyncer::new(api, state_store, work_dir.clone())?.with_progress(true);
 
         match syncer.sync_repository(repo_config) {
             Ok(result) => {
@@ -179,6 +259,7 @@ fn format_querystring(fmt: &str, pkg: &Package, download_url: Option<&str>) -> S
         .replace("%{license}", pkg.license.as_deref().unwrap_or(""))
         .replace("%{repo}", &pkg.repo)
         .replace("%{vcs}", pkg.vcs.as_deref().unwrap_or(""))
+        .replace("%{sourcerpm}", pkg.sourcerpm.as_deref().unwrap_or(""))
         .replace("%{location}", pkg.location_href.as_deref().unwrap_or(""))
         .replace("%{download_url}", download_url.unwrap_or(""))
         .replace(
@@ -189,24 +270,60 @@ fn format_querystring(fmt: &str, pkg: &Package, download_url: Option<&str>) -> S
         .replace("\\t", "\t")
 }
 
-fn filter_latest(packages: Vec<Package>) -> Vec<Package> {
-    let mut latest_map: HashMap<(String, String), Package> = HashMap::new();
+/// Fetch each package's RPM from its repo's base URL into `dest`, skipping
+/// files that already exist there (by name) and packages without a known URL
+fn download_packages(
+    state_store: &sync::SyncStateStore,
+    packages: &[Package],
+    dest: &Path,
+) -> Result<()> {
+    std::fs::create_dir_all(dest)?;
+    let http = reqwest::blocking::Client::builder()
+        .build()
+        .map_err(|e| RpmSearchError::Fetch(format!("Failed to build HTTP client: {}", e)))?;
+
+    let (mut downloaded, mut skipped, mut total_bytes) = (0usize, 0usize, 0u64);
     for pkg in packages {
-        let key = (pkg.name.clone(), pkg.arch.clone());
-        match latest_map.entry(key) {
-            std::collections::hash_map::Entry::Vacant(e) => {
-                e.insert(pkg);
-            }
-            std::collections::hash_map::Entry::Occupied(mut e) => {
-                if pkg > *e.get() {
-                    e.insert(pkg);
-                }
-            }
+        let nevra = format!("{}-{}.{}", pkg.name, pkg.full_version(), pkg.arch);
+        let (Some(url), Some(filename)) = (
+            build_download_url(state_store, pkg),
+            pkg.location_href
+                .as_deref()
+                .and_then(|loc| loc.rsplit('/').next()),
+        ) else {
+            eprintln!("Skipping {}: no download URL (repo has no base URL)", nevra);
+            skipped += 1;
+            continue;
+        };
+
+        let path = dest.join(filename);
+        if path.exists() {
+            println!("{} already present", filename);
+            skipped += 1;
+            continue;
         }
+
+        let response = http
+            .get(&url)
+            .send()
+            .map_err(|e| RpmSearchError::Fetch(format!("HTTP request failed: {}", e)))?
+            .error_for_status()
+            .map_err(|e| RpmSearchError::Fetch(format!("HTTP status error: {}", e)))?;
+        let bytes = RepoFetcher::save_download(response, &path)?;
+
+        println!("{} ({} bytes)", filename, bytes);
+        downloaded += 1;
+        total_bytes += bytes;
     }
-    let mut result: Vec<Package> = latest_map.into_values().collect();
-    result.sort();
-    result
+
+    println!(
+        "Downloaded {} package(s), {} bytes to {} ({} skipped)",
+        downloaded,
+        total_bytes,
+        dest.display(),
+        skipped
+    );
+    Ok(())
 }
 
 fn build_download_url(state_store: &sync::SyncStateStore, pkg: &Package) -> Option<String> {
@@ -219,7 +336,14 @@ fn build_download_url(state_store: &sync::SyncStateStore, pkg: &Package) -> Opti
     ))
 }
 
-fn main() -> Result<()> {
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        std::process::exit(e.exit_code());
+    }
+}
+
+fn run() -> Result<()> {
     // Restore default SIGPIPE handling so piping to head/grep etc. exits cleanly
     #[cfg(unix)]
     unsafe {
@@ -248,7 +372,7 @@ fn main() -> Result<()> {
     let gbs_config = gbs::GbsConfig::from_path(&gbs_conf_path)?;
 
     // 3. Resolve DB path
-    let db_path = get_db_path()?;
+    let db_path = get_db_path(cli.cache_dir.as_deref())?;
     let config = Config::new(db_path);
 
     // 4. Sync repositories (unless --no-sync)
@@ -274,14 +398,21 @@ fn main() -> Result<()> {
 
     let filter = FindFilter {
         name: cli.package.clone(),
+        name_exact: cli.name_exact,
+        regex: cli.regex,
         summary: cli.summary,
         description: cli.description,
         provides: cli.whatprovides.clone(),
         requires: cli.whatrequires.clone(),
+        conflicts: cli.whatconflicts.clone(),
+        obsoletes: cli.whatobsoletes.clone(),
         file: cli.file.clone(),
-        arch: cli.arch.clone(),
+        file_type: cli.file_type,
+        arches: cli.arch.clone(),
         repos: repos.clone(),
         limit: cli.limit,
+        sort: cli.sort,
+        reverse: cli.reverse,
     };
 
     let has_query = filter.name.is_some()
@@ -289,25 +420,55 @@ fn main() -> Result<()> {
         || filter.description.is_some()
         || filter.provides.is_some()
         || filter.requires.is_some()
+        || filter.conflicts.is_some()
+        || filter.obsoletes.is_some()
         || filter.file.is_some()
-        || filter.arch.is_some();
+        || !filter.arches.is_empty();
 
-    let mut packages = if has_query {
-        api.find(&filter)?
+    let filter = if has_query {
+        filter
     } else {
         // No query specified: list all packages in the filtered repos
-        let all_filter = FindFilter {
+        FindFilter {
             name: Some("*".to_string()),
-            repos,
-            limit: cli.limit,
-            ..Default::default()
-        };
-        api.find(&all_filter)?
+            ..filter
+        }
     };
 
-    // Filter: --latest
-    if cli.latest {
-        packages = filter_latest(packages);
+    // NDJSON streams each package as it is loaded (--latest and --arch-order
+    // need them all first)
+    let latest_n = if cli.latest { Some(1) } else { cli.latest_n };
+    if cli.format == Some(OutputFormat::Ndjson) && latest_n.is_none() && cli.arch_order.is_empty() {
+        let mut writer =
+            output::PackageWriter::new(std::io::stdout().lock(), OutputFormat::Ndjson, false)?;
+        api.find_each(&filter, |mut pkg| {
+            if let Some(ref lang) = cli.lang {
+                pkg.localize(lang);
+            }
+            writer.write(&pkg)
+        })?;
+        return writer.finish();
+    }
+
+    let mut packages = api.find(&filter)?;
+
+    // Filter: --latest / --latest-n
+    if let Some(k) = latest_n {
+        packages = filter_latest_n(packages, k as usize, &cli.repo_priority);
+        cli.sort.sort_packages(&mut packages, cli.reverse);
+    }
+    sort_by_arch_order(&mut packages, &cli.arch_order);
+
+    if let Some(ref lang) = cli.lang {
+        for pkg in &mut packages {
+            pkg.localize(lang);
+        }
+    }
+
+    // --format: machine-readable output (header only when nothing matched)
+    if let Some(format) = cli.format {
+        output::write_packages(std::io::stdout().lock(), format, cli.pretty, &packages)?;
+        return Ok(());
     }
 
     if packages.is_empty() {
@@ -317,6 +478,10 @@ fn main() -> Result<()> {
             println!("No packages found providing '{}'", cap);
         } else if let Some(ref cap) = cli.whatrequires {
             println!("No packages found requiring '{}'", cap);
+        } else if let Some(ref cap) = cli.whatconflicts {
+            println!("No packages found conflicting with '{}'", cap);
+        } else if let Some(ref cap) = cli.whatobsoletes {
+            println!("No packages found obsoleting '{}'", cap);
         } else if let Some(ref f) = cli.file {
             println!("No packages found owning '{}'", f);
         } else {
@@ -331,7 +496,9 @@ fn main() -> Result<()> {
         sync::SyncStateStore::new(conn)?
     };
 
-    if cli.info {
+    if cli.download {
+        download_packages(&state_store, &packages, &cli.dest)?;
+    } else if cli.info {
         for pkg in &packages {
             println!("Name        : {}", pkg.name);
             println!(
@@ -370,11 +537,7 @@ fn main() -> Result<()> {
                 println!("# {}-{}.{}", pkg.name, pkg.full_version(), pkg.arch);
             }
             for dep in &pkg.requires {
-                if let (Some(flags), Some(ver)) = (&dep.flags, &dep.version) {
-                    println!("{} {} {}", dep.name, flags, ver);
-                } else {
-                    println!("{}", dep.name);
-                }
+                println!("{}", dep);
             }
         }
     } else if cli.provides {
@@ -386,11 +549,7 @@ fn main() -> Result<()> {
                 println!("# {}-{}.{}", pkg.name, pkg.full_version(), pkg.arch);
             }
             for dep in &pkg.provides {
-                if let (Some(flags), Some(ver)) = (&dep.flags, &dep.version) {
-                    println!("{} {} {}", dep.name, flags, ver);
-                } else {
-                    println!("{}", dep.name);
-                }
+                println!("{}", dep);
             }
         }
     } else if cli.list {
@@ -416,6 +575,16 @@ fn main() -> Result<()> {
                 }
             }
         }
+    } else if cli.source {
+        // Source RPM of each package, each listed once
+        let mut seen = std::collections::HashSet::new();
+        for pkg in &packages {
+            if let Some(ref srpm) = pkg.sourcerpm {
+                if seen.insert(srpm.as_str()) {
+                    println!("{}", srpm);
+                }
+            }
+        }
     } else if let Some(ref fmt) = cli.queryformat {
         for pkg in &packages {
             let url = build_download_url(&state_store, pkg);
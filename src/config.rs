@@ -1,8 +1,11 @@
+use crate::error::{Result, RpmSearchError};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 /// Embedding model type
-#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq, clap::ValueEnum)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq, Hash, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
 pub enum ModelType {
     /// all-MiniLM-L6-v2 (English, 384 dim, fast)
     #[default]
@@ -86,6 +89,373 @@ impl std::fmt::Display for ModelType {
     }
 }
 
+/// Which package fields go into the text that is embedded
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum EmbeddingTextMode {
+    /// Name and summary only (for repos with boilerplate descriptions)
+    Summary,
+    /// Name, summary, description and AppStream data, without provides/requires
+    NameSummary,
+    /// Everything, including provides and requires
+    #[default]
+    Full,
+}
+
+impl EmbeddingTextMode {
+    /// Mode string for DB metadata storage
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            Self::Summary => "summary",
+            Self::NameSummary => "name-summary",
+            Self::Full => "full",
+        }
+    }
+
+    /// Parse from DB metadata string
+    pub fn from_db_str(s: &str) -> Option<Self> {
+        match s {
+            "summary" => Some(Self::Summary),
+            "name-summary" => Some(Self::NameSummary),
+            "full" => Some(Self::Full),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for EmbeddingTextMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_db_str())
+    }
+}
+
+/// How vector search turns a cosine distance into the similarity score that
+/// is blended with the structured score
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScoreTransform {
+    /// Cosine similarity (`1 - distance`), clamped to [0, 1]
+    #[default]
+    Linear,
+    /// Cosine similarity without clamping (negative for opposed vectors)
+    Identity,
+    /// Logistic curve around a similarity of 0.5; a smaller temperature
+    /// separates strong matches from the rest more sharply
+    Sigmoid { temperature: f32 },
+}
+
+impl ScoreTransform {
+    /// Score for a vec0 cosine distance
+    pub fn apply(self, distance: f32) -> f32 {
+        let similarity = 1.0 - distance;
+        match self {
+            Self::Linear => similarity.clamp(0.0, 1.0),
+            Self::Identity => similarity,
+            Self::Sigmoid { temperature } => {
+                1.0 / (1.0 + (-(similarity - 0.5) / temperature.max(f32::EPSILON)).exp())
+            }
+        }
+    }
+}
+
+/// SQLite journal mode (`PRAGMA journal_mode`)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JournalMode {
+    /// Write-ahead log: readers are not blocked while a sync writes
+    #[default]
+    Wal,
+    /// Rollback journal (SQLite's default): a write blocks readers while it commits
+    Delete,
+    Truncate,
+    Persist,
+}
+
+/// How often SQLite waits for data to reach the disk (`PRAGMA synchronous`)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SynchronousMode {
+    Off,
+    /// With WAL, the database can't be corrupted, but the last commits before
+    /// a power loss or OS crash may be rolled back (an application crash loses nothing)
+    #[default]
+    Normal,
+    /// Every commit is durable, at the cost of an fsync per transaction
+    Full,
+}
+
+/// Pragmas applied to the package database connection
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SqlitePragmas {
+    pub journal_mode: JournalMode,
+    pub synchronous: SynchronousMode,
+    /// Page cache size in KiB
+    pub cache_size_kib: u32,
+}
+
+impl Default for SqlitePragmas {
+    fn default() -> Self {
+        Self {
+            journal_mode: JournalMode::Wal,
+            synchronous: SynchronousMode::Normal,
+            cache_size_kib: 64000,
+        }
+    }
+}
+
+impl SqlitePragmas {
+    /// The pragmas as an SQL batch (plus the fixed temp_store/mmap settings)
+    pub fn to_sql(&self) -> String {
+        let journal_mode = match self.journal_mode {
+            JournalMode::Wal => "WAL",
+            JournalMode::Delete => "DELETE",
+            JournalMode::Truncate => "TRUNCATE",
+            JournalMode::Persist => "PERSIST",
+        };
+        let synchronous = match self.synchronous {
+            SynchronousMode::Off => "OFF",
+            SynchronousMode::Normal => "NORMAL",
+            SynchronousMode::Full => "FULL",
+        };
+        // A negative cache_size is in KiB rather than pages
+        format!(
+            "PRAGMA journal_mode = {};
+             PRAGMA synchronous = {};
+             PRAGMA cache_size = -{};
+             PRAGMA temp_store = MEMORY;
+             PRAGMA mmap_size = 268435456;",
+            journal_mode, synchronous, self.cache_size_kib
+        )
+    }
+}
+
+/// Relative weights of the structured (name match) and semantic (vector)
+/// scores in hybrid search. Always non-negative and summing to 1.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(try_from = "RawScoringWeights")]
+pub struct ScoringWeights {
+    pub structured: f32,
+    pub semantic: f32,
+}
+
+impl Default for ScoringWeights {
+    fn default() -> Self {
+        Self {
+            structured: 0.45,
+            semantic: 0.55,
+        }
+    }
+}
+
+impl ScoringWeights {
+    /// Validate the weights and scale them to sum to 1
+    pub fn new(structured: f32, semantic: f32) -> Result<Self> {
+        if !(structured.is_finite() && semantic.is_finite() && structured >= 0.0 && semantic >= 0.0)
+        {
+            return Err(RpmSearchError::Config(format!(
+                "Scoring weights must be non-negative numbers (structured={}, semantic={})",
+                structured, semantic
+            )));
+        }
+        let sum = structured + semantic;
+        if sum <= 0.0 {
+            return Err(RpmSearchError::Config(
+                "Scoring weights must not both be zero".to_string(),
+            ));
+        }
+        Ok(Self {
+            structured: structured / sum,
+            semantic: semantic / sum,
+        })
+    }
+}
+
+impl std::str::FromStr for ScoringWeights {
+    type Err = RpmSearchError;
+
+    /// Parse `structured=X,semantic=Y`
+    fn from_str(s: &str) -> Result<Self> {
+        let (mut structured, mut semantic) = (None, None);
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = part.split_once('=').ok_or_else(|| {
+                RpmSearchError::Config(format!("Invalid weight '{}' (expected key=value)", part))
+            })?;
+            let value: f32 = value.trim().parse().map_err(|_| {
+                RpmSearchError::Config(format!("Invalid weight value '{}'", value.trim()))
+            })?;
+            match key.trim() {
+                "structured" => structured = Some(value),
+                "semantic" => semantic = Some(value),
+                other => {
+                    return Err(RpmSearchError::Config(format!(
+                        "Unknown weight '{}' (expected structured or semantic)",
+                        other
+                    )))
+                }
+            }
+        }
+        match (structured, semantic) {
+            (Some(structured), Some(semantic)) => Self::new(structured, semantic),
+            _ => Err(RpmSearchError::Config(
+                "Both weights are required: structured=X,semantic=Y".to_string(),
+            )),
+        }
+    }
+}
+
+/// Unvalidated weights as written in a config file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawScoringWeights {
+    structured: f32,
+    semantic: f32,
+}
+
+impl TryFrom<RawScoringWeights> for ScoringWeights {
+    type Error = RpmSearchError;
+
+    fn try_from(raw: RawScoringWeights) -> Result<Self> {
+        Self::new(raw.structured, raw.semantic)
+    }
+}
+
+/// Per-repository multipliers applied to hybrid search scores, so results
+/// from a preferred repo rank higher without filtering others out
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RepoWeights(pub HashMap<String, f32>);
+
+impl RepoWeights {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Weight of `repo` (1.0 for repos not listed)
+    pub fn weight(&self, repo: &str) -> f32 {
+        self.0.get(repo).copied().unwrap_or(1.0)
+    }
+}
+
+impl std::str::FromStr for RepoWeights {
+    type Err = RpmSearchError;
+
+    /// Parse `repoA=1.2,repoB=0.8`
+    fn from_str(s: &str) -> Result<Self> {
+        let mut weights = HashMap::new();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (repo, value) = part.split_once('=').ok_or_else(|| {
+                RpmSearchError::Config(format!(
+                    "Invalid repo weight '{}' (expected repo=weight)",
+                    part
+                ))
+            })?;
+            let value: f32 = value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|v: &f32| v.is_finite() && *v >= 0.0)
+                .ok_or_else(|| {
+                    RpmSearchError::Config(format!(
+                        "Invalid repo weight '{}' (expected a non-negative number)",
+                        value.trim()
+                    ))
+                })?;
+            weights.insert(repo.trim().to_string(), value);
+        }
+        Ok(Self(weights))
+    }
+}
+
+/// Scores of the name-search match tiers (structured relevance), each in 0..=1.
+/// A package matching several tiers gets its best score.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(try_from = "RawStructuredWeights")]
+pub struct StructuredWeights {
+    /// Name equals the query (case-insensitive)
+    pub exact: f32,
+    /// Name starts with the query
+    pub prefix: f32,
+    /// Name contains the query
+    pub contains: f32,
+    /// Summary contains the query
+    pub summary: f32,
+    /// A provided capability contains the query
+    pub provides: f32,
+    /// Description contains the query
+    pub description: f32,
+}
+
+impl Default for StructuredWeights {
+    fn default() -> Self {
+        Self {
+            exact: 1.0,
+            prefix: 0.85,
+            contains: 0.7,
+            summary: 0.5,
+            provides: 0.45,
+            description: 0.35,
+        }
+    }
+}
+
+/// Structured weights as written in a config file; missing keys keep their default
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawStructuredWeights {
+    exact: f32,
+    prefix: f32,
+    contains: f32,
+    summary: f32,
+    provides: f32,
+    description: f32,
+}
+
+impl Default for RawStructuredWeights {
+    fn default() -> Self {
+        let d = StructuredWeights::default();
+        Self {
+            exact: d.exact,
+            prefix: d.prefix,
+            contains: d.contains,
+            summary: d.summary,
+            provides: d.provides,
+            description: d.description,
+        }
+    }
+}
+
+impl TryFrom<RawStructuredWeights> for StructuredWeights {
+    type Error = RpmSearchError;
+
+    fn try_from(raw: RawStructuredWeights) -> Result<Self> {
+        let weights = Self {
+            exact: raw.exact,
+            prefix: raw.prefix,
+            contains: raw.contains,
+            summary: raw.summary,
+            provides: raw.provides,
+            description: raw.description,
+        };
+        let values = [
+            ("exact", weights.exact),
+            ("prefix", weights.prefix),
+            ("contains", weights.contains),
+            ("summary", weights.summary),
+            ("provides", weights.provides),
+            ("description", weights.description),
+        ];
+        for (name, value) in values {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(RpmSearchError::Config(format!(
+                    "Structured weight '{}' must be between 0 and 1 (got {})",
+                    name, value
+                )));
+            }
+        }
+        Ok(weights)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     /// Database file path
@@ -94,33 +464,132 @@ pub struct Config {
     /// Embedding model type
     pub model_type: ModelType,
 
+    /// HuggingFace revision (branch, tag or commit) to download the model
+    /// from (None = default branch)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_revision: Option<String>,
+
     /// Embedding model path (local)
     pub model_path: PathBuf,
 
     /// Tokenizer path (local)
     pub tokenizer_path: PathBuf,
 
-    /// Vector dimension (384 for both MiniLM-L6-v2 and multilingual-e5-small)
+    /// Vector dimension (384 for both MiniLM-L6-v2 and multilingual-e5-small).
+    /// When building embeddings, the loaded model's hidden size takes precedence.
     pub embedding_dim: usize,
 
-    /// Batch size for embedding
-    pub batch_size: usize,
+    /// Batch size for embedding (None = auto-tune for the selected device)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_size: Option<usize>,
+
+    /// Package fields included in the embedded text
+    #[serde(default)]
+    pub embedding_text_mode: EmbeddingTextMode,
+
+    /// Boilerplate phrases removed from descriptions before embedding
+    /// (matched ignoring ASCII case; empty = keep descriptions as they are)
+    #[serde(default = "default_embedding_boilerplate")]
+    pub embedding_boilerplate: Vec<String>,
 
     /// Top-N results for vector search
     pub top_k: usize,
+
+    /// Queries slower than this (in milliseconds) are logged as warnings (0 = disabled)
+    pub slow_query_ms: u64,
+
+    /// Distance-to-score transform for vector search results
+    #[serde(default)]
+    pub score_transform: ScoreTransform,
+
+    /// Weights of the structured and semantic scores in hybrid search
+    #[serde(default)]
+    pub scoring_weights: ScoringWeights,
+
+    /// Scores of the structured (name search) match tiers
+    #[serde(default)]
+    pub structured_weights: StructuredWeights,
+
+    /// Queries shorter than this many characters skip semantic search and
+    /// only match package names
+    #[serde(default = "default_min_semantic_query_len")]
+    pub min_semantic_query_len: usize,
+
+    /// Match equivalent arch names (`arm64` = `aarch64`) in arch filters
+    #[serde(default = "default_arch_alias")]
+    pub arch_alias: bool,
+
+    /// Store packages under the canonical arch name when indexing
+    #[serde(default)]
+    pub normalize_arch: bool,
+
+    /// Directory for the default database and the HuggingFace model cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Seconds an MCP tool call may run before a timeout error is returned (0 = no limit)
+    #[serde(default = "default_mcp_tool_timeout_secs")]
+    pub mcp_tool_timeout_secs: u64,
+
+    /// SQLite journal/sync/cache pragmas for the package database
+    #[serde(default)]
+    pub sqlite: SqlitePragmas,
+}
+
+/// Database file name used when no explicit path is configured
+const DEFAULT_DB_FILENAME: &str = "rpm_search.db";
+
+fn default_arch_alias() -> bool {
+    true
+}
+
+/// Openers shared by many descriptions that pull unrelated embeddings together
+fn default_embedding_boilerplate() -> Vec<String> {
+    [
+        "This package contains",
+        "This package provides",
+        "This package includes",
+        "This package is",
+        "The package contains",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+/// One- and two-letter queries embed to noise; name matching serves them better
+fn default_min_semantic_query_len() -> usize {
+    3
+}
+
+/// Long enough for the first semantic search, which also loads the model
+fn default_mcp_tool_timeout_secs() -> u64 {
+    30
 }
 
 impl Default for Config {
     fn default() -> Self {
         let model_type = ModelType::default();
         Self {
-            db_path: PathBuf::from("rpm_search.db"),
+            db_path: PathBuf::from(DEFAULT_DB_FILENAME),
             model_path: model_type.default_model_path(),
             tokenizer_path: model_type.default_tokenizer_path(),
             model_type,
+            model_revision: None,
             embedding_dim: 384,
-            batch_size: 32,
+            batch_size: None,
+            embedding_text_mode: EmbeddingTextMode::default(),
+            embedding_boilerplate: default_embedding_boilerplate(),
             top_k: 50,
+            slow_query_ms: 500,
+            score_transform: ScoreTransform::default(),
+            scoring_weights: ScoringWeights::default(),
+            structured_weights: StructuredWeights::default(),
+            min_semantic_query_len: default_min_semantic_query_len(),
+            arch_alias: true,
+            normalize_arch: false,
+            cache_dir: None,
+            mcp_tool_timeout_secs: default_mcp_tool_timeout_secs(),
+            sqlite: SqlitePragmas::default(),
         }
     }
 }
@@ -133,6 +602,133 @@ impl Config {
         }
     }
 
+    /// Default config file location (`~/.config/rpm-search/config.toml`)
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".config/rpm-search/config.toml"))
+    }
+
+    /// Load configuration from a TOML file, using built-in defaults for missing keys
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let mut config = Self::default();
+        config.apply(ConfigOverrides::from_file(path)?);
+        Ok(config)
+    }
+
+    /// Build the effective configuration.
+    ///
+    /// CLI flags override values from the config file, which override the
+    /// built-in defaults. A missing config file is not an error.
+    pub fn load_with_overrides(path: Option<&Path>, cli: ConfigOverrides) -> Result<Self> {
+        let mut config = Self::default();
+        if let Some(path) = path.filter(|p| p.exists()) {
+            config.apply(ConfigOverrides::from_file(path)?);
+        }
+        config.apply(cli);
+        Ok(config)
+    }
+
+    /// Save configuration to a TOML file, creating parent directories
+    pub fn to_file(&self, path: &Path) -> Result<()> {
+        let content = toml::to_string_pretty(self)
+            .map_err(|e| RpmSearchError::Config(format!("Failed to serialize config: {}", e)))?;
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, content)?;
+
+        Ok(())
+    }
+
+    /// Apply the values that are set in `overrides`.
+    ///
+    /// Changing the model type without explicit paths also switches the model
+    /// and tokenizer paths to that model's defaults, and drops a revision pinned
+    /// for the previous model. Likewise, a cache directory moves a database
+    /// that is still at its default path into that directory.
+    pub fn apply(&mut self, overrides: ConfigOverrides) {
+        if let Some(cache_dir) = overrides.cache_dir {
+            if self.db_path == Path::new(DEFAULT_DB_FILENAME) {
+                self.db_path = cache_dir.join(DEFAULT_DB_FILENAME);
+            }
+            self.cache_dir = Some(cache_dir);
+        }
+        if let Some(db_path) = overrides.db_path {
+            self.db_path = db_path;
+        }
+        if let Some(model_type) = overrides.model_type {
+            self.model_path = model_type.default_model_path();
+            self.tokenizer_path = model_type.default_tokenizer_path();
+            if model_type != self.model_type {
+                // A revision names a commit of one model's repository
+                self.model_revision = None;
+            }
+            self.model_type = model_type;
+        }
+        if overrides.model_revision.is_some() {
+            self.model_revision = overrides.model_revision;
+        }
+        if let Some(model_path) = overrides.model_path {
+            self.model_path = model_path;
+        }
+        if let Some(tokenizer_path) = overrides.tokenizer_path {
+            self.tokenizer_path = tokenizer_path;
+        }
+        if let Some(embedding_dim) = overrides.embedding_dim {
+            self.embedding_dim = embedding_dim;
+        }
+        if overrides.batch_size.is_some() {
+            self.batch_size = overrides.batch_size;
+        }
+        if let Some(mode) = overrides.embedding_text_mode {
+            self.embedding_text_mode = mode;
+        }
+        if let Some(boilerplate) = overrides.embedding_boilerplate {
+            self.embedding_boilerplate = boilerplate;
+        }
+        if let Some(top_k) = overrides.top_k {
+            self.top_k = top_k;
+        }
+        if let Some(slow_query_ms) = overrides.slow_query_ms {
+            self.slow_query_ms = slow_query_ms;
+        }
+        if let Some(score_transform) = overrides.score_transform {
+            self.score_transform = score_transform;
+        }
+        if let Some(scoring_weights) = overrides.scoring_weights {
+            self.scoring_weights = scoring_weights;
+        }
+        if let Some(structured_weights) = overrides.structured_weights {
+            self.structured_weights = structured_weights;
+        }
+        if let Some(len) = overrides.min_semantic_query_len {
+            self.min_semantic_query_len = len;
+        }
+        if let Some(arch_alias) = overrides.arch_alias {
+            self.arch_alias = arch_alias;
+        }
+        if let Some(normalize_arch) = overrides.normalize_arch {
+            self.normalize_arch = normalize_arch;
+        }
+        if let Some(timeout) = overrides.mcp_tool_timeout_secs {
+            self.mcp_tool_timeout_secs = timeout;
+        }
+        if let Some(sqlite) = overrides.sqlite {
+            self.sqlite = sqlite;
+        }
+    }
+
+    /// Model directory, if it was changed from the model type's default
+    pub fn custom_model_path(&self) -> Option<&Path> {
+        (self.model_path != self.model_type.default_model_path()).then_some(&self.model_path)
+    }
+
+    /// Tokenizer file, if it was changed from the model type's default
+    pub fn custom_tokenizer_path(&self) -> Option<&Path> {
+        (self.tokenizer_path != self.model_type.default_tokenizer_path())
+            .then_some(&self.tokenizer_path)
+    }
+
     /// Create config with a specific model type, using its default paths
     #[allow(dead_code)]
     pub fn with_model_type(mut self, model_type: ModelType) -> Self {
@@ -142,3 +738,239 @@ impl Config {
         self
     }
 }
+
+/// Partial configuration, as read from a config file or collected from CLI flags.
+///
+/// Unset fields leave the underlying value untouched (see [`Config::apply`]).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigOverrides {
+    pub db_path: Option<PathBuf>,
+    pub model_type: Option<ModelType>,
+    pub model_revision: Option<String>,
+    pub model_path: Option<PathBuf>,
+    pub tokenizer_path: Option<PathBuf>,
+    pub embedding_dim: Option<usize>,
+    pub batch_size: Option<usize>,
+    pub embedding_text_mode: Option<EmbeddingTextMode>,
+    pub embedding_boilerplate: Option<Vec<String>>,
+    pub top_k: Option<usize>,
+    pub slow_query_ms: Option<u64>,
+    pub score_transform: Option<ScoreTransform>,
+    pub scoring_weights: Option<ScoringWeights>,
+    pub structured_weights: Option<StructuredWeights>,
+    pub min_semantic_query_len: Option<usize>,
+    pub arch_alias: Option<bool>,
+    pub normalize_arch: Option<bool>,
+    pub cache_dir: Option<PathBuf>,
+    pub mcp_tool_timeout_secs: Option<u64>,
+    pub sqlite: Option<SqlitePragmas>,
+}
+
+impl ConfigOverrides {
+    /// Read overrides from a TOML config file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+
+        toml::from_str(&content).map_err(|e| {
+            RpmSearchError::Config(format!("Invalid config file {}: {}", path.display(), e))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_with_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            "model_type = \"e5-multilingual\"\ntop_k = 20\nslow_query_ms = 100\n",
+        )
+        .unwrap();
+
+        let cli = ConfigOverrides {
+            slow_query_ms: Some(0),
+            arch_alias: Some(false),
+            ..Default::default()
+        };
+        let config = Config::load_with_overrides(Some(&path), cli).unwrap();
+
+        // File overrides defaults
+        assert_eq!(config.model_type, ModelType::E5Multilingual);
+        assert_eq!(
+            config.model_path,
+            ModelType::E5Multilingual.default_model_path()
+        );
+        assert!(config.custom_model_path().is_none());
+        assert_eq!(config.top_k, 20);
+        // CLI overrides file
+        assert_eq!(config.slow_query_ms, 0);
+        assert!(!config.arch_alias);
+        // Untouched keys keep built-in defaults
+        assert_eq!(config.db_path, PathBuf::from("rpm_search.db"));
+        assert!(!config.normalize_arch);
+
+        // A missing file falls back to defaults
+        let missing = dir.path().join("missing.toml");
+        let config = Config::load_with_overrides(Some(&missing), Default::default()).unwrap();
+        assert_eq!(config.top_k, 50);
+    }
+
+    #[test]
+    fn test_cache_dir_moves_default_db() {
+        let config = Config::load_with_overrides(
+            None,
+            ConfigOverrides {
+                cache_dir: Some(PathBuf::from("/tmp/ci-cache")),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(config.db_path, PathBuf::from("/tmp/ci-cache/rpm_search.db"));
+
+        // An explicit database path wins
+        let config = Config::load_with_overrides(
+            None,
+            ConfigOverrides {
+                db_path: Some(PathBuf::from("other.db")),
+                cache_dir: Some(PathBuf::from("/tmp/ci-cache")),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(config.db_path, PathBuf::from("other.db"));
+        assert_eq!(config.cache_dir, Some(PathBuf::from("/tmp/ci-cache")));
+    }
+
+    #[test]
+    fn test_model_revision_follows_model_type() {
+        let mut config = Config::default();
+        config.apply(ConfigOverrides {
+            model_type: Some(ModelType::E5Multilingual),
+            model_revision: Some("abc123".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(config.model_revision.as_deref(), Some("abc123"));
+
+        // Re-stating the same model keeps the pin; switching models drops it
+        config.apply(ConfigOverrides {
+            model_type: Some(ModelType::E5Multilingual),
+            ..Default::default()
+        });
+        assert_eq!(config.model_revision.as_deref(), Some("abc123"));
+        config.apply(ConfigOverrides {
+            model_type: Some(ModelType::Minilm),
+            ..Default::default()
+        });
+        assert_eq!(config.model_revision, None);
+    }
+
+    #[test]
+    fn test_config_file_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested/config.toml");
+        let config = Config {
+            batch_size: Some(32),
+            score_transform: ScoreTransform::Sigmoid { temperature: 0.1 },
+            ..Config::default().with_model_type(ModelType::E5Multilingual)
+        };
+        config.to_file(&path).unwrap();
+
+        let loaded = Config::from_file(&path).unwrap();
+        assert_eq!(loaded.model_type, ModelType::E5Multilingual);
+        assert_eq!(loaded.batch_size, Some(32));
+        assert_eq!(
+            loaded.score_transform,
+            ScoreTransform::Sigmoid { temperature: 0.1 }
+        );
+
+        std::fs::write(&path, "unknown_key = 1\n").unwrap();
+        assert!(Config::from_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_score_transform() {
+        assert_eq!(ScoreTransform::Linear.apply(0.25), 0.75);
+        assert_eq!(ScoreTransform::Linear.apply(1.5), 0.0);
+        assert_eq!(ScoreTransform::Identity.apply(1.5), -0.5);
+
+        let sigmoid = ScoreTransform::Sigmoid { temperature: 0.1 };
+        assert!((sigmoid.apply(0.5) - 0.5).abs() < 1e-6);
+        // Sharper than linear on both sides of the midpoint
+        assert!(sigmoid.apply(0.2) > 0.95);
+        assert!(sigmoid.apply(0.8) < 0.05);
+
+        let parsed: ConfigOverrides =
+            toml::from_str("score_transform = { sigmoid = { temperature = 0.2 } }").unwrap();
+        assert_eq!(
+            parsed.score_transform,
+            Some(ScoreTransform::Sigmoid { temperature: 0.2 })
+        );
+    }
+
+    #[test]
+    fn test_scoring_weights() {
+        let weights: ScoringWeights = "structured=0.3,semantic=0.7".parse().unwrap();
+        assert_eq!(weights, ScoringWeights::new(0.3, 0.7).unwrap());
+        assert!((weights.structured - 0.3).abs() < 1e-6);
+
+        // Normalized to sum to 1
+        let weights: ScoringWeights = "semantic=3, structured=1".parse().unwrap();
+        assert!((weights.structured - 0.25).abs() < 1e-6);
+        assert!((weights.semantic - 0.75).abs() < 1e-6);
+
+        assert!("structured=-0.1,semantic=1"
+            .parse::<ScoringWeights>()
+            .is_err());
+        assert!("structured=0,semantic=0".parse::<ScoringWeights>().is_err());
+        assert!("structured=0.5".parse::<ScoringWeights>().is_err());
+        assert!("lexical=0.5,semantic=0.5"
+            .parse::<ScoringWeights>()
+            .is_err());
+
+        let repo_weights: RepoWeights = "curated=1.2, mirror=0.8".parse().unwrap();
+        assert!((repo_weights.weight("curated") - 1.2).abs() < 1e-6);
+        assert!((repo_weights.weight("mirror") - 0.8).abs() < 1e-6);
+        assert_eq!(repo_weights.weight("other"), 1.0);
+        assert!("curated".parse::<RepoWeights>().is_err());
+        assert!("curated=-1".parse::<RepoWeights>().is_err());
+
+        let parsed: ConfigOverrides =
+            toml::from_str("scoring_weights = { structured = 2.0, semantic = 2.0 }").unwrap();
+        assert_eq!(
+            parsed.scoring_weights,
+            Some(ScoringWeights::new(0.5, 0.5).unwrap())
+        );
+        assert!(toml::from_str::<ConfigOverrides>(
+            "scoring_weights = { structured = -1.0, semantic = 1.0 }"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_structured_weights_partial() {
+        let parsed: ConfigOverrides =
+            toml::from_str("structured_weights = { provides = 0.9 }").unwrap();
+        let weights = parsed.structured_weights.unwrap();
+        assert_eq!(weights.provides, 0.9);
+        assert_eq!(weights.exact, StructuredWeights::default().exact);
+        assert!(
+            toml::from_str::<ConfigOverrides>("structured_weights = { summary = 1.5 }").is_err()
+        );
+        assert!(toml::from_str::<ConfigOverrides>("structured_weights = { name = 0.5 }").is_err());
+    }
+
+    #[test]
+    fn test_sqlite_pragmas_partial() {
+        let parsed: ConfigOverrides = toml::from_str("[sqlite]\nsynchronous = \"full\"").unwrap();
+        let pragmas = parsed.sqlite.unwrap();
+        assert_eq!(pragmas.synchronous, SynchronousMode::Full);
+        assert_eq!(pragmas.journal_mode, JournalMode::Wal);
+        assert!(pragmas.to_sql().contains("PRAGMA synchronous = FULL;"));
+        assert!(toml::from_str::<ConfigOverrides>("[sqlite]\nsynchronous = \"fast\"").is_err());
+    }
+}
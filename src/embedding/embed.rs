@@ -1,8 +1,10 @@
-use crate::config::ModelType;
+use crate::config::{Config, ModelType};
 use crate::embedding::hub::ModelFiles;
 use crate::embedding::model::EmbeddingModel;
 use crate::error::{Result, RpmSearchError};
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
 
 use tokenizers::Tokenizer;
 
@@ -10,6 +12,7 @@ pub struct Embedder {
     model: EmbeddingModel,
     tokenizer: Tokenizer,
     model_type: ModelType,
+    revision: Option<String>,
 }
 
 impl Embedder {
@@ -43,6 +46,7 @@ impl Embedder {
             model,
             tokenizer,
             model_type,
+            revision: None,
         })
     }
 
@@ -57,14 +61,65 @@ impl Embedder {
             model,
             tokenizer,
             model_type,
+            revision: None,
         })
     }
 
+    /// Load `model_type`, from the config's model paths and pinned revision
+    /// when it is the configured model and from the hub's defaults otherwise
+    pub fn for_config(config: &Config, model_type: &ModelType) -> Result<Self> {
+        let (model, tokenizer) = if *model_type == config.model_type {
+            (config.custom_model_path(), config.custom_tokenizer_path())
+        } else {
+            (None, None)
+        };
+        let revision = Self::config_revision(config, model_type);
+        let files = crate::embedding::hub::resolve_model_files(
+            model_type,
+            model,
+            tokenizer,
+            revision.as_deref(),
+        )?;
+        Ok(Self::from_model_files(&files, model_type.clone())?.with_revision(revision))
+    }
+
+    /// The revision [`Embedder::for_config`] loads `model_type` at: the pinned
+    /// `model_revision` for the configured model, unless it comes from local
+    /// files (which have no known revision)
+    pub fn config_revision(config: &Config, model_type: &ModelType) -> Option<String> {
+        config
+            .model_revision
+            .clone()
+            .filter(|_| *model_type == config.model_type && config.custom_model_path().is_none())
+    }
+
+    /// Record the HuggingFace revision the model files were resolved from
+    pub fn with_revision(mut self, revision: Option<String>) -> Self {
+        self.revision = revision;
+        self
+    }
+
     /// Get the model type
     pub fn model_type(&self) -> &ModelType {
         &self.model_type
     }
 
+    /// HuggingFace revision of the model (None = default branch or local files)
+    pub fn revision(&self) -> Option<&str> {
+        self.revision.as_deref()
+    }
+
+    /// Dimension of the vectors this embedder produces, as reported by the
+    /// loaded model (authoritative over `Config::embedding_dim`)
+    pub fn embedding_dim(&self) -> usize {
+        self.model.hidden_size()
+    }
+
+    /// Recommended batch size for the device the model was loaded on
+    pub fn recommended_batch_size(&self) -> usize {
+        self.model.recommended_batch_size()
+    }
+
     /// Embed a single search query (auto-adds "query: " prefix for E5 models)
     pub fn embed_query(&self, text: &str) -> Result<Vec<f32>> {
         if self.model_type.requires_prefix() {
@@ -123,10 +178,69 @@ impl Embedder {
     }
 }
 
+/// Embedding models loaded for searches, kept so later searches reuse them.
+/// Several API instances can share one (the HTTP server does), so each model
+/// is loaded once per process.
+#[derive(Default)]
+pub struct ModelCache {
+    models: Mutex<HashMap<ModelKey, Arc<Embedder>>>,
+}
+
+/// A model and the HuggingFace revision it was loaded at
+type ModelKey = (ModelType, Option<String>);
+
+impl ModelCache {
+    /// The cached `model_type` at `revision`, loaded with `load` on first use.
+    /// Concurrent callers wait for a load in progress instead of repeating it.
+    pub fn get_or_load(
+        &self,
+        model_type: &ModelType,
+        revision: Option<&str>,
+        load: impl FnOnce() -> Result<Embedder>,
+    ) -> Result<Arc<Embedder>> {
+        let mut models = self.models.lock().unwrap_or_else(PoisonError::into_inner);
+        let key = (model_type.clone(), revision.map(str::to_string));
+        if let Some(embedder) = models.get(&key) {
+            return Ok(Arc::clone(embedder));
+        }
+        let embedder = Arc::new(load()?);
+        models.insert(key, Arc::clone(&embedder));
+        Ok(embedder)
+    }
+}
+
+/// Whether an embedding is unusable for cosine search: a NaN/infinite
+/// component or a (near-)zero norm
+pub fn is_degenerate(embedding: &[f32]) -> bool {
+    if embedding.iter().any(|x| !x.is_finite()) {
+        return true;
+    }
+    let norm_sq: f32 = embedding.iter().map(|x| x * x).sum();
+    norm_sq < 1e-12
+}
+
+/// Fixed unit vector stored in place of a degenerate embedding
+pub fn placeholder_embedding(dim: usize) -> Vec<f32> {
+    vec![1.0 / (dim.max(1) as f32).sqrt(); dim]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_degenerate_embeddings() {
+        assert!(is_degenerate(&[0.0; 4]));
+        assert!(is_degenerate(&[0.5, f32::NAN, 0.5, 0.5]));
+        assert!(!is_degenerate(&[0.5, 0.5, 0.5, 0.5]));
+
+        let placeholder = placeholder_embedding(384);
+        assert_eq!(placeholder.len(), 384);
+        assert!(!is_degenerate(&placeholder));
+        let norm: f32 = placeholder.iter().map(|x| x * x).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-4);
+    }
+
     #[test]
     #[ignore] // Requires model files to be present
     fn test_embedding_minilm() {
@@ -1,12 +1,19 @@
 use crate::config::ModelType;
 use crate::error::{Result, RpmSearchError};
 use hf_hub::api::tokio::{Api, ApiBuilder, ApiRepo};
+use hf_hub::{Repo, RepoType};
 use std::path::{Path, PathBuf};
 use tracing::{debug, info};
 
 /// Default HuggingFace Hub endpoint
 const DEFAULT_HF_ENDPOINT: &str = "https://huggingface.co";
 
+/// Environment variable that disables model downloads (same as `--offline`)
+pub const OFFLINE_ENV: &str = "RPM_SEARCH_OFFLINE";
+
+/// Model files every model needs
+const MODEL_FILES: [&str; 3] = ["config.json", "model.safetensors", "tokenizer.json"];
+
 /// Paths to the required model files
 pub struct ModelFiles {
     /// Path to config.json
@@ -39,6 +46,13 @@ impl ModelHub {
     /// export HF_ENDPOINT=https://myArtifactory.jfrog.io/artifactory/api/huggingfaceml/repo
     /// ```
     pub fn new() -> Result<Self> {
+        if is_offline() {
+            return Err(RpmSearchError::ModelLoad(format!(
+                "Offline mode is enabled (--offline / {}); not contacting HuggingFace Hub",
+                OFFLINE_ENV
+            )));
+        }
+
         let endpoint =
             std::env::var("HF_ENDPOINT").unwrap_or_else(|_| DEFAULT_HF_ENDPOINT.to_string());
 
@@ -58,14 +72,19 @@ impl ModelHub {
         Ok(Self { api })
     }
 
-    /// Download (or retrieve from cache) all required model files
-    pub fn get_model_files(&self, model_type: &ModelType) -> Result<ModelFiles> {
-        let repo_id = model_type.hf_repo_id();
-        let repo = self.api.model(repo_id.to_string());
+    /// Download (or retrieve from cache) all required model files, from
+    /// `revision` (branch, tag or commit) or else the default branch
+    pub fn get_model_files(
+        &self,
+        model_type: &ModelType,
+        revision: Option<&str>,
+    ) -> Result<ModelFiles> {
+        let repo = self.api.repo(hf_repo(model_type, revision));
 
         info!(
             model = %model_type.display_name(),
-            repo = %repo_id,
+            repo = %model_type.hf_repo_id(),
+            revision = revision.unwrap_or("main"),
             "Resolving model files from HuggingFace Hub"
         );
 
@@ -88,13 +107,24 @@ impl ModelHub {
     }
 
     /// Check if all required model files are already cached
-    pub fn is_cached(model_type: &ModelType) -> bool {
-        let repo_id = model_type.hf_repo_id();
-        let cache = hf_hub::Cache::default();
-        let cache_repo = cache.model(repo_id.to_string());
-        ["config.json", "model.safetensors", "tokenizer.json"]
-            .iter()
-            .all(|f| cache_repo.get(f).is_some())
+    pub fn is_cached(model_type: &ModelType, revision: Option<&str>) -> bool {
+        Self::cached_model_files(model_type, revision).is_some()
+    }
+
+    /// Paths of the model files in the HuggingFace cache (`HF_HOME`), without
+    /// any network access; `None` unless all of them are cached
+    pub fn cached_model_files(
+        model_type: &ModelType,
+        revision: Option<&str>,
+    ) -> Option<ModelFiles> {
+        let cache = hf_hub::Cache::from_env();
+        let cache_repo = cache.repo(hf_repo(model_type, revision));
+        let [config, weights, tokenizer] = MODEL_FILES.map(|f| cache_repo.get(f));
+        Some(ModelFiles {
+            config: config?,
+            weights: weights?,
+            tokenizer: tokenizer?,
+        })
     }
 
     fn get_file(&self, repo: &ApiRepo, filename: &str, model_type: &ModelType) -> Result<PathBuf> {
@@ -125,16 +155,57 @@ impl ModelHub {
     }
 }
 
+/// Hub repository of a model, pinned to `revision` when given
+fn hf_repo(model_type: &ModelType, revision: Option<&str>) -> Repo {
+    let repo_id = model_type.hf_repo_id().to_string();
+    match revision {
+        Some(revision) => Repo::with_revision(repo_id, RepoType::Model, revision.to_string()),
+        None => Repo::model(repo_id),
+    }
+}
+
+/// Point the HuggingFace cache at `<cache_dir>/huggingface` (overrides `HF_HOME`).
+///
+/// Must be called before the first [`ModelHub::new`], while the process is
+/// still single-threaded.
+pub fn set_cache_dir(cache_dir: &Path) {
+    let hf_home = cache_dir.join("huggingface");
+    debug!(path = %hf_home.display(), "Using HuggingFace cache directory");
+    std::env::set_var("HF_HOME", hf_home);
+}
+
+/// Disable model downloads for this process (`--offline`).
+///
+/// Like [`set_cache_dir`], must be called while the process is still
+/// single-threaded.
+pub fn set_offline() {
+    std::env::set_var(OFFLINE_ENV, "1");
+}
+
+/// Whether model downloads are disabled (`--offline` or `RPM_SEARCH_OFFLINE`)
+pub fn is_offline() -> bool {
+    std::env::var(OFFLINE_ENV).is_ok_and(|v| {
+        !matches!(
+            v.trim().to_ascii_lowercase().as_str(),
+            "" | "0" | "false" | "no" | "off"
+        )
+    })
+}
+
 /// Resolve model files with fallback: custom paths > local directory > hf-hub download
 ///
 /// Priority:
 /// 1. Custom paths provided via CLI (`--model` / `--tokenizer`) - use directly
-/// 2. Default local directory (`models/...`) with all files present - use it
-/// 3. Download from HuggingFace Hub via hf-hub (cached in `~/.cache/huggingface/`)
+/// 2. Default local directory (`models/...`) with all files present - use it,
+///    unless a `revision` is pinned (the local copy's revision is unknown)
+/// 3. Download from HuggingFace Hub via hf-hub (cached in `~/.cache/huggingface/`),
+///    or in offline mode ([`is_offline`]) use the cache only and fail if the
+///    files are missing
 pub fn resolve_model_files(
     model_type: &ModelType,
     custom_model_path: Option<&Path>,
     custom_tokenizer_path: Option<&Path>,
+    revision: Option<&str>,
 ) -> Result<ModelFiles> {
     // Case 1: Both custom paths provided
     if let Some(model_dir) = custom_model_path {
@@ -152,7 +223,8 @@ pub fn resolve_model_files(
 
     // Case 2: Check default local directory
     let default_path = model_type.default_model_path();
-    let has_local = default_path.join("config.json").exists()
+    let has_local = revision.is_none()
+        && default_path.join("config.json").exists()
         && default_path.join("model.safetensors").exists()
         && default_path.join("tokenizer.json").exists();
 
@@ -166,7 +238,32 @@ pub fn resolve_model_files(
     }
 
     // Case 3: Download via hf-hub
-    if ModelHub::is_cached(model_type) {
+    if is_offline() {
+        if let Some(files) = ModelHub::cached_model_files(model_type, revision) {
+            info!("Offline mode: using model from HuggingFace cache");
+            return Ok(files);
+        }
+        let mut searched = Vec::new();
+        if revision.is_none() {
+            searched.push(format!("  - local directory: {}", default_path.display()));
+        }
+        searched.push(format!(
+            "  - HuggingFace cache: {} ({} @ {})",
+            hf_hub::Cache::from_env().path().display(),
+            model_type.hf_repo_id(),
+            revision.unwrap_or("main")
+        ));
+        return Err(RpmSearchError::ModelLoad(format!(
+            "Model '{}' is not available locally and offline mode is enabled \
+             (--offline / {}). Searched:\n{}\n\
+             Copy the model files there or run `index download-model` without --offline.",
+            model_type.display_name(),
+            OFFLINE_ENV,
+            searched.join("\n")
+        )));
+    }
+
+    if ModelHub::is_cached(model_type, revision) {
         info!("Model found in HuggingFace cache");
     } else {
         println!(
@@ -176,5 +273,22 @@ pub fn resolve_model_files(
     }
 
     let hub = ModelHub::new()?;
-    hub.get_model_files(model_type)
+    hub.get_model_files(model_type, revision)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offline_missing_model_is_not_found() {
+        set_offline();
+        // No cache holds this revision, and offline mode can't download it
+        let err = resolve_model_files(&ModelType::Minilm, None, None, Some("0000000000000000"))
+            .err()
+            .unwrap();
+        assert!(matches!(err, RpmSearchError::ModelLoad(_)));
+        assert_eq!(err.exit_code(), 4);
+        assert_eq!(ModelHub::new().err().unwrap().exit_code(), 4);
+    }
 }
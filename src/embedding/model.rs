@@ -8,6 +8,7 @@ use std::path::Path;
 pub struct EmbeddingModel {
     model: BertModel,
     device: Device,
+    hidden_size: usize,
 }
 
 impl EmbeddingModel {
@@ -41,6 +42,21 @@ impl EmbeddingModel {
         Device::Cpu
     }
 
+    /// Recommended embedding batch size for the selected device.
+    /// GPUs benefit from large batches, while large CPU batches thrash memory.
+    pub fn recommended_batch_size(&self) -> usize {
+        if self.device.is_cuda() {
+            64
+        } else {
+            16
+        }
+    }
+
+    /// Output embedding dimension (`hidden_size` from the model's config.json)
+    pub fn hidden_size(&self) -> usize {
+        self.hidden_size
+    }
+
     /// Load an embedding model from individual file paths
     pub fn load_from_files(config_path: &Path, weights_path: &Path) -> Result<Self> {
         let device = Self::select_device();
@@ -75,7 +91,11 @@ impl EmbeddingModel {
         let model = BertModel::load(vb, &config)
             .map_err(|e| RpmSearchError::ModelLoad(format!("Failed to load model: {}", e)))?;
 
-        Ok(Self { model, device })
+        Ok(Self {
+            model,
+            device,
+            hidden_size: config.hidden_size,
+        })
     }
 
     /// Load an embedding model from a local directory
@@ -37,4 +37,39 @@ pub enum RpmSearchError {
     Parse(String),
 }
 
+impl RpmSearchError {
+    /// Process exit code for this error, so scripts can tell failures apart:
+    /// 2 configuration, 3 fetch/network, 4 model not found, 5 database, 1 anything else
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Config(_) => 2,
+            Self::Fetch(_) | Self::ModelDownload(_) => 3,
+            Self::ModelLoad(_) => 4,
+            Self::Database(_) | Self::Storage(_) => 5,
+            Self::Io(_)
+            | Self::XmlParse(_)
+            | Self::Embedding(_)
+            | Self::InvalidPackage(_)
+            | Self::Parse(_) => 1,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, RpmSearchError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(RpmSearchError::Config("x".into()).exit_code(), 2);
+        assert_eq!(RpmSearchError::Fetch("x".into()).exit_code(), 3);
+        assert_eq!(RpmSearchError::ModelLoad("x".into()).exit_code(), 4);
+        assert_eq!(
+            RpmSearchError::Database(rusqlite::Error::QueryReturnedNoRows).exit_code(),
+            5
+        );
+        assert_eq!(RpmSearchError::Parse("x".into()).exit_code(), 1);
+    }
+}
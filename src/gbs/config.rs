@@ -209,6 +209,8 @@ impl GbsConfig {
                 interval_seconds: 3600,
                 enabled: true,
                 sync_filelists: false,
+                keep_metadata: false,
+                tags: Vec::new(),
             })
             .collect();
 
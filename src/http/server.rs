@@ -0,0 +1,436 @@
+use crate::api::{FileMatch, RpmSearchApi};
+use crate::config::Config;
+use crate::embedding::ModelCache;
+use crate::error::{Result, RpmSearchError};
+use crate::normalize::Package;
+use crate::repomd::model::RpmFileType;
+use crate::search::SearchFilters;
+use crate::storage::FindFilter;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, PoisonError};
+use tracing::{error, info};
+
+type SharedApi = Arc<ApiPool>;
+
+/// Most idle API instances kept for reuse; busier moments open extra ones
+const MAX_IDLE_APIS: usize = 8;
+
+/// API instances for request handlers. Each has its own SQLite connection, so
+/// requests run concurrently, and all share one [`ModelCache`], so the
+/// embedding model is loaded once and stays loaded.
+struct ApiPool {
+    config: Config,
+    models: Arc<ModelCache>,
+    idle: Mutex<Vec<RpmSearchApi>>,
+}
+
+impl ApiPool {
+    fn new(api: RpmSearchApi, config: Config) -> Self {
+        let models = Arc::new(ModelCache::default());
+        Self {
+            idle: Mutex::new(vec![api.with_model_cache(Arc::clone(&models))]),
+            config,
+            models,
+        }
+    }
+
+    /// An idle API instance, or a new one when all are in use
+    fn take(&self) -> Result<RpmSearchApi> {
+        let idle = self
+            .idle
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop();
+        match idle {
+            Some(api) => Ok(api),
+            None => {
+                Ok(RpmSearchApi::new(self.config.clone())?
+                    .with_model_cache(Arc::clone(&self.models)))
+            }
+        }
+    }
+
+    /// Return an instance taken with [`ApiPool::take`]
+    fn put(&self, api: RpmSearchApi) {
+        let mut idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+        if idle.len() < MAX_IDLE_APIS {
+            idle.push(api);
+        }
+    }
+}
+
+/// JSON REST API over [`RpmSearchApi`], for tools that do not speak MCP.
+///
+/// Routes (all `GET`; `arch` and `repo` take comma-separated lists):
+/// - `/search?q=&arch=&repo=&top_k=`: hybrid search, with scores
+/// - `/find?name=&summary=&description=&provides=&requires=&file=&arch=&repo=&limit=`
+/// - `/file?path=&limit=`: packages owning a file
+/// - `/package/{name}?arch=&repo=`: every indexed version of a package
+pub struct HttpServer {
+    pool: ApiPool,
+}
+
+impl HttpServer {
+    pub fn new(config: Config) -> Result<Self> {
+        // Opened up front so a bad database fails at startup
+        let api = RpmSearchApi::new(config.clone())?;
+        Ok(Self {
+            pool: ApiPool::new(api, config),
+        })
+    }
+
+    /// Router serving the REST endpoints
+    pub fn router(self) -> Router {
+        Router::new()
+            .route("/search", get(handle_search))
+            .route("/find", get(handle_find))
+            .route("/file", get(handle_file))
+            .route("/package/{name}", get(handle_package))
+            .with_state(Arc::new(self.pool))
+    }
+
+    /// Serve the REST API on `addr` until the process is stopped
+    pub async fn run(self, addr: SocketAddr) -> Result<()> {
+        let app = self.router();
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(RpmSearchError::Io)?;
+        info!(addr = %addr, "HTTP server started");
+
+        axum::serve(listener, app).await.map_err(RpmSearchError::Io)
+    }
+}
+
+/// A search hit
+#[derive(Debug, Serialize)]
+struct ScoredPackage {
+    #[serde(flatten)]
+    package: Package,
+    score: f32,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchParams {
+    q: String,
+    arch: Option<String>,
+    repo: Option<String>,
+    top_k: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FindParams {
+    name: Option<String>,
+    #[serde(default)]
+    name_exact: bool,
+    summary: Option<String>,
+    description: Option<String>,
+    provides: Option<String>,
+    requires: Option<String>,
+    file: Option<String>,
+    file_type: Option<String>,
+    arch: Option<String>,
+    repo: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FileParams {
+    path: String,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackageParams {
+    arch: Option<String>,
+    repo: Option<String>,
+}
+
+/// Error response: `{"error": "..."}` with 400 for bad input, 404 for
+/// unknown packages and 500 otherwise
+struct ApiError(StatusCode, String);
+
+impl From<RpmSearchError> for ApiError {
+    fn from(e: RpmSearchError) -> Self {
+        let status = match e {
+            RpmSearchError::Config(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self(status, e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        if self.0.is_server_error() {
+            error!("HTTP request failed: {}", self.1);
+        }
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+type ApiResult<T> = std::result::Result<Json<T>, ApiError>;
+
+/// Run `f` on a blocking thread (SQLite and model inference block)
+async fn with_api<T, F>(api: SharedApi, f: F) -> ApiResult<T>
+where
+    F: FnOnce(&RpmSearchApi) -> std::result::Result<T, ApiError> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let instance = api.take()?;
+        let result = f(&instance);
+        api.put(instance);
+        result
+    })
+    .await
+    .map_err(|e| {
+        ApiError(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Request handler failed: {}", e),
+        )
+    })?
+    .map(Json)
+}
+
+/// Split a comma-separated query parameter
+fn split_list(value: Option<&str>) -> Vec<String> {
+    value
+        .into_iter()
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+async fn handle_search(
+    State(api): State<SharedApi>,
+    Query(params): Query<SearchParams>,
+) -> ApiResult<Vec<ScoredPackage>> {
+    with_api(api, move |api| {
+        let filters = SearchFilters {
+            arches: split_list(params.arch.as_deref()),
+            repos: split_list(params.repo.as_deref()),
+            ..Default::default()
+        };
+        let result = api.search_with_top_k(&params.q, filters, params.top_k.unwrap_or(10))?;
+        Ok(result
+            .packages
+            .into_iter()
+            .zip(result.scores)
+            .map(|(package, score)| ScoredPackage { package, score })
+            .collect())
+    })
+    .await
+}
+
+async fn handle_find(
+    State(api): State<SharedApi>,
+    Query(params): Query<FindParams>,
+) -> ApiResult<Vec<Package>> {
+    with_api(api, move |api| {
+        let file_type = params
+            .file_type
+            .as_deref()
+            .map(str::parse::<RpmFileType>)
+            .transpose()?;
+        let filter = FindFilter {
+            arches: split_list(params.arch.as_deref()),
+            repos: split_list(params.repo.as_deref()),
+            name: params.name,
+            name_exact: params.name_exact,
+            summary: params.summary,
+            description: params.description,
+            provides: params.provides,
+            requires: params.requires,
+            file: params.file,
+            file_type,
+            limit: params.limit.unwrap_or(50),
+            ..Default::default()
+        };
+        Ok(api.find(&filter)?)
+    })
+    .await
+}
+
+async fn handle_file(
+    State(api): State<SharedApi>,
+    Query(params): Query<FileParams>,
+) -> ApiResult<Vec<FileMatch>> {
+    with_api(api, move |api| {
+        let mut matches = api.search_file(&params.path)?;
+        matches.truncate(params.limit.unwrap_or(20));
+        Ok(matches)
+    })
+    .await
+}
+
+async fn handle_package(
+    State(api): State<SharedApi>,
+    Path(name): Path<String>,
+    Query(params): Query<PackageParams>,
+) -> ApiResult<Vec<Package>> {
+    with_api(api, move |api| {
+        let arches = split_list(params.arch.as_deref());
+        let repos = split_list(params.repo.as_deref());
+        let packages: Vec<Package> = api
+            .get_packages_by_name(&name)?
+            .into_iter()
+            .filter(|pkg| pkg.matches_arch_repo(&arches, &repos))
+            .collect();
+        if packages.is_empty() {
+            return Err(ApiError(
+                StatusCode::NOT_FOUND,
+                format!("Package '{}' not found", name),
+            ));
+        }
+        Ok(packages)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalize::test_package;
+    use crate::normalize::Package;
+
+    fn test_pool(db_path: std::path::PathBuf) -> SharedApi {
+        let config = Config::new(db_path);
+        let api = RpmSearchApi::new(config.clone()).unwrap();
+        Arc::new(ApiPool::new(api, config))
+    }
+
+    #[test]
+    fn test_pool_serves_concurrent_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = test_pool(dir.path().join("test.db"));
+
+        // A second request while the first holds an instance gets its own
+        let first = pool.take().unwrap();
+        let second = pool.take().unwrap();
+        assert_eq!(first.package_count().unwrap(), 0);
+        assert_eq!(second.package_count().unwrap(), 0);
+        pool.put(first);
+        pool.put(second);
+        assert_eq!(pool.idle.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_split_list() {
+        assert_eq!(
+            split_list(Some("x86_64, noarch,,")),
+            vec!["x86_64".to_string(), "noarch".to_string()]
+        );
+        assert!(split_list(None).is_empty());
+    }
+
+    #[test]
+    fn test_package_endpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let pkg = Package {
+            summary: "The GNU Bourne Again shell".to_string(),
+            ..test_package("bash", "5.2")
+        };
+        crate::storage::PackageStore::new(&db_path)
+            .unwrap()
+            .insert_package(&pkg)
+            .unwrap();
+        let api = test_pool(db_path);
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let params = |arch: Option<&str>| {
+                Query(PackageParams {
+                    arch: arch.map(String::from),
+                    repo: None,
+                })
+            };
+
+            let Json(packages) =
+                handle_package(State(api.clone()), Path("bash".to_string()), params(None))
+                    .await
+                    .ok()
+                    .unwrap();
+            assert_eq!(packages.len(), 1);
+            assert_eq!(packages[0].summary, "The GNU Bourne Again shell");
+
+            let Err(ApiError(status, _)) = handle_package(
+                State(api.clone()),
+                Path("bash".to_string()),
+                params(Some("aarch64")),
+            )
+            .await
+            else {
+                panic!("expected an error for a missing arch");
+            };
+            assert_eq!(status, StatusCode::NOT_FOUND);
+        });
+    }
+
+    #[test]
+    fn test_file_endpoint_groups_paths_by_package() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let package = |name: &str| test_package(name, "3.12");
+        let mut store = crate::storage::PackageStore::new(&db_path).unwrap();
+        let ids = store
+            .insert_packages_batch(&[package("python3"), package("alternatives")])
+            .unwrap();
+        store
+            .insert_filelists_batch(&[
+                (
+                    ids[0],
+                    vec![
+                        ("/usr/bin/python3".to_string(), 0),
+                        ("/usr/libexec/python3".to_string(), 0),
+                    ],
+                ),
+                (ids[1], vec![("/usr/bin/python3".to_string(), 2)]),
+            ])
+            .unwrap();
+        drop(store);
+        let api = test_pool(db_path);
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let Json(matches) = runtime
+            .block_on(handle_file(
+                State(api),
+                Query(FileParams {
+                    path: "python3".to_string(),
+                    limit: None,
+                }),
+            ))
+            .ok()
+            .unwrap();
+
+        let grouped: Vec<(&str, Vec<(&str, &str)>)> = matches
+            .iter()
+            .map(|m| {
+                (
+                    m.package.name.as_str(),
+                    m.files
+                        .iter()
+                        .map(|f| (f.path.as_str(), f.file_type.as_str()))
+                        .collect(),
+                )
+            })
+            .collect();
+        assert_eq!(grouped.len(), 2);
+        assert!(grouped.contains(&(
+            "python3",
+            vec![
+                ("/usr/bin/python3", "file"),
+                ("/usr/libexec/python3", "file")
+            ]
+        )));
+        assert!(grouped.contains(&("alternatives", vec![("/usr/bin/python3", "ghost")])));
+    }
+}
@@ -0,0 +1,154 @@
+use crate::error::{Result, RpmSearchError};
+use crate::normalize::version::RpmVersion;
+use crate::normalize::Package;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+use tracing::warn;
+
+/// `rpm -qa` query format carrying the epoch, so NEVRAs round-trip exactly
+const RPM_QUERY_FORMAT: &str = "%{NAME}-%{EPOCHNUM}:%{VERSION}-%{RELEASE}.%{ARCH}\\n";
+
+/// A package installed on the local machine, parsed from a NEVRA string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub epoch: Option<i64>,
+    pub version: String,
+    pub release: String,
+    pub arch: String,
+}
+
+impl InstalledPackage {
+    /// Parse `name-[epoch:]version-release.arch` (as printed by `rpm -qa`).
+    /// A trailing `.rpm` is ignored. Returns None when a component is missing.
+    pub fn parse(nevra: &str) -> Option<Self> {
+        let nevra = nevra.trim();
+        let nevra = nevra.strip_suffix(".rpm").unwrap_or(nevra);
+        let (nevr, arch) = nevra.rsplit_once('.')?;
+        let (nev, release) = nevr.rsplit_once('-')?;
+        let (name, ev) = nev.rsplit_once('-')?;
+        let (epoch, version) = match ev.split_once(':') {
+            Some((epoch, version)) => (Some(epoch.parse().ok()?), version),
+            None => (None, ev),
+        };
+        if [name, version, release, arch].iter().any(|s| s.is_empty()) {
+            return None;
+        }
+        Some(Self {
+            name: name.to_string(),
+            epoch,
+            version: version.to_string(),
+            release: release.to_string(),
+            arch: arch.to_string(),
+        })
+    }
+
+    pub fn to_rpm_version(&self) -> RpmVersion {
+        RpmVersion::new(self.epoch, self.version.clone(), self.release.clone())
+    }
+
+    /// Whether an indexed package can replace this one: same name, and the
+    /// same arch unless either side is `noarch`
+    pub fn is_replaced_by(&self, pkg: &Package) -> bool {
+        pkg.name == self.name
+            && (pkg.arch == self.arch || pkg.arch == "noarch" || self.arch == "noarch")
+    }
+}
+
+impl fmt::Display for InstalledPackage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-", self.name)?;
+        if let Some(epoch) = self.epoch.filter(|&e| e != 0) {
+            write!(f, "{}:", epoch)?;
+        }
+        write!(f, "{}-{}.{}", self.version, self.release, self.arch)
+    }
+}
+
+/// An indexed package newer than the installed version of the same package
+#[derive(Debug, Clone, Serialize)]
+pub struct Upgrade {
+    /// Installed NEVRA
+    pub installed: String,
+    /// Newest indexed package replacing it
+    pub available: Package,
+}
+
+/// Parse installed NEVRAs, one per line. Blank lines and `#` comments are
+/// skipped; lines that are not a NEVRA (e.g. `gpg-pubkey` entries) are
+/// skipped with a warning.
+pub fn parse_installed_list(content: &str) -> Vec<InstalledPackage> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let pkg = InstalledPackage::parse(line);
+            if pkg.is_none() {
+                warn!(line, "Skipping unparseable installed package entry");
+            }
+            pkg
+        })
+        .collect()
+}
+
+/// Read a list of installed NEVRAs from a file (e.g. saved `rpm -qa` output)
+pub fn read_installed_list(path: &Path) -> Result<Vec<InstalledPackage>> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        RpmSearchError::Config(format!(
+            "Failed to read installed list {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    Ok(parse_installed_list(&content))
+}
+
+/// Query the local rpmdb by running `rpm -qa`
+pub fn query_rpmdb() -> Result<Vec<InstalledPackage>> {
+    let output = Command::new("rpm")
+        .args(["-qa", "--qf", RPM_QUERY_FORMAT])
+        .output()
+        .map_err(|e| RpmSearchError::Parse(format!("Failed to run 'rpm -qa': {}", e)))?;
+    if !output.status.success() {
+        return Err(RpmSearchError::Parse(format!(
+            "'rpm -qa' failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(parse_installed_list(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_installed_nevra() {
+        let pkg = InstalledPackage::parse("bash-5.2.15-1.fc38.x86_64").unwrap();
+        assert_eq!(pkg.name, "bash");
+        assert_eq!(pkg.epoch, None);
+        assert_eq!(pkg.version, "5.2.15");
+        assert_eq!(pkg.release, "1.fc38");
+        assert_eq!(pkg.arch, "x86_64");
+
+        let pkg = InstalledPackage::parse("perl-Carp-1:1.54-500.fc38.noarch").unwrap();
+        assert_eq!(pkg.name, "perl-Carp");
+        assert_eq!(pkg.epoch, Some(1));
+        assert_eq!(pkg.to_string(), "perl-Carp-1:1.54-500.fc38.noarch");
+
+        // rpm -qa with EPOCHNUM prints 0 for packages without an epoch
+        let pkg = InstalledPackage::parse("zlib-0:1.2.13-3.x86_64").unwrap();
+        assert_eq!(pkg.to_string(), "zlib-1.2.13-3.x86_64");
+
+        assert!(InstalledPackage::parse("bash").is_none());
+        assert!(InstalledPackage::parse("bash-5.2.x86_64").is_none());
+
+        let list = parse_installed_list("# saved\nbash-5.2.15-1.x86_64\n\ngpg-pubkey\n");
+        assert_eq!(list.len(), 1);
+    }
+}
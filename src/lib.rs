@@ -2,13 +2,18 @@ pub mod api;
 pub mod config;
 pub mod error;
 pub mod gbs;
+pub mod installed;
 pub mod normalize;
+pub mod output;
 pub mod repomd;
+pub mod resolve;
 pub mod storage;
 pub mod sync;
 
 #[cfg(feature = "embedding")]
 pub mod embedding;
+#[cfg(feature = "http")]
+pub mod http;
 #[cfg(feature = "embedding")]
 pub mod mcp;
 #[cfg(feature = "embedding")]
@@ -1,22 +1,35 @@
 use rpm_repo_search::api;
-use rpm_repo_search::config::Config;
+use rpm_repo_search::config::{Config, ConfigOverrides};
 #[cfg(feature = "embedding")]
-use rpm_repo_search::config::ModelType;
+use rpm_repo_search::config::{EmbeddingTextMode, ModelType};
+#[cfg(feature = "embedding")]
+use rpm_repo_search::config::{RepoWeights, ScoringWeights};
 #[cfg(feature = "embedding")]
 use rpm_repo_search::embedding;
 use rpm_repo_search::error;
 use rpm_repo_search::error::Result;
 use rpm_repo_search::gbs;
+#[cfg(feature = "http")]
+use rpm_repo_search::http;
+use rpm_repo_search::installed;
 #[cfg(feature = "embedding")]
 use rpm_repo_search::mcp;
-use rpm_repo_search::normalize::Package;
+#[cfg(feature = "embedding")]
+use rpm_repo_search::normalize::truncate_at_char_boundary;
+use rpm_repo_search::normalize::{filter_latest_n, sort_by_arch_order, Package};
+use rpm_repo_search::output::{self, OutputFormat};
+use rpm_repo_search::repomd::model::RpmFileType;
 #[cfg(feature = "embedding")]
 use rpm_repo_search::search::SearchFilters;
-use rpm_repo_search::storage::FindFilter;
+use rpm_repo_search::storage::{FindFilter, SortField};
 use rpm_repo_search::sync;
 
 use clap::{Parser, Subcommand};
+use std::collections::BTreeMap;
+#[cfg(feature = "embedding")]
 use std::collections::HashMap;
+#[cfg(feature = "embedding")]
+use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
 use tracing::info;
 
@@ -27,9 +40,47 @@ struct Cli {
     #[command(subcommand)]
     command: Commands,
 
-    /// Database file path
-    #[arg(short, long, default_value = "rpm_search.db")]
-    db: PathBuf,
+    /// Database file path [default: rpm_search.db]
+    #[arg(short, long)]
+    db: Option<PathBuf>,
+
+    /// Config file path (default: ~/.config/rpm-search/config.toml)
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Log queries slower than this many milliseconds as warnings (0 = disabled) [default: 500]
+    #[arg(long, global = true)]
+    slow_query_ms: Option<u64>,
+
+    /// Print only result lines (no headers, separators or notices), for piping
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Match arch filters exactly (by default `aarch64` also matches `arm64`, etc.)
+    #[arg(long, global = true)]
+    no_arch_alias: bool,
+
+    /// Directory for the default database and downloaded models
+    /// (instead of ./rpm_search.db and ~/.cache/huggingface)
+    #[arg(long, global = true, env = "RPM_SEARCH_CACHE_DIR", value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// Never download models; fail if they are not in the local directory or cache
+    #[arg(long, global = true, env = "RPM_SEARCH_OFFLINE", value_parser = clap::builder::FalseyValueParser::new())]
+    offline: bool,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Print the effective configuration (defaults < config file < CLI flags)
+    Show,
+
+    /// Write a config file with the default settings
+    Init {
+        /// Overwrite an existing config file
+        #[arg(long)]
+        force: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -49,7 +100,7 @@ enum SyncCommands {
         gbs_profile: Option<String>,
     },
 
-    /// Perform one-time sync of all repositories
+    /// Perform one-time sync of all repositories (or those given with --repo)
     Once {
         /// Sync configuration file (TOML format)
         #[arg(short, long)]
@@ -63,9 +114,25 @@ enum SyncCommands {
         #[arg(long, requires = "gbs_conf")]
         gbs_profile: Option<String>,
 
-        /// Skip automatic embedding generation after sync
-        #[arg(long)]
+        /// Skip automatic embedding generation after sync (structured search only)
+        #[arg(long, visible_alias = "no-embeddings")]
         no_embedding: bool,
+
+        /// Report per-repository changes without modifying the database
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Keep downloaded metadata files in <work_dir>/kept/<repo>/ for debugging
+        #[arg(long, conflicts_with = "dry_run")]
+        keep_metadata: bool,
+
+        /// Sync only this repository (can be specified multiple times)
+        #[arg(long, value_name = "NAME")]
+        repo: Vec<String>,
+
+        /// Report per-repository changes and download/parse/insert timings
+        #[arg(short, long, conflicts_with = "dry_run")]
+        verbose: bool,
     },
 
     /// Run sync daemon (continuous background syncing)
@@ -82,15 +149,34 @@ enum SyncCommands {
         #[arg(long, requires = "gbs_conf")]
         gbs_profile: Option<String>,
 
-        /// Skip automatic embedding generation after sync
-        #[arg(long)]
+        /// Skip automatic embedding generation after sync (structured search only)
+        #[arg(long, visible_alias = "no-embeddings")]
         no_embedding: bool,
+
+        /// Keep downloaded metadata files in <work_dir>/kept/<repo>/ for debugging
+        /// (only the last few syncs per repository are retained)
+        #[arg(long)]
+        keep_metadata: bool,
+
+        /// Sync only this repository (can be specified multiple times)
+        #[arg(long, value_name = "NAME")]
+        repo: Vec<String>,
     },
 
     /// Show sync status for all repositories
     Status,
 }
 
+#[derive(Subcommand)]
+enum EmbeddingCommands {
+    /// Show per-repository embedding coverage (packages with embeddings / total)
+    Status {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
 #[derive(Subcommand)]
 enum RepoCommands {
     /// List all indexed repositories
@@ -111,6 +197,61 @@ enum RepoCommands {
         #[arg(short, long)]
         yes: bool,
     },
+
+    /// Compare two repositories (packages only in A, only in B, and version differences)
+    Compare {
+        /// First repository name (A)
+        repo_a: String,
+
+        /// Second repository name (B)
+        repo_b: String,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List capabilities provided by packages in more than one repository
+    Conflicts {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List files owned by more than one package in a repository (needs filelists)
+    FileConflicts {
+        /// Repository name
+        repo: String,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Export a repository (packages, dependencies, files) as a JSONL snapshot
+    Export {
+        /// Repository name
+        repo: String,
+
+        /// Output file (use - for stdout)
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Include the repository's embedding vectors
+        #[arg(long)]
+        with_embeddings: bool,
+    },
+
+    /// Import a JSONL snapshot created by `repo export` as a new repository
+    Import {
+        /// Snapshot file (use - for stdin)
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Repository name to import into
+        #[arg(short, long)]
+        repo: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -138,6 +279,26 @@ enum IndexCommands {
         base_url: Option<String>,
     },
 
+    /// Index a remote repository in one step from its base URL (fetches
+    /// repodata/repomd.xml, then the primary.xml it lists; no sync state is kept)
+    Url {
+        /// Repository base URL (the directory containing repodata/)
+        #[arg(long)]
+        url: String,
+
+        /// Repository name
+        #[arg(short, long)]
+        repo: String,
+
+        /// Update existing repository (incremental update)
+        #[arg(short, long)]
+        update: bool,
+
+        /// Also download and index filelists.xml
+        #[arg(long)]
+        filelists: bool,
+    },
+
     /// Index filelists from filelists.xml file (run after 'index repo')
     Filelists {
         /// Path to filelists.xml, filelists.xml.gz, or filelists.xml.zst
@@ -149,12 +310,44 @@ enum IndexCommands {
         repo: String,
     },
 
+    /// Index a directory of .rpm files directly from their headers (no repodata needed)
+    Rpms {
+        /// Directory containing .rpm files (searched recursively)
+        #[arg(short, long)]
+        dir: PathBuf,
+
+        /// Repository name
+        #[arg(short, long)]
+        repo: String,
+
+        /// Base URL the directory is served from (for generating RPM download URLs)
+        #[arg(long)]
+        base_url: Option<String>,
+    },
+
+    /// Index AppStream metadata (components.xml) to enrich embedding text
+    Appstream {
+        /// Path to components.xml, components.xml.gz, or components.xml.zst
+        #[arg(short, long)]
+        file: PathBuf,
+
+        /// Repository name (must match the repo used in 'index repo')
+        #[arg(short, long)]
+        repo: String,
+    },
+
     /// Build embeddings for indexed packages
     #[cfg(feature = "embedding")]
     Embeddings {
         /// Embedding model type (minilm = English, e5-multilingual = 100 languages)
-        #[arg(long, value_enum, default_value = "minilm")]
-        model_type: ModelType,
+        /// [default: from config file, else minilm]
+        #[arg(long, value_enum)]
+        model_type: Option<ModelType>,
+
+        /// HuggingFace revision (branch, tag or commit) to download the model from;
+        /// recorded in the database [default: from config file, else main]
+        #[arg(long, value_name = "REV")]
+        model_revision: Option<String>,
 
         /// Model directory path (default: auto from model-type)
         #[arg(short, long)]
@@ -168,9 +361,28 @@ enum IndexCommands {
         #[arg(short, long)]
         verbose: bool,
 
-        /// Force full rebuild (drop all embeddings and regenerate)
+        /// Force full rebuild (drop all embeddings and regenerate). Re-running an
+        /// interrupted rebuild with the same model resumes where it stopped
         #[arg(long)]
         rebuild: bool,
+
+        /// With --rebuild, start over even if an interrupted rebuild could be resumed
+        #[arg(long, requires = "rebuild")]
+        restart: bool,
+
+        /// Embedding batch size (default: 64 on CUDA, 16 on CPU)
+        #[arg(long)]
+        batch_size: Option<usize>,
+
+        /// Package fields to embed: summary (name + summary), name-summary (no
+        /// provides/requires) or full [default: from config file, else full]
+        #[arg(long, value_enum)]
+        text_mode: Option<EmbeddingTextMode>,
+
+        /// Build embeddings only for this repository. A model type different from
+        /// the DB default is assigned to this repository alone.
+        #[arg(long)]
+        repo: Option<String>,
     },
 
     /// Download embedding model from HuggingFace Hub
@@ -179,6 +391,10 @@ enum IndexCommands {
         /// Model type to download
         #[arg(long, value_enum, default_value = "minilm")]
         model_type: ModelType,
+
+        /// HuggingFace revision (branch, tag or commit) to download (default: main)
+        #[arg(long, value_name = "REV")]
+        model_revision: Option<String>,
     },
 }
 
@@ -198,14 +414,24 @@ enum Commands {
         /// Natural language search query (e.g., 'compression library', 'image processing tool')
         query: String,
 
-        /// Filter by architecture
+        /// Filter by architecture (can be specified multiple times)
         #[arg(short, long)]
-        arch: Option<String>,
+        arch: Vec<String>,
 
         /// Filter by repository (can be specified multiple times)
         #[arg(short, long)]
         repo: Vec<String>,
 
+        /// Filter by every repository carrying a tag from the sync config
+        /// (can be specified multiple times)
+        #[arg(long, value_name = "TAG")]
+        repo_tag: Vec<String>,
+
+        /// Read repository filters from a file, one name per line
+        /// (blank lines and `#` comments are ignored)
+        #[arg(long, value_name = "PATH")]
+        repos_from_file: Option<PathBuf>,
+
         /// Use GBS configuration file to resolve repos from profile
         #[arg(long, value_name = "PATH")]
         gbs_conf: Option<PathBuf>,
@@ -214,14 +440,73 @@ enum Commands {
         #[arg(long, requires = "gbs_conf")]
         gbs_profile: Option<String>,
 
-        /// Exclude packages requiring this dependency
+        /// Exclude packages requiring this dependency (e.g., "libfoo" or "libfoo >= 2.0";
+        /// `*` and `?` wildcards allowed in the name)
         #[arg(long)]
         not_requiring: Option<String>,
 
-        /// Include only packages providing this capability
+        /// Include only packages providing this capability (e.g., "libssl.so.3 >= 3.0"
+        /// or "libssl.so*")
         #[arg(long)]
         providing: Option<String>,
 
+        /// Exclude packages providing this capability (same syntax as --providing)
+        #[arg(long)]
+        not_providing: Option<String>,
+
+        /// If --arch/--repo match no packages, search all repositories instead
+        #[arg(long)]
+        relax_filters: bool,
+
+        /// Domain hint prepended to the query before embedding (e.g., --context database
+        /// for "client"). Biases semantic ranking only; it is not a filter
+        #[arg(long)]
+        context: Option<String>,
+
+        /// Collapse binary packages built from the same source package,
+        /// showing the best-scoring variant per source
+        #[arg(long)]
+        group_by_source: bool,
+
+        /// List results under a header per repository (repos ordered by their
+        /// best result, score order kept within each)
+        #[arg(long, conflicts_with = "format")]
+        group_by_repo: bool,
+
+        /// Guarantee at least this many results by including the best matches
+        /// below the relevance threshold (marked as low confidence)
+        #[arg(long, value_name = "N")]
+        min_results: Option<usize>,
+
+        /// Weights of the name-match and semantic scores, normalized to sum to 1
+        /// (default: structured=0.45,semantic=0.55)
+        #[arg(long, value_name = "structured=X,semantic=Y")]
+        weights: Option<ScoringWeights>,
+
+        /// Multiply scores of results from these repositories (e.g., "curated=1.2,mirror=0.8");
+        /// unlisted repos keep weight 1.0. Re-ranks only, never filters
+        #[arg(long, value_name = "REPO=W,...")]
+        repo_weight: Option<RepoWeights>,
+
+        /// Print the planner's decisions (prefilter, candidate counts, hits per
+        /// source) to stderr
+        #[arg(long)]
+        explain_plan: bool,
+
+        /// Highlight query terms in summaries and descriptions
+        /// (ignored when stdout is not a terminal)
+        #[arg(long)]
+        highlight: bool,
+
+        /// Truncate descriptions to about this many bytes (0 = show in full)
+        #[arg(long, value_name = "N", default_value = "200")]
+        desc_len: usize,
+
+        /// Print the results in a machine-readable format (same formats as
+        /// `repoquery --format`, in score order, without scores)
+        #[arg(long, value_enum, conflicts_with = "highlight")]
+        format: Option<OutputFormat>,
+
         /// Number of results to return
         #[arg(short = 'n', long, default_value = "10")]
         top_k: usize,
@@ -231,12 +516,25 @@ enum Commands {
     /// Show database statistics
     Stats,
 
+    /// Check the database setup (schema, sqlite-vec, embeddings, filelists)
+    Doctor {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Repository management commands
     Repo {
         #[command(subcommand)]
         command: RepoCommands,
     },
 
+    /// Embedding commands
+    Embedding {
+        #[command(subcommand)]
+        command: EmbeddingCommands,
+    },
+
     // ── Sync ─────────────────────────────────────────────────────────
     /// Sync repository metadata
     Sync {
@@ -244,12 +542,28 @@ enum Commands {
         command: SyncCommands,
     },
 
+    // ── Configuration ────────────────────────────────────────────────
+    /// Show or initialize the config file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+
     // ── Repoquery ─────────────────────────────────────────────────────
     /// Query packages from indexed repositories (similar to dnf repoquery)
     Repoquery {
         /// Package name or glob pattern (e.g., "bash", "lib*ssl*")
         package: Option<String>,
 
+        /// Match the package name exactly instead of as a substring (e.g., "bash" but not "bash-completion")
+        #[arg(long, requires = "package")]
+        name_exact: bool,
+
+        /// Treat the package name, --summary and --description as regular expressions
+        /// instead of globs (full table scan; at most 1000 results)
+        #[arg(long, conflicts_with = "name_exact")]
+        regex: bool,
+
         // -- Query mode --
         /// Find packages that provide a capability (e.g., "libssl.so*")
         #[arg(long)]
@@ -259,10 +573,23 @@ enum Commands {
         #[arg(long)]
         whatrequires: Option<String>,
 
+        /// Find packages that conflict with a capability
+        #[arg(long)]
+        whatconflicts: Option<String>,
+
+        /// Find packages that obsolete a capability (e.g., what replaces an old package)
+        #[arg(long)]
+        whatobsoletes: Option<String>,
+
         /// Find packages that own a specific file (e.g., "/usr/bin/python*", "*.so")
         #[arg(long)]
         file: Option<String>,
 
+        /// With --file, only match entries of this type: file, dir or ghost
+        /// (e.g., "dir" for owned directories)
+        #[arg(long, value_parser = str::parse::<RpmFileType>, requires = "file")]
+        file_type: Option<RpmFileType>,
+
         // -- Additional filters (from find) --
         /// Summary keyword pattern
         #[arg(short, long)]
@@ -281,29 +608,75 @@ enum Commands {
         #[arg(short, long)]
         list: bool,
 
+        /// With --list, show files as an indented directory tree
+        #[arg(long, requires = "list")]
+        tree: bool,
+
         /// Show requires of matched packages
         #[arg(long)]
         requires: bool,
 
+        /// With --requires, leave out prerequisites of install scriptlets
+        /// (`pre="1"` entries)
+        #[arg(long, requires = "requires")]
+        no_pre: bool,
+
         /// Show provides of matched packages
         #[arg(long)]
         provides: bool,
 
+        /// Show the source RPM (name-version-release.src.rpm) of matched packages
+        #[arg(long)]
+        source: bool,
+
+        /// List packages indexed with more than one version (honors --arch and --repo)
+        #[arg(long, conflicts_with_all = ["package", "whatprovides", "whatrequires", "whatconflicts", "whatobsoletes", "file", "summary", "description", "info", "list", "requires", "provides", "source", "queryformat", "format", "latest", "latest_n"])]
+        duplicates: bool,
+
+        /// List indexed packages newer than the installed version
+        /// (installed packages come from --installed-list, or `rpm -qa`)
+        #[arg(long, conflicts_with_all = ["package", "whatprovides", "whatrequires", "whatconflicts", "whatobsoletes", "file", "summary", "description", "info", "list", "requires", "provides", "source", "queryformat", "latest", "latest_n", "duplicates"])]
+        upgrades: bool,
+
+        /// File of installed NEVRAs, one per line (e.g. saved `rpm -qa` output)
+        #[arg(long, value_name = "FILE", requires = "upgrades")]
+        installed_list: Option<PathBuf>,
+
         /// Custom output format (supports %{name}, %{version}, %{release}, %{epoch}, %{arch},
         /// %{summary}, %{description}, %{license}, %{repo}, %{vcs}, %{nevra},
-        /// %{location}, %{download_url})
+        /// %{sourcerpm}, %{location}, %{download_url})
         #[arg(long)]
         queryformat: Option<String>,
 
+        /// Machine-readable output: csv/tsv with a header row
+        /// (name, epoch, version, release, arch, repo, summary, license),
+        /// a JSON array, NDJSON (one package per line, streamed) or a YAML sequence
+        #[arg(long, value_enum, conflicts_with_all = ["info", "list", "requires", "provides", "source", "queryformat"])]
+        format: Option<OutputFormat>,
+
+        /// Indent --format json output for reading
+        #[arg(long, requires = "format")]
+        pretty: bool,
+
         // -- Filters --
-        /// Filter by architecture
+        /// Filter by architecture (can be specified multiple times)
         #[arg(short, long)]
-        arch: Option<String>,
+        arch: Vec<String>,
 
         /// Filter by repository (can be specified multiple times)
         #[arg(long)]
         repo: Vec<String>,
 
+        /// Filter by every repository carrying a tag from the sync config
+        /// (can be specified multiple times)
+        #[arg(long, value_name = "TAG")]
+        repo_tag: Vec<String>,
+
+        /// Read repository filters from a file, one name per line
+        /// (blank lines and `#` comments are ignored)
+        #[arg(long, value_name = "PATH")]
+        repos_from_file: Option<PathBuf>,
+
         /// Use GBS configuration file to resolve repos from profile
         #[arg(long, value_name = "PATH")]
         gbs_conf: Option<PathBuf>,
@@ -312,19 +685,167 @@ enum Commands {
         #[arg(long, requires = "gbs_conf")]
         gbs_profile: Option<String>,
 
-        /// Show only the latest version per package name+arch
-        #[arg(long)]
+        /// Show only the latest version per package name+arch (same as --latest-n 1)
+        #[arg(long, group = "latest_filter")]
         latest: bool,
 
-        /// Maximum results
+        /// Show the K newest versions per package name+arch
+        #[arg(long, value_name = "K", group = "latest_filter", value_parser = clap::value_parser!(u32).range(1..))]
+        latest_n: Option<u32>,
+
+        /// With --latest/--latest-n, prefer packages from earlier repos when versions
+        /// are equal (e.g., "updates,base"); unlisted repos come last
+        #[arg(long, value_delimiter = ',', requires = "latest_filter")]
+        repo_priority: Vec<String>,
+
+        /// Show localized summary/description for this language where available (e.g., "de")
+        #[arg(long)]
+        lang: Option<String>,
+
+        /// Maximum results (0 = no limit)
         #[arg(long, default_value = "200")]
         limit: usize,
+
+        /// Result ordering (version sorting loads every match into memory first)
+        #[arg(long, value_enum, default_value = "name")]
+        sort: SortField,
+
+        /// Reverse the --sort ordering
+        #[arg(long)]
+        reverse: bool,
+
+        /// List each package's versions newest first and each version's arches
+        /// in this order (e.g., "x86_64,i686,noarch"); unlisted arches follow
+        #[arg(long, value_delimiter = ',', value_name = "ARCHES")]
+        arch_order: Vec<String>,
+    },
+
+    /// Export packages matching repoquery-style filters as rpm-md metadata
+    /// (primary.xml.gz + repomd.xml) for republishing a subset
+    ExportRepomd {
+        /// Package name or glob pattern (e.g., "bash", "lib*ssl*")
+        package: Option<String>,
+
+        /// Only packages that provide a capability
+        #[arg(long)]
+        whatprovides: Option<String>,
+
+        /// Only packages that require a capability
+        #[arg(long)]
+        whatrequires: Option<String>,
+
+        /// Only packages that own a specific file
+        #[arg(long)]
+        file: Option<String>,
+
+        /// With --file, only match entries of this type: file, dir or ghost
+        #[arg(long, value_parser = str::parse::<RpmFileType>, requires = "file")]
+        file_type: Option<RpmFileType>,
+
+        /// Summary keyword pattern
+        #[arg(short, long)]
+        summary: Option<String>,
+
+        /// Description keyword pattern
+        #[arg(long)]
+        description: Option<String>,
+
+        /// Filter by architecture (can be specified multiple times)
+        #[arg(short, long)]
+        arch: Vec<String>,
+
+        /// Filter by repository (can be specified multiple times)
+        #[arg(long)]
+        repo: Vec<String>,
+
+        /// Filter by every repository carrying a tag from the sync config
+        /// (can be specified multiple times)
+        #[arg(long, value_name = "TAG")]
+        repo_tag: Vec<String>,
+
+        /// Read repository filters from a file, one name per line
+        /// (blank lines and `#` comments are ignored)
+        #[arg(long, value_name = "PATH")]
+        repos_from_file: Option<PathBuf>,
+
+        /// Use GBS configuration file to resolve repos from profile
+        #[arg(long, value_name = "PATH")]
+        gbs_conf: Option<PathBuf>,
+
+        /// GBS profile to use (default: from gbs.conf [general] section)
+        #[arg(long, requires = "gbs_conf")]
+        gbs_profile: Option<String>,
+
+        /// Export only the latest version per package name+arch
+        #[arg(long)]
+        latest: bool,
+
+        /// Maximum number of packages (default: 0 = no limit)
+        #[arg(long, default_value = "0")]
+        limit: usize,
+
+        /// Output directory for primary.xml.gz and repomd.xml
+        #[arg(short, long, default_value = "repodata")]
+        output: PathBuf,
+    },
+
+    /// Compute the runtime dependency closure of a package within the indexed repos
+    Resolve {
+        /// Package name or NEVRA (e.g., "bash", "bash-5.2.15-1.x86_64")
+        package: String,
+
+        /// Architecture of the package and its providers (noarch is always allowed)
+        #[arg(short, long)]
+        arch: Option<String>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
     },
 
     // ── Server & Debug ───────────────────────────────────────────────
     /// Run MCP (Model Context Protocol) server
     #[cfg(feature = "embedding")]
-    McpServer,
+    McpServer {
+        /// Serve the streamable HTTP transport on this address (e.g., 127.0.0.1:3000)
+        /// instead of stdio
+        #[arg(long, value_name = "ADDR")]
+        http: Option<std::net::SocketAddr>,
+
+        /// Seconds a tool call may run before returning a timeout error
+        /// (0 = no limit) [default: from config file, else 30]
+        #[arg(long, value_name = "SECS", env = "RPM_SEARCH_MCP_TOOL_TIMEOUT")]
+        tool_timeout: Option<u64>,
+    },
+
+    /// Run a REST API server (JSON endpoints /search, /find, /file, /package/{name})
+    #[cfg(feature = "http")]
+    Serve {
+        /// Address to listen on
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+        bind: std::net::SocketAddr,
+    },
+
+    /// Rebuild SQLite indexes and the name index (after upgrades or external imports)
+    Reindex {
+        /// Also regenerate all embeddings with the model recorded in the database
+        /// (resuming an interrupted regeneration)
+        #[arg(long)]
+        embeddings: bool,
+
+        /// With --embeddings, start over even if an interrupted regeneration
+        /// could be resumed
+        #[arg(long, requires = "embeddings")]
+        restart: bool,
+    },
+
+    /// Delete embeddings whose package no longer exists
+    #[cfg(feature = "embedding")]
+    PurgeOrphanEmbeddings {
+        /// Only report what would be deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
 
     /// Debug search - diagnose embedding quality
     #[cfg(feature = "embedding")]
@@ -332,9 +853,21 @@ enum Commands {
         /// Search query
         query: String,
 
+        /// Domain hint prepended to the query before embedding (same as search --context)
+        #[arg(long)]
+        context: Option<String>,
+
         /// Specific pkg_ids to check (comma-separated)
         #[arg(long)]
         pkg_ids: Option<String>,
+
+        /// Print a histogram of query similarity over a random sample of embeddings
+        #[arg(long)]
+        histogram: bool,
+
+        /// Number of embeddings to sample for --histogram
+        #[arg(long, default_value = "1000", requires = "histogram")]
+        sample: usize,
     },
 }
 
@@ -397,7 +930,7 @@ fn check_and_exec_cuda_version() -> Result<()> {
 /// Format a package using a custom query format string.
 /// Supports tags: %{name}, %{version}, %{release}, %{epoch}, %{arch},
 /// %{summary}, %{description}, %{license}, %{repo}, %{vcs}, %{nevra},
-/// %{location}, %{download_url}.
+/// %{sourcerpm}, %{location}, %{download_url}.
 /// Also handles \n and \t escape sequences.
 fn format_querystring(fmt: &str, pkg: &Package, download_url: Option<&str>) -> String {
     fmt.replace("%{name}", &pkg.name)
@@ -413,6 +946,7 @@ fn format_querystring(fmt: &str, pkg: &Package, download_url: Option<&str>) -> S
         .replace("%{license}", pkg.license.as_deref().unwrap_or(""))
         .replace("%{repo}", &pkg.repo)
         .replace("%{vcs}", pkg.vcs.as_deref().unwrap_or(""))
+        .replace("%{sourcerpm}", pkg.sourcerpm.as_deref().unwrap_or(""))
         .replace("%{location}", pkg.location_href.as_deref().unwrap_or(""))
         .replace("%{download_url}", download_url.unwrap_or(""))
         .replace(
@@ -423,30 +957,9 @@ fn format_querystring(fmt: &str, pkg: &Package, download_url: Option<&str>) -> S
         .replace("\\t", "\t")
 }
 
-/// Filter packages to keep only the latest version per (name, arch) pair.
-/// Uses RPM version comparison via Package::Ord.
-fn filter_latest(packages: Vec<Package>) -> Vec<Package> {
-    let mut latest_map: HashMap<(String, String), Package> = HashMap::new();
-    for pkg in packages {
-        let key = (pkg.name.clone(), pkg.arch.clone());
-        match latest_map.entry(key) {
-            std::collections::hash_map::Entry::Vacant(e) => {
-                e.insert(pkg);
-            }
-            std::collections::hash_map::Entry::Occupied(mut e) => {
-                if pkg > *e.get() {
-                    e.insert(pkg);
-                }
-            }
-        }
-    }
-    let mut result: Vec<Package> = latest_map.into_values().collect();
-    result.sort();
-    result
-}
-
 /// Build the full RPM download URL for a package.
 /// Combines the repo base_url (from sync state) with the package's location_href.
+/// The base_url is the configured template, so `${VAR}` references stay unexpanded.
 fn build_download_url(state_store: &sync::SyncStateStore, pkg: &Package) -> Option<String> {
     let location = pkg.location_href.as_deref()?;
     let base_url = state_store.get_base_url(&pkg.repo).ok()??;
@@ -457,33 +970,206 @@ fn build_download_url(state_store: &sync::SyncStateStore, pkg: &Package) -> Opti
     ))
 }
 
-/// Resolve repository filter from --repo flags and --gbs-conf/--gbs-profile options.
-/// If both --repo and --gbs-conf are provided, the repos are merged.
-fn resolve_repos(
-    repo: Vec<String>,
-    gbs_conf: Option<&Path>,
-    gbs_profile: Option<&str>,
-) -> Result<Vec<String>> {
-    let mut repos = repo;
-    if let Some(gbs_path) = gbs_conf {
-        let gbs = gbs::GbsConfig::from_path(gbs_path)?;
-        let gbs_repos = gbs.get_repo_urls(gbs_profile)?;
-        for (name, _url) in gbs_repos {
-            if !repos.contains(&name) {
-                repos.push(name);
-            }
-        }
+/// Node of a file tree built from flat `(path, file_type)` pairs
+#[derive(Default)]
+struct FileTreeNode {
+    file_type: Option<String>,
+    children: BTreeMap<String, FileTreeNode>,
+}
+
+impl FileTreeNode {
+    fn is_dir(&self) -> bool {
+        !self.children.is_empty() || self.file_type.as_deref() == Some("dir")
     }
-    Ok(repos)
 }
 
-fn main() -> Result<()> {
-    // Restore default SIGPIPE handling so piping to head/grep etc. exits cleanly
-    #[cfg(unix)]
-    unsafe {
-        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+/// Print a package file list as an indented directory tree.
+/// Directories are listed before files at each level.
+fn print_file_tree(files: &[(String, String)]) {
+    let mut root = FileTreeNode::default();
+    for (path, file_type) in files {
+        let mut node = &mut root;
+        for component in path.split('/').filter(|c| !c.is_empty()) {
+            node = node.children.entry(component.to_string()).or_default();
+        }
+        node.file_type = Some(file_type.clone());
     }
 
+    fn print_children(node: &FileTreeNode, depth: usize) {
+        let (dirs, files): (Vec<_>, Vec<_>) =
+            node.children.iter().partition(|(_, child)| child.is_dir());
+        for (name, child) in dirs {
+            println!("{}{}/", "  ".repeat(depth), name);
+            print_children(child, depth + 1);
+        }
+        for (name, child) in files {
+            match child.file_type.as_deref() {
+                Some("ghost") => println!("{}{} (ghost)", "  ".repeat(depth), name),
+                _ => println!("{}{}", "  ".repeat(depth), name),
+            }
+        }
+    }
+
+    println!("/");
+    print_children(&root, 1);
+}
+
+/// Print an ASCII histogram of cosine similarities in buckets of 0.1
+#[cfg(feature = "embedding")]
+fn print_similarity_histogram(sims: &[f32]) {
+    const BAR_WIDTH: usize = 50;
+
+    let valid: Vec<f32> = sims.iter().copied().filter(|s| s.is_finite()).collect();
+    if valid.is_empty() {
+        println!("  (no embeddings)");
+        return;
+    }
+
+    let bucket = |s: f32| (s.clamp(-1.0, 1.0) * 10.0).floor().min(9.0) as i32;
+    let lo = valid.iter().map(|&s| bucket(s)).min().unwrap_or(0);
+    let hi = valid.iter().map(|&s| bucket(s)).max().unwrap_or(0);
+    let mut counts = vec![0usize; (hi - lo + 1) as usize];
+    for &s in &valid {
+        counts[(bucket(s) - lo) as usize] += 1;
+    }
+
+    let max = counts.iter().copied().max().unwrap_or(1).max(1);
+    for (i, count) in counts.iter().enumerate() {
+        let start = (lo + i as i32) as f32 / 10.0;
+        let bar = "█".repeat((count * BAR_WIDTH).div_ceil(max));
+        println!(
+            "  [{:>4.1}, {:>4.1}) {:>6} {}",
+            start,
+            start + 0.1,
+            count,
+            bar
+        );
+    }
+
+    let mean = valid.iter().sum::<f32>() / valid.len() as f32;
+    let min = valid.iter().copied().fold(f32::INFINITY, f32::min);
+    let max_sim = valid.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    println!("  min={:.4} mean={:.4} max={:.4}", min, mean, max_sim);
+}
+
+/// Resolve repository filter from --repo, --repo-tag, --repos-from-file and
+/// --gbs-conf/--gbs-profile options.
+/// Repos from all sources are merged; a tag carried by no repository is an error.
+fn resolve_repos(
+    repo: Vec<String>,
+    repo_tags: &[String],
+    repos_file: Option<&Path>,
+    db_path: &Path,
+    gbs_conf: Option<&Path>,
+    gbs_profile: Option<&str>,
+) -> Result<Vec<String>> {
+    let mut repos = repo;
+    if let Some(path) = repos_file {
+        for name in read_repo_list(path)? {
+            if !repos.contains(&name) {
+                repos.push(name);
+            }
+        }
+    }
+    if !repo_tags.is_empty() {
+        // Resolving tags only reads; don't create or migrate the database
+        let conn = if db_path.exists() {
+            rusqlite::Connection::open_with_flags(
+                db_path,
+                rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+            )?
+        } else {
+            rusqlite::Connection::open_in_memory()?
+        };
+        let state_store = sync::SyncStateStore::read_only(conn)?;
+        for tag in repo_tags {
+            let tagged = state_store.repos_with_tag(tag)?;
+            if tagged.is_empty() {
+                return Err(error::RpmSearchError::Config(format!(
+                    "No repository is tagged '{}' (tags are set by sync from the config)",
+                    tag
+                )));
+            }
+            for name in tagged {
+                if !repos.contains(&name) {
+                    repos.push(name);
+                }
+            }
+        }
+    }
+    if let Some(gbs_path) = gbs_conf {
+        let gbs = gbs::GbsConfig::from_path(gbs_path)?;
+        let gbs_repos = gbs.get_repo_urls(gbs_profile)?;
+        for (name, _url) in gbs_repos {
+            if !repos.contains(&name) {
+                repos.push(name);
+            }
+        }
+    }
+    Ok(repos)
+}
+
+/// Read newline-separated repository names, skipping blanks and `#` comments.
+/// A list without any name is an error rather than an unfiltered search.
+fn read_repo_list(path: &Path) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        error::RpmSearchError::Config(format!(
+            "Failed to read repo list {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    let repos: Vec<String> = content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    if repos.is_empty() {
+        return Err(error::RpmSearchError::Config(format!(
+            "Repo list {} contains no repository names",
+            path.display()
+        )));
+    }
+    Ok(repos)
+}
+
+/// Run a repoquery-style filter; without any criteria, every package matches
+fn find_packages(api: &api::RpmSearchApi, filter: FindFilter) -> Result<Vec<Package>> {
+    api.find(&match_all_if_empty(filter))
+}
+
+/// Without any criteria, a repoquery-style filter matches every package
+fn match_all_if_empty(filter: FindFilter) -> FindFilter {
+    let has_any_condition = filter.name.is_some()
+        || filter.summary.is_some()
+        || filter.description.is_some()
+        || filter.provides.is_some()
+        || filter.requires.is_some()
+        || filter.conflicts.is_some()
+        || filter.obsoletes.is_some()
+        || filter.file.is_some()
+        || !filter.arches.is_empty()
+        || !filter.repos.is_empty();
+
+    if has_any_condition {
+        filter
+    } else {
+        FindFilter {
+            name: Some("*".to_string()),
+            ..filter
+        }
+    }
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        std::process::exit(e.exit_code());
+    }
+}
+
+fn run() -> Result<()> {
     // Check if CUDA is available and exec CUDA version if it is
     #[cfg(all(not(feature = "cuda"), feature = "embedding"))]
     check_and_exec_cuda_version()?;
@@ -509,6 +1195,16 @@ fn main() -> Result<()> {
     #[cfg(not(feature = "embedding"))]
     let is_mcp_mode = false;
 
+    // Restore default SIGPIPE handling so piping to head/grep etc. exits cleanly.
+    // Not in MCP mode: a client closing the stdio transport should surface as a
+    // write error that ends the server loop, not kill the process mid-response.
+    #[cfg(unix)]
+    if !is_mcp_mode {
+        unsafe {
+            libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+        }
+    }
+
     if is_mcp_mode {
         // MCP mode: write logs to stderr to avoid polluting the JSON-RPC channel on stdout
         tracing_subscriber::fmt()
@@ -532,7 +1228,28 @@ fn main() -> Result<()> {
     }
 
     let cli = Cli::parse();
-    let config = Config::new(cli.db);
+    let config_path = cli.config.clone().or_else(Config::default_path);
+    let config = Config::load_with_overrides(
+        config_path.as_deref(),
+        ConfigOverrides {
+            db_path: cli.db,
+            slow_query_ms: cli.slow_query_ms,
+            arch_alias: cli.no_arch_alias.then_some(false),
+            cache_dir: cli.cache_dir,
+            ..Default::default()
+        },
+    )?;
+    let quiet = cli.quiet;
+
+    if let Some(ref cache_dir) = config.cache_dir {
+        std::fs::create_dir_all(cache_dir)?;
+        #[cfg(feature = "embedding")]
+        embedding::hub::set_cache_dir(cache_dir);
+    }
+    #[cfg(feature = "embedding")]
+    if cli.offline {
+        embedding::hub::set_offline();
+    }
 
     match cli.command {
         Commands::Index { command } => match command {
@@ -574,6 +1291,31 @@ fn main() -> Result<()> {
                 }
             }
 
+            IndexCommands::Url {
+                url,
+                repo,
+                update,
+                filelists,
+            } => {
+                let _span =
+                    tracing::info_span!("index_url", repo = %repo, url = %url, update).entered();
+                info!("Indexing remote repository");
+                let db_path = config.db_path.clone();
+                let mut api = api::RpmSearchApi::new(config)?;
+                let (count, files) =
+                    sync::syncer::index_from_url(&mut api, &url, &repo, filelists, update)?;
+                info!(count, "Successfully indexed packages");
+                if let Some(files) = files {
+                    info!(files, "Successfully indexed file entries");
+                }
+
+                // Same as `index repo --base-url`, so download URLs resolve
+                let conn = rusqlite::Connection::open(&db_path)?;
+                let state_store = sync::SyncStateStore::new(conn)?;
+                state_store.set_base_url(&repo, &url)?;
+                info!(base_url = %url, "Saved repository base URL");
+            }
+
             IndexCommands::Filelists { file, repo } => {
                 let _span =
                     tracing::info_span!("index_filelists", repo = %repo, file = %file.display())
@@ -584,18 +1326,86 @@ fn main() -> Result<()> {
                 info!(count, "Successfully indexed file entries");
             }
 
+            IndexCommands::Rpms {
+                dir,
+                repo,
+                base_url,
+            } => {
+                let _span =
+                    tracing::info_span!("index_rpms", repo = %repo, dir = %dir.display()).entered();
+                info!("Indexing RPM files");
+                let db_path = config.db_path.clone();
+                let mut api = api::RpmSearchApi::new(config)?;
+                let (count, files) = api.index_rpm_directory(&dir, &repo)?;
+                info!(count, files, "Successfully indexed RPM files");
+
+                if let Some(ref url) = base_url {
+                    let conn = rusqlite::Connection::open(&db_path)?;
+                    let state_store = sync::SyncStateStore::new(conn)?;
+                    state_store.set_base_url(&repo, url)?;
+                    info!(base_url = %url, "Saved repository base URL");
+                }
+            }
+
+            IndexCommands::Appstream { file, repo } => {
+                let _span =
+                    tracing::info_span!("index_appstream", repo = %repo, file = %file.display())
+                        .entered();
+                info!("Indexing AppStream metadata");
+                let mut api = api::RpmSearchApi::new(config)?;
+                let count = api.index_appstream(&file, &repo)?;
+                info!(count, "Successfully indexed AppStream metadata");
+                if count > 0 {
+                    println!(
+                        "Matched {} package(s); run 'index embeddings' to re-embed them",
+                        count
+                    );
+                }
+            }
+
             #[cfg(feature = "embedding")]
             IndexCommands::Embeddings {
                 model_type,
+                model_revision,
                 model,
                 tokenizer,
                 verbose,
                 rebuild,
+                restart,
+                batch_size,
+                text_mode,
+                repo,
             } => {
+                if batch_size == Some(0) {
+                    return Err(error::RpmSearchError::Config(
+                        "--batch-size must be greater than 0".to_string(),
+                    ));
+                }
+
+                let mut config = config;
+                if let Some(model_type) = model_type.filter(|m| *m != config.model_type) {
+                    config.apply(ConfigOverrides {
+                        model_type: Some(model_type),
+                        ..Default::default()
+                    });
+                }
+                config.apply(ConfigOverrides {
+                    model_revision,
+                    embedding_text_mode: text_mode,
+                    ..Default::default()
+                });
+                let model_type = config.model_type.clone();
+                let model_path = model.as_deref().or(config.custom_model_path());
+                // Local model files have no known revision
+                let revision = config
+                    .model_revision
+                    .clone()
+                    .filter(|_| model_path.is_none());
                 let model_files = embedding::hub::resolve_model_files(
                     &model_type,
-                    model.as_deref(),
-                    tokenizer.as_deref(),
+                    model_path,
+                    tokenizer.as_deref().or(config.custom_tokenizer_path()),
+                    revision.as_deref(),
                 )?;
 
                 let _span = tracing::info_span!("build_embeddings",
@@ -608,28 +1418,39 @@ fn main() -> Result<()> {
                 )
                 .entered();
                 info!("Building embeddings");
-                let mut config = config;
-                config.model_type = model_type;
                 config.model_path = model_files
                     .weights
                     .parent()
                     .unwrap_or(std::path::Path::new("."))
                     .to_path_buf();
                 config.tokenizer_path = model_files.tokenizer.clone();
+                if batch_size.is_some() {
+                    config.batch_size = batch_size;
+                }
 
                 let api = api::RpmSearchApi::new(config.clone())?;
-                let embedder = embedding::Embedder::from_model_files(
-                    &model_files,
-                    config.model_type.clone(),
-                )?;
-                let count = api.build_embeddings(&embedder, verbose, rebuild)?;
+                if restart {
+                    api.discard_pending_rebuild(repo.as_deref())?;
+                }
+                let embedder =
+                    embedding::Embedder::from_model_files(&model_files, config.model_type.clone())?
+                        .with_revision(revision);
+                let count = match repo {
+                    Some(ref repo) => {
+                        api.build_repo_embeddings(&embedder, repo, verbose, rebuild)?
+                    }
+                    None => api.build_embeddings(&embedder, verbose, rebuild)?,
+                };
                 info!(count, "Successfully built embeddings");
             }
 
             #[cfg(feature = "embedding")]
-            IndexCommands::DownloadModel { model_type } => {
-                let _span = tracing::info_span!("download_model", model_type = %model_type)
-                    .entered();
+            IndexCommands::DownloadModel {
+                model_type,
+                model_revision,
+            } => {
+                let _span =
+                    tracing::info_span!("download_model", model_type = %model_type).entered();
                 info!("Downloading model");
 
                 println!(
@@ -640,7 +1461,7 @@ fn main() -> Result<()> {
                 println!();
 
                 let hub = embedding::ModelHub::new()?;
-                let files = hub.get_model_files(&model_type)?;
+                let files = hub.get_model_files(&model_type, model_revision.as_deref())?;
 
                 println!("Model files downloaded successfully:");
                 println!("  Config:    {}", files.config.display());
@@ -654,16 +1475,39 @@ fn main() -> Result<()> {
             query,
             arch,
             repo,
+            repo_tag,
+            repos_from_file,
             gbs_conf,
             gbs_profile,
             not_requiring,
             providing,
+            not_providing,
+            relax_filters,
+            context,
+            group_by_source,
+            group_by_repo,
+            min_results,
+            weights,
+            repo_weight,
+            explain_plan,
+            highlight,
+            desc_len,
+            format,
             top_k,
         } => {
-            let repos = resolve_repos(repo, gbs_conf.as_deref(), gbs_profile.as_deref())?;
+            let highlight = highlight && std::io::stdout().is_terminal();
+            let repos = resolve_repos(
+                repo,
+                &repo_tag,
+                repos_from_file.as_deref(),
+                &config.db_path,
+                gbs_conf.as_deref(),
+                gbs_profile.as_deref(),
+            )?;
 
             let _span = tracing::info_span!("search",
                 query = %query,
+                ?context,
                 ?arch,
                 ?repos,
                 top_k
@@ -672,20 +1516,83 @@ fn main() -> Result<()> {
 
             let mut config = config;
             config.top_k = top_k;
+            if let Some(weights) = weights {
+                config.scoring_weights = weights;
+            }
             let db_path = config.db_path.clone();
 
             let api = api::RpmSearchApi::new(config)?;
             let filters = SearchFilters {
                 name: None,
-                arch,
+                arches: arch,
                 repos,
                 not_requiring,
                 providing,
+                not_providing,
+                relax_filters,
+                context,
+                min_results,
+                repo_weights: repo_weight.unwrap_or_default(),
             };
 
             let result = api.search_with_scores(&query, filters)?;
 
             info!(count = result.packages.len(), "Search completed");
+            if explain_plan {
+                eprintln!("{}", result.plan);
+            }
+
+            // --group-by-source: results are ordered by score, so the first
+            // binary seen for a source package is its best-scoring variant
+            let mut variants: HashMap<&str, Vec<String>> = HashMap::new();
+            let mut shown: Vec<usize> = if group_by_source {
+                let mut best = Vec::new();
+                for (i, pkg) in result.packages.iter().enumerate() {
+                    let entry = variants.entry(pkg.source_name()).or_default();
+                    if entry.is_empty() {
+                        best.push(i);
+                    }
+                    entry.push(format!("{}.{}", pkg.name, pkg.arch));
+                }
+                best
+            } else {
+                (0..result.packages.len()).collect()
+            };
+
+            // --group-by-repo: a repo's first result is its best, so groups are
+            // ordered by best score and keep score order inside
+            let repo_groups = if group_by_repo {
+                output::group_by_repo(&result.packages, &shown)
+            } else {
+                vec![("", shown.clone())]
+            };
+            if group_by_repo {
+                shown = repo_groups
+                    .iter()
+                    .flat_map(|(_, group)| group.iter().copied())
+                    .collect();
+            }
+
+            if let Some(format) = format {
+                let mut writer =
+                    output::PackageWriter::new(std::io::stdout().lock(), format, false)?;
+                for &i in &shown {
+                    writer.write(&result.packages[i])?;
+                }
+                return writer.finish();
+            }
+
+            if quiet {
+                for &i in &shown {
+                    let pkg = &result.packages[i];
+                    if group_by_source {
+                        println!("{}", pkg.source_name());
+                    } else {
+                        println!("{}-{}.{}", pkg.name, pkg.full_version(), pkg.arch);
+                    }
+                }
+                return Ok(());
+            }
 
             // Open state store for download URL resolution
             let state_store = {
@@ -694,37 +1601,104 @@ fn main() -> Result<()> {
             };
 
             // Output results to stdout (not logged)
-            println!("\nFound {} packages:\n", result.packages.len());
-            for (i, pkg) in result.packages.iter().enumerate() {
-                let score = result.scores.get(i).copied().unwrap_or(0.0);
-                println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            if result.relaxed {
                 println!(
-                    "📦 {} - {}  (score: {:.3})",
-                    pkg.name,
-                    pkg.full_version(),
-                    score
+                    "\n⚠️  No packages match the --arch/--repo filters; \
+                     showing results from all repositories instead."
                 );
-                println!("   Architecture: {}", pkg.arch);
-                println!("   Repository: {}", pkg.repo);
-                println!("   Summary: {}", pkg.summary);
-                if let Some(ref license) = pkg.license {
-                    println!("   License: {}", license);
-                }
-                if let Some(ref vcs) = pkg.vcs {
-                    println!("   VCS: {}", vcs);
-                }
-                if let Some(url) = build_download_url(&state_store, pkg) {
-                    println!("   Download: {}", url);
+            } else if result.prefilter_empty {
+                println!(
+                    "\n⚠️  No packages match the --arch/--repo filters. \
+                     Check the names with `repo list`, or use --relax-filters."
+                );
+            }
+            if group_by_source {
+                println!(
+                    "\nFound {} packages from {} source packages:\n",
+                    result.packages.len(),
+                    shown.len()
+                );
+            } else if group_by_repo {
+                println!(
+                    "\nFound {} packages in {} repositories:\n",
+                    result.packages.len(),
+                    repo_groups.len()
+                );
+            } else {
+                println!("\nFound {} packages:\n", result.packages.len());
+            }
+            let first_low_confidence = result.packages.len() - result.low_confidence;
+            for (repo, group) in &repo_groups {
+                if group_by_repo {
+                    println!("══ {} ({} packages) ══\n", repo, group.len());
                 }
-                if !pkg.description.is_empty() {
-                    let desc = if pkg.description.len() > 200 {
-                        format!("{}...", &pkg.description[..200])
+                for &i in group {
+                    let pkg = &result.packages[i];
+                    let score = result.scores.get(i).copied().unwrap_or(0.0);
+                    let confidence = if i >= first_low_confidence {
+                        ", low confidence"
                     } else {
-                        pkg.description.clone()
+                        ""
                     };
-                    println!("   Description: {}", desc);
+                    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+                    println!(
+                        "📦 {} - {}  (score: {:.3}{})",
+                        pkg.name,
+                        pkg.full_version(),
+                        score,
+                        confidence
+                    );
+                    if group_by_source {
+                        let source_variants = &variants[pkg.source_name()];
+                        println!(
+                            "   Source: {} ({} variants: {})",
+                            pkg.source_name(),
+                            source_variants.len(),
+                            source_variants.join(", ")
+                        );
+                    }
+                    println!("   Architecture: {}", pkg.arch);
+                    if result.relaxed {
+                        println!("   Repository: {} (outside filter)", pkg.repo);
+                    } else {
+                        println!("   Repository: {}", pkg.repo);
+                    }
+                    if highlight {
+                        println!(
+                            "   Summary: {}",
+                            output::highlight_terms(&pkg.summary, &query)
+                        );
+                    } else {
+                        println!("   Summary: {}", pkg.summary);
+                    }
+                    if let Some(ref license) = pkg.license {
+                        println!("   License: {}", license);
+                    }
+                    if let Some(ref vcs) = pkg.vcs {
+                        println!("   VCS: {}", vcs);
+                    }
+                    if let Some(url) = build_download_url(&state_store, pkg) {
+                        println!("   Download: {}", url);
+                    }
+                    if !pkg.description.is_empty() {
+                        let shown = if desc_len > 0 {
+                            truncate_at_char_boundary(&pkg.description, desc_len)
+                        } else {
+                            &pkg.description
+                        };
+                        let mut desc = if shown.len() < pkg.description.len() {
+                            format!("{}...", shown)
+                        } else {
+                            pkg.description.clone()
+                        };
+                        // Highlight after truncating so no escape sequence is cut
+                        if highlight {
+                            desc = output::highlight_terms(&desc, &query);
+                        }
+                        println!("   Description: {}", desc);
+                    }
+                    println!();
                 }
-                println!();
             }
         }
 
@@ -741,71 +1715,393 @@ fn main() -> Result<()> {
             println!("  Total directories: {}", dir_count);
         }
 
-        Commands::Repo { command } => match command {
-            RepoCommands::List => {
-                let _span = tracing::info_span!("list_repos").entered();
+        Commands::Doctor { json } => {
+            let _span = tracing::info_span!("doctor").entered();
+            let api = api::RpmSearchApi::new(config)?;
+            let diag = api.diagnostics()?;
+
+            if json {
+                let output = serde_json::to_string_pretty(&diag).map_err(|e| {
+                    error::RpmSearchError::Config(format!("Failed to serialize diagnostics: {}", e))
+                })?;
+                println!("{}", output);
+                return Ok(());
+            }
+
+            println!("Database: {}", diag.db_path);
+            println!("  Schema version:  {}", diag.schema_version);
+            println!(
+                "  sqlite-vec:      {}",
+                diag.sqlite_vec_version.as_deref().unwrap_or("not loaded")
+            );
+            println!("  Packages:        {}", diag.total_packages);
+            println!(
+                "  Embedded:        {} / {}",
+                diag.embedded_packages, diag.total_packages
+            );
+            println!(
+                "  Embedding model: {}",
+                diag.embedding_model.as_deref().unwrap_or("(none)")
+            );
+            if let Some(ref mode) = diag.embedding_text_mode {
+                println!("  Embedding text:  {}", mode);
+            }
+
+            if !diag.repos.is_empty() {
+                println!(
+                    "\n{:<30} {:>10} {:>10}  {:<10} Model",
+                    "Repository", "Packages", "Embedded", "Filelists"
+                );
+                println!("{}", "─".repeat(76));
+                for repo in &diag.repos {
+                    println!(
+                        "{:<30} {:>10} {:>10}  {:<10} {}",
+                        repo.name,
+                        repo.packages,
+                        repo.embedded_packages,
+                        if repo.has_filelists { "yes" } else { "no" },
+                        repo.embedding_model.as_deref().unwrap_or("-")
+                    );
+                }
+            }
+
+            let mut problems = Vec::new();
+            if cfg!(feature = "embedding") && diag.sqlite_vec_version.is_none() {
+                problems.push("sqlite-vec extension is not loaded".to_string());
+            }
+            if diag.total_packages == 0 {
+                problems.push("no packages indexed (run 'index repo' or 'sync once')".to_string());
+            } else if diag.embedded_packages < diag.total_packages {
+                problems.push(format!(
+                    "{} packages have no embeddings (run 'index embeddings')",
+                    diag.total_packages - diag.embedded_packages
+                ));
+            }
+
+            println!();
+            if problems.is_empty() {
+                println!("✓ No problems found");
+            } else {
+                for problem in &problems {
+                    println!("⚠️  {}", problem);
+                }
+            }
+        }
+
+        Commands::Embedding { command } => match command {
+            EmbeddingCommands::Status { json } => {
+                let _span = tracing::info_span!("embedding_status").entered();
+                let api = api::RpmSearchApi::new(config)?;
+                let coverage = api.embedding_coverage()?;
+
+                if json {
+                    let output = serde_json::to_string_pretty(&coverage).map_err(|e| {
+                        error::RpmSearchError::Config(format!(
+                            "Failed to serialize embedding coverage: {}",
+                            e
+                        ))
+                    })?;
+                    println!("{}", output);
+                    return Ok(());
+                }
+
+                if coverage.is_empty() {
+                    println!("No repositories indexed.");
+                    return Ok(());
+                }
+
+                println!(
+                    "{:<30} {:>10} {:>10} {:>9}",
+                    "Repository", "Total", "Embedded", "Coverage"
+                );
+                println!("{}", "─".repeat(62));
+                for repo in &coverage {
+                    println!(
+                        "{:<30} {:>10} {:>10} {:>8.1}%",
+                        repo.repo,
+                        repo.packages,
+                        repo.embedded_packages,
+                        repo.percent()
+                    );
+                }
+                if coverage
+                    .iter()
+                    .any(|repo| repo.embedded_packages < repo.packages)
+                {
+                    println!("\nRun `index embeddings` to embed the missing packages.");
+                }
+            }
+        },
+
+        Commands::Repo { command } => match command {
+            RepoCommands::List => {
+                let _span = tracing::info_span!("list_repos").entered();
+                let api = api::RpmSearchApi::new(config)?;
+                let repos = api.list_repositories()?;
+
+                info!(repo_count = repos.len(), "Retrieved repository list");
+
+                if quiet {
+                    for (repo_name, count) in repos {
+                        println!("{}\t{}", repo_name, count);
+                    }
+                } else if repos.is_empty() {
+                    println!("No repositories indexed yet.");
+                } else {
+                    println!("\nIndexed Repositories:\n");
+                    println!("{:<30} {:>10}", "Repository", "Packages");
+                    println!("{}", "─".repeat(42));
+                    for (repo_name, count) in repos {
+                        println!("{:<30} {:>10}", repo_name, count);
+                    }
+                }
+            }
+
+            RepoCommands::Stats { repo } => {
+                let _span = tracing::info_span!("repo_stats", repo = %repo).entered();
+                let api = api::RpmSearchApi::new(config)?;
+                let count = api.repo_package_count(&repo)?;
+
+                info!(count, "Retrieved repository statistics");
+
+                println!("\nRepository: {}", repo);
+                println!("  Packages: {}", count);
+            }
+
+            RepoCommands::Delete { repo, yes } => {
+                let _span = tracing::info_span!("delete_repo", repo = %repo).entered();
+
+                if !yes {
+                    println!(
+                        "⚠️  This will permanently delete repository '{}' and all its packages.",
+                        repo
+                    );
+                    println!("   Use --yes to confirm deletion.");
+                    return Ok(());
+                }
+
+                let mut api = api::RpmSearchApi::new(config)?;
+                let deleted = api.delete_repository(&repo)?;
+
+                info!(deleted, "Deleted repository");
+
+                if deleted == 0 {
+                    println!("Repository '{}' not found or already empty.", repo);
+                } else {
+                    println!(
+                        "✓ Deleted repository '{}' ({} packages removed)",
+                        repo, deleted
+                    );
+                }
+            }
+
+            RepoCommands::Compare {
+                repo_a,
+                repo_b,
+                json,
+            } => {
+                let _span =
+                    tracing::info_span!("compare_repos", repo_a = %repo_a, repo_b = %repo_b)
+                        .entered();
+                let api = api::RpmSearchApi::new(config)?;
+                let diff = api.compare_repositories(&repo_a, &repo_b)?;
+
+                info!(
+                    only_in_a = diff.only_in_a.len(),
+                    only_in_b = diff.only_in_b.len(),
+                    changed = diff.changed.len(),
+                    "Compared repositories"
+                );
+
+                if json {
+                    let output = serde_json::to_string_pretty(&diff).map_err(|e| {
+                        error::RpmSearchError::Config(format!("Failed to serialize diff: {}", e))
+                    })?;
+                    println!("{}", output);
+                    return Ok(());
+                }
+
+                println!("\nOnly in {} ({}):", repo_a, diff.only_in_a.len());
+                for entry in &diff.only_in_a {
+                    println!("  {}-{}.{}", entry.name, entry.version, entry.arch);
+                }
+
+                println!("\nOnly in {} ({}):", repo_b, diff.only_in_b.len());
+                for entry in &diff.only_in_b {
+                    println!("  {}-{}.{}", entry.name, entry.version, entry.arch);
+                }
+
+                println!("\nVersion differences ({}):", diff.changed.len());
+                if !diff.changed.is_empty() {
+                    println!("  {:<40} {:>25}   {:<25} Newer", "Package", repo_a, repo_b);
+                    println!("  {}", "─".repeat(100));
+                }
+                for change in &diff.changed {
+                    println!(
+                        "  {:<40} {:>25}   {:<25} {}",
+                        format!("{}.{}", change.name, change.arch),
+                        change.version_a,
+                        change.version_b,
+                        change.newer
+                    );
+                }
+            }
+
+            RepoCommands::Conflicts { json } => {
+                let _span = tracing::info_span!("repo_conflicts").entered();
                 let api = api::RpmSearchApi::new(config)?;
-                let repos = api.list_repositories()?;
+                let duplicates = api.duplicate_provides()?;
+
+                info!(count = duplicates.len(), "Found duplicate capabilities");
+
+                if json {
+                    let output = serde_json::to_string_pretty(&duplicates).map_err(|e| {
+                        error::RpmSearchError::Config(format!(
+                            "Failed to serialize conflicts: {}",
+                            e
+                        ))
+                    })?;
+                    println!("{}", output);
+                    return Ok(());
+                }
 
-                info!(repo_count = repos.len(), "Retrieved repository list");
+                if duplicates.is_empty() {
+                    println!("No capabilities are provided by more than one repository.");
+                    return Ok(());
+                }
 
-                if repos.is_empty() {
-                    println!("No repositories indexed yet.");
-                } else {
-                    println!("\nIndexed Repositories:\n");
-                    println!("{:<30} {:>10}", "Repository", "Packages");
-                    println!("{}", "─".repeat(42));
-                    for (repo_name, count) in repos {
-                        println!("{:<30} {:>10}", repo_name, count);
+                println!(
+                    "Capabilities provided in multiple repositories ({}):",
+                    duplicates.len()
+                );
+                for duplicate in &duplicates {
+                    println!("\n{}", duplicate.capability);
+                    for provider in &duplicate.providers {
+                        println!("  {:<25} {}", provider.repo, provider.nevra);
                     }
                 }
             }
 
-            RepoCommands::Stats { repo } => {
-                let _span = tracing::info_span!("repo_stats", repo = %repo).entered();
+            RepoCommands::FileConflicts { repo, json } => {
+                let _span = tracing::info_span!("repo_file_conflicts", repo = %repo).entered();
                 let api = api::RpmSearchApi::new(config)?;
-                let count = api.repo_package_count(&repo)?;
+                let conflicts = api.conflicting_files(&repo)?;
+
+                info!(count = conflicts.len(), "Found conflicting files");
+
+                if json {
+                    let output = serde_json::to_string_pretty(&conflicts).map_err(|e| {
+                        error::RpmSearchError::Config(format!(
+                            "Failed to serialize file conflicts: {}",
+                            e
+                        ))
+                    })?;
+                    println!("{}", output);
+                    return Ok(());
+                }
 
-                info!(count, "Retrieved repository statistics");
+                if conflicts.is_empty() {
+                    println!("No files in '{}' are owned by more than one package.", repo);
+                    return Ok(());
+                }
 
-                println!("\nRepository: {}", repo);
-                println!("  Packages: {}", count);
+                println!(
+                    "Files owned by more than one package in '{}' ({}):",
+                    repo,
+                    conflicts.len()
+                );
+                for conflict in &conflicts {
+                    println!("\n{}", conflict.path);
+                    for owner in &conflict.owners {
+                        println!("  {}", owner.nevra);
+                    }
+                }
             }
 
-            RepoCommands::Delete { repo, yes } => {
-                let _span = tracing::info_span!("delete_repo", repo = %repo).entered();
+            RepoCommands::Export {
+                repo,
+                output,
+                with_embeddings,
+            } => {
+                let _span = tracing::info_span!("export_repo", repo = %repo).entered();
+                let api = api::RpmSearchApi::new(config)?;
 
-                if !yes {
+                if output.as_os_str() == "-" {
+                    let stdout = std::io::stdout();
+                    api.export_repository(&repo, stdout.lock(), with_embeddings)?;
+                } else {
+                    let file = std::fs::File::create(&output)?;
+                    let count = api.export_repository(
+                        &repo,
+                        std::io::BufWriter::new(file),
+                        with_embeddings,
+                    )?;
                     println!(
-                        "⚠️  This will permanently delete repository '{}' and all its packages.",
-                        repo
+                        "✓ Exported {} packages from '{}' to {}",
+                        count,
+                        repo,
+                        output.display()
                     );
-                    println!("   Use --yes to confirm deletion.");
-                    return Ok(());
                 }
+            }
 
+            RepoCommands::Import { input, repo } => {
+                let _span = tracing::info_span!("import_repo", repo = %repo).entered();
                 let mut api = api::RpmSearchApi::new(config)?;
-                let deleted = api.delete_repository(&repo)?;
-
-                info!(deleted, "Deleted repository");
 
-                if deleted == 0 {
-                    println!("Repository '{}' not found or already empty.", repo);
+                let (packages, files, embeddings) = if input.as_os_str() == "-" {
+                    api.import_repository(std::io::stdin().lock(), &repo)?
                 } else {
-                    println!(
-                        "✓ Deleted repository '{}' ({} packages removed)",
-                        repo, deleted
-                    );
+                    let file = std::fs::File::open(&input)?;
+                    api.import_repository(std::io::BufReader::new(file), &repo)?
+                };
+
+                println!("✓ Imported {} packages into '{}'", packages, repo);
+                if files > 0 {
+                    println!("  Files:      {}", files);
+                }
+                if embeddings > 0 {
+                    println!("  Embeddings: {}", embeddings);
                 }
             }
         },
 
         #[cfg(feature = "embedding")]
-        Commands::McpServer => {
+        Commands::McpServer { http, tool_timeout } => {
             let _span = tracing::info_span!("mcp_server").entered();
             info!("Starting MCP server");
+            let mut config = config;
+            config.apply(ConfigOverrides {
+                mcp_tool_timeout_secs: tool_timeout,
+                ..Default::default()
+            });
             let server = mcp::McpServer::new(config)?;
-            server.run()?;
+            match http {
+                #[cfg(feature = "http")]
+                Some(addr) => {
+                    let runtime = tokio::runtime::Runtime::new().map_err(|e| {
+                        error::RpmSearchError::Storage(format!("Failed to create runtime: {}", e))
+                    })?;
+                    runtime.block_on(server.run_http(addr))?;
+                }
+                #[cfg(not(feature = "http"))]
+                Some(_) => {
+                    return Err(error::RpmSearchError::Config(
+                        "--http needs a build with the `http` feature".to_string(),
+                    ));
+                }
+                None => server.run()?,
+            }
+        }
+
+        #[cfg(feature = "http")]
+        Commands::Serve { bind } => {
+            let _span = tracing::info_span!("serve").entered();
+            let server = http::HttpServer::new(config)?;
+            let runtime = tokio::runtime::Runtime::new().map_err(|e| {
+                error::RpmSearchError::Storage(format!("Failed to create runtime: {}", e))
+            })?;
+            runtime.block_on(server.run(bind))?;
         }
 
         Commands::Sync { command } => match command {
@@ -840,8 +2136,12 @@ fn main() -> Result<()> {
                 gbs_conf,
                 gbs_profile,
                 no_embedding,
+                dry_run,
+                keep_metadata,
+                repo,
+                verbose,
             } => {
-                let sync_config = if let Some(gbs_path) = gbs_conf {
+                let mut sync_config = if let Some(gbs_path) = gbs_conf {
                     let _span =
                         tracing::info_span!("sync_once", gbs_conf = %gbs_path.display()).entered();
                     info!("Performing one-time sync from GBS config");
@@ -854,45 +2154,128 @@ fn main() -> Result<()> {
                     info!("Performing one-time sync");
                     sync::SyncConfig::from_file(&config_path)?
                 };
-                let scheduler = sync::SyncScheduler::new(sync_config, config.clone());
+                sync_config.select_repositories(&repo)?;
+                if keep_metadata {
+                    sync_config.keep_metadata();
+                }
+                let mut scheduler = sync::SyncScheduler::new(sync_config, config.clone());
+                scheduler.set_progress(true);
 
                 let runtime = tokio::runtime::Runtime::new().map_err(|e| {
                     error::RpmSearchError::Config(format!("Failed to create runtime: {}", e))
                 })?;
 
+                if dry_run {
+                    let results = runtime.block_on(scheduler.dry_run_once())?;
+
+                    println!("\nSync Preview (dry run, no changes written):");
+                    println!(
+                        "{:<30} {:<12} {:>8} {:>8} {:>8}",
+                        "Repository", "Status", "Added", "Updated", "Removed"
+                    );
+                    println!("{}", "─".repeat(70));
+
+                    let mut repos: Vec<_> = results.into_iter().collect();
+                    repos.sort_by(|a, b| a.0.cmp(&b.0));
+                    for (repo, result) in repos {
+                        match result {
+                            Ok(preview) if preview.changed => println!(
+                                "{:<30} {:<12} {:>8} {:>8} {:>8}",
+                                repo, "changed", preview.added, preview.updated, preview.removed
+                            ),
+                            Ok(_) => println!("{:<30} {:<12}", repo, "unchanged"),
+                            Err(e) => {
+                                eprintln!("Error for {}: {}", repo, e);
+                                println!("{:<30} {:<12}", repo, "✗ Failed");
+                            }
+                        }
+                    }
+                    return Ok(());
+                }
+
                 let results = runtime.block_on(scheduler.sync_once())?;
 
                 println!("\nSync Results:");
-                println!("{:<30} {:<15}", "Repository", "Status");
-                println!("{}", "─".repeat(47));
+                if verbose {
+                    println!(
+                        "{:<30} {:<12} {:>8} {:>8} {:>8} {:>10} {:>8} {:>8}",
+                        "Repository",
+                        "Status",
+                        "Added",
+                        "Updated",
+                        "Removed",
+                        "Download",
+                        "Parse",
+                        "Insert"
+                    );
+                    println!("{}", "─".repeat(100));
+                } else {
+                    println!("{:<30} {:<15}", "Repository", "Status");
+                    println!("{}", "─".repeat(47));
+                }
 
+                let mut changed_repos = Vec::new();
+                let mut synced_repos = Vec::new();
                 for (repo, result) in results {
                     let status = match result {
-                        Ok(_) => "✓ Success",
+                        Ok(ref sync_result) => {
+                            if sync_result.changed {
+                                changed_repos.push(repo.clone());
+                            }
+                            synced_repos.push(repo.clone());
+                            "✓ Success"
+                        }
                         Err(ref e) => {
                             eprintln!("Error for {}: {}", repo, e);
                             "✗ Failed"
                         }
                     };
-                    println!("{:<30} {:<15}", repo, status);
+                    match result {
+                        Ok(ref r) if verbose => println!(
+                            "{:<30} {:<12} {:>8} {:>8} {:>8} {:>9.1}s {:>7.1}s {:>7.1}s",
+                            repo,
+                            if r.changed { status } else { "unchanged" },
+                            r.added,
+                            r.updated,
+                            r.removed,
+                            r.download_time.as_secs_f64(),
+                            r.parse_time.as_secs_f64(),
+                            r.insert_time.as_secs_f64()
+                        ),
+                        _ => println!("{:<30} {:<15}", repo, status),
+                    }
                 }
 
-                // Automatically build embeddings incrementally after sync
+                // Automatically build embeddings incrementally for the synced repos
                 #[cfg(feature = "embedding")]
                 if !no_embedding {
-                    println!("\n🔨 Building embeddings for new packages...");
-                    let model_files =
-                        embedding::hub::resolve_model_files(&config.model_type, None, None)?;
                     let api = api::RpmSearchApi::new(config.clone())?;
-                    let embedder = embedding::Embedder::from_model_files(
-                        &model_files,
-                        config.model_type.clone(),
-                    )?;
-                    let count = api.build_embeddings(&embedder, false, false)?;
-                    if count > 0 {
-                        println!("✅ Built embeddings for {} new packages", count);
+                    // Unchanged repos may still lack embeddings, e.g. from an
+                    // earlier sync with --no-embedding
+                    let mut embed_repos = changed_repos;
+                    for coverage in api.embedding_coverage()? {
+                        if coverage.embedded_packages < coverage.packages
+                            && synced_repos.contains(&coverage.repo)
+                            && !embed_repos.contains(&coverage.repo)
+                        {
+                            embed_repos.push(coverage.repo);
+                        }
+                    }
+
+                    if embed_repos.is_empty() {
+                        println!("\n✅ No repositories changed, embeddings up to date");
                     } else {
-                        println!("✅ All embeddings up to date");
+                        println!("\n🔨 Building embeddings for new packages...");
+                        let count = api.build_embeddings_for_repos(
+                            &embed_repos,
+                            |model_type| embedding::Embedder::for_config(&config, model_type),
+                            false,
+                        )?;
+                        if count > 0 {
+                            println!("✅ Built embeddings for {} new packages", count);
+                        } else {
+                            println!("✅ All embeddings up to date");
+                        }
                     }
                 }
                 #[cfg(feature = "embedding")]
@@ -901,7 +2284,7 @@ fn main() -> Result<()> {
                 }
                 #[cfg(not(feature = "embedding"))]
                 {
-                    let _ = no_embedding;
+                    let _ = (no_embedding, changed_repos, synced_repos);
                 }
             }
 
@@ -910,8 +2293,10 @@ fn main() -> Result<()> {
                 gbs_conf,
                 gbs_profile,
                 no_embedding,
+                keep_metadata,
+                repo,
             } => {
-                let sync_config = if let Some(gbs_path) = gbs_conf {
+                let mut sync_config = if let Some(gbs_path) = gbs_conf {
                     let _span = tracing::info_span!("sync_daemon", gbs_conf = %gbs_path.display())
                         .entered();
                     info!("Starting sync daemon from GBS config");
@@ -924,6 +2309,10 @@ fn main() -> Result<()> {
                     info!("Starting sync daemon");
                     sync::SyncConfig::from_file(&config_path)?
                 };
+                sync_config.select_repositories(&repo)?;
+                if keep_metadata {
+                    sync_config.keep_metadata();
+                }
                 let mut scheduler = sync::SyncScheduler::new(sync_config, config);
                 scheduler.set_embedding_enabled(!no_embedding);
 
@@ -988,71 +2377,226 @@ fn main() -> Result<()> {
             }
         },
 
+        Commands::Config { command } => {
+            let path = config_path.ok_or_else(|| {
+                error::RpmSearchError::Config(
+                    "Cannot determine config file location (use --config)".to_string(),
+                )
+            })?;
+
+            match command {
+                ConfigCommands::Show => {
+                    let status = if path.exists() {
+                        "loaded"
+                    } else {
+                        "not found, using defaults"
+                    };
+                    println!("# Config file: {} ({})", path.display(), status);
+                    let content = toml::to_string_pretty(&config).map_err(|e| {
+                        error::RpmSearchError::Config(format!("Failed to serialize config: {}", e))
+                    })?;
+                    print!("{}", content);
+                }
+                ConfigCommands::Init { force } => {
+                    if path.exists() && !force {
+                        return Err(error::RpmSearchError::Config(format!(
+                            "Config file already exists: {} (use --force to overwrite)",
+                            path.display()
+                        )));
+                    }
+                    Config::default().to_file(&path)?;
+                    println!("Wrote default configuration to {}", path.display());
+                }
+            }
+        }
+
         Commands::Repoquery {
             package,
+            name_exact,
+            regex,
             whatprovides,
             whatrequires,
+            whatconflicts,
+            whatobsoletes,
             file,
+            file_type,
             summary,
             description,
             info,
             list,
+            tree,
             requires,
+            no_pre,
             provides,
+            source,
+            duplicates,
+            upgrades,
+            installed_list,
             queryformat,
+            format,
+            pretty,
             arch,
             repo,
+            repo_tag,
+            repos_from_file,
             gbs_conf,
             gbs_profile,
             latest,
+            latest_n,
+            repo_priority,
+            lang,
             limit,
+            sort,
+            reverse,
+            arch_order,
         } => {
-            let repos = resolve_repos(repo, gbs_conf.as_deref(), gbs_profile.as_deref())?;
+            let repos = resolve_repos(
+                repo,
+                &repo_tag,
+                repos_from_file.as_deref(),
+                &config.db_path,
+                gbs_conf.as_deref(),
+                gbs_profile.as_deref(),
+            )?;
 
             let _span = tracing::info_span!("repoquery").entered();
             let db_path = config.db_path.clone();
             let api = api::RpmSearchApi::new(config)?;
 
+            // --duplicates: (name, arch) groups with several indexed versions
+            if duplicates {
+                let groups = api.duplicate_versions(&arch, &repos)?;
+                if groups.is_empty() {
+                    if !quiet {
+                        println!("No packages are indexed with more than one version.");
+                    }
+                    return Ok(());
+                }
+                for group in &groups {
+                    if !quiet {
+                        println!(
+                            "{}.{} ({} versions)",
+                            group.name,
+                            group.arch,
+                            group.packages.len()
+                        );
+                    }
+                    for pkg in &group.packages {
+                        if quiet {
+                            println!("{}", pkg.nevra);
+                        } else {
+                            println!("  {:<25} {}", pkg.repo, pkg.nevra);
+                        }
+                    }
+                }
+                return Ok(());
+            }
+
+            // --upgrades: indexed packages newer than what is installed
+            if upgrades {
+                let installed = match installed_list {
+                    Some(ref path) => installed::read_installed_list(path)?,
+                    None => installed::query_rpmdb()?,
+                };
+                let upgrades = api.find_upgrades(&installed, &arch, &repos)?;
+                info!(
+                    installed = installed.len(),
+                    upgrades = upgrades.len(),
+                    "Compared installed packages"
+                );
+
+                if let Some(format) = format {
+                    let packages: Vec<Package> =
+                        upgrades.into_iter().map(|u| u.available).collect();
+                    output::write_packages(std::io::stdout().lock(), format, pretty, &packages)?;
+                    return Ok(());
+                }
+                if upgrades.is_empty() {
+                    if !quiet {
+                        println!("No upgrades available.");
+                    }
+                    return Ok(());
+                }
+                for upgrade in &upgrades {
+                    let pkg = &upgrade.available;
+                    if quiet {
+                        println!("{}-{}.{}", pkg.name, pkg.full_version(), pkg.arch);
+                    } else {
+                        println!(
+                            "{:<40} {:<30} {:<25} (installed: {})",
+                            format!("{}.{}", pkg.name, pkg.arch),
+                            pkg.full_version(),
+                            pkg.repo,
+                            upgrade.installed
+                        );
+                    }
+                }
+                return Ok(());
+            }
+
             // 1. Query phase: build FindFilter from all criteria
             let filter = FindFilter {
                 name: package.clone(),
+                name_exact,
+                regex,
                 summary,
                 description,
                 provides: whatprovides.clone(),
                 requires: whatrequires.clone(),
+                conflicts: whatconflicts.clone(),
+                obsoletes: whatobsoletes.clone(),
                 file: file.clone(),
-                arch: arch.clone(),
+                file_type,
+                arches: arch.clone(),
                 repos: repos.clone(),
                 limit,
+                sort,
+                reverse,
             };
 
-            let has_any_condition = filter.name.is_some()
-                || filter.summary.is_some()
-                || filter.description.is_some()
-                || filter.provides.is_some()
-                || filter.requires.is_some()
-                || filter.file.is_some()
-                || filter.arch.is_some()
-                || !filter.repos.is_empty();
-
-            let mut packages = if has_any_condition {
-                api.find(&filter)?
-            } else {
-                // No criteria given at all: list all packages
-                let all_filter = FindFilter {
-                    name: Some("*".to_string()),
-                    limit,
-                    ..Default::default()
-                };
-                api.find(&all_filter)?
-            };
+            // NDJSON streams each package as it is loaded (--latest and
+            // --arch-order need them all first)
+            let latest_n = if latest { Some(1) } else { latest_n };
+            if format == Some(OutputFormat::Ndjson) && latest_n.is_none() && arch_order.is_empty() {
+                let mut writer = output::PackageWriter::new(
+                    std::io::stdout().lock(),
+                    OutputFormat::Ndjson,
+                    false,
+                )?;
+                api.find_each(&match_all_if_empty(filter), |mut pkg| {
+                    if let Some(ref lang) = lang {
+                        pkg.localize(lang);
+                    }
+                    writer.write(&pkg)
+                })?;
+                return writer.finish();
+            }
 
-            // 2. Filter phase: --latest
-            if latest {
-                packages = filter_latest(packages);
+            let mut packages = find_packages(&api, filter)?;
+
+            // 2. Filter phase: --latest / --latest-n
+            if let Some(k) = latest_n {
+                packages = filter_latest_n(packages, k as usize, &repo_priority);
+                sort.sort_packages(&mut packages, reverse);
+            }
+            sort_by_arch_order(&mut packages, &arch_order);
+
+            if let Some(ref lang) = lang {
+                for pkg in &mut packages {
+                    pkg.localize(lang);
+                }
+            }
+
+            // --format: machine-readable output (header only when nothing matched)
+            if let Some(format) = format {
+                output::write_packages(std::io::stdout().lock(), format, pretty, &packages)?;
+                return Ok(());
             }
 
             if packages.is_empty() {
+                if quiet {
+                    return Ok(());
+                }
                 // Describe what was searched
                 if let Some(ref p) = package {
                     println!("No packages found matching '{}'", p);
@@ -1060,6 +2604,10 @@ fn main() -> Result<()> {
                     println!("No packages found providing '{}'", cap);
                 } else if let Some(ref cap) = whatrequires {
                     println!("No packages found requiring '{}'", cap);
+                } else if let Some(ref cap) = whatconflicts {
+                    println!("No packages found conflicting with '{}'", cap);
+                } else if let Some(ref cap) = whatobsoletes {
+                    println!("No packages found obsoleting '{}'", cap);
                 } else if let Some(ref f) = file {
                     println!("No packages found owning '{}'", f);
                 } else {
@@ -1109,35 +2657,27 @@ fn main() -> Result<()> {
             } else if requires {
                 // --requires: show requires for each package
                 for pkg in &packages {
-                    if packages.len() > 1 {
+                    if packages.len() > 1 && !quiet {
                         println!("# {}-{}.{}", pkg.name, pkg.full_version(), pkg.arch);
                     }
-                    for dep in &pkg.requires {
-                        if let (Some(flags), Some(ver)) = (&dep.flags, &dep.version) {
-                            println!("{} {} {}", dep.name, flags, ver);
-                        } else {
-                            println!("{}", dep.name);
-                        }
+                    for dep in pkg.requires.iter().filter(|d| !(no_pre && d.pre)) {
+                        println!("{}", dep);
                     }
                 }
             } else if provides {
                 // --provides: show provides for each package
                 for pkg in &packages {
-                    if packages.len() > 1 {
+                    if packages.len() > 1 && !quiet {
                         println!("# {}-{}.{}", pkg.name, pkg.full_version(), pkg.arch);
                     }
                     for dep in &pkg.provides {
-                        if let (Some(flags), Some(ver)) = (&dep.flags, &dep.version) {
-                            println!("{} {} {}", dep.name, flags, ver);
-                        } else {
-                            println!("{}", dep.name);
-                        }
+                        println!("{}", dep);
                     }
                 }
             } else if list {
                 // --list: list files for each package
                 for pkg in &packages {
-                    if packages.len() > 1 {
+                    if packages.len() > 1 && !quiet {
                         println!("# {}-{}.{}", pkg.name, pkg.full_version(), pkg.arch);
                     }
                     if pkg.pkg_id.is_some() {
@@ -1146,16 +2686,33 @@ fn main() -> Result<()> {
                             api.list_package_files(&pkg.name, Some(&pkg.arch), &pkg_repo)?;
                         let mut found = false;
                         for (_, file_list) in &files {
-                            for (path, _) in file_list {
-                                println!("{}", path);
-                                found = true;
+                            if file_list.is_empty() {
+                                continue;
+                            }
+                            found = true;
+                            if tree {
+                                print_file_tree(file_list);
+                            } else {
+                                for (path, _) in file_list {
+                                    println!("{}", path);
+                                }
                             }
                         }
-                        if !found {
+                        if !found && !quiet {
                             println!("  (no filelists indexed — run 'index filelists' first)");
                         }
                     }
                 }
+            } else if source {
+                // --source: source RPM of each package, each listed once
+                let mut seen = std::collections::HashSet::new();
+                for pkg in &packages {
+                    if let Some(ref srpm) = pkg.sourcerpm {
+                        if seen.insert(srpm.as_str()) {
+                            println!("{}", srpm);
+                        }
+                    }
+                }
             } else if let Some(ref fmt) = queryformat {
                 // --queryformat: custom format
                 for pkg in &packages {
@@ -1170,17 +2727,180 @@ fn main() -> Result<()> {
             }
         }
 
+        Commands::ExportRepomd {
+            package,
+            whatprovides,
+            whatrequires,
+            file,
+            file_type,
+            summary,
+            description,
+            arch,
+            repo,
+            repo_tag,
+            repos_from_file,
+            gbs_conf,
+            gbs_profile,
+            latest,
+            limit,
+            output,
+        } => {
+            let repos = resolve_repos(
+                repo,
+                &repo_tag,
+                repos_from_file.as_deref(),
+                &config.db_path,
+                gbs_conf.as_deref(),
+                gbs_profile.as_deref(),
+            )?;
+            let api = api::RpmSearchApi::new(config)?;
+
+            let filter = FindFilter {
+                name: package,
+                summary,
+                description,
+                provides: whatprovides,
+                requires: whatrequires,
+                file,
+                file_type,
+                arches: arch,
+                repos,
+                limit,
+                ..Default::default()
+            };
+            let mut packages = find_packages(&api, filter)?;
+            if latest {
+                packages = filter_latest_n(packages, 1, &[]);
+            }
+
+            api.export_repomd(&packages, &output)?;
+            if !quiet {
+                println!(
+                    "Exported {} package(s) to {}",
+                    packages.len(),
+                    output.display()
+                );
+            }
+        }
+
+        Commands::Resolve {
+            package,
+            arch,
+            json,
+        } => {
+            let _span = tracing::info_span!("resolve", package = %package).entered();
+            let api = api::RpmSearchApi::new(config)?;
+            let resolution = api.resolve_dependencies(&package, arch.as_deref())?;
+
+            if json {
+                let output = serde_json::to_string_pretty(&resolution).map_err(|e| {
+                    error::RpmSearchError::Config(format!("Failed to serialize resolution: {}", e))
+                })?;
+                println!("{}", output);
+                return Ok(());
+            }
+
+            if !quiet {
+                println!("Install set ({}):", resolution.install.len());
+            }
+            for pkg in &resolution.install {
+                match (&pkg.required_by, &pkg.requirement) {
+                    (Some(by), Some(req)) if !quiet => {
+                        println!("  {:<50} {:<15} ({} <- {})", pkg.nevra, pkg.repo, req, by)
+                    }
+                    _ => println!("  {:<50} {}", pkg.nevra, pkg.repo),
+                }
+            }
+
+            if !resolution.unresolved.is_empty() {
+                println!(
+                    "\nUnresolved requirements ({}):",
+                    resolution.unresolved.len()
+                );
+                for unresolved in &resolution.unresolved {
+                    println!("  {:<50} {}", unresolved.requirement, unresolved.package);
+                }
+            }
+
+            if !resolution.conflicts.is_empty() {
+                println!("\nVersion conflicts ({}):", resolution.conflicts.len());
+                for conflict in &resolution.conflicts {
+                    println!(
+                        "  {:<50} {} (installing {})",
+                        conflict.requirement, conflict.package, conflict.installed
+                    );
+                }
+            }
+
+            if !resolution.cycles.is_empty() && !quiet {
+                println!("\nDependency cycles ({}):", resolution.cycles.len());
+                for cycle in &resolution.cycles {
+                    println!("  {}", cycle.join(" -> "));
+                }
+            }
+        }
+
+        Commands::Reindex {
+            embeddings,
+            restart,
+        } => {
+            let _span = tracing::info_span!("reindex", embeddings, restart).entered();
+            let mut api = api::RpmSearchApi::new(config)?;
+            #[cfg(feature = "embedding")]
+            if restart {
+                api.discard_pending_rebuild(None)?;
+            }
+            let count = api.reindex(embeddings)?;
+            println!("✓ Rebuilt indexes");
+            if embeddings {
+                println!("✓ Rebuilt {} embeddings", count);
+            }
+        }
+
+        #[cfg(feature = "embedding")]
+        Commands::PurgeOrphanEmbeddings { dry_run } => {
+            let _span = tracing::info_span!("purge_orphan_embeddings", dry_run).entered();
+            let api = api::RpmSearchApi::new(config)?;
+
+            if dry_run {
+                let orphans = api.orphan_embeddings()?;
+                if orphans.is_empty() {
+                    println!("No orphan embeddings found.");
+                }
+                for (table, ids) in &orphans {
+                    println!("{}: {} orphan embedding(s)", table, ids.len());
+                }
+                let total: usize = orphans.iter().map(|(_, ids)| ids.len()).sum();
+                if total > 0 {
+                    println!(
+                        "Would delete {} embedding(s). Run without --dry-run to purge.",
+                        total
+                    );
+                }
+                return Ok(());
+            }
+
+            let deleted = api.purge_orphan_embeddings()?;
+            println!("✓ Deleted {} orphan embedding(s)", deleted);
+        }
+
         #[cfg(feature = "embedding")]
-        Commands::DebugSearch { query, pkg_ids } => {
+        Commands::DebugSearch {
+            query,
+            context,
+            pkg_ids,
+            histogram,
+            sample,
+        } => {
             let mut config = config;
             config.top_k = 10;
 
+            // One connection serves the metadata, vector and package lookups below
+            let conn = rusqlite::Connection::open(&config.db_path)?;
+            let vector_store = rpm_repo_search::storage::VectorStore::new(&conn)?;
+
             // Auto-detect model type from DB metadata
-            let db_model_type = {
-                let conn = rusqlite::Connection::open(&config.db_path)?;
-                let vector_store = rpm_repo_search::storage::VectorStore::new(conn)?;
-                vector_store.get_embedding_model_type()?
-            };
+            let db_model_type = vector_store.get_embedding_model_type()?;
             if let Some(ref db_type_str) = db_model_type {
                 if let Some(detected) = ModelType::from_db_str(db_type_str) {
                     info!(model = %detected, "Auto-detected embedding model from DB");
@@ -1188,11 +2908,19 @@ fn main() -> Result<()> {
                 }
             }
 
-            let model_files = embedding::hub::resolve_model_files(&config.model_type, None, None)?;
+            // Load the same model revision the embeddings were built with
+            let revision = vector_store.get_embedding_model_revision()?;
+            let model_files = embedding::hub::resolve_model_files(
+                &config.model_type,
+                None,
+                None,
+                revision.as_deref(),
+            )?;
             let embedder =
                 embedding::Embedder::from_model_files(&model_files, config.model_type.clone())?;
 
             // Embed the query (auto-adds prefix for E5 models)
+            let query = rpm_repo_search::search::semantic_query_text(&query, context.as_deref());
             let query_embedding = embedder.embed_query(&query)?;
             let norm: f32 = query_embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
             println!("Query: \"{}\"", query);
@@ -1203,20 +2931,8 @@ fn main() -> Result<()> {
             );
             println!();
 
-            // Open vector store and get raw distances
-            let conn = rusqlite::Connection::open(&config.db_path)?;
-            let pkg_conn = rusqlite::Connection::open(&config.db_path)?;
-
             // Get top 20 nearest by L2 distance
-            let mut stmt = conn.prepare(
-                "SELECT pkg_id, distance FROM embeddings WHERE embedding MATCH ? ORDER BY distance LIMIT 20"
-            )?;
-            let embedding_json = serde_json::to_string(&query_embedding).unwrap();
-            let results: Vec<(i64, f64)> = stmt
-                .query_map(rusqlite::params![embedding_json], |row| {
-                    Ok((row.get(0)?, row.get(1)?))
-                })?
-                .collect::<std::result::Result<_, _>>()?;
+            let results = vector_store.search_similar_with_distance(&query_embedding, 20)?;
 
             println!("Top 20 nearest by raw L2 distance:");
             println!(
@@ -1224,11 +2940,8 @@ fn main() -> Result<()> {
                 "pkg_id", "L2_dist", "cos_sim", "name", "summary"
             );
             println!("{}", "─".repeat(92));
-            for (pkg_id, dist) in &results {
-                let cos_sim = 1.0 - dist * dist / 2.0;
-                let cos_sim = cos_sim.clamp(0.0, 1.0);
-
-                let name_summary: (String, String) = pkg_conn
+            for (pkg_id, cos_sim, dist) in &results {
+                let name_summary: (String, String) = conn
                     .query_row(
                         "SELECT name, summary FROM packages WHERE pkg_id = ?",
                         [pkg_id],
@@ -1281,7 +2994,7 @@ fn main() -> Result<()> {
                                 .sum();
                             let cos_sim = dot / (norm * stored_norm);
 
-                            let name: String = pkg_conn
+                            let name: String = conn
                                 .query_row(
                                     "SELECT name FROM packages WHERE pkg_id = ?",
                                     [pkg_id],
@@ -1298,6 +3011,27 @@ fn main() -> Result<()> {
                 }
             }
 
+            if histogram {
+                let sampled = vector_store.sample_embeddings(sample)?;
+                let sims: Vec<f32> = sampled
+                    .iter()
+                    .map(|(_, emb)| {
+                        let n: f32 = emb.iter().map(|x| x * x).sum::<f32>().sqrt();
+                        let dot: f32 = query_embedding
+                            .iter()
+                            .zip(emb.iter())
+                            .map(|(a, b)| a * b)
+                            .sum();
+                        dot / (norm * n)
+                    })
+                    .collect();
+                println!(
+                    "\nCosine similarity histogram ({} sampled embeddings):",
+                    sims.len()
+                );
+                print_similarity_histogram(&sims);
+            }
+
             // Embed a few reference texts and compare
             println!("\nReference embedding similarities:");
             let ref_texts = vec![
@@ -0,0 +1,209 @@
+use crate::error::{Result, RpmSearchError};
+use crate::mcp::protocol::JsonRpcResponse;
+use crate::mcp::McpServer;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use futures_util::{stream, StreamExt};
+use serde_json::Value;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, error, info, warn};
+
+/// Path of the MCP endpoint (streamable HTTP transport)
+pub const MCP_ENDPOINT: &str = "/mcp";
+
+type SharedServer = Arc<Mutex<McpServer>>;
+
+impl McpServer {
+    /// Run the MCP server over the streamable HTTP transport.
+    ///
+    /// Clients POST JSON-RPC messages to `/mcp`. Responses are sent as an SSE
+    /// stream when the client accepts `text/event-stream`, otherwise as plain
+    /// JSON. A GET on the same endpoint opens an SSE stream for
+    /// server-to-client messages.
+    pub async fn run_http(self, addr: SocketAddr) -> Result<()> {
+        let app = Router::new()
+            .route(MCP_ENDPOINT, post(handle_post).get(handle_get))
+            .with_state(Arc::new(Mutex::new(self)));
+
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(RpmSearchError::Io)?;
+        info!(addr = %addr, endpoint = MCP_ENDPOINT, "MCP server started (HTTP mode)");
+
+        axum::serve(listener, app).await.map_err(RpmSearchError::Io)
+    }
+}
+
+async fn handle_post(
+    State(server): State<SharedServer>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    if !origin_allowed(&headers) {
+        warn!("Rejected MCP request from non-local origin");
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    debug!("Received: {}", body);
+
+    let raw: Value = match serde_json::from_str(&body) {
+        Ok(raw) => raw,
+        Err(e) => {
+            let response = JsonRpcResponse::error(None, -32700, format!("Parse error: {}", e));
+            return (StatusCode::BAD_REQUEST, Json(response)).into_response();
+        }
+    };
+
+    // JSON-RPC batches are arrays of messages
+    let is_batch = raw.is_array();
+    let messages = match raw {
+        Value::Array(messages) if messages.is_empty() => {
+            let response =
+                JsonRpcResponse::error(None, -32600, "Invalid request: empty batch".to_string());
+            return (StatusCode::BAD_REQUEST, Json(response)).into_response();
+        }
+        Value::Array(messages) => messages,
+        message => vec![message],
+    };
+
+    // Request handling uses blocking SQLite and model inference
+    let responses = tokio::task::spawn_blocking(move || {
+        let server = server.lock().unwrap_or_else(|e| e.into_inner());
+        messages
+            .iter()
+            .filter_map(|message| server.process_message(message))
+            .collect::<Vec<_>>()
+    })
+    .await;
+
+    let responses = match responses {
+        Ok(responses) => responses,
+        Err(e) => {
+            error!("MCP request handler failed: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    // Only notifications or responses were sent
+    if responses.is_empty() {
+        return StatusCode::ACCEPTED.into_response();
+    }
+
+    if accepts_event_stream(&headers) {
+        let events: Vec<std::result::Result<Event, Infallible>> = responses
+            .iter()
+            .filter_map(|response| serde_json::to_string(response).ok())
+            .map(|json| Ok(Event::default().event("message").data(json)))
+            .collect();
+        return Sse::new(stream::iter(events)).into_response();
+    }
+
+    if is_batch {
+        Json(responses).into_response()
+    } else {
+        Json(responses.into_iter().next()).into_response()
+    }
+}
+
+/// Open a standalone SSE stream for server-to-client messages
+async fn handle_get(headers: HeaderMap) -> Response {
+    if !origin_allowed(&headers) {
+        warn!("Rejected MCP request from non-local origin");
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    if !accepts_event_stream(&headers) {
+        return StatusCode::NOT_ACCEPTABLE.into_response();
+    }
+
+    debug!("Client opened SSE stream");
+    // No server-initiated messages yet; the initial comment flushes the
+    // response headers so the client sees the stream open right away.
+    let events =
+        stream::once(async { Ok::<_, Infallible>(Event::default().comment("stream opened")) })
+            .chain(stream::pending());
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+fn accepts_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .any(|v| v.contains("text/event-stream"))
+}
+
+/// Only allow browser requests from local pages (DNS rebinding protection).
+/// Requests without an `Origin` header (non-browser clients) are accepted.
+fn origin_allowed(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN).and_then(|v| v.to_str().ok()) else {
+        return true;
+    };
+
+    let authority = origin.split_once("://").map_or(origin, |(_, rest)| rest);
+    let authority = authority.split('/').next().unwrap_or_default();
+    let host = if authority.starts_with('[') {
+        authority
+            .split(']')
+            .next()
+            .map(|h| &h[1..])
+            .unwrap_or_default()
+    } else {
+        authority.split(':').next().unwrap_or_default()
+    };
+
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(name: header::HeaderName, value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn test_origin_allowed() {
+        assert!(origin_allowed(&HeaderMap::new()));
+        assert!(origin_allowed(&headers(
+            header::ORIGIN,
+            "http://localhost:3000"
+        )));
+        assert!(origin_allowed(&headers(header::ORIGIN, "http://127.0.0.1")));
+        assert!(origin_allowed(&headers(
+            header::ORIGIN,
+            "http://[::1]:8080"
+        )));
+        assert!(!origin_allowed(&headers(
+            header::ORIGIN,
+            "https://evil.example.com"
+        )));
+        assert!(!origin_allowed(&headers(
+            header::ORIGIN,
+            "http://localhost.evil.example.com"
+        )));
+    }
+
+    #[test]
+    fn test_accepts_event_stream() {
+        assert!(accepts_event_stream(&headers(
+            header::ACCEPT,
+            "application/json, text/event-stream"
+        )));
+        assert!(!accepts_event_stream(&headers(
+            header::ACCEPT,
+            "application/json"
+        )));
+    }
+}
@@ -1,3 +1,5 @@
+#[cfg(feature = "http")]
+pub mod http;
 pub mod protocol;
 pub mod server;
 pub mod tools;
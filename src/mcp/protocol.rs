@@ -82,7 +82,12 @@ pub struct ToolCallParams {
 #[derive(Debug, Serialize)]
 pub struct ToolResult {
     pub content: Vec<TextContent>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The same result as JSON for clients that parse it (`structuredContent`,
+    /// see [`supports_structured_content`]); `content` stays the human-readable
+    /// fallback
+    #[serde(rename = "structuredContent", skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
+    #[serde(rename = "isError", skip_serializing_if = "Option::is_none")]
     pub is_error: Option<bool>,
 }
 
@@ -106,18 +111,50 @@ impl ToolResult {
     pub fn success(text: String) -> Self {
         Self {
             content: vec![TextContent::new(text)],
+            structured_content: None,
             is_error: None,
         }
     }
 
+    /// Attach a JSON object as `structuredContent`
+    pub fn with_structured(mut self, value: Value) -> Self {
+        self.structured_content = Some(value);
+        self
+    }
+
     pub fn error(text: String) -> Self {
         Self {
             content: vec![TextContent::new(text)],
+            structured_content: None,
             is_error: Some(true),
         }
     }
 }
 
+/// Latest MCP revision the server speaks: the first one that defines a tool
+/// result's `structuredContent`
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// MCP revisions the server accepts, newest first
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &[PROTOCOL_VERSION, "2025-03-26", "2024-11-05"];
+
+/// The revision to answer `initialize` with: the client's requested one if
+/// the server supports it, otherwise the latest (the client then decides
+/// whether to continue)
+pub fn negotiate_protocol_version(requested: Option<&str>) -> &'static str {
+    SUPPORTED_PROTOCOL_VERSIONS
+        .iter()
+        .find(|version| Some(**version) == requested)
+        .copied()
+        .unwrap_or(PROTOCOL_VERSION)
+}
+
+/// Whether tool results may carry `structuredContent` in a session that
+/// negotiated `version` (revisions are dates, so they order as strings)
+pub fn supports_structured_content(version: &str) -> bool {
+    version >= PROTOCOL_VERSION
+}
+
 /// MCP Initialize Result
 #[derive(Debug, Serialize)]
 pub struct InitializeResult {
@@ -147,14 +184,15 @@ pub struct ServerInfo {
 
 impl Default for InitializeResult {
     fn default() -> Self {
-        Self::new()
+        Self::new(PROTOCOL_VERSION)
     }
 }
 
 impl InitializeResult {
-    pub fn new() -> Self {
+    /// Result for a session that negotiated `protocol_version`
+    pub fn new(protocol_version: &str) -> Self {
         Self {
-            protocol_version: "2024-11-05".to_string(),
+            protocol_version: protocol_version.to_string(),
             capabilities: ServerCapabilities {
                 tools: ToolsCapability {
                     list_changed: false,
@@ -167,3 +205,51 @@ impl InitializeResult {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_tool_result_serialization() {
+        let plain = serde_json::to_value(ToolResult::success("ok".to_string())).unwrap();
+        assert_eq!(
+            plain,
+            json!({ "content": [{ "type": "text", "text": "ok" }] })
+        );
+
+        let structured = serde_json::to_value(
+            ToolResult::success("1 package".to_string())
+                .with_structured(json!({ "packages": [{ "name": "bash" }] })),
+        )
+        .unwrap();
+        assert_eq!(
+            structured["structuredContent"],
+            json!({ "packages": [{ "name": "bash" }] })
+        );
+        assert_eq!(structured["content"][0]["text"], "1 package");
+
+        let error = serde_json::to_value(ToolResult::error("boom".to_string())).unwrap();
+        assert_eq!(error["isError"], true);
+        assert!(error.get("structuredContent").is_none());
+    }
+
+    #[test]
+    fn test_negotiate_protocol_version() {
+        assert_eq!(negotiate_protocol_version(Some("2024-11-05")), "2024-11-05");
+        assert_eq!(negotiate_protocol_version(Some("2025-06-18")), "2025-06-18");
+        assert_eq!(
+            negotiate_protocol_version(Some("1999-01-01")),
+            PROTOCOL_VERSION
+        );
+        assert_eq!(negotiate_protocol_version(None), PROTOCOL_VERSION);
+
+        assert!(supports_structured_content("2025-06-18"));
+        assert!(!supports_structured_content("2025-03-26"));
+        assert!(!supports_structured_content("2024-11-05"));
+
+        let init = serde_json::to_value(InitializeResult::new("2024-11-05")).unwrap();
+        assert_eq!(init["protocolVersion"], "2024-11-05");
+    }
+}
@@ -1,24 +1,52 @@
-use crate::api::RpmSearchApi;
+use crate::api::{FileMatch, RpmSearchApi};
 use crate::config::Config;
 use crate::error::{Result, RpmSearchError};
 use crate::mcp::protocol::*;
 use crate::mcp::tools::get_tools;
 use crate::normalize::Package;
+use crate::repomd::model::RpmFileType;
 use crate::search::SearchFilters;
 use crate::storage::FindFilter;
-use serde_json::Value;
+use serde_json::{json, Value};
 
 use std::io::{BufRead, BufReader, Write};
-use tracing::{debug, error, info};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
 
 pub struct McpServer {
+    tools: Arc<Mutex<ToolHandler>>,
+    /// Longest a tool call may run before the client gets a timeout error
+    /// (None = wait indefinitely)
+    tool_timeout: Option<Duration>,
+    /// Aborts the SQL statement of a tool call that timed out
+    interrupt: rusqlite::InterruptHandle,
+    /// Set while a tool call runs on a worker thread, including one that
+    /// timed out and is still finishing
+    busy: Arc<AtomicBool>,
+    /// MCP revision agreed on in `initialize`
+    protocol_version: Mutex<&'static str>,
+}
+
+/// Runs the MCP tools against the API; shared with tool-call worker threads
+struct ToolHandler {
     api: RpmSearchApi,
 }
 
 impl McpServer {
     pub fn new(config: Config) -> Result<Self> {
+        let tool_timeout = (config.mcp_tool_timeout_secs > 0)
+            .then(|| Duration::from_secs(config.mcp_tool_timeout_secs));
         let api = RpmSearchApi::new(config)?;
-        Ok(Self { api })
+        Ok(Self {
+            interrupt: api.interrupt_handle(),
+            busy: Arc::new(AtomicBool::new(false)),
+            tools: Arc::new(Mutex::new(ToolHandler { api })),
+            tool_timeout,
+            protocol_version: Mutex::new(PROTOCOL_VERSION),
+        })
     }
 
     /// Run the MCP server (stdio mode)
@@ -42,24 +70,8 @@ impl McpServer {
             let raw: Value = serde_json::from_str(&line)
                 .map_err(|e| RpmSearchError::Config(format!("Invalid JSON: {}", e)))?;
 
-            let is_notification = raw.get("id").is_none_or(|v| v.is_null());
-
-            if is_notification {
-                // JSON-RPC 2.0: Notifications MUST NOT be responded to
-                self.handle_notification(&raw);
+            let Some(response) = self.process_message(&raw) else {
                 continue;
-            }
-
-            let response = match self.handle_request(&line) {
-                Ok(resp) => resp,
-                Err(e) => {
-                    error!("Error handling request: {}", e);
-                    JsonRpcResponse::error(
-                        raw.get("id").cloned(),
-                        -32603,
-                        format!("Internal error: {}", e),
-                    )
-                }
             };
 
             let response_json = serde_json::to_string(&response).map_err(|e| {
@@ -74,6 +86,30 @@ impl McpServer {
         Ok(())
     }
 
+    /// Handle a single JSON-RPC message, returning `None` for notifications
+    pub(crate) fn process_message(&self, raw: &Value) -> Option<JsonRpcResponse> {
+        let is_notification = raw.get("id").is_none_or(|v| v.is_null());
+
+        if is_notification {
+            // JSON-RPC 2.0: Notifications MUST NOT be responded to
+            self.handle_notification(raw);
+            return None;
+        }
+
+        let response = match self.handle_request(raw) {
+            Ok(resp) => resp,
+            Err(e) => {
+                error!("Error handling request: {}", e);
+                JsonRpcResponse::error(
+                    raw.get("id").cloned(),
+                    -32603,
+                    format!("Internal error: {}", e),
+                )
+            }
+        };
+        Some(response)
+    }
+
     /// Handle JSON-RPC notifications (no response expected)
     fn handle_notification(&self, raw: &Value) {
         let method = raw
@@ -98,13 +134,30 @@ impl McpServer {
         }
     }
 
-    fn handle_request(&self, line: &str) -> Result<JsonRpcResponse> {
-        let request: JsonRpcRequest = serde_json::from_str(line)
+    fn handle_request(&self, raw: &Value) -> Result<JsonRpcResponse> {
+        let request: JsonRpcRequest = serde_json::from_value(raw.clone())
             .map_err(|e| RpmSearchError::Config(format!("Invalid JSON-RPC request: {}", e)))?;
 
         let result = match request.method.as_str() {
             "initialize" => {
-                let init_result = InitializeResult::new();
+                let requested = request
+                    .params
+                    .as_ref()
+                    .and_then(|p| p.get("protocolVersion"))
+                    .and_then(|v| v.as_str());
+                let version = negotiate_protocol_version(requested);
+                if requested != Some(version) {
+                    warn!(
+                        requested = requested.unwrap_or("none"),
+                        offered = version,
+                        "Client requested an unsupported MCP protocol version"
+                    );
+                }
+                *self
+                    .protocol_version
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = version;
+                let init_result = InitializeResult::new(version);
                 serde_json::to_value(init_result)
                     .map_err(|e| RpmSearchError::Storage(format!("Serialization error: {}", e)))?
             }
@@ -151,86 +204,225 @@ impl McpServer {
         let tool_params: ToolCallParams = serde_json::from_value(params.clone())
             .map_err(|e| RpmSearchError::Config(format!("Invalid tool call params: {}", e)))?;
 
-        let result_text = match tool_params.name.as_str() {
-            "rpm_search" => self.search_packages(&tool_params.arguments)?,
-            "rpm_package_info" => self.get_package_info(&tool_params.arguments)?,
-            "rpm_repositories" => self.list_repositories()?,
-            "rpm_file_search" => self.search_by_file(&tool_params.arguments)?,
-            "rpm_find" => self.find_packages(&tool_params.arguments)?,
-            _ => {
-                return Ok(serde_json::to_value(ToolResult::error(format!(
-                    "Unknown tool: {}",
-                    tool_params.name
-                )))
-                .unwrap());
-            }
+        let mut tool_result = match self.tool_timeout {
+            Some(timeout) => self.call_with_timeout(tool_params, timeout)?,
+            None => lock_tools(&self.tools).call(&tool_params.name, &tool_params.arguments)?,
         };
-
-        let tool_result = ToolResult::success(result_text);
+        let version = *self
+            .protocol_version
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if !supports_structured_content(version) {
+            tool_result.structured_content = None;
+        }
         serde_json::to_value(tool_result)
             .map_err(|e| RpmSearchError::Storage(format!("Serialization error: {}", e)))
     }
 
-    fn search_packages(&self, args: &Value) -> Result<String> {
+    /// Run a tool on a worker thread and give up waiting after `timeout`.
+    ///
+    /// A timed-out call has its SQL statement interrupted. Work outside SQLite
+    /// (e.g. model inference) can't be stopped, so until the worker finishes,
+    /// new calls are refused instead of queueing behind it.
+    fn call_with_timeout(&self, params: ToolCallParams, timeout: Duration) -> Result<ToolResult> {
+        let name = params.name.clone();
+        if self.busy.swap(true, Ordering::AcqRel) {
+            warn!(tool = %name, "Refusing tool call while a timed-out call is still running");
+            return Ok(ToolResult::error(format!(
+                "Tool '{}' was not run: an earlier call that timed out is still finishing. \
+                 Try again shortly.",
+                name
+            )));
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let tools = Arc::clone(&self.tools);
+        let busy = Arc::clone(&self.busy);
+        std::thread::spawn(move || {
+            // Cleared even if the tool panics, and before the result is sent
+            // so the next call doesn't see it
+            let busy = BusyGuard(busy);
+            let result = lock_tools(&tools).call(&params.name, &params.arguments);
+            drop(busy);
+            // The receiver is gone if the call already timed out
+            let _ = tx.send(result);
+        });
+
+        match rx.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => {
+                warn!(tool = %name, timeout_secs = timeout.as_secs(), "Tool call timed out");
+                self.interrupt.interrupt();
+                Ok(ToolResult::error(format!(
+                    "Tool '{}' timed out after {} seconds. Narrow the query \
+                     (e.g. add arch or repo filters, or lower the limit) and try again.",
+                    name,
+                    timeout.as_secs()
+                )))
+            }
+            Err(RecvTimeoutError::Disconnected) => Err(RpmSearchError::Storage(format!(
+                "Tool '{}' failed without a result",
+                name
+            ))),
+        }
+    }
+}
+
+/// Clears [`McpServer::busy`] when a worker thread ends
+struct BusyGuard(Arc<AtomicBool>);
+
+impl Drop for BusyGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// Lock the tool handler; a panic in an earlier call doesn't make it unusable
+fn lock_tools(tools: &Mutex<ToolHandler>) -> std::sync::MutexGuard<'_, ToolHandler> {
+    tools.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The `arch` argument, given as a single name or an array of names
+fn arch_list(args: &Value) -> Vec<String> {
+    match args.get("arch") {
+        Some(Value::String(arch)) => vec![arch.clone()],
+        Some(Value::Array(arches)) => arches
+            .iter()
+            .filter_map(|a| a.as_str())
+            .map(String::from)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Filters and result limit shared by `rpm_search` and `rpm_search_batch`
+fn search_filters(args: &Value) -> (SearchFilters, usize) {
+    let arches = arch_list(args);
+    let repos: Vec<String> = args
+        .get("repo")
+        .and_then(|v| v.as_str())
+        .map(|r| vec![r.to_string()])
+        .unwrap_or_default();
+    let top_k = args.get("top_k").and_then(|v| v.as_u64()).unwrap_or(10) as usize;
+    let context = args
+        .get("context")
+        .and_then(|v| v.as_str())
+        .map(String::from);
+
+    let filters = SearchFilters {
+        name: None,
+        arches,
+        repos,
+        not_requiring: None,
+        providing: None,
+        not_providing: None,
+        relax_filters: false,
+        context,
+        min_results: None,
+        repo_weights: Default::default(),
+    };
+    (filters, top_k)
+}
+
+fn format_search_results(packages: &[Package]) -> String {
+    if packages.is_empty() {
+        return "No packages found matching the query.".to_string();
+    }
+
+    let mut result = format!("Found {} package(s):\n\n", packages.len());
+    for (i, pkg) in packages.iter().enumerate() {
+        result.push_str(&format!(
+            "{}. {} ({})\n   Version: {}\n   Arch: {}\n   Repo: {}\n   Summary: {}\n\n",
+            i + 1,
+            pkg.name,
+            pkg.pkg_id
+                .map(|id| id.to_string())
+                .unwrap_or_else(|| "N/A".to_string()),
+            pkg.full_version(),
+            pkg.arch,
+            pkg.repo,
+            pkg.summary
+        ));
+    }
+    result
+}
+
+impl ToolHandler {
+    fn call(&self, name: &str, arguments: &Value) -> Result<ToolResult> {
+        let result = match name {
+            "rpm_search" => self.search_packages(arguments)?,
+            "rpm_search_batch" => ToolResult::success(self.search_packages_batch(arguments)?),
+            "rpm_package_info" => self.get_package_info(arguments)?,
+            "rpm_repositories" => ToolResult::success(self.list_repositories()?),
+            "rpm_file_search" => ToolResult::success(self.search_by_file(arguments)?),
+            "rpm_find" => self.find_packages(arguments)?,
+            _ => return Ok(ToolResult::error(format!("Unknown tool: {}", name))),
+        };
+        Ok(result)
+    }
+
+    fn search_packages(&self, args: &Value) -> Result<ToolResult> {
         let query = args["query"]
             .as_str()
             .ok_or_else(|| RpmSearchError::Config("Missing 'query' parameter".to_string()))?;
-
-        let arch = args.get("arch").and_then(|v| v.as_str()).map(String::from);
-        let repos: Vec<String> = args
-            .get("repo")
-            .and_then(|v| v.as_str())
-            .map(|r| vec![r.to_string()])
-            .unwrap_or_default();
-        let top_k = args.get("top_k").and_then(|v| v.as_u64()).unwrap_or(10) as usize;
+        let (filters, top_k) = search_filters(args);
 
         info!(
-            "Searching packages: query='{}', arch={:?}, repos={:?}, top_k={}",
-            query, arch, repos, top_k
+            "Searching packages: query='{}', context={:?}, arches={:?}, repos={:?}, top_k={}",
+            query, filters.context, filters.arches, filters.repos, top_k
         );
 
-        let filters = SearchFilters {
-            name: None,
-            arch,
-            repos,
-            not_requiring: None,
-            providing: None,
-        };
-
         let mut packages = self.api.search(query, filters)?;
 
         // Limit results to top_k
         packages.truncate(top_k);
 
-        if packages.is_empty() {
-            return Ok("No packages found matching the query.".to_string());
-        }
+        Ok(ToolResult::success(format_search_results(&packages))
+            .with_structured(json!({ "packages": packages })))
+    }
 
-        let mut result = format!("Found {} package(s):\n\n", packages.len());
-        for (i, pkg) in packages.iter().enumerate() {
-            result.push_str(&format!(
-                "{}. {} ({})\n   Version: {}\n   Arch: {}\n   Repo: {}\n   Summary: {}\n\n",
-                i + 1,
-                pkg.name,
-                pkg.pkg_id
-                    .map(|id| id.to_string())
-                    .unwrap_or_else(|| "N/A".to_string()),
-                pkg.full_version(),
-                pkg.arch,
-                pkg.repo,
-                pkg.summary
+    fn search_packages_batch(&self, args: &Value) -> Result<String> {
+        let queries: Vec<String> = args["queries"]
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|q| q.as_str())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        if queries.is_empty() {
+            return Err(RpmSearchError::Config(
+                "Missing 'queries' parameter (a non-empty array of strings)".to_string(),
             ));
         }
+        let (filters, top_k) = search_filters(args);
 
-        Ok(result)
+        info!(
+            "Searching packages in batch: queries={:?}, context={:?}, arches={:?}, repos={:?}, top_k={}",
+            queries, filters.context, filters.arches, filters.repos, top_k
+        );
+
+        let results = self.api.search_batch(&queries, filters)?;
+
+        let mut output = String::new();
+        for (i, (query, result)) in queries.iter().zip(results).enumerate() {
+            let mut packages = result.packages;
+            packages.truncate(top_k);
+            output.push_str(&format!("=== Query {}: {} ===\n", i + 1, query));
+            output.push_str(&format_search_results(&packages));
+            output.push_str("\n\n");
+        }
+        Ok(output.trim_end().to_string())
     }
 
-    fn get_package_info(&self, args: &Value) -> Result<String> {
+    fn get_package_info(&self, args: &Value) -> Result<ToolResult> {
         let name = args["name"]
             .as_str()
             .ok_or_else(|| RpmSearchError::Config("Missing 'name' parameter".to_string()))?;
 
-        let arch = args.get("arch").and_then(|v| v.as_str()).map(String::from);
+        let arches = arch_list(args);
         let repos: Vec<String> = args
             .get("repo")
             .and_then(|v| v.as_str())
@@ -242,17 +434,22 @@ impl McpServer {
             .unwrap_or(true);
 
         info!(
-            "Getting package info: name='{}', arch={:?}, repos={:?}",
-            name, arch, repos
+            "Getting package info: name='{}', arches={:?}, repos={:?}",
+            name, arches, repos
         );
 
         // Search for exact package name
         let filters = SearchFilters {
             name: None,
-            arch: arch.clone(),
+            arches,
             repos: repos.clone(),
             not_requiring: None,
             providing: None,
+            not_providing: None,
+            relax_filters: false,
+            context: None,
+            min_results: None,
+            repo_weights: Default::default(),
         };
 
         let packages = self.api.search(name, filters)?;
@@ -260,12 +457,18 @@ impl McpServer {
         let matching: Vec<&Package> = packages.iter().filter(|p| p.name == name).collect();
 
         if matching.is_empty() {
-            return Ok(format!("Package '{}' not found.", name));
+            return Ok(
+                ToolResult::success(format!("Package '{}' not found.", name))
+                    .with_structured(json!({ "packages": [] })),
+            );
         }
 
         let mut result = format!("Package information for '{}':\n\n", name);
+        let mut structured = Vec::with_capacity(matching.len());
 
         for pkg in &matching {
+            let mut entry = json!(pkg);
+
             result.push_str(&format!(
                 "Package: {}\n\
                  Version: {}\n\
@@ -310,6 +513,10 @@ impl McpServer {
                         self.api
                             .list_package_files(&pkg.name, Some(&pkg.arch), &pkg_repo)?;
                     for (_, file_list) in &files {
+                        entry["files"] = file_list
+                            .iter()
+                            .map(|(path, ft)| json!({ "path": path, "file_type": ft }))
+                            .collect();
                         if !file_list.is_empty() {
                             result.push_str(&format!("\nFiles ({}):\n", file_list.len()));
                             for (path, ft) in file_list {
@@ -326,9 +533,10 @@ impl McpServer {
             }
 
             result.push_str("\n---\n\n");
+            structured.push(entry);
         }
 
-        Ok(result)
+        Ok(ToolResult::success(result).with_structured(json!({ "packages": structured })))
     }
 
     fn list_repositories(&self) -> Result<String> {
@@ -369,30 +577,47 @@ impl McpServer {
             results.len(),
             path
         );
-        for (i, (pkg, full_path, file_type)) in results.iter().enumerate() {
-            let marker = match file_type.as_str() {
-                "dir" => "[d]",
-                "ghost" => "[g]",
-                _ => "   ",
-            };
+        for (
+            i,
+            FileMatch {
+                package: pkg,
+                files,
+            },
+        ) in results.iter().enumerate()
+        {
             text.push_str(&format!(
-                "{}. {}-{}.{} ({})\n   {} {}\n",
+                "{}. {}-{}.{} ({})\n",
                 i + 1,
                 pkg.name,
                 pkg.full_version(),
                 pkg.arch,
                 pkg.repo,
-                marker,
-                full_path,
             ));
+            for file in files {
+                let marker = match file.file_type.as_str() {
+                    "dir" => "[d]",
+                    "ghost" => "[g]",
+                    _ => "   ",
+                };
+                text.push_str(&format!("   {} {}\n", marker, file.path));
+            }
         }
 
         Ok(text)
     }
 
-    fn find_packages(&self, args: &Value) -> Result<String> {
+    fn find_packages(&self, args: &Value) -> Result<ToolResult> {
+        let file_type = args
+            .get("file_type")
+            .and_then(|v| v.as_str())
+            .map(str::parse::<RpmFileType>)
+            .transpose()?;
         let filter = FindFilter {
             name: args.get("name").and_then(|v| v.as_str()).map(String::from),
+            name_exact: args
+                .get("name_exact")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
             summary: args
                 .get("summary")
                 .and_then(|v| v.as_str())
@@ -410,21 +635,27 @@ impl McpServer {
                 .and_then(|v| v.as_str())
                 .map(String::from),
             file: args.get("file").and_then(|v| v.as_str()).map(String::from),
-            arch: args.get("arch").and_then(|v| v.as_str()).map(String::from),
+            file_type,
+            arches: arch_list(args),
             repos: args
                 .get("repo")
                 .and_then(|v| v.as_str())
                 .map(|r| vec![r.to_string()])
                 .unwrap_or_default(),
             limit: args.get("limit").and_then(|v| v.as_u64()).unwrap_or(50) as usize,
+            ..Default::default()
         };
 
         info!("Finding packages with structured filters");
 
         let results = self.api.find(&filter)?;
 
+        let structured = json!({ "packages": results });
         if results.is_empty() {
-            return Ok("No packages found matching the given criteria.".to_string());
+            return Ok(ToolResult::success(
+                "No packages found matching the given criteria.".to_string(),
+            )
+            .with_structured(structured));
         }
 
         let mut text = format!("Found {} package(s):\n\n", results.len());
@@ -440,6 +671,98 @@ impl McpServer {
             ));
         }
 
-        Ok(text)
+        Ok(ToolResult::success(text).with_structured(structured))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arch_list_accepts_string_or_array() {
+        assert_eq!(arch_list(&json!({ "arch": "x86_64" })), vec!["x86_64"]);
+        assert_eq!(
+            arch_list(&json!({ "arch": ["x86_64", "noarch"] })),
+            vec!["x86_64", "noarch"]
+        );
+        assert!(arch_list(&json!({})).is_empty());
+
+        let (filters, _) = search_filters(&json!({ "arch": ["aarch64", "noarch"] }));
+        assert_eq!(filters.arches, vec!["aarch64", "noarch"]);
+    }
+
+    fn initialize(server: &McpServer, version: &str) -> Value {
+        let response = server
+            .process_message(&json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "initialize",
+                "params": { "protocolVersion": version, "capabilities": {} }
+            }))
+            .unwrap();
+        serde_json::to_value(response).unwrap()
+    }
+
+    fn call_find(server: &McpServer) -> Value {
+        let response = server
+            .process_message(&json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "tools/call",
+                "params": { "name": "rpm_find", "arguments": { "name": "bash" } }
+            }))
+            .unwrap();
+        serde_json::to_value(response).unwrap()
+    }
+
+    #[test]
+    fn test_timed_out_call_blocks_new_calls_until_it_finishes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = McpServer::new(Config::new(dir.path().join("test.db"))).unwrap();
+        server.tool_timeout = Some(Duration::from_millis(50));
+        let is_error = |result: &Value| result["result"]["isError"] == true;
+
+        // A call stuck behind the API (held here) times out...
+        let stuck = lock_tools(&server.tools);
+        let result = call_find(&server);
+        assert!(is_error(&result));
+        assert!(result["result"]["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("timed out"));
+
+        // ...and new calls are refused rather than queued while it runs
+        let result = call_find(&server);
+        assert!(result["result"]["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("still finishing"));
+
+        drop(stuck);
+        for _ in 0..100 {
+            if !server.busy.load(Ordering::Acquire) {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(!is_error(&call_find(&server)));
+    }
+
+    #[test]
+    fn test_initialize_negotiates_protocol_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = McpServer::new(Config::new(dir.path().join("test.db"))).unwrap();
+
+        let init = initialize(&server, "2024-11-05");
+        assert_eq!(init["result"]["protocolVersion"], "2024-11-05");
+        let result = call_find(&server);
+        assert!(result["result"]["content"].is_array());
+        assert!(result["result"].get("structuredContent").is_none());
+
+        let init = initialize(&server, PROTOCOL_VERSION);
+        assert_eq!(init["result"]["protocolVersion"], PROTOCOL_VERSION);
+        let result = call_find(&server);
+        assert!(result["result"]["structuredContent"]["packages"].is_array());
     }
 }
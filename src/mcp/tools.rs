@@ -15,9 +15,16 @@ pub fn get_tools() -> Vec<Tool> {
                         "type": "string",
                         "description": "Natural language search query in English (e.g., 'compression library', 'network packet capture tool')"
                     },
-                    "arch": {
+                    "context": {
                         "type": "string",
-                        "description": "Filter by architecture (e.g., x86_64, aarch64, noarch)"
+                        "description": "Optional domain hint to disambiguate the query (e.g., 'database' for 'client'). Biases ranking, does not filter"
+                    },
+                    "arch": {
+                        "anyOf": [
+                            { "type": "string" },
+                            { "type": "array", "items": { "type": "string" } }
+                        ],
+                        "description": "Filter by architecture, one name or a list (e.g., x86_64, [\"x86_64\", \"noarch\"])"
                     },
                     "repo": {
                         "type": "string",
@@ -32,6 +39,43 @@ pub fn get_tools() -> Vec<Tool> {
                 "required": ["query"]
             }),
         },
+        Tool {
+            name: "rpm_search_batch".to_string(),
+            description: "Run several semantic searches in one call (same as rpm_search, with the embedding model loaded once). Use when comparing related queries, e.g. 'http server' and 'reverse proxy'. Results are grouped per query."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "queries": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "minItems": 1,
+                        "description": "Natural language search queries in English"
+                    },
+                    "context": {
+                        "type": "string",
+                        "description": "Optional domain hint applied to every query. Biases ranking, does not filter"
+                    },
+                    "arch": {
+                        "anyOf": [
+                            { "type": "string" },
+                            { "type": "array", "items": { "type": "string" } }
+                        ],
+                        "description": "Filter by architecture, one name or a list (e.g., x86_64, [\"x86_64\", \"noarch\"])"
+                    },
+                    "repo": {
+                        "type": "string",
+                        "description": "Filter by repository name"
+                    },
+                    "top_k": {
+                        "type": "integer",
+                        "description": "Maximum number of results per query",
+                        "default": 10
+                    }
+                },
+                "required": ["queries"]
+            }),
+        },
         Tool {
             name: "rpm_package_info".to_string(),
             description: "Get detailed information about a specific RPM package including version, requires, provides, and file list".to_string(),
@@ -43,8 +87,11 @@ pub fn get_tools() -> Vec<Tool> {
                         "description": "Package name"
                     },
                     "arch": {
-                        "type": "string",
-                        "description": "Architecture (optional, helps narrow down results)"
+                        "anyOf": [
+                            { "type": "string" },
+                            { "type": "array", "items": { "type": "string" } }
+                        ],
+                        "description": "Architecture, one name or a list (optional, helps narrow down results)"
                     },
                     "repo": {
                         "type": "string",
@@ -96,6 +143,10 @@ pub fn get_tools() -> Vec<Tool> {
                         "type": "string",
                         "description": "Package name pattern (e.g., 'lib*ssl*', 'python?')"
                     },
+                    "name_exact": {
+                        "type": "boolean",
+                        "description": "Match 'name' exactly instead of as a substring (default: false)"
+                    },
                     "summary": {
                         "type": "string",
                         "description": "Summary keyword pattern"
@@ -112,9 +163,17 @@ pub fn get_tools() -> Vec<Tool> {
                         "type": "string",
                         "description": "File path pattern (e.g., '/usr/bin/python*')"
                     },
-                    "arch": {
+                    "file_type": {
                         "type": "string",
-                        "description": "Architecture filter"
+                        "enum": ["file", "dir", "ghost"],
+                        "description": "Only match file entries of this type (requires 'file')"
+                    },
+                    "arch": {
+                        "anyOf": [
+                            { "type": "string" },
+                            { "type": "array", "items": { "type": "string" } }
+                        ],
+                        "description": "Architecture filter, one name or a list"
                     },
                     "repo": {
                         "type": "string",
@@ -0,0 +1,125 @@
+use crate::normalize::Package;
+
+/// Groups of architecture names that denote the same ABI.
+/// The first name of each group is the canonical (rpm) name.
+const ARCH_ALIASES: &[&[&str]] = &[
+    &["aarch64", "arm64"],
+    &["armv7hl", "armv7l", "armhfp"],
+    &["x86_64", "amd64"],
+    &["ppc64le", "ppc64el"],
+];
+
+fn alias_group(arch: &str) -> Option<&'static [&'static str]> {
+    ARCH_ALIASES
+        .iter()
+        .copied()
+        .find(|group| group.iter().any(|a| a.eq_ignore_ascii_case(arch)))
+}
+
+/// Canonical name of `arch` (`arm64` → `aarch64`); unknown arches are returned unchanged
+pub fn canonical_arch(arch: &str) -> &str {
+    alias_group(arch).map_or(arch, |group| group[0])
+}
+
+/// Add the equivalent names of each arch in an arch filter
+/// (`["aarch64"]` → `["aarch64", "arm64"]`)
+pub fn expand_arch_aliases(arches: &[String]) -> Vec<String> {
+    let mut expanded: Vec<String> = Vec::with_capacity(arches.len());
+    for arch in arches {
+        let names = match alias_group(arch) {
+            Some(group) => group.iter().map(|a| a.to_string()).collect(),
+            None => vec![arch.clone()],
+        };
+        for name in names {
+            if !expanded.iter().any(|a| a.eq_ignore_ascii_case(&name)) {
+                expanded.push(name);
+            }
+        }
+    }
+    expanded
+}
+
+/// Reorder each run of adjacent same-name packages newest version first, and
+/// each version's builds by preferred arch (`--arch-order x86_64,i686,noarch`),
+/// so every version's arches stay together. The order of the runs, and of
+/// packages with the same version and the same or an unlisted arch, is kept.
+/// Arch aliases match.
+pub fn sort_by_arch_order(packages: &mut [Package], arch_order: &[String]) {
+    if arch_order.is_empty() {
+        return;
+    }
+    let rank = |arch: &str| {
+        arch_order
+            .iter()
+            .position(|a| canonical_arch(a).eq_ignore_ascii_case(canonical_arch(arch)))
+            .unwrap_or(arch_order.len())
+    };
+    for run in packages.chunk_by_mut(|a, b| a.name == b.name) {
+        run.sort_by(|a, b| {
+            b.to_rpm_version()
+                .cmp(&a.to_rpm_version())
+                .then_with(|| rank(&a.arch).cmp(&rank(&b.arch)))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalize::test_package;
+
+    #[test]
+    fn test_arch_aliases() {
+        assert_eq!(canonical_arch("arm64"), "aarch64");
+        assert_eq!(canonical_arch("ARMv7l"), "armv7hl");
+        assert_eq!(canonical_arch("noarch"), "noarch");
+
+        let arches = vec![
+            "arm64".to_string(),
+            "noarch".to_string(),
+            "aarch64".to_string(),
+        ];
+        assert_eq!(
+            expand_arch_aliases(&arches),
+            vec!["aarch64", "arm64", "noarch"]
+        );
+    }
+
+    #[test]
+    fn test_sort_by_arch_order() {
+        let mut packages: Vec<Package> = [
+            ("bash", "5.1", "x86_64"),
+            ("bash", "5.2", "i686"),
+            ("bash", "5.1", "i686"),
+            ("bash", "5.2", "noarch"),
+            ("bash", "5.2", "x86_64"),
+            ("zlib", "1", "aarch64"),
+            ("zlib", "1", "amd64"),
+        ]
+        .iter()
+        .map(|(name, version, arch)| Package {
+            arch: arch.to_string(),
+            ..test_package(name, version)
+        })
+        .collect();
+        let order = vec!["x86_64".to_string(), "i686".to_string()];
+        sort_by_arch_order(&mut packages, &order);
+
+        let got: Vec<(&str, &str, &str)> = packages
+            .iter()
+            .map(|p| (p.name.as_str(), p.version.as_str(), p.arch.as_str()))
+            .collect();
+        assert_eq!(
+            got,
+            [
+                ("bash", "5.2", "x86_64"),
+                ("bash", "5.2", "i686"),
+                ("bash", "5.2", "noarch"),
+                ("bash", "5.1", "x86_64"),
+                ("bash", "5.1", "i686"),
+                ("zlib", "1", "amd64"),
+                ("zlib", "1", "aarch64"),
+            ]
+        );
+    }
+}
@@ -1,4 +1,6 @@
+pub mod arch;
 pub mod package;
 pub mod version;
 
+pub use arch::{canonical_arch, expand_arch_aliases, sort_by_arch_order};
 pub use package::*;
@@ -1,6 +1,28 @@
 use super::version::RpmVersion;
-use crate::repomd::model::{RpmDependency, RpmPackage};
+use crate::config::EmbeddingTextMode;
+use crate::repomd::model::{AppstreamComponent, LocalizedText, RpmDependency, RpmPackage};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Application metadata from AppStream (see `repomd::appstream`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AppstreamInfo {
+    pub description: String,
+    pub categories: Vec<String>,
+    pub keywords: Vec<String>,
+}
+
+impl From<AppstreamComponent> for AppstreamInfo {
+    fn from(component: AppstreamComponent) -> Self {
+        Self {
+            description: component.description,
+            categories: component.categories,
+            keywords: component.keywords,
+        }
+    }
+}
 
 /// Normalized package model for internal use
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,42 +38,255 @@ pub struct Package {
     pub license: Option<String>,
     pub vcs: Option<String>,
     pub location_href: Option<String>,
+    /// Source RPM file name (e.g. `bash-5.2-1.src.rpm`)
+    #[serde(default)]
+    pub sourcerpm: Option<String>,
+    /// Build time in seconds since the Unix epoch
+    #[serde(default)]
+    pub build_time: Option<i64>,
+    /// Localized summaries/descriptions (`summary`/`description` hold the default)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub localized: Vec<LocalizedText>,
+    /// AppStream application metadata, preferred over the RPM description for embeddings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub appstream: Option<AppstreamInfo>,
     pub repo: String,
     pub requires: Vec<Dependency>,
     pub provides: Vec<Dependency>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<Dependency>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub obsoletes: Vec<Dependency>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Dependency {
     pub name: String,
     pub flags: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epoch: Option<i64>,
     pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release: Option<String>,
+    /// Prerequisite (`pre="1"`) requirement of install scriptlets
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pre: bool,
 }
 
 impl From<RpmDependency> for Dependency {
     fn from(rpm_dep: RpmDependency) -> Self {
-        // Combine epoch:version-release into a single version string
-        let version = if let Some(ver) = rpm_dep.version {
-            let mut full_version = String::new();
-            if let Some(epoch) = rpm_dep.epoch {
-                full_version.push_str(&epoch);
-                full_version.push(':');
-            }
-            full_version.push_str(&ver);
-            if let Some(rel) = rpm_dep.release {
-                full_version.push('-');
-                full_version.push_str(&rel);
-            }
-            Some(full_version)
-        } else {
-            None
-        };
-
         Self {
             name: rpm_dep.name,
             flags: rpm_dep.flags,
-            version,
+            epoch: rpm_dep.epoch.and_then(|e| e.parse().ok()),
+            version: rpm_dep.version,
+            release: rpm_dep.release,
+            pre: rpm_dep.pre,
+        }
+    }
+}
+
+impl From<&Dependency> for RpmDependency {
+    fn from(dep: &Dependency) -> Self {
+        Self {
+            name: dep.name.clone(),
+            flags: dep.flags.clone(),
+            epoch: dep.epoch.map(|e| e.to_string()),
+            version: dep.version.clone(),
+            release: dep.release.clone(),
+            pre: dep.pre,
+        }
+    }
+}
+
+impl std::fmt::Display for Dependency {
+    /// RPM-style dependency string as printed by `dnf repoquery`
+    /// (`glibc >= 2.34`, `openssl(x86-64) = 1:3.0.0-1`); the comparison is
+    /// only shown when the dependency carries a version
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some(version) = self.evr() else {
+            return f.write_str(&self.name);
+        };
+        let op = match self.flags.as_deref().map(str::trim) {
+            Some("EQ") | None => "=",
+            Some("LT") => "<",
+            Some("LE") => "<=",
+            Some("GT") => ">",
+            Some("GE") => ">=",
+            Some(other) => other,
+        };
+        write!(f, "{} {} {}", self.name, op, version)
+    }
+}
+
+/// Range bits of a dependency comparison flag (`EQ`, `GE`, `<=`, ...)
+const SENSE_LESS: u8 = 1;
+const SENSE_GREATER: u8 = 2;
+const SENSE_EQUAL: u8 = 4;
+
+pub(crate) fn parse_sense(flags: &str) -> Option<u8> {
+    match flags.trim() {
+        "EQ" | "=" | "==" => Some(SENSE_EQUAL),
+        "LT" | "<" => Some(SENSE_LESS),
+        "LE" | "<=" => Some(SENSE_LESS | SENSE_EQUAL),
+        "GT" | ">" => Some(SENSE_GREATER),
+        "GE" | ">=" => Some(SENSE_GREATER | SENSE_EQUAL),
+        _ => None,
+    }
+}
+
+impl Dependency {
+    /// Build a dependency from a combined `[epoch:]version[-release]` string
+    pub fn from_evr(name: impl Into<String>, flags: Option<&str>, evr: Option<&str>) -> Self {
+        let mut dep = Self {
+            name: name.into(),
+            flags: flags.map(str::to_string),
+            epoch: None,
+            version: None,
+            release: None,
+            pre: false,
+        };
+        if let Some(evr) = evr {
+            let (epoch, vr) = match evr.split_once(':') {
+                Some((epoch, vr)) => match epoch.parse() {
+                    Ok(epoch) => (Some(epoch), vr),
+                    Err(_) => (None, evr),
+                },
+                None => (None, evr),
+            };
+            let (version, release) = match vr.rsplit_once('-') {
+                Some((v, r)) => (v, Some(r.to_string())),
+                None => (vr, None),
+            };
+            dep.epoch = epoch;
+            dep.version = Some(version.to_string());
+            dep.release = release;
+        }
+        dep
+    }
+
+    /// Combined `[epoch:]version[-release]` string, as written in specs and
+    /// shown by `dnf repoquery`
+    pub fn evr(&self) -> Option<String> {
+        let version = self.version.as_deref()?;
+        let mut evr = String::new();
+        if let Some(epoch) = self.epoch {
+            evr.push_str(&epoch.to_string());
+            evr.push(':');
+        }
+        evr.push_str(version);
+        if let Some(ref release) = self.release {
+            evr.push('-');
+            evr.push_str(release);
+        }
+        Some(evr)
+    }
+
+    /// The dependency's version for comparison (`None` when unversioned).
+    /// A missing epoch is 0 and a missing release is empty.
+    pub fn to_rpm_version(&self) -> Option<RpmVersion> {
+        let version = self.version.as_deref()?;
+        if self.epoch.is_none() && self.release.is_none() {
+            // Also accepts an `epoch:version-release` string in `version`,
+            // as stored before the parts were kept separately
+            return Some(RpmVersion::parse(version));
+        }
+        Some(RpmVersion::new(
+            self.epoch,
+            version.to_string(),
+            self.release.clone().unwrap_or_default(),
+        ))
+    }
+
+    /// Whether this dependency, read as a provide, satisfies a requirement
+    /// with the given `flags` and `[epoch:]version[-release]`.
+    ///
+    /// Follows rpm's range-overlap rules: an unversioned provide or requirement
+    /// matches anything, a missing epoch is 0, and the release is only compared
+    /// when both sides carry one.
+    pub fn version_matches(&self, flags: Option<&str>, version: Option<&str>) -> bool {
+        let (Some(req_sense), Some(req_evr)) = (flags.and_then(parse_sense), version) else {
+            return true;
+        };
+        let (Some(have_sense), Some(mut have)) = (
+            self.flags.as_deref().and_then(parse_sense),
+            self.to_rpm_version(),
+        ) else {
+            return true;
+        };
+
+        let mut want = RpmVersion::parse(req_evr);
+        if have.release.is_empty() || want.release.is_empty() {
+            have.release.clear();
+            want.release.clear();
         }
+
+        match have.cmp(&want) {
+            Ordering::Less => have_sense & SENSE_GREATER != 0 || req_sense & SENSE_LESS != 0,
+            Ordering::Greater => have_sense & SENSE_LESS != 0 || req_sense & SENSE_GREATER != 0,
+            Ordering::Equal => have_sense & req_sense != 0,
+        }
+    }
+}
+
+/// Cut `text` to about `max_len` bytes without splitting a UTF-8 character
+///
+/// The character straddling `max_len` is kept whole, so the result may run a
+/// few bytes past the limit.
+pub fn truncate_at_char_boundary(text: &str, max_len: usize) -> &str {
+    if text.len() <= max_len {
+        return text;
+    }
+    let end = text
+        .char_indices()
+        .take_while(|(i, _)| *i < max_len)
+        .last()
+        .map(|(i, c)| i + c.len_utf8())
+        .unwrap_or(0);
+    &text[..end]
+}
+
+/// Remove the `phrases` from `text` where they open a sentence, ignoring
+/// ASCII case. A phrase matches at the start of the text, after a line break
+/// or after sentence punctuation, and only as whole words ("This package is"
+/// leaves "This package isolates" alone). When anything was removed,
+/// whitespace is collapsed so no gaps are left.
+pub fn strip_boilerplate<'a>(text: &'a str, phrases: &[String]) -> Cow<'a, str> {
+    let mut stripped = Cow::Borrowed(text);
+    for phrase in phrases.iter().filter(|p| !p.is_empty()) {
+        // ASCII lowercasing keeps byte offsets aligned with the original
+        let needle = phrase.to_ascii_lowercase();
+        let haystack = stripped.to_ascii_lowercase();
+        let opens_sentence = |start: usize| {
+            let before = haystack[..start].trim_end_matches([' ', '\t']);
+            before.is_empty() || before.ends_with(['.', '!', '?', ':', '\n'])
+        };
+        let ends_word = |end: usize| {
+            !haystack[end..]
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_alphanumeric() || c == '_')
+        };
+        let matches: Vec<usize> = haystack
+            .match_indices(&needle)
+            .map(|(start, _)| start)
+            .filter(|&start| opens_sentence(start) && ends_word(start + needle.len()))
+            .collect();
+        if matches.is_empty() {
+            continue;
+        }
+        let mut out = String::with_capacity(stripped.len());
+        let mut last = 0;
+        for start in matches {
+            out.push_str(&stripped[last..start]);
+            last = start + needle.len();
+        }
+        out.push_str(&stripped[last..]);
+        stripped = Cow::Owned(out);
+    }
+    match stripped {
+        Cow::Owned(s) => Cow::Owned(s.split_whitespace().collect::<Vec<_>>().join(" ")),
+        borrowed => borrowed,
     }
 }
 
@@ -70,9 +305,88 @@ impl Package {
             license: rpm_pkg.license,
             vcs: rpm_pkg.vcs,
             location_href: rpm_pkg.location_href,
+            sourcerpm: rpm_pkg.sourcerpm,
+            build_time: rpm_pkg.build_time,
+            appstream: None,
+            localized: rpm_pkg.localized,
             repo,
             requires: rpm_pkg.requires.into_iter().map(Dependency::from).collect(),
             provides: rpm_pkg.provides.into_iter().map(Dependency::from).collect(),
+            conflicts: rpm_pkg
+                .conflicts
+                .into_iter()
+                .map(Dependency::from)
+                .collect(),
+            obsoletes: rpm_pkg
+                .obsoletes
+                .into_iter()
+                .map(Dependency::from)
+                .collect(),
+        }
+    }
+
+    /// Convert back to rpm-md package metadata (e.g. for writing primary.xml)
+    pub fn to_rpm_package(&self) -> RpmPackage {
+        RpmPackage {
+            name: self.name.clone(),
+            epoch: self.epoch,
+            version: self.version.clone(),
+            release: self.release.clone(),
+            arch: self.arch.clone(),
+            summary: self.summary.clone(),
+            description: self.description.clone(),
+            license: self.license.clone(),
+            vcs: self.vcs.clone(),
+            packager: None,
+            url: None,
+            location_href: self.location_href.clone(),
+            sourcerpm: self.sourcerpm.clone(),
+            build_time: self.build_time,
+            localized: self.localized.clone(),
+            requires: self.requires.iter().map(RpmDependency::from).collect(),
+            provides: self.provides.iter().map(RpmDependency::from).collect(),
+            conflicts: self.conflicts.iter().map(RpmDependency::from).collect(),
+            obsoletes: self.obsoletes.iter().map(RpmDependency::from).collect(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Whether the package passes arch/repo filters (an empty list matches anything).
+    /// Like the SQL filters, matching ignores ASCII case.
+    pub fn matches_arch_repo(&self, arches: &[String], repos: &[String]) -> bool {
+        (arches.is_empty() || arches.iter().any(|a| a.eq_ignore_ascii_case(&self.arch)))
+            && (repos.is_empty() || repos.iter().any(|r| r.eq_ignore_ascii_case(&self.repo)))
+    }
+
+    /// Name of the source package this binary was built from
+    /// (`bash-5.2-1.src.rpm` → `bash`). Falls back to the package name.
+    pub fn source_name(&self) -> &str {
+        self.sourcerpm
+            .as_deref()
+            .and_then(|srpm| srpm.strip_suffix(".rpm"))
+            .and_then(|srpm| srpm.rsplit_once('.'))
+            .and_then(|(nvr, _arch)| nvr.rsplitn(3, '-').nth(2))
+            .unwrap_or(&self.name)
+    }
+
+    /// Show the summary/description for `lang` where a localized variant exists.
+    /// Without an exact entry, `de` also matches `de_DE` (and vice versa).
+    pub fn localize(&mut self, lang: &str) {
+        let base = |l: &str| l.split(['_', '-']).next().unwrap_or(l).to_string();
+        let entry = self
+            .localized
+            .iter()
+            .find(|l| l.lang == lang)
+            .or_else(|| self.localized.iter().find(|l| base(&l.lang) == base(lang)))
+            .cloned();
+
+        if let Some(entry) = entry {
+            if let Some(summary) = entry.summary {
+                self.summary = summary;
+            }
+            if let Some(description) = entry.description {
+                self.description = description;
+            }
         }
     }
 
@@ -81,6 +395,30 @@ impl Package {
         RpmVersion::new(self.epoch, self.version.clone(), self.release.clone())
     }
 
+    /// Whether one of this package's provides satisfies `req`, by exact name
+    /// and [`Dependency::version_matches`]. The package's own `name = EVR` is
+    /// always an implicit provide, as in rpm.
+    pub fn provides_satisfies(&self, req: &Dependency) -> bool {
+        let evr = req.evr();
+        let (flags, version) = (req.flags.as_deref(), evr.as_deref());
+        if self.name == req.name {
+            let own = Dependency {
+                name: self.name.clone(),
+                flags: Some("EQ".to_string()),
+                epoch: self.epoch,
+                version: Some(self.version.clone()),
+                release: Some(self.release.clone()),
+                pre: false,
+            };
+            if own.version_matches(flags, version) {
+                return true;
+            }
+        }
+        self.provides
+            .iter()
+            .any(|p| p.name == req.name && p.version_matches(flags, version))
+    }
+
     /// Maximum description length in chars for embedding text.
     /// Keeps total token count well within the 512-token context window.
     const MAX_DESCRIPTION_CHARS: usize = 400;
@@ -96,7 +434,14 @@ impl Package {
     /// Description is truncated to [`MAX_DESCRIPTION_CHARS`] and provides/requires are
     /// limited to [`MAX_DEPS_COUNT`] entries to stay within the 512-token context window
     /// (important for both MiniLM and E5 models).
-    pub fn build_embedding_text(&self) -> String {
+    ///
+    /// `mode` selects the fields: [`EmbeddingTextMode::Summary`] keeps only the
+    /// name and summary, [`EmbeddingTextMode::NameSummary`] leaves out
+    /// provides/requires.
+    ///
+    /// `boilerplate` phrases ("This package contains") are removed from the
+    /// description before truncation, see [`strip_boilerplate`].
+    pub fn build_embedding_text(&self, mode: EmbeddingTextMode, boilerplate: &[String]) -> String {
         let mut text = String::new();
 
         // Name appears twice for higher weight in embedding
@@ -116,22 +461,40 @@ impl Package {
         text.push_str(&self.summary);
         text.push('\n');
 
-        text.push_str("Description:\n");
-        if self.description.len() > Self::MAX_DESCRIPTION_CHARS {
-            // Truncate at char boundary
-            let truncated = &self.description[..self
-                .description
-                .char_indices()
-                .take_while(|(i, _)| *i < Self::MAX_DESCRIPTION_CHARS)
-                .last()
-                .map(|(i, c)| i + c.len_utf8())
-                .unwrap_or(0)];
-            text.push_str(truncated);
-        } else {
-            text.push_str(&self.description);
+        if mode == EmbeddingTextMode::Summary {
+            return text;
         }
+
+        // AppStream descriptions are written for users and beat terse RPM text
+        let description = match self.appstream {
+            Some(ref appstream) if !appstream.description.is_empty() => &appstream.description,
+            _ => &self.description,
+        };
+        let description = strip_boilerplate(description, boilerplate);
+        text.push_str("Description:\n");
+        text.push_str(truncate_at_char_boundary(
+            &description,
+            Self::MAX_DESCRIPTION_CHARS,
+        ));
         text.push('\n');
 
+        if let Some(ref appstream) = self.appstream {
+            if !appstream.categories.is_empty() {
+                text.push_str("Categories: ");
+                text.push_str(&appstream.categories.join(", "));
+                text.push('\n');
+            }
+            if !appstream.keywords.is_empty() {
+                text.push_str("Keywords: ");
+                text.push_str(&appstream.keywords.join(", "));
+                text.push('\n');
+            }
+        }
+
+        if mode == EmbeddingTextMode::NameSummary {
+            return text;
+        }
+
         if !self.provides.is_empty() {
             text.push_str("Provides: ");
             let provides_str: Vec<String> = self
@@ -173,6 +536,37 @@ impl Package {
     }
 }
 
+/// Keep the `k` newest versions per (name, arch) pair, in RPM version order.
+///
+/// The same version indexed in several repositories counts once; the copy from
+/// the repo listed earliest in `repo_priority` is kept (unlisted repos rank last).
+pub fn filter_latest_n(packages: Vec<Package>, k: usize, repo_priority: &[String]) -> Vec<Package> {
+    let rank = |repo: &str| {
+        repo_priority
+            .iter()
+            .position(|r| r == repo)
+            .unwrap_or(repo_priority.len())
+    };
+
+    let mut groups: HashMap<(String, String), Vec<Package>> = HashMap::new();
+    for pkg in packages {
+        groups
+            .entry((pkg.name.clone(), pkg.arch.clone()))
+            .or_default()
+            .push(pkg);
+    }
+
+    let mut result = Vec::new();
+    for mut group in groups.into_values() {
+        group.sort_by(|a, b| b.cmp(a).then_with(|| rank(&a.repo).cmp(&rank(&b.repo))));
+        group.dedup_by(|later, kept| (*later).cmp(&*kept) == Ordering::Equal);
+        group.truncate(k);
+        result.extend(group);
+    }
+    result.sort();
+    result
+}
+
 // Version comparison for Package
 impl PartialEq for Package {
     fn eq(&self, other: &Self) -> bool {
@@ -211,102 +605,307 @@ impl Ord for Package {
     }
 }
 
+/// A package with only a name and version (release `1`, arch `x86_64`,
+/// repository `base`), for tests to fill in the fields they need
+#[cfg(test)]
+pub(crate) fn test_package(name: &str, version: &str) -> Package {
+    Package {
+        pkg_id: None,
+        name: name.to_string(),
+        epoch: None,
+        version: version.to_string(),
+        release: "1".to_string(),
+        arch: "x86_64".to_string(),
+        summary: String::new(),
+        description: String::new(),
+        license: None,
+        vcs: None,
+        location_href: None,
+        sourcerpm: None,
+        build_time: None,
+        appstream: None,
+        localized: Vec::new(),
+        repo: "base".to_string(),
+        requires: Vec::new(),
+        provides: Vec::new(),
+        conflicts: Vec::new(),
+        obsoletes: Vec::new(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_dependency_display() {
+        let dep = |flags: Option<&str>, version: Option<&str>| {
+            Dependency::from_evr("openssl(x86-64)", flags, version)
+        };
+
+        assert_eq!(
+            dep(Some("EQ"), Some("1:3.0.0-1")).to_string(),
+            "openssl(x86-64) = 1:3.0.0-1"
+        );
+        assert_eq!(
+            dep(Some("GE"), Some("3.0")).to_string(),
+            "openssl(x86-64) >= 3.0"
+        );
+        assert_eq!(dep(Some("LT"), None).to_string(), "openssl(x86-64)");
+        assert_eq!(dep(None, None).to_string(), "openssl(x86-64)");
+    }
+
+    #[test]
+    fn test_truncate_at_char_boundary() {
+        assert_eq!(truncate_at_char_boundary("hello", 10), "hello");
+        assert_eq!(truncate_at_char_boundary("hello", 3), "hel");
+        // "패키지" is 9 bytes; byte 4 falls inside the second character
+        assert_eq!(truncate_at_char_boundary("패키지", 4), "패키");
+        assert_eq!(truncate_at_char_boundary("패키지", 0), "");
+    }
+
     #[test]
     fn test_build_embedding_text() {
         let pkg = Package {
-            pkg_id: None,
-            name: "openssl".to_string(),
             epoch: Some(1),
-            version: "3.0.0".to_string(),
             release: "1.el9".to_string(),
-            arch: "x86_64".to_string(),
             summary: "Cryptography library".to_string(),
             description: "OpenSSL is a robust cryptography library".to_string(),
-            license: None,
-            vcs: None,
-            location_href: None,
             repo: "baseos".to_string(),
-            requires: vec![Dependency {
-                name: "glibc".to_string(),
-                flags: Some(">=".to_string()),
-                version: Some("2.34".to_string()),
-            }],
-            provides: vec![Dependency {
-                name: "libssl.so.3".to_string(),
-                flags: None,
-                version: None,
-            }],
+            requires: vec![Dependency::from_evr("glibc", Some(">="), Some("2.34"))],
+            provides: vec![Dependency::from_evr("libssl.so.3", None, None)],
+            ..test_package("openssl", "3.0.0")
         };
 
-        let text = pkg.build_embedding_text();
+        let text = pkg.build_embedding_text(EmbeddingTextMode::Full, &[]);
         assert!(text.contains("Package: openssl"));
         assert!(text.contains("Name: openssl"));
         assert!(text.contains("Architecture: x86_64"));
         assert!(text.contains("Summary: Cryptography library"));
         assert!(text.contains("Provides: libssl.so.3"));
         assert!(text.contains("Requires: glibc"));
+
+        let text = pkg.build_embedding_text(EmbeddingTextMode::NameSummary, &[]);
+        assert!(text.contains("Description:\nOpenSSL is a robust cryptography library"));
+        assert!(!text.contains("Provides:"));
+        assert!(!text.contains("Requires:"));
+
+        let text = pkg.build_embedding_text(EmbeddingTextMode::Summary, &[]);
+        assert!(text.contains("Summary: Cryptography library"));
+        assert!(!text.contains("Description:"));
+        assert!(!text.contains("Provides:"));
+    }
+
+    #[test]
+    fn test_build_embedding_text_appstream() {
+        let mut pkg = Package {
+            summary: "File manager for GNOME".to_string(),
+            description: "Nautilus is the file manager.".to_string(),
+            repo: "fedora".to_string(),
+            ..test_package("nautilus", "46.0")
+        };
+        pkg.appstream = Some(AppstreamInfo {
+            description: "Files lets you browse and organize your documents.".to_string(),
+            categories: vec!["System".to_string(), "FileManager".to_string()],
+            keywords: vec!["folder".to_string()],
+        });
+
+        let text = pkg.build_embedding_text(EmbeddingTextMode::Full, &[]);
+        assert!(text.contains("Files lets you browse"));
+        assert!(!text.contains("Nautilus is the file manager."));
+        assert!(text.contains("Categories: System, FileManager"));
+        assert!(text.contains("Keywords: folder"));
+    }
+
+    #[test]
+    fn test_build_embedding_text_strips_boilerplate() {
+        let mut pkg = Package {
+            description: "This package contains the zlib compression library.\n\
+                          THIS PACKAGE PROVIDES deflate streams."
+                .to_string(),
+            ..Package::from_rpm_package(
+                RpmPackage {
+                    name: "zlib".to_string(),
+                    epoch: None,
+                    version: "1.3".to_string(),
+                    release: "1".to_string(),
+                    arch: "x86_64".to_string(),
+                    summary: "Compression library".to_string(),
+                    description: String::new(),
+                    license: None,
+                    vcs: None,
+                    packager: None,
+                    url: None,
+                    location_href: None,
+                    sourcerpm: None,
+                    build_time: None,
+                    localized: Vec::new(),
+                    requires: Vec::new(),
+                    provides: Vec::new(),
+                    conflicts: Vec::new(),
+                    obsoletes: Vec::new(),
+                    files: Vec::new(),
+                },
+                "base".to_string(),
+            )
+        };
+        let boilerplate = ["This package contains", "This package provides"].map(String::from);
+
+        let text = pkg.build_embedding_text(EmbeddingTextMode::Full, &boilerplate);
+        assert!(text.contains("Description:\nthe zlib compression library. deflate streams.\n"));
+        assert!(!text.to_lowercase().contains("this package"));
+
+        // Only whole words at the start of a sentence are removed
+        let boilerplate = ["This package is"].map(String::from);
+        pkg.description =
+            "This package isolates builds. It says this package is small. This package is tiny."
+                .to_string();
+        let text = pkg.build_embedding_text(EmbeddingTextMode::Full, &boilerplate);
+        assert!(text.contains(
+            "Description:\nThis package isolates builds. It says this package is small. tiny.\n"
+        ));
+
+        // Descriptions without boilerplate are left untouched
+        pkg.description = "Deflate\n\nlibrary".to_string();
+        let text = pkg.build_embedding_text(EmbeddingTextMode::Full, &boilerplate);
+        assert!(text.contains("Description:\nDeflate\n\nlibrary\n"));
+    }
+
+    #[test]
+    fn test_dependency_round_trip() {
+        let rpm_dep = RpmDependency {
+            name: "glibc".to_string(),
+            flags: Some("GE".to_string()),
+            epoch: Some("1".to_string()),
+            version: Some("2.34".to_string()),
+            release: Some("5.el9".to_string()),
+            pre: false,
+        };
+        let dep = Dependency::from(rpm_dep.clone());
+        assert_eq!(dep.epoch, Some(1));
+        assert_eq!(dep.version.as_deref(), Some("2.34"));
+        assert_eq!(dep.release.as_deref(), Some("5.el9"));
+        assert_eq!(dep.evr().as_deref(), Some("1:2.34-5.el9"));
+        assert_eq!(
+            dep.to_rpm_version(),
+            Some(RpmVersion::new(
+                Some(1),
+                "2.34".to_string(),
+                "5.el9".to_string()
+            ))
+        );
+        assert_eq!(RpmDependency::from(&dep), rpm_dep);
+        assert_eq!(
+            Dependency::from_evr("glibc", Some("GE"), Some("1:2.34-5.el9")),
+            dep
+        );
+
+        let bare = Dependency::from_evr("sh", None, None);
+        assert!(bare.to_rpm_version().is_none());
+        assert_eq!(
+            RpmDependency::from(&bare),
+            RpmDependency::new("sh".to_string())
+        );
     }
 
     #[test]
     fn test_full_version() {
         let pkg = Package {
-            pkg_id: None,
-            name: "test".to_string(),
             epoch: Some(2),
-            version: "1.0.0".to_string(),
             release: "1.el9".to_string(),
-            arch: "x86_64".to_string(),
-            summary: "".to_string(),
-            description: "".to_string(),
-            license: None,
-            vcs: None,
-            location_href: None,
-            repo: "".to_string(),
-            requires: vec![],
-            provides: vec![],
+            ..test_package("test", "1.0.0")
         };
 
         assert_eq!(pkg.full_version(), "2:1.0.0-1.el9");
     }
 
+    #[test]
+    fn test_source_name() {
+        let mut pkg = Package {
+            release: "2.fc40".to_string(),
+            sourcerpm: Some("python3.12-3.12.1-2.fc40.src.rpm".to_string()),
+            ..test_package("python3-libs", "3.12.1")
+        };
+        assert_eq!(pkg.source_name(), "python3.12");
+
+        pkg.sourcerpm = Some("kernel-headers-6.8.0-1.nosrc.rpm".to_string());
+        assert_eq!(pkg.source_name(), "kernel-headers");
+
+        pkg.sourcerpm = None;
+        assert_eq!(pkg.source_name(), "python3-libs");
+    }
+
+    #[test]
+    fn test_localize() {
+        let mut pkg = Package {
+            summary: "The GNU Bourne Again shell".to_string(),
+            description: "Bash is the shell.".to_string(),
+            localized: vec![LocalizedText {
+                lang: "de_DE".to_string(),
+                summary: Some("Die GNU Bourne Again Shell".to_string()),
+                description: None,
+            }],
+            ..test_package("bash", "5.2")
+        };
+
+        let mut french = pkg.clone();
+        french.localize("fr");
+        assert_eq!(french.summary, "The GNU Bourne Again shell");
+
+        pkg.localize("de");
+        assert_eq!(pkg.summary, "Die GNU Bourne Again Shell");
+        // No localized description: the default is kept
+        assert_eq!(pkg.description, "Bash is the shell.");
+    }
+
+    #[test]
+    fn test_filter_latest_n() {
+        let pkg = |name: &str, version: &str, release: &str, repo: &str| Package {
+            release: release.to_string(),
+            repo: repo.to_string(),
+            ..test_package(name, version)
+        };
+        let packages = vec![
+            pkg("bash", "5.1", "1", "old"),
+            pkg("bash", "5.2", "1", "old"),
+            pkg("bash", "5.2", "10", "updates"),
+            pkg("bash", "5.2", "2", "old"),
+            pkg("bash", "5.2", "2", "updates"),
+            pkg("zsh", "5.9", "1", "old"),
+        ];
+        let nvr = |pkgs: &[Package]| -> Vec<String> {
+            pkgs.iter()
+                .map(|p| format!("{}-{}@{}", p.name, p.full_version(), p.repo))
+                .collect()
+        };
+
+        let priority = vec!["updates".to_string()];
+        assert_eq!(
+            nvr(&filter_latest_n(packages.clone(), 3, &priority)),
+            [
+                "bash-5.2-1@old",
+                "bash-5.2-2@updates",
+                "bash-5.2-10@updates",
+                "zsh-5.9-1@old"
+            ]
+        );
+        assert_eq!(
+            nvr(&filter_latest_n(packages.clone(), 1, &[])),
+            ["bash-5.2-10@updates", "zsh-5.9-1@old"]
+        );
+        assert_eq!(filter_latest_n(packages, 10, &[]).len(), 5);
+    }
+
     #[test]
     fn test_version_comparison() {
         let pkg1 = Package {
-            pkg_id: None,
-            name: "kernel".to_string(),
-            epoch: None,
-            version: "5.14.0".to_string(),
             release: "279.el9".to_string(),
-            arch: "x86_64".to_string(),
-            summary: "".to_string(),
-            description: "".to_string(),
-            license: None,
-            vcs: None,
-            location_href: None,
-            repo: "".to_string(),
-            requires: vec![],
-            provides: vec![],
+            ..test_package("kernel", "5.14.0")
         };
 
         let pkg2 = Package {
-            pkg_id: None,
-            name: "kernel".to_string(),
-            epoch: None,
-            version: "5.14.0".to_string(),
             release: "754.el9".to_string(),
-            arch: "x86_64".to_string(),
-            summary: "".to_string(),
-            description: "".to_string(),
-            license: None,
-            vcs: None,
-            location_href: None,
-            repo: "".to_string(),
-            requires: vec![],
-            provides: vec![],
+            ..test_package("kernel", "5.14.0")
         };
 
         // pkg1 (279) < pkg2 (754)
@@ -316,40 +915,62 @@ mod tests {
     #[test]
     fn test_epoch_comparison() {
         let pkg1 = Package {
-            pkg_id: None,
-            name: "glibc".to_string(),
             epoch: Some(1),
-            version: "2.34".to_string(),
             release: "1.el9".to_string(),
-            arch: "x86_64".to_string(),
-            summary: "".to_string(),
-            description: "".to_string(),
-            license: None,
-            vcs: None,
-            location_href: None,
-            repo: "".to_string(),
-            requires: vec![],
-            provides: vec![],
+            ..test_package("glibc", "2.34")
         };
 
+        // No epoch, i.e. epoch 0
         let pkg2 = Package {
-            pkg_id: None,
-            name: "glibc".to_string(),
-            epoch: None, // epoch 0
-            version: "3.0".to_string(),
             release: "1.el9".to_string(),
-            arch: "x86_64".to_string(),
-            summary: "".to_string(),
-            description: "".to_string(),
-            license: None,
-            vcs: None,
-            location_href: None,
-            repo: "".to_string(),
-            requires: vec![],
-            provides: vec![],
+            ..test_package("glibc", "3.0")
         };
 
         // epoch 1 > epoch 0, even though 2.34 < 3.0
         assert!(pkg1 > pkg2);
     }
+
+    fn dep(name: &str, flags: Option<&str>, version: Option<&str>) -> Dependency {
+        Dependency::from_evr(name, flags, version)
+    }
+
+    #[test]
+    fn test_version_matches() {
+        let provide = dep("libfoo", Some("EQ"), Some("0:2.35-1"));
+        assert!(provide.version_matches(Some("GE"), Some("2.34")));
+        assert!(provide.version_matches(Some(">="), Some("2.35")));
+        assert!(provide.version_matches(Some("EQ"), Some("2.35-1")));
+        assert!(!provide.version_matches(Some("EQ"), Some("2.35-2")));
+        assert!(provide.version_matches(Some("LT"), Some("2.36")));
+        assert!(!provide.version_matches(Some("GT"), Some("2.35")));
+        // Epoch dominates the version
+        assert!(!provide.version_matches(Some("GE"), Some("1:2.34")));
+        assert!(
+            dep("libfoo", Some("EQ"), Some("1:1.0-1")).version_matches(Some("GE"), Some("1:0.9"))
+        );
+        // Unversioned requirement or provide matches anything
+        assert!(provide.version_matches(None, None));
+        assert!(dep("libfoo", None, None).version_matches(Some("GE"), Some("9:9.9")));
+    }
+
+    #[test]
+    fn test_provides_satisfies() {
+        let pkg = Package {
+            epoch: Some(1),
+            release: "60.el9".to_string(),
+            provides: vec![
+                dep("libc.so.6()(64bit)", None, None),
+                dep("rtld(GNU_HASH)", None, None),
+            ],
+            ..test_package("glibc", "2.34")
+        };
+
+        // Implicit self-provide, epoch-qualified
+        assert!(pkg.provides_satisfies(&dep("glibc", Some("GE"), Some("1:2.34"))));
+        assert!(!pkg.provides_satisfies(&dep("glibc", Some("GE"), Some("2:2.0"))));
+        // Unversioned provide satisfies a versioned requirement
+        assert!(pkg.provides_satisfies(&dep("libc.so.6()(64bit)", Some("GE"), Some("2.0"))));
+        assert!(pkg.provides_satisfies(&dep("rtld(GNU_HASH)", None, None)));
+        assert!(!pkg.provides_satisfies(&dep("libm.so.6()(64bit)", None, None)));
+    }
 }
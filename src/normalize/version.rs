@@ -53,6 +53,23 @@ impl RpmVersion {
         }
     }
 
+    /// Parse an `[epoch:]version[-release]` string.
+    /// A missing release is represented as an empty string.
+    pub fn parse(evr: &str) -> Self {
+        let (epoch, rest) = match evr.split_once(':') {
+            Some((e, rest)) => match e.parse::<i64>() {
+                Ok(epoch) => (Some(epoch), rest),
+                Err(_) => (None, evr),
+            },
+            None => (None, evr),
+        };
+        let (version, release) = match rest.rsplit_once('-') {
+            Some((v, r)) => (v, r),
+            None => (rest, ""),
+        };
+        Self::new(epoch, version.to_string(), release.to_string())
+    }
+
     /// Compare two version/release strings using RPM algorithm
     fn compare_segments(a: &str, b: &str) -> Ordering {
         let mut a_chars = a.chars().peekable();
@@ -210,6 +227,22 @@ impl Ord for RpmVersion {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_evr() {
+        assert_eq!(
+            RpmVersion::parse("1:3.0.7-1.el9"),
+            RpmVersion::new(Some(1), "3.0.7".to_string(), "1.el9".to_string())
+        );
+        assert_eq!(
+            RpmVersion::parse("2.34"),
+            RpmVersion::new(None, "2.34".to_string(), String::new())
+        );
+        assert_eq!(
+            RpmVersion::parse("1.0-2"),
+            RpmVersion::new(None, "1.0".to_string(), "2".to_string())
+        );
+    }
+
     #[test]
     fn test_epoch_comparison() {
         let v1 = RpmVersion::new(Some(1), "1.0".to_string(), "1".to_string());
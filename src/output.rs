@@ -0,0 +1,392 @@
+use crate::error::{Result, RpmSearchError};
+use crate::normalize::Package;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Machine-readable output format for package lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Comma-separated values (RFC 4180) with a header row
+    Csv,
+    /// Tab-separated values with a header row
+    Tsv,
+    /// A JSON array of full package records
+    Json,
+    /// Newline-delimited JSON: one package record per line
+    Ndjson,
+    /// A YAML sequence of full package records
+    Yaml,
+}
+
+/// Columns written for each package
+const PACKAGE_COLUMNS: [&str; 8] = [
+    "name", "epoch", "version", "release", "arch", "repo", "summary", "license",
+];
+
+/// Write packages in the given format, one row (or record) per package.
+/// `pretty` indents `Json` output and is ignored by the other formats.
+pub fn write_packages<W: Write>(
+    writer: W,
+    format: OutputFormat,
+    pretty: bool,
+    packages: &[Package],
+) -> Result<()> {
+    let mut writer = PackageWriter::new(writer, format, pretty)?;
+    for pkg in packages {
+        writer.write(pkg)?;
+    }
+    writer.finish()
+}
+
+/// Streaming package writer: each package is written as soon as it is passed
+/// in, so large result sets never have to be collected first
+pub struct PackageWriter<W: Write> {
+    sink: Sink<W>,
+    written: usize,
+}
+
+enum Sink<W: Write> {
+    Delimited(Box<csv::Writer<W>>),
+    Json { writer: W, pretty: bool },
+    Ndjson(W),
+    Yaml(W),
+}
+
+impl<W: Write> PackageWriter<W> {
+    /// Start writing packages (emits the CSV/TSV header right away)
+    pub fn new(writer: W, format: OutputFormat, pretty: bool) -> Result<Self> {
+        let sink = match format {
+            OutputFormat::Csv | OutputFormat::Tsv => {
+                let delimiter = if format == OutputFormat::Csv {
+                    b','
+                } else {
+                    b'\t'
+                };
+                let mut writer = csv::WriterBuilder::new()
+                    .delimiter(delimiter)
+                    .from_writer(writer);
+                writer.write_record(PACKAGE_COLUMNS).map_err(csv_error)?;
+                Sink::Delimited(Box::new(writer))
+            }
+            OutputFormat::Json => Sink::Json { writer, pretty },
+            OutputFormat::Ndjson => Sink::Ndjson(writer),
+            OutputFormat::Yaml => Sink::Yaml(writer),
+        };
+        Ok(Self { sink, written: 0 })
+    }
+
+    /// Write a single package
+    pub fn write(&mut self, pkg: &Package) -> Result<()> {
+        match self.sink {
+            Sink::Delimited(ref mut writer) => {
+                let epoch = pkg.epoch.unwrap_or(0).to_string();
+                writer
+                    .write_record([
+                        pkg.name.as_str(),
+                        epoch.as_str(),
+                        pkg.version.as_str(),
+                        pkg.release.as_str(),
+                        pkg.arch.as_str(),
+                        pkg.repo.as_str(),
+                        pkg.summary.as_str(),
+                        pkg.license.as_deref().unwrap_or(""),
+                    ])
+                    .map_err(csv_error)?;
+            }
+            Sink::Json {
+                ref mut writer,
+                pretty,
+            } => {
+                let record = if pretty {
+                    // Indent the record one level inside the array
+                    serde_json::to_string_pretty(pkg)
+                        .map_err(json_error)?
+                        .replace('\n', "\n  ")
+                } else {
+                    serde_json::to_string(pkg).map_err(json_error)?
+                };
+                let separator = match (self.written, pretty) {
+                    (0, true) => "[\n  ",
+                    (0, false) => "[",
+                    (_, true) => ",\n  ",
+                    (_, false) => ",",
+                };
+                write!(writer, "{}{}", separator, record)?;
+            }
+            Sink::Ndjson(ref mut writer) => {
+                serde_json::to_writer(&mut *writer, pkg).map_err(json_error)?;
+                writer.write_all(b"\n")?;
+            }
+            Sink::Yaml(ref mut writer) => {
+                // A one-element sequence per package; consecutive items form one sequence
+                let item =
+                    serde_norway::to_string(std::slice::from_ref(pkg)).map_err(yaml_error)?;
+                writer.write_all(item.as_bytes())?;
+            }
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Finish the output (closes the JSON array) and flush
+    pub fn finish(self) -> Result<()> {
+        match self.sink {
+            Sink::Delimited(mut writer) => writer.flush()?,
+            Sink::Json { mut writer, pretty } => {
+                let close = match (self.written, pretty) {
+                    (0, _) => "[]\n",
+                    (_, true) => "\n]\n",
+                    (_, false) => "]\n",
+                };
+                writer.write_all(close.as_bytes())?;
+                writer.flush()?;
+            }
+            Sink::Ndjson(mut writer) => writer.flush()?,
+            Sink::Yaml(mut writer) => {
+                if self.written == 0 {
+                    writer.write_all(b"[]\n")?;
+                }
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Wrap case-insensitive occurrences of the query's words in ANSI bold yellow.
+/// Words shorter than two characters are ignored; overlapping matches keep the
+/// longest word. Case folding is ASCII-only, so other text must match exactly.
+pub fn highlight_terms(text: &str, query: &str) -> String {
+    let mut terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric() && c != '-' && c != '_' && c != '+')
+        .filter(|t| t.chars().count() >= 2)
+        .map(str::to_ascii_lowercase)
+        .collect();
+    terms.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    terms.dedup();
+    if terms.is_empty() {
+        return text.to_string();
+    }
+
+    // ASCII lowercasing keeps byte offsets identical to `text`
+    let lower = text.to_ascii_lowercase();
+    let mut out = String::with_capacity(text.len());
+    let mut pos = 0;
+    while pos < text.len() {
+        match terms.iter().find(|t| lower[pos..].starts_with(t.as_str())) {
+            Some(term) => {
+                out.push_str("\x1b[1;33m");
+                out.push_str(&text[pos..pos + term.len()]);
+                out.push_str("\x1b[0m");
+                pos += term.len();
+            }
+            None => {
+                let ch = text[pos..].chars().next().unwrap_or_default();
+                out.push(ch);
+                pos += ch.len_utf8();
+            }
+        }
+    }
+    out
+}
+
+/// Group result indices by repository, for `search --group-by-repo`.
+/// Groups are ordered by the position of each repository's first result and
+/// keep the order of `indices` inside, so ranked results stay ranked.
+pub fn group_by_repo<'a>(packages: &'a [Package], indices: &[usize]) -> Vec<(&'a str, Vec<usize>)> {
+    let mut groups: Vec<(&str, Vec<usize>)> = Vec::new();
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    for &i in indices {
+        let repo = packages[i].repo.as_str();
+        let pos = *positions.entry(repo).or_insert_with(|| {
+            groups.push((repo, Vec::new()));
+            groups.len() - 1
+        });
+        groups[pos].1.push(i);
+    }
+    groups
+}
+
+fn csv_error(e: csv::Error) -> RpmSearchError {
+    RpmSearchError::Io(e.into())
+}
+
+fn json_error(e: serde_json::Error) -> RpmSearchError {
+    RpmSearchError::Io(e.into())
+}
+
+fn yaml_error(e: serde_norway::Error) -> RpmSearchError {
+    RpmSearchError::Io(std::io::Error::other(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalize::test_package;
+
+    fn package(name: &str, summary: &str) -> Package {
+        Package {
+            summary: summary.to_string(),
+            license: Some("MIT".to_string()),
+            ..test_package(name, "1.0")
+        }
+    }
+
+    #[test]
+    fn test_write_csv_quoting() {
+        let packages = vec![
+            package("foo", "plain summary"),
+            package("bar", "has, comma and \"quotes\"\nand a newline"),
+        ];
+        let mut out = Vec::new();
+        write_packages(&mut out, OutputFormat::Csv, false, &packages).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "name,epoch,version,release,arch,repo,summary,license\n\
+             foo,0,1.0,1,x86_64,base,plain summary,MIT\n\
+             bar,0,1.0,1,x86_64,base,\"has, comma and \"\"quotes\"\"\nand a newline\",MIT\n"
+        );
+    }
+
+    #[test]
+    fn test_write_tsv() {
+        let mut out = Vec::new();
+        write_packages(
+            &mut out,
+            OutputFormat::Tsv,
+            false,
+            &[package("foo", "a, b")],
+        )
+        .unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0].split('\t').count(), 8);
+        assert_eq!(lines[1], "foo\t0\t1.0\t1\tx86_64\tbase\ta, b\tMIT");
+    }
+
+    #[test]
+    fn test_write_json() {
+        let packages = vec![package("foo", "a"), package("bar", "b")];
+
+        let mut out = Vec::new();
+        write_packages(&mut out, OutputFormat::Json, false, &packages).unwrap();
+        let compact: Vec<Package> = serde_json::from_slice(&out).unwrap();
+        assert_eq!(compact.len(), 2);
+        assert_eq!(String::from_utf8(out).unwrap().lines().count(), 1);
+
+        let mut out = Vec::new();
+        write_packages(&mut out, OutputFormat::Json, true, &packages).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(
+            text,
+            format!("{}\n", serde_json::to_string_pretty(&packages).unwrap())
+        );
+
+        let mut out = Vec::new();
+        write_packages(&mut out, OutputFormat::Json, true, &[]).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "[]\n");
+    }
+
+    #[test]
+    fn test_write_ndjson() {
+        let mut out = Vec::new();
+        let mut writer = PackageWriter::new(&mut out, OutputFormat::Ndjson, false).unwrap();
+        writer.write(&package("foo", "a")).unwrap();
+        writer.write(&package("bar", "b")).unwrap();
+        writer.finish().unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        let names: Vec<String> = text
+            .lines()
+            .map(|line| serde_json::from_str::<Package>(line).unwrap().name)
+            .collect();
+        assert_eq!(names, ["foo", "bar"]);
+    }
+
+    #[test]
+    fn test_write_yaml() {
+        let packages = vec![package("foo", "a: colon"), package("bar", "b")];
+        let mut out = Vec::new();
+        write_packages(&mut out, OutputFormat::Yaml, false, &packages).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text, serde_norway::to_string(&packages).unwrap());
+        let parsed: Vec<Package> = serde_norway::from_str(&text).unwrap();
+        assert_eq!(parsed[0].summary, "a: colon");
+
+        let mut out = Vec::new();
+        write_packages(&mut out, OutputFormat::Yaml, false, &[]).unwrap();
+        let parsed: Vec<Package> = serde_norway::from_slice(&out).unwrap();
+        assert!(parsed.is_empty());
+    }
+
+    #[test]
+    fn test_highlight_terms_case_insensitive() {
+        assert_eq!(
+            highlight_terms("OpenSSL and openssl", "openSSL"),
+            "\x1b[1;33mOpenSSL\x1b[0m and \x1b[1;33mopenssl\x1b[0m"
+        );
+        // Single-character words are not highlighted
+        assert_eq!(highlight_terms("a b c", "a b"), "a b c");
+        assert_eq!(highlight_terms("no match", "zlib"), "no match");
+    }
+
+    #[test]
+    fn test_highlight_terms_overlapping() {
+        // The longest word wins where terms overlap; repeated words count once
+        assert_eq!(
+            highlight_terms("libssl-devel", "ssl libssl ssl"),
+            "\x1b[1;33mlibssl\x1b[0m-devel"
+        );
+        assert_eq!(
+            highlight_terms("ssl libssl", "ssl libssl"),
+            "\x1b[1;33mssl\x1b[0m \x1b[1;33mlibssl\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_highlight_terms_multibyte() {
+        assert_eq!(
+            highlight_terms("Bibliothèque für XML — libxml2", "xml für"),
+            "Bibliothèque \x1b[1;33mfür\x1b[0m \x1b[1;33mXML\x1b[0m — lib\x1b[1;33mxml\x1b[0m2"
+        );
+        assert_eq!(
+            highlight_terms("한국어 패키지", "패키지"),
+            "한국어 \x1b[1;33m패키지\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_group_by_repo_order() {
+        let packages: Vec<Package> = [
+            ("a", "updates"),
+            ("b", "base"),
+            ("c", "updates"),
+            ("d", "extra"),
+            ("e", "base"),
+        ]
+        .into_iter()
+        .map(|(name, repo)| Package {
+            repo: repo.to_string(),
+            ..test_package(name, "1.0")
+        })
+        .collect();
+
+        // Groups follow each repo's first (best) result; members keep the given order
+        let groups = group_by_repo(&packages, &[0, 1, 2, 3, 4]);
+        assert_eq!(
+            groups,
+            vec![
+                ("updates", vec![0, 2]),
+                ("base", vec![1, 4]),
+                ("extra", vec![3])
+            ]
+        );
+
+        // Only the given indices are grouped, in their order
+        let groups = group_by_repo(&packages, &[4, 2, 1]);
+        assert_eq!(groups, vec![("base", vec![4, 1]), ("updates", vec![2])]);
+        assert!(group_by_repo(&packages, &[]).is_empty());
+    }
+}
@@ -0,0 +1,187 @@
+use crate::error::{Result, RpmSearchError};
+use crate::repomd::model::AppstreamComponent;
+use crate::repomd::parser::{is_text_char, push_sanitized};
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::io::BufRead;
+
+pub struct AppstreamParser;
+
+impl AppstreamParser {
+    /// Parse AppStream collection XML (components.xml / appdata) and extract the
+    /// untranslated description, categories and keywords of each component.
+    /// Components without a `<pkgname>` cannot be matched and are skipped.
+    pub fn parse<R: BufRead>(reader: R) -> Result<Vec<AppstreamComponent>> {
+        let mut xml_reader = Reader::from_reader(reader);
+
+        let mut components = Vec::new();
+        let mut buf = Vec::new();
+        let mut current: Option<AppstreamComponent> = None;
+        let mut current_text = String::new();
+        let mut paragraphs: Vec<String> = Vec::new();
+        let mut in_description = false;
+        let mut depth = 0usize;
+        // Depth of the translated (`xml:lang`) element being skipped, if any
+        let mut skip_from: Option<usize> = None;
+
+        loop {
+            match xml_reader.read_event_into(&mut buf) {
+                Ok(Event::Start(e)) => {
+                    depth += 1;
+                    if skip_from.is_some() {
+                        buf.clear();
+                        continue;
+                    }
+                    let translated = e.attributes().flatten().any(|a| {
+                        a.key.as_ref() == b"xml:lang" && !a.value.is_empty() && &*a.value != b"C"
+                    });
+                    if translated && current.is_some() {
+                        skip_from = Some(depth);
+                        buf.clear();
+                        continue;
+                    }
+
+                    match e.name().as_ref() {
+                        b"component" => {
+                            current = Some(AppstreamComponent::default());
+                        }
+                        b"description" => {
+                            in_description = true;
+                            paragraphs.clear();
+                        }
+                        b"id" | b"pkgname" | b"category" | b"keyword" | b"p" | b"li" => {
+                            current_text.clear();
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(Event::Text(e)) if skip_from.is_none() => {
+                    push_sanitized(&mut current_text, e.as_ref());
+                }
+                Ok(Event::GeneralRef(e)) if skip_from.is_none() => {
+                    if let Ok(Some(ch)) = e.resolve_char_ref() {
+                        if is_text_char(ch) {
+                            current_text.push(ch);
+                        }
+                    } else if let Some(value) = e
+                        .decode()
+                        .ok()
+                        .and_then(|name| resolve_predefined_entity(&name))
+                    {
+                        current_text.push_str(value);
+                    }
+                }
+                Ok(Event::End(e)) => {
+                    let skipping = skip_from.is_some();
+                    if skip_from == Some(depth) {
+                        skip_from = None;
+                    }
+                    depth = depth.saturating_sub(1);
+                    if skipping {
+                        buf.clear();
+                        continue;
+                    }
+
+                    if let Some(component) = current.as_mut() {
+                        let text = current_text.trim();
+                        match e.name().as_ref() {
+                            b"component" => {
+                                if let Some(component) = current.take() {
+                                    if !component.pkgname.is_empty() {
+                                        components.push(component);
+                                    }
+                                }
+                            }
+                            // The first <id> is the component's own (later ones
+                            // appear in <provides>, <extends>, ...)
+                            b"id" if component.id.is_empty() => {
+                                component.id = text.to_string();
+                            }
+                            b"pkgname" if component.pkgname.is_empty() => {
+                                component.pkgname = text.to_string();
+                            }
+                            b"category" if !text.is_empty() => {
+                                component.categories.push(text.to_string());
+                            }
+                            b"keyword" if !text.is_empty() => {
+                                component.keywords.push(text.to_string());
+                            }
+                            b"p" | b"li" if in_description && !text.is_empty() => {
+                                paragraphs
+                                    .push(text.split_whitespace().collect::<Vec<_>>().join(" "));
+                            }
+                            b"description" => {
+                                in_description = false;
+                                component.description = paragraphs.join("\n");
+                            }
+                            _ => {}
+                        }
+                    }
+                    current_text.clear();
+                }
+                Ok(Event::Eof) => break,
+                Err(e) => {
+                    return Err(RpmSearchError::XmlParse(format!(
+                        "AppStream XML parsing error: {}",
+                        e
+                    )))
+                }
+                _ => {}
+            }
+            buf.clear();
+        }
+
+        Ok(components)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_component() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <components version="0.14" origin="fedora">
+          <component type="desktop-application">
+            <id>org.gnome.Nautilus</id>
+            <pkgname>nautilus</pkgname>
+            <name>Files</name>
+            <summary>Access and organize files</summary>
+            <description>
+              <p>Files, also known as Nautilus, is the default file manager of the GNOME desktop.</p>
+              <p xml:lang="de">Dateien ist der Standard-Dateimanager.</p>
+              <ul>
+                <li>Browse &amp; search files</li>
+              </ul>
+            </description>
+            <categories>
+              <category>System</category>
+              <category>FileManager</category>
+            </categories>
+            <keywords>
+              <keyword>folder</keyword>
+              <keyword xml:lang="de">Ordner</keyword>
+            </keywords>
+            <provides><id>nautilus.desktop</id></provides>
+          </component>
+          <component type="addon">
+            <id>no.package.Addon</id>
+          </component>
+        </components>"#;
+
+        let components = AppstreamParser::parse(xml.as_bytes()).unwrap();
+        assert_eq!(components.len(), 1);
+        let c = &components[0];
+        assert_eq!(c.id, "org.gnome.Nautilus");
+        assert_eq!(c.pkgname, "nautilus");
+        assert_eq!(
+            c.description,
+            "Files, also known as Nautilus, is the default file manager of the GNOME desktop.\n\
+             Browse & search files"
+        );
+        assert_eq!(c.categories, vec!["System", "FileManager"]);
+        assert_eq!(c.keywords, vec!["folder"]);
+    }
+}
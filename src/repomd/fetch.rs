@@ -1,9 +1,60 @@
-use crate::error::Result;
-use std::io::Read;
-use std::path::Path;
+use crate::error::{Result, RpmSearchError};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
 
 pub struct RepoFetcher;
 
+/// Compression of a metadata file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+impl Compression {
+    /// Detect compression from the leading bytes. `None` (the Option) when they
+    /// are ambiguous: too short, or neither a known magic number nor XML.
+    fn sniff(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            Some(Self::Gzip)
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Self::Zstd)
+        } else if bytes.starts_with(&[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00]) {
+            Some(Self::Xz)
+        } else {
+            let text = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+            text.iter()
+                .find(|b| !b.is_ascii_whitespace())
+                .filter(|&&b| b == b'<')
+                .map(|_| Self::None)
+        }
+    }
+
+    fn from_extension(path: &Path) -> Self {
+        match path.extension().and_then(|s| s.to_str()).unwrap_or("") {
+            "gz" => Self::Gzip,
+            "zst" | "zstd" => Self::Zstd,
+            "xz" => Self::Xz,
+            _ => Self::None,
+        }
+    }
+
+    /// Magic bytes first (mirrors sometimes serve content that doesn't match
+    /// the URL's extension), then the extension
+    fn detect(path: &Path, head: &[u8]) -> Result<Self> {
+        let compression = Self::sniff(head).unwrap_or_else(|| Self::from_extension(path));
+        if compression == Self::Xz {
+            return Err(RpmSearchError::Parse(format!(
+                "{} is xz-compressed, which is not supported (use gzip or zstd metadata)",
+                path.display()
+            )));
+        }
+        Ok(compression)
+    }
+}
+
 impl RepoFetcher {
     /// Fetch repository metadata from local file
     pub fn fetch_local<P: AsRef<Path>>(path: P) -> Result<Vec<u8>> {
@@ -28,18 +79,157 @@ impl RepoFetcher {
         Ok(decompressed)
     }
 
-    /// Auto-detect compression and decompress
+    /// Open a local metadata file as a streaming reader, decompressing on the
+    /// fly (detected like [`Self::auto_decompress`])
+    pub fn open_local<P: AsRef<Path>>(path: P) -> Result<Box<dyn BufRead>> {
+        let mut reader = BufReader::new(std::fs::File::open(&path)?);
+        let compression = Compression::detect(path.as_ref(), reader.fill_buf()?)?;
+
+        Ok(match compression {
+            Compression::Gzip => Box::new(BufReader::new(flate2::read::GzDecoder::new(reader))),
+            Compression::Zstd => {
+                Box::new(BufReader::new(zstd::stream::read::Decoder::new(reader)?))
+            }
+            Compression::None | Compression::Xz => Box::new(reader),
+        })
+    }
+
+    /// Auto-detect compression from the magic bytes (falling back to the
+    /// extension when they are ambiguous) and decompress
     pub fn auto_decompress<P: AsRef<Path>>(path: P, data: &[u8]) -> Result<Vec<u8>> {
-        let extension = path
-            .as_ref()
-            .extension()
-            .and_then(|s| s.to_str())
-            .unwrap_or("");
-
-        match extension {
-            "gz" => Self::decompress_gz(data),
-            "zst" | "zstd" => Self::decompress_zstd(data),
-            _ => Ok(data.to_vec()),
+        match Compression::detect(path.as_ref(), data)? {
+            Compression::Gzip => Self::decompress_gz(data),
+            Compression::Zstd => Self::decompress_zstd(data),
+            Compression::None | Compression::Xz => Ok(data.to_vec()),
+        }
+    }
+
+    /// Save a download to `path`. The body is written to `<path>.part` and
+    /// renamed into place once complete, so an interrupted download is never
+    /// mistaken for a finished file; the partial file is removed on error.
+    /// Returns the number of bytes written.
+    pub fn save_download<R: Read>(mut body: R, path: &Path) -> Result<u64> {
+        let mut partial_name = path.as_os_str().to_owned();
+        partial_name.push(".part");
+        let partial = PathBuf::from(partial_name);
+
+        let saved = std::fs::File::create(&partial)
+            .map_err(RpmSearchError::Io)
+            .and_then(|mut file| {
+                let bytes = std::io::copy(&mut body, &mut file).map_err(|e| {
+                    RpmSearchError::Fetch(format!("Failed to download {}: {}", path.display(), e))
+                })?;
+                file.sync_all()?;
+                std::fs::rename(&partial, path)?;
+                Ok(bytes)
+            });
+        if saved.is_err() {
+            let _ = std::fs::remove_file(&partial);
         }
+        saved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const XML: &[u8] = b"<?xml version=\"1.0\"?><metadata/>";
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_sniff_magic_bytes() {
+        assert_eq!(Compression::sniff(&gzip(XML)), Some(Compression::Gzip));
+        assert_eq!(
+            Compression::sniff(&zstd::encode_all(XML, 0).unwrap()),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(
+            Compression::sniff(&[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00, 0x00]),
+            Some(Compression::Xz)
+        );
+        assert_eq!(Compression::sniff(XML), Some(Compression::None));
+        assert_eq!(
+            Compression::sniff(b"\xef\xbb\xbf\n<metadata/>"),
+            Some(Compression::None)
+        );
+        assert_eq!(Compression::sniff(b""), None);
+        assert_eq!(Compression::sniff(&[0x1f]), None);
+    }
+
+    #[test]
+    fn test_auto_decompress_ignores_mislabeled_extension() {
+        let gz = gzip(XML);
+        let zst = zstd::encode_all(XML, 0).unwrap();
+        assert_eq!(
+            RepoFetcher::auto_decompress("primary.xml", &gz).unwrap(),
+            XML
+        );
+        assert_eq!(
+            RepoFetcher::auto_decompress("primary.xml.gz", &zst).unwrap(),
+            XML
+        );
+        assert_eq!(
+            RepoFetcher::auto_decompress("primary.xml.gz", XML).unwrap(),
+            XML
+        );
+        assert!(RepoFetcher::auto_decompress(
+            "primary.xml",
+            &[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00, 0x00]
+        )
+        .is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("primary.xml");
+        std::fs::write(&path, &gz).unwrap();
+        let mut text = String::new();
+        RepoFetcher::open_local(&path)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text.as_bytes(), XML);
+    }
+
+    /// Yields `data`, then fails like a dropped connection
+    struct FailingReader<'a> {
+        data: &'a [u8],
+    }
+
+    impl Read for FailingReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.data.is_empty() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::ConnectionReset,
+                    "connection reset",
+                ));
+            }
+            let n = self.data.len().min(buf.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_save_download() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bash-5.2-1.x86_64.rpm");
+        let partial = dir.path().join("bash-5.2-1.x86_64.rpm.part");
+
+        let failed = RepoFetcher::save_download(FailingReader { data: b"partial" }, &path);
+        assert!(matches!(failed, Err(RpmSearchError::Fetch(_))));
+        assert!(!path.exists());
+        assert!(!partial.exists());
+
+        let bytes = RepoFetcher::save_download(&b"complete"[..], &path).unwrap();
+        assert_eq!(bytes, 8);
+        assert_eq!(std::fs::read(&path).unwrap(), b"complete");
+        assert!(!partial.exists());
     }
 }
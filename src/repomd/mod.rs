@@ -1,4 +1,7 @@
+pub mod appstream;
 pub mod fetch;
 pub mod filelists_parser;
 pub mod model;
 pub mod parser;
+pub mod rpm_header;
+pub mod writer;
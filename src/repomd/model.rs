@@ -1,3 +1,4 @@
+use crate::error::{Result, RpmSearchError};
 use serde::{Deserialize, Serialize};
 
 /// Raw RPM package metadata from rpm-md XML
@@ -15,11 +16,47 @@ pub struct RpmPackage {
     pub packager: Option<String>,
     pub url: Option<String>,
     pub location_href: Option<String>,
+    /// Source RPM file name (e.g. `bash-5.2-1.src.rpm`)
+    pub sourcerpm: Option<String>,
+    /// Build time in seconds since the Unix epoch (`<time build="...">`)
+    #[serde(default)]
+    pub build_time: Option<i64>,
+    /// Summaries/descriptions tagged with a `lang` attribute
+    #[serde(default)]
+    pub localized: Vec<LocalizedText>,
     pub requires: Vec<RpmDependency>,
     pub provides: Vec<RpmDependency>,
+    #[serde(default)]
+    pub conflicts: Vec<RpmDependency>,
+    #[serde(default)]
+    pub obsoletes: Vec<RpmDependency>,
     pub files: Vec<String>,
 }
 
+/// Localized summary/description (`<summary lang="de">`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct LocalizedText {
+    pub lang: String,
+    pub summary: Option<String>,
+    pub description: Option<String>,
+}
+
+impl LocalizedText {
+    /// Entry for `lang` in `list`, created if missing
+    pub fn entry<'a>(list: &'a mut Vec<LocalizedText>, lang: &str) -> &'a mut LocalizedText {
+        match list.iter().position(|l| l.lang == lang) {
+            Some(idx) => &mut list[idx],
+            None => {
+                list.push(LocalizedText {
+                    lang: lang.to_string(),
+                    ..Default::default()
+                });
+                list.last_mut().unwrap()
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct RpmDependency {
     pub name: String,
@@ -27,6 +64,9 @@ pub struct RpmDependency {
     pub epoch: Option<String>,
     pub version: Option<String>,
     pub release: Option<String>,
+    /// `pre="1"`: needed by install scriptlets, so it must be installed first
+    #[serde(default)]
+    pub pre: bool,
 }
 
 impl RpmDependency {
@@ -38,6 +78,7 @@ impl RpmDependency {
             epoch: None,
             version: None,
             release: None,
+            pre: false,
         }
     }
 
@@ -78,6 +119,24 @@ impl RpmFileType {
     }
 }
 
+impl std::str::FromStr for RpmFileType {
+    type Err = RpmSearchError;
+
+    /// Parse a type name ("file", "dir" or "ghost", case-insensitive) as
+    /// given on the command line, to the MCP server or to the HTTP API
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "file" => Ok(RpmFileType::File),
+            "dir" => Ok(RpmFileType::Dir),
+            "ghost" => Ok(RpmFileType::Ghost),
+            _ => Err(RpmSearchError::Config(format!(
+                "Invalid file_type '{}' (expected file, dir or ghost)",
+                s
+            ))),
+        }
+    }
+}
+
 /// File entry with type information from filelists.xml
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RpmFileEntry {
@@ -96,6 +155,17 @@ pub struct FilelistsPackage {
     pub files: Vec<RpmFileEntry>,
 }
 
+/// Application component from AppStream metadata (matched to packages by `pkgname`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AppstreamComponent {
+    pub id: String,
+    pub pkgname: String,
+    /// Untranslated description, one paragraph or list item per line
+    pub description: String,
+    pub categories: Vec<String>,
+    pub keywords: Vec<String>,
+}
+
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct RepoMetadata {
@@ -103,3 +173,18 @@ pub struct RepoMetadata {
     pub checksum: String,
     pub timestamp: i64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_type_from_str() {
+        assert_eq!("dir".parse::<RpmFileType>().unwrap(), RpmFileType::Dir);
+        assert_eq!("GHOST".parse::<RpmFileType>().unwrap(), RpmFileType::Ghost);
+        assert_eq!("File".parse::<RpmFileType>().unwrap(), RpmFileType::File);
+        let err = "symlink".parse::<RpmFileType>().unwrap_err();
+        assert!(matches!(err, RpmSearchError::Config(_)));
+        assert!(err.to_string().contains("expected file, dir or ghost"));
+    }
+}
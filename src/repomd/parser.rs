@@ -1,15 +1,37 @@
 use crate::error::{Result, RpmSearchError};
-use crate::repomd::model::{RpmDependency, RpmPackage};
+use crate::repomd::model::{LocalizedText, RpmDependency, RpmPackage};
+use quick_xml::escape::resolve_predefined_entity;
 use quick_xml::events::Event;
 use quick_xml::Reader;
 use std::io::BufRead;
 
+/// Whether `ch` belongs in package text: control characters other than
+/// tab and line breaks are dropped
+pub(crate) fn is_text_char(ch: char) -> bool {
+    !ch.is_control() || matches!(ch, '\t' | '\n' | '\r')
+}
+
+/// Append raw XML text to `out`. Malformed metadata can carry invalid UTF-8
+/// or stray control bytes; those are replaced or dropped instead of losing
+/// the whole field.
+pub(crate) fn push_sanitized(out: &mut String, raw: &[u8]) {
+    out.extend(
+        String::from_utf8_lossy(raw)
+            .chars()
+            .filter(|&c| is_text_char(c)),
+    );
+}
+
 /// Tracks which dependency section we're currently inside
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum DepSection {
     None,
     Requires,
     Provides,
+    Conflicts,
+    Obsoletes,
+    /// Weak dependencies (recommends, suggests, ...), which are not indexed
+    Other,
 }
 
 pub struct PrimaryXmlParser;
@@ -17,14 +39,32 @@ pub struct PrimaryXmlParser;
 impl PrimaryXmlParser {
     /// Parse primary.xml (or primary.xml.gz) and extract package metadata
     pub fn parse<R: BufRead>(reader: R) -> Result<Vec<RpmPackage>> {
+        let mut packages = Vec::new();
+        Self::parse_streaming(reader, |pkg| {
+            packages.push(pkg);
+            Ok(())
+        })?;
+        Ok(packages)
+    }
+
+    /// Parse primary.xml, handing each package to `on_package` as soon as its
+    /// `</package>` is read instead of collecting them all. An error returned
+    /// by the callback stops parsing. Returns the number of packages parsed.
+    pub fn parse_streaming<R, F>(reader: R, mut on_package: F) -> Result<usize>
+    where
+        R: BufRead,
+        F: FnMut(RpmPackage) -> Result<()>,
+    {
         let mut xml_reader = Reader::from_reader(reader);
 
-        let mut packages = Vec::new();
+        let mut count = 0;
         let mut buf = Vec::new();
         let mut current_package: Option<RpmPackage> = None;
         let mut current_text = String::new();
         let mut in_element = String::new();
         let mut dep_section = DepSection::None;
+        // `lang` attribute of the summary/description being read
+        let mut text_lang: Option<String> = None;
 
         loop {
             match xml_reader.read_event_into(&mut buf) {
@@ -47,8 +87,13 @@ impl PrimaryXmlParser {
                                 packager: None,
                                 url: None,
                                 location_href: None,
+                                sourcerpm: None,
+                                build_time: None,
+                                localized: Vec::new(),
                                 requires: Vec::new(),
                                 provides: Vec::new(),
+                                conflicts: Vec::new(),
+                                obsoletes: Vec::new(),
                                 files: Vec::new(),
                             });
                         }
@@ -81,14 +126,26 @@ impl PrimaryXmlParser {
                         "arch" => {
                             current_text.clear();
                         }
-                        "summary" => {
+                        "summary" | "description" => {
                             current_text.clear();
+                            text_lang = e
+                                .attributes()
+                                .flatten()
+                                .find(|a| matches!(a.key.as_ref(), b"lang" | b"xml:lang"))
+                                .map(|a| String::from_utf8_lossy(&a.value).to_string())
+                                .filter(|lang| !lang.is_empty());
                         }
-                        "description" => {
+                        "rpm:license" | "rpm:sourcerpm" => {
                             current_text.clear();
                         }
-                        "rpm:license" => {
-                            current_text.clear();
+                        "time" => {
+                            if let Some(pkg) = current_package.as_mut() {
+                                pkg.build_time = e
+                                    .attributes()
+                                    .flatten()
+                                    .find(|a| a.key.as_ref() == b"build")
+                                    .and_then(|a| String::from_utf8_lossy(&a.value).parse().ok());
+                            }
                         }
                         "location" => {
                             if let Some(pkg) = current_package.as_mut() {
@@ -106,12 +163,22 @@ impl PrimaryXmlParser {
                         "rpm:provides" => {
                             dep_section = DepSection::Provides;
                         }
+                        "rpm:conflicts" => {
+                            dep_section = DepSection::Conflicts;
+                        }
+                        "rpm:obsoletes" => {
+                            dep_section = DepSection::Obsoletes;
+                        }
+                        "rpm:recommends" | "rpm:suggests" | "rpm:supplements" | "rpm:enhances" => {
+                            dep_section = DepSection::Other;
+                        }
                         "rpm:entry" => {
                             let mut dep_name = String::new();
                             let mut dep_flags = None;
                             let mut dep_epoch = None;
                             let mut dep_ver = None;
                             let mut dep_rel = None;
+                            let mut dep_pre = false;
 
                             for attr in e.attributes().flatten() {
                                 let key = String::from_utf8_lossy(attr.key.as_ref());
@@ -122,6 +189,7 @@ impl PrimaryXmlParser {
                                     "epoch" => dep_epoch = Some(value.to_string()),
                                     "ver" => dep_ver = Some(value.to_string()),
                                     "rel" => dep_rel = Some(value.to_string()),
+                                    "pre" => dep_pre = value == "1",
                                     _ => {}
                                 }
                             }
@@ -133,12 +201,16 @@ impl PrimaryXmlParser {
                                     epoch: dep_epoch,
                                     version: dep_ver,
                                     release: dep_rel,
+                                    pre: dep_pre,
                                 };
 
                                 if let Some(pkg) = current_package.as_mut() {
                                     match dep_section {
                                         DepSection::Provides => pkg.provides.push(dep),
-                                        _ => pkg.requires.push(dep),
+                                        DepSection::Requires => pkg.requires.push(dep),
+                                        DepSection::Conflicts => pkg.conflicts.push(dep),
+                                        DepSection::Obsoletes => pkg.obsoletes.push(dep),
+                                        DepSection::None | DepSection::Other => {}
                                     }
                                 }
                             }
@@ -146,12 +218,22 @@ impl PrimaryXmlParser {
                         _ => {}
                     }
                 }
+                // Text is split around entity references (`&amp;`), so append
                 Ok(Event::Text(e)) => {
-                    current_text = xml_reader
-                        .decoder()
-                        .decode(e.as_ref())
-                        .unwrap_or_default()
-                        .to_string();
+                    push_sanitized(&mut current_text, e.as_ref());
+                }
+                Ok(Event::GeneralRef(e)) => {
+                    if let Ok(Some(ch)) = e.resolve_char_ref() {
+                        if is_text_char(ch) {
+                            current_text.push(ch);
+                        }
+                    } else if let Some(value) = e
+                        .decode()
+                        .ok()
+                        .and_then(|name| resolve_predefined_entity(&name))
+                    {
+                        current_text.push_str(value);
+                    }
                 }
                 Ok(Event::End(e)) => {
                     let e_name = e.name();
@@ -159,7 +241,8 @@ impl PrimaryXmlParser {
                     match name.as_ref() {
                         "package" => {
                             if let Some(pkg) = current_package.take() {
-                                packages.push(pkg);
+                                on_package(pkg)?;
+                                count += 1;
                             }
                         }
                         "name" => {
@@ -174,14 +257,27 @@ impl PrimaryXmlParser {
                                 pkg.arch = current_text.clone();
                             }
                         }
+                        // Localized variants never overwrite the default text
                         "summary" => {
                             if let Some(pkg) = current_package.as_mut() {
-                                pkg.summary = current_text.clone();
+                                match text_lang.take() {
+                                    Some(lang) => {
+                                        LocalizedText::entry(&mut pkg.localized, &lang).summary =
+                                            Some(current_text.clone());
+                                    }
+                                    None => pkg.summary = current_text.clone(),
+                                }
                             }
                         }
                         "description" => {
                             if let Some(pkg) = current_package.as_mut() {
-                                pkg.description = current_text.clone();
+                                match text_lang.take() {
+                                    Some(lang) => {
+                                        LocalizedText::entry(&mut pkg.localized, &lang)
+                                            .description = Some(current_text.clone());
+                                    }
+                                    None => pkg.description = current_text.clone(),
+                                }
                             }
                         }
                         "rpm:license" => {
@@ -191,7 +287,16 @@ impl PrimaryXmlParser {
                                 }
                             }
                         }
-                        "rpm:requires" | "rpm:provides" => {
+                        "rpm:sourcerpm" => {
+                            if let Some(pkg) = current_package.as_mut() {
+                                if !current_text.is_empty() {
+                                    pkg.sourcerpm = Some(current_text.clone());
+                                }
+                            }
+                        }
+                        "rpm:requires" | "rpm:provides" | "rpm:conflicts" | "rpm:obsoletes"
+                        | "rpm:recommends" | "rpm:suggests" | "rpm:supplements"
+                        | "rpm:enhances" => {
                             dep_section = DepSection::None;
                         }
                         _ => {}
@@ -210,7 +315,7 @@ impl PrimaryXmlParser {
             buf.clear();
         }
 
-        Ok(packages)
+        Ok(count)
     }
 }
 
@@ -244,6 +349,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_streaming_stops_on_callback_error() {
+        let xml = r#"<metadata>
+          <package><name>a</name><arch>noarch</arch></package>
+          <package><name>b</name><arch>noarch</arch></package>
+          <package><name>c</name><arch>noarch</arch></package>
+        </metadata>"#;
+
+        let mut names = Vec::new();
+        let count = PrimaryXmlParser::parse_streaming(xml.as_bytes(), |pkg| {
+            names.push(pkg.name);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(names, ["a", "b", "c"]);
+
+        let mut seen = 0;
+        let result = PrimaryXmlParser::parse_streaming(xml.as_bytes(), |_| {
+            seen += 1;
+            if seen == 2 {
+                return Err(RpmSearchError::Storage("disk full".to_string()));
+            }
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(seen, 2);
+    }
+
     #[test]
     fn test_parse_requires_and_provides() {
         let xml = r#"<?xml version="1.0"?>
@@ -284,6 +418,41 @@ mod tests {
         assert_eq!(pkg.requires[1].name, "libcrypto.so.3()(64bit)");
     }
 
+    #[test]
+    fn test_parse_dependency_sections() {
+        let xml = r#"<?xml version="1.0"?>
+        <metadata xmlns="http://linux.duke.edu/metadata/common"
+                  xmlns:rpm="http://linux.duke.edu/metadata/rpm">
+          <package>
+            <name>dbus</name>
+            <arch>x86_64</arch>
+            <version epoch="0" ver="1.14" rel="1"/>
+            <rpm:requires>
+              <rpm:entry name="/bin/sh" pre="1"/>
+              <rpm:entry name="shadow-utils" pre="1"/>
+              <rpm:entry name="dbus-libs" flags="EQ" ver="1.14" rel="1"/>
+            </rpm:requires>
+            <rpm:conflicts>
+              <rpm:entry name="dbus-x11" flags="LT" ver="1.0"/>
+            </rpm:conflicts>
+            <rpm:obsoletes>
+              <rpm:entry name="dbus-compat"/>
+            </rpm:obsoletes>
+            <rpm:recommends>
+              <rpm:entry name="dbus-tools"/>
+            </rpm:recommends>
+          </package>
+        </metadata>"#;
+
+        let packages = PrimaryXmlParser::parse(xml.as_bytes()).unwrap();
+        let pre: Vec<bool> = packages[0].requires.iter().map(|d| d.pre).collect();
+        assert_eq!(pre, vec![true, true, false]);
+
+        // Conflicts/obsoletes get their own lists; weak deps are not requires
+        assert_eq!(packages[0].conflicts[0].name, "dbus-x11");
+        assert_eq!(packages[0].obsoletes[0].name, "dbus-compat");
+    }
+
     #[test]
     fn test_parse_license_and_vcs() {
         let xml = r#"<?xml version="1.0"?>
@@ -295,7 +464,9 @@ mod tests {
             <version epoch="0" ver="5.2.15" rel="3.el9" vcs="https://github.com/bminor/bash#devel"/>
             <summary>The GNU Bourne Again shell</summary>
             <description>The GNU Bourne Again shell</description>
+            <time file="1700000100" build="1700000000"/>
             <rpm:license>GPLv3+</rpm:license>
+            <rpm:sourcerpm>bash-5.2.15-3.el9.src.rpm</rpm:sourcerpm>
           </package>
         </metadata>"#;
 
@@ -304,12 +475,37 @@ mod tests {
         let pkg = &packages[0];
         assert_eq!(pkg.name, "bash");
         assert_eq!(pkg.license.as_deref(), Some("GPLv3+"));
+        assert_eq!(pkg.sourcerpm.as_deref(), Some("bash-5.2.15-3.el9.src.rpm"));
+        assert_eq!(pkg.build_time, Some(1700000000));
         assert_eq!(
             pkg.vcs.as_deref(),
             Some("https://github.com/bminor/bash#devel")
         );
     }
 
+    #[test]
+    fn test_parse_sanitizes_control_chars_and_invalid_utf8() {
+        let mut xml = br#"<?xml version="1.0"?>
+        <metadata xmlns="http://linux.duke.edu/metadata/common">
+          <package>
+            <name>broken</name>
+            <arch>noarch</arch>
+            <version epoch="0" ver="1.0" rel="1"/>
+            <summary>Summary&#1; text</summary>
+            <description>First line"#
+            .to_vec();
+        xml.extend_from_slice(b"\x01\x1b with \xff byte\n\tsecond line");
+        xml.extend_from_slice(b"</description>\n          </package>\n        </metadata>");
+
+        let packages = PrimaryXmlParser::parse(&xml[..]).unwrap();
+        let pkg = &packages[0];
+        assert_eq!(pkg.summary, "Summary text");
+        assert_eq!(
+            pkg.description,
+            "First line with \u{fffd} byte\n\tsecond line"
+        );
+    }
+
     #[test]
     fn test_parse_no_license_no_vcs() {
         let xml = r#"<?xml version="1.0"?>
@@ -327,5 +523,54 @@ mod tests {
         let pkg = &packages[0];
         assert!(pkg.license.is_none());
         assert!(pkg.vcs.is_none());
+        assert!(pkg.sourcerpm.is_none());
+    }
+
+    #[test]
+    fn test_parse_localized_text() {
+        let xml = r#"<?xml version="1.0"?>
+        <metadata xmlns="http://linux.duke.edu/metadata/common">
+          <package>
+            <name>bash</name>
+            <arch>x86_64</arch>
+            <version epoch="0" ver="5.2" rel="1"/>
+            <summary>The GNU Bourne Again shell</summary>
+            <summary xml:lang="de">Die GNU Bourne Again Shell</summary>
+            <description>Bash is the shell.</description>
+            <description lang="de">Bash ist die Shell.</description>
+          </package>
+        </metadata>"#;
+
+        let packages = PrimaryXmlParser::parse(xml.as_bytes()).unwrap();
+        let pkg = &packages[0];
+        assert_eq!(pkg.summary, "The GNU Bourne Again shell");
+        assert_eq!(pkg.description, "Bash is the shell.");
+        assert_eq!(
+            pkg.localized,
+            vec![LocalizedText {
+                lang: "de".to_string(),
+                summary: Some("Die GNU Bourne Again Shell".to_string()),
+                description: Some("Bash ist die Shell.".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_escaped_text() {
+        let xml = r#"<?xml version="1.0"?>
+        <metadata xmlns="http://linux.duke.edu/metadata/common">
+          <package>
+            <name>gcc-c++</name>
+            <arch>x86_64</arch>
+            <version epoch="0" ver="13.2" rel="1"/>
+            <summary>C++ support for GCC &amp; &lt;libstdc++&gt;</summary>
+            <description>Caf&#233; &quot;edition&quot;</description>
+          </package>
+        </metadata>"#;
+
+        let packages = PrimaryXmlParser::parse(xml.as_bytes()).unwrap();
+        let pkg = &packages[0];
+        assert_eq!(pkg.summary, "C++ support for GCC & <libstdc++>");
+        assert_eq!(pkg.description, "Café \"edition\"");
     }
 }
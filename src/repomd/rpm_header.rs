@@ -0,0 +1,635 @@
+//! Minimal reader for the header of a `.rpm` file, so packages can be indexed
+//! without generating repodata first.
+//!
+//! Layout: a 96-byte lead, the signature header (padded to 8 bytes), then the
+//! main header. Each header is a magic/count/size preamble, `nindex` 16-byte
+//! index entries (tag, type, offset, count) and a data store. The payload
+//! after the main header is never read.
+
+use crate::error::{Result, RpmSearchError};
+use crate::repomd::model::{RpmDependency, RpmFileEntry, RpmFileType, RpmPackage};
+use std::collections::HashMap;
+use std::io::Read;
+
+const LEAD_MAGIC: [u8; 4] = [0xed, 0xab, 0xee, 0xdb];
+const LEAD_SIZE: usize = 96;
+const HEADER_MAGIC: [u8; 4] = [0x8e, 0xad, 0xe8, 0x01];
+/// Lead `type` value for source packages
+const LEAD_TYPE_SOURCE: u16 = 1;
+/// Upper bound on header size, to reject corrupt input before allocating
+const MAX_HEADER_SIZE: usize = 256 * 1024 * 1024;
+
+// Header data types
+const TYPE_INT8: u32 = 2;
+const TYPE_INT16: u32 = 3;
+const TYPE_INT32: u32 = 4;
+const TYPE_INT64: u32 = 5;
+const TYPE_STRING: u32 = 6;
+const TYPE_STRING_ARRAY: u32 = 8;
+const TYPE_I18NSTRING: u32 = 9;
+
+// Header tags
+const TAG_NAME: u32 = 1000;
+const TAG_VERSION: u32 = 1001;
+const TAG_RELEASE: u32 = 1002;
+const TAG_EPOCH: u32 = 1003;
+const TAG_SUMMARY: u32 = 1004;
+const TAG_DESCRIPTION: u32 = 1005;
+const TAG_BUILDTIME: u32 = 1006;
+const TAG_LICENSE: u32 = 1014;
+const TAG_PACKAGER: u32 = 1015;
+const TAG_URL: u32 = 1020;
+const TAG_ARCH: u32 = 1022;
+const TAG_OLDFILENAMES: u32 = 1027;
+const TAG_FILEMODES: u32 = 1030;
+const TAG_FILEFLAGS: u32 = 1037;
+const TAG_SOURCERPM: u32 = 1044;
+const TAG_PROVIDENAME: u32 = 1047;
+const TAG_REQUIREFLAGS: u32 = 1048;
+const TAG_REQUIRENAME: u32 = 1049;
+const TAG_REQUIREVERSION: u32 = 1050;
+const TAG_PROVIDEFLAGS: u32 = 1112;
+const TAG_PROVIDEVERSION: u32 = 1113;
+const TAG_CONFLICTFLAGS: u32 = 1053;
+const TAG_CONFLICTNAME: u32 = 1054;
+const TAG_CONFLICTVERSION: u32 = 1055;
+const TAG_OBSOLETENAME: u32 = 1090;
+const TAG_OBSOLETEFLAGS: u32 = 1114;
+const TAG_OBSOLETEVERSION: u32 = 1115;
+const TAG_DIRINDEXES: u32 = 1116;
+const TAG_BASENAMES: u32 = 1117;
+const TAG_DIRNAMES: u32 = 1118;
+const TAG_VCS: u32 = 5034;
+
+// Dependency sense flags
+const SENSE_LESS: u64 = 1 << 1;
+const SENSE_GREATER: u64 = 1 << 2;
+const SENSE_EQUAL: u64 = 1 << 3;
+const SENSE_POSTTRANS: u64 = 1 << 5;
+const SENSE_PREREQ: u64 = 1 << 6;
+const SENSE_PRETRANS: u64 = 1 << 7;
+const SENSE_SCRIPT_PRE: u64 = 1 << 9;
+const SENSE_SCRIPT_POST: u64 = 1 << 10;
+/// Flags createrepo turns into `pre="1"`
+const SENSE_PRE_MASK: u64 =
+    SENSE_PREREQ | SENSE_PRETRANS | SENSE_POSTTRANS | SENSE_SCRIPT_PRE | SENSE_SCRIPT_POST;
+const SENSE_RPMLIB: u64 = 1 << 24;
+
+const FILE_FLAG_GHOST: u64 = 1 << 6;
+const MODE_TYPE_MASK: u64 = 0o170000;
+const MODE_DIR: u64 = 0o040000;
+
+/// Package metadata and file list read from an RPM header
+#[derive(Debug, Clone)]
+pub struct RpmHeaderPackage {
+    pub package: RpmPackage,
+    pub files: Vec<RpmFileEntry>,
+}
+
+struct IndexEntry {
+    data_type: u32,
+    offset: usize,
+    count: usize,
+}
+
+/// Parsed header: index entries by tag plus the data store they point into
+struct Header {
+    entries: HashMap<u32, IndexEntry>,
+    store: Vec<u8>,
+}
+
+impl Header {
+    /// Read one header structure; returns it with its size in bytes
+    fn read<R: Read>(reader: &mut R) -> Result<(Self, usize)> {
+        let mut preamble = [0u8; 16];
+        reader.read_exact(&mut preamble)?;
+        if preamble[..4] != HEADER_MAGIC {
+            return Err(RpmSearchError::Parse("Bad RPM header magic".to_string()));
+        }
+        let nindex = be_u32(&preamble[8..12]) as usize;
+        let hsize = be_u32(&preamble[12..16]) as usize;
+        if nindex.saturating_mul(16).saturating_add(hsize) > MAX_HEADER_SIZE {
+            return Err(RpmSearchError::Parse(format!(
+                "RPM header too large ({} entries, {} bytes)",
+                nindex, hsize
+            )));
+        }
+
+        let index = read_bytes(reader, nindex * 16)?;
+        let store = read_bytes(reader, hsize)?;
+
+        let entries = index
+            .chunks_exact(16)
+            .map(|e| {
+                (
+                    be_u32(&e[0..4]),
+                    IndexEntry {
+                        data_type: be_u32(&e[4..8]),
+                        offset: be_u32(&e[8..12]) as usize,
+                        count: be_u32(&e[12..16]) as usize,
+                    },
+                )
+            })
+            .collect();
+        Ok((Self { entries, store }, 16 + nindex * 16 + hsize))
+    }
+
+    fn strings(&self, tag: u32) -> Result<Vec<String>> {
+        let Some(entry) = self.entries.get(&tag) else {
+            return Ok(Vec::new());
+        };
+        let count = match entry.data_type {
+            TYPE_STRING_ARRAY => entry.count,
+            // I18N strings hold one translation per locale; the first is the default
+            TYPE_STRING | TYPE_I18NSTRING => 1,
+            other => return Err(type_error(tag, other)),
+        };
+        let mut pos = entry.offset;
+        // `count` comes from the file; each string takes at least one byte
+        let mut strings = Vec::with_capacity(count.min(self.store.len()));
+        for _ in 0..count {
+            let rest = self.store.get(pos..).ok_or_else(|| bounds_error(tag))?;
+            let len = rest
+                .iter()
+                .position(|&b| b == 0)
+                .ok_or_else(|| bounds_error(tag))?;
+            strings.push(String::from_utf8_lossy(&rest[..len]).into_owned());
+            pos += len + 1;
+        }
+        Ok(strings)
+    }
+
+    fn string(&self, tag: u32) -> Result<Option<String>> {
+        Ok(self
+            .strings(tag)?
+            .into_iter()
+            .next()
+            .filter(|s| !s.is_empty()))
+    }
+
+    fn ints(&self, tag: u32) -> Result<Vec<u64>> {
+        let Some(entry) = self.entries.get(&tag) else {
+            return Ok(Vec::new());
+        };
+        let width = match entry.data_type {
+            TYPE_INT8 => 1,
+            TYPE_INT16 => 2,
+            TYPE_INT32 => 4,
+            TYPE_INT64 => 8,
+            other => return Err(type_error(tag, other)),
+        };
+        let end = entry
+            .count
+            .checked_mul(width)
+            .and_then(|len| entry.offset.checked_add(len))
+            .ok_or_else(|| bounds_error(tag))?;
+        let data = self
+            .store
+            .get(entry.offset..end)
+            .ok_or_else(|| bounds_error(tag))?;
+        Ok(data
+            .chunks_exact(width)
+            .map(|b| b.iter().fold(0u64, |acc, &byte| (acc << 8) | byte as u64))
+            .collect())
+    }
+
+    fn int(&self, tag: u32) -> Result<Option<u64>> {
+        Ok(self.ints(tag)?.first().copied())
+    }
+
+    /// Zip the name/flags/version arrays of a dependency tag set
+    fn dependencies(
+        &self,
+        name_tag: u32,
+        flags_tag: u32,
+        version_tag: u32,
+    ) -> Result<Vec<RpmDependency>> {
+        let names = self.strings(name_tag)?;
+        let flags = self.ints(flags_tag)?;
+        let versions = self.strings(version_tag)?;
+        let deps = names
+            .into_iter()
+            .enumerate()
+            .filter(|(i, name)| {
+                // rpmlib() requirements are rpm features, not packages
+                !name.starts_with("rpmlib(") && flags.get(*i).is_none_or(|f| f & SENSE_RPMLIB == 0)
+            })
+            .map(|(i, name)| {
+                let evr = versions.get(i).map(String::as_str).unwrap_or("");
+                dependency(name, flags.get(i).copied().unwrap_or(0), evr)
+            })
+            .collect();
+        Ok(deps)
+    }
+
+    fn files(&self) -> Result<Vec<RpmFileEntry>> {
+        let basenames = self.strings(TAG_BASENAMES)?;
+        let paths = if basenames.is_empty() {
+            self.strings(TAG_OLDFILENAMES)?
+        } else {
+            let dirnames = self.strings(TAG_DIRNAMES)?;
+            let dirindexes = self.ints(TAG_DIRINDEXES)?;
+            basenames
+                .iter()
+                .enumerate()
+                .map(|(i, base)| {
+                    let dir = dirindexes
+                        .get(i)
+                        .and_then(|&d| dirnames.get(d as usize))
+                        .ok_or_else(|| bounds_error(TAG_DIRINDEXES))?;
+                    Ok(format!("{}{}", dir, base))
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        let modes = self.ints(TAG_FILEMODES)?;
+        let flags = self.ints(TAG_FILEFLAGS)?;
+        Ok(paths
+            .into_iter()
+            .enumerate()
+            .map(|(i, path)| {
+                let file_type = if flags.get(i).is_some_and(|f| f & FILE_FLAG_GHOST != 0) {
+                    RpmFileType::Ghost
+                } else if modes.get(i).is_some_and(|m| m & MODE_TYPE_MASK == MODE_DIR) {
+                    RpmFileType::Dir
+                } else {
+                    RpmFileType::File
+                };
+                RpmFileEntry { path, file_type }
+            })
+            .collect())
+    }
+}
+
+pub struct RpmHeaderParser;
+
+impl RpmHeaderParser {
+    /// Read the lead, signature and main header of an RPM file
+    pub fn parse<R: Read>(mut reader: R) -> Result<RpmHeaderPackage> {
+        let mut lead = [0u8; LEAD_SIZE];
+        reader.read_exact(&mut lead)?;
+        if lead[..4] != LEAD_MAGIC {
+            return Err(RpmSearchError::Parse("Not an RPM file".to_string()));
+        }
+        let is_source = u16::from_be_bytes([lead[6], lead[7]]) == LEAD_TYPE_SOURCE;
+
+        // The signature header is padded to an 8-byte boundary
+        let (_, sig_size) = Header::read(&mut reader)?;
+        let mut padding = vec![0u8; (8 - sig_size % 8) % 8];
+        reader.read_exact(&mut padding)?;
+
+        let (header, _) = Header::read(&mut reader)?;
+        let name = header
+            .string(TAG_NAME)?
+            .ok_or_else(|| RpmSearchError::Parse("RPM header has no name".to_string()))?;
+        let arch = if is_source {
+            "src".to_string()
+        } else {
+            header.string(TAG_ARCH)?.unwrap_or_default()
+        };
+
+        let package = RpmPackage {
+            name,
+            epoch: header.int(TAG_EPOCH)?.map(|e| e as i64),
+            version: header.string(TAG_VERSION)?.unwrap_or_default(),
+            release: header.string(TAG_RELEASE)?.unwrap_or_default(),
+            arch,
+            summary: header.string(TAG_SUMMARY)?.unwrap_or_default(),
+            description: header.string(TAG_DESCRIPTION)?.unwrap_or_default(),
+            license: header.string(TAG_LICENSE)?,
+            vcs: header.string(TAG_VCS)?,
+            packager: header.string(TAG_PACKAGER)?,
+            url: header.string(TAG_URL)?,
+            location_href: None,
+            sourcerpm: header.string(TAG_SOURCERPM)?,
+            build_time: header.int(TAG_BUILDTIME)?.map(|t| t as i64),
+            localized: Vec::new(),
+            requires: header.dependencies(TAG_REQUIRENAME, TAG_REQUIREFLAGS, TAG_REQUIREVERSION)?,
+            provides: header.dependencies(TAG_PROVIDENAME, TAG_PROVIDEFLAGS, TAG_PROVIDEVERSION)?,
+            conflicts: header.dependencies(
+                TAG_CONFLICTNAME,
+                TAG_CONFLICTFLAGS,
+                TAG_CONFLICTVERSION,
+            )?,
+            obsoletes: header.dependencies(
+                TAG_OBSOLETENAME,
+                TAG_OBSOLETEFLAGS,
+                TAG_OBSOLETEVERSION,
+            )?,
+            files: Vec::new(),
+        };
+        Ok(RpmHeaderPackage {
+            package,
+            files: header.files()?,
+        })
+    }
+}
+
+/// Build a dependency from header flags and an `[epoch:]version[-release]` string
+fn dependency(name: String, flags: u64, evr: &str) -> RpmDependency {
+    let pre = flags & SENSE_PRE_MASK != 0;
+    let flags = match flags & (SENSE_LESS | SENSE_GREATER | SENSE_EQUAL) {
+        SENSE_LESS => Some("LT"),
+        SENSE_GREATER => Some("GT"),
+        SENSE_EQUAL => Some("EQ"),
+        f if f == SENSE_LESS | SENSE_EQUAL => Some("LE"),
+        f if f == SENSE_GREATER | SENSE_EQUAL => Some("GE"),
+        _ => None,
+    };
+    let mut dep = RpmDependency::new(name);
+    dep.pre = pre;
+    if evr.is_empty() {
+        return dep;
+    }
+    let (epoch, vr) = match evr.split_once(':') {
+        Some((epoch, vr)) => (Some(epoch.to_string()), vr),
+        None => (None, evr),
+    };
+    let (version, release) = match vr.rsplit_once('-') {
+        Some((v, r)) => (v.to_string(), Some(r.to_string())),
+        None => (vr.to_string(), None),
+    };
+    dep.flags = flags.map(String::from);
+    dep.epoch = epoch;
+    dep.version = Some(version);
+    dep.release = release;
+    dep
+}
+
+/// Read exactly `len` bytes. The buffer grows as data arrives, so a corrupt
+/// length in a truncated file fails without allocating it up front.
+fn read_bytes<R: Read>(reader: &mut R, len: usize) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    reader.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() < len {
+        return Err(RpmSearchError::Parse("Truncated RPM header".to_string()));
+    }
+    Ok(buf)
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn type_error(tag: u32, data_type: u32) -> RpmSearchError {
+    RpmSearchError::Parse(format!(
+        "Unexpected data type {} for RPM header tag {}",
+        data_type, tag
+    ))
+}
+
+fn bounds_error(tag: u32) -> RpmSearchError {
+    RpmSearchError::Parse(format!("RPM header tag {} points outside the header", tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serialize a header from (tag, type, count, data) entries
+    fn header(entries: &[(u32, u32, u32, Vec<u8>)]) -> Vec<u8> {
+        let mut index = Vec::new();
+        let mut store = Vec::new();
+        for (tag, data_type, count, data) in entries {
+            for v in [*tag, *data_type, store.len() as u32, *count] {
+                index.extend_from_slice(&v.to_be_bytes());
+            }
+            store.extend_from_slice(data);
+        }
+        let mut out = HEADER_MAGIC.to_vec();
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+        out.extend_from_slice(&(store.len() as u32).to_be_bytes());
+        out.extend(index);
+        out.extend(store);
+        out
+    }
+
+    /// Wrap a main header in a lead and a padded signature header
+    fn rpm(main_header: Vec<u8>) -> Vec<u8> {
+        let mut rpm = LEAD_MAGIC.to_vec();
+        rpm.resize(LEAD_SIZE, 0);
+        // One-entry signature header, 4-byte store: 36 bytes, padded to 40
+        rpm.extend(header(&[(1000, TYPE_INT32, 1, vec![0, 0, 0, 1])]));
+        rpm.extend_from_slice(&[0; 4]);
+        rpm.extend(main_header);
+        rpm
+    }
+
+    /// Overwrite a big-endian u32 field of a serialized header
+    fn patch_u32(header: &mut [u8], at: usize, value: u32) {
+        header[at..at + 4].copy_from_slice(&value.to_be_bytes());
+    }
+
+    fn assert_parse_error(rpm: &[u8]) {
+        match RpmHeaderParser::parse(rpm) {
+            Err(RpmSearchError::Parse(_)) | Err(RpmSearchError::Io(_)) => {}
+            other => panic!("expected a parse error, got {:?}", other.map(|p| p.package)),
+        }
+    }
+
+    fn string(s: &str) -> (u32, u32, Vec<u8>) {
+        (TYPE_STRING, 1, format!("{}\0", s).into_bytes())
+    }
+
+    fn string_array(items: &[&str]) -> (u32, u32, Vec<u8>) {
+        let data = items.iter().flat_map(|s| format!("{}\0", s).into_bytes());
+        (TYPE_STRING_ARRAY, items.len() as u32, data.collect())
+    }
+
+    fn int32(values: &[u32]) -> (u32, u32, Vec<u8>) {
+        let data = values.iter().flat_map(|v| v.to_be_bytes());
+        (TYPE_INT32, values.len() as u32, data.collect())
+    }
+
+    fn int16(values: &[u16]) -> (u32, u32, Vec<u8>) {
+        let data = values.iter().flat_map(|v| v.to_be_bytes());
+        (TYPE_INT16, values.len() as u32, data.collect())
+    }
+
+    #[test]
+    fn test_parse_rpm_header() {
+        let tags = [
+            (TAG_NAME, string("hello")),
+            (TAG_VERSION, string("2.12")),
+            (TAG_RELEASE, string("1.fc40")),
+            (TAG_EPOCH, int32(&[1])),
+            (
+                TAG_SUMMARY,
+                (TYPE_I18NSTRING, 1, b"Prints a greeting\0".to_vec()),
+            ),
+            (TAG_DESCRIPTION, string("The GNU hello program")),
+            (TAG_BUILDTIME, int32(&[1700000000])),
+            (TAG_LICENSE, string("GPLv3+")),
+            (TAG_ARCH, string("x86_64")),
+            (TAG_SOURCERPM, string("hello-2.12-1.fc40.src.rpm")),
+            (TAG_PROVIDENAME, string_array(&["hello", "hello(x86-64)"])),
+            (TAG_PROVIDEFLAGS, int32(&[8, 8])),
+            (
+                TAG_PROVIDEVERSION,
+                string_array(&["1:2.12-1.fc40", "1:2.12-1.fc40"]),
+            ),
+            (
+                TAG_REQUIRENAME,
+                string_array(&["libc.so.6()(64bit)", "rpmlib(CompressedFileNames)", "info"]),
+            ),
+            (TAG_REQUIREFLAGS, int32(&[0, (1 << 24) | 10, 12])),
+            (TAG_REQUIREVERSION, string_array(&["", "3.0.4-1", "6.0"])),
+            (TAG_FILEMODES, int16(&[0o100755, 0o040755, 0o100644])),
+            (TAG_FILEFLAGS, int32(&[0, 0, 1 << 6])),
+            (TAG_DIRINDEXES, int32(&[0, 1, 2])),
+            (
+                TAG_BASENAMES,
+                string_array(&["hello", "hello", "hello.log"]),
+            ),
+            (
+                TAG_DIRNAMES,
+                string_array(&["/usr/bin/", "/usr/share/doc/", "/var/log/"]),
+            ),
+        ];
+        let entries: Vec<_> = tags
+            .into_iter()
+            .map(|(tag, (data_type, count, data))| (tag, data_type, count, data))
+            .collect();
+
+        let rpm = rpm(header(&entries));
+
+        let parsed = RpmHeaderParser::parse(&rpm[..]).unwrap();
+        let pkg = &parsed.package;
+        assert_eq!(pkg.name, "hello");
+        assert_eq!(pkg.epoch, Some(1));
+        assert_eq!(pkg.version, "2.12");
+        assert_eq!(pkg.release, "1.fc40");
+        assert_eq!(pkg.arch, "x86_64");
+        assert_eq!(pkg.summary, "Prints a greeting");
+        assert_eq!(pkg.build_time, Some(1700000000));
+        assert_eq!(pkg.sourcerpm.as_deref(), Some("hello-2.12-1.fc40.src.rpm"));
+
+        assert_eq!(pkg.provides.len(), 2);
+        assert_eq!(pkg.provides[0].flags.as_deref(), Some("EQ"));
+        assert_eq!(pkg.provides[0].epoch.as_deref(), Some("1"));
+        assert_eq!(pkg.provides[0].release.as_deref(), Some("1.fc40"));
+
+        // rpmlib() requirement dropped
+        assert_eq!(pkg.requires.len(), 2);
+        assert_eq!(pkg.requires[0].flags, None);
+        assert_eq!(pkg.requires[1].flags.as_deref(), Some("GE"));
+        assert_eq!(pkg.requires[1].version.as_deref(), Some("6.0"));
+
+        let files: Vec<_> = parsed
+            .files
+            .iter()
+            .map(|f| (f.path.as_str(), f.file_type))
+            .collect();
+        assert_eq!(
+            files,
+            [
+                ("/usr/bin/hello", RpmFileType::File),
+                ("/usr/share/doc/hello", RpmFileType::Dir),
+                ("/var/log/hello.log", RpmFileType::Ghost),
+            ]
+        );
+
+        assert!(RpmHeaderParser::parse(&b"not an rpm"[..]).is_err());
+    }
+
+    #[test]
+    fn test_truncated_input_is_rejected() {
+        let name = (TAG_NAME, TYPE_STRING, 1, b"hello\0".to_vec());
+        let rpm = rpm(header(&[name]));
+        assert!(RpmHeaderParser::parse(&rpm[..]).is_ok());
+
+        // Inside the lead, the signature header and the main header's store
+        for len in [LEAD_SIZE / 2, LEAD_SIZE + 20, rpm.len() - 3] {
+            assert_parse_error(&rpm[..len]);
+        }
+    }
+
+    #[test]
+    fn test_out_of_range_entries_are_rejected() {
+        // Index entry fields: tag at 16, type at 20, offset at 24, count at 28
+        let name = (TAG_NAME, TYPE_STRING, 1, b"hello\0".to_vec());
+
+        let mut bad_offset = header(std::slice::from_ref(&name));
+        patch_u32(&mut bad_offset, 24, 0xffff_fff0);
+        assert_parse_error(&rpm(bad_offset));
+
+        // An unterminated string runs off the end of the store
+        let unterminated = header(&[(TAG_NAME, TYPE_STRING, 1, b"hello".to_vec())]);
+        assert_parse_error(&rpm(unterminated));
+
+        let (data_type, _, data) = int32(&[1]);
+        let mut bad_count = header(&[name.clone(), (TAG_EPOCH, data_type, 1, data)]);
+        patch_u32(&mut bad_count, 16 + 16 + 12, u32::MAX);
+        assert_parse_error(&rpm(bad_count));
+
+        let (data_type, _, data) = string_array(&["a", "b"]);
+        let mut bad_array = header(&[name.clone(), (TAG_PROVIDENAME, data_type, 2, data)]);
+        patch_u32(&mut bad_array, 16 + 16 + 12, u32::MAX);
+        assert_parse_error(&rpm(bad_array));
+
+        // A file whose directory index points past the directory names
+        let tags = [
+            (TAG_BASENAMES, string_array(&["hello"])),
+            (TAG_DIRNAMES, string_array(&["/usr/bin/"])),
+            (TAG_DIRINDEXES, int32(&[5])),
+        ];
+        let mut entries = vec![name];
+        entries.extend(
+            tags.into_iter()
+                .map(|(tag, (data_type, count, data))| (tag, data_type, count, data)),
+        );
+        assert_parse_error(&rpm(header(&entries)));
+    }
+
+    #[test]
+    fn test_huge_header_is_rejected_before_allocating() {
+        let mut huge_index = header(&[]);
+        patch_u32(&mut huge_index, 8, u32::MAX);
+        assert_parse_error(&rpm(huge_index));
+
+        let mut huge_store = header(&[]);
+        patch_u32(&mut huge_store, 12, u32::MAX);
+        assert_parse_error(&rpm(huge_store));
+
+        // Within the size limit, but the file ends long before
+        let mut truncated = header(&[]);
+        patch_u32(&mut truncated, 8, 1 << 20);
+        assert_parse_error(&rpm(truncated));
+
+        // Also in the signature header
+        let mut rpm = rpm(header(&[]));
+        patch_u32(&mut rpm, LEAD_SIZE + 8, u32::MAX);
+        assert_parse_error(&rpm);
+    }
+
+    #[test]
+    fn test_prerequisite_flags_map_to_pre() {
+        const SENSE_SCRIPT_PREUN: u64 = 1 << 11;
+        for flags in [
+            SENSE_PREREQ,
+            SENSE_PRETRANS,
+            SENSE_POSTTRANS,
+            SENSE_SCRIPT_PRE,
+            SENSE_SCRIPT_POST,
+        ] {
+            assert!(
+                dependency("/bin/sh".to_string(), flags, "").pre,
+                "{:#x}",
+                flags
+            );
+        }
+        // Uninstall scriptlets aren't install-time prerequisites
+        assert!(!dependency("/bin/sh".to_string(), SENSE_SCRIPT_PREUN, "").pre);
+        assert!(!dependency("glibc".to_string(), 0, "").pre);
+
+        let dep = dependency(
+            "glibc".to_string(),
+            SENSE_SCRIPT_POST | SENSE_GREATER | SENSE_EQUAL,
+            "2.34",
+        );
+        assert!(dep.pre);
+        assert_eq!(dep.flags.as_deref(), Some("GE"));
+        assert_eq!(dep.version.as_deref(), Some("2.34"));
+    }
+}
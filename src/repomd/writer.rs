@@ -0,0 +1,362 @@
+use crate::error::Result;
+use crate::repomd::model::{RpmDependency, RpmFileEntry, RpmFileType, RpmPackage};
+use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::Writer;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+
+const COMMON_NS: &str = "http://linux.duke.edu/metadata/common";
+const RPM_NS: &str = "http://linux.duke.edu/metadata/rpm";
+const REPO_NS: &str = "http://linux.duke.edu/metadata/repo";
+
+/// Streaming writer for primary.xml, the inverse of
+/// [`PrimaryXmlParser`](crate::repomd::parser::PrimaryXmlParser)
+pub struct PrimaryXmlWriter<W: Write> {
+    writer: Writer<W>,
+}
+
+impl<W: Write> PrimaryXmlWriter<W> {
+    /// Start a document holding `package_count` packages
+    pub fn new(inner: W, package_count: usize) -> Result<Self> {
+        let mut writer = Writer::new_with_indent(inner, b' ', 2);
+        writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+        let count = package_count.to_string();
+        writer.write_event(Event::Start(BytesStart::new("metadata").with_attributes([
+            ("xmlns", COMMON_NS),
+            ("xmlns:rpm", RPM_NS),
+            ("packages", count.as_str()),
+        ])))?;
+        Ok(Self { writer })
+    }
+
+    /// Write one `<package>` element. `files` are listed in its `<format>` section.
+    pub fn write_package(&mut self, pkg: &RpmPackage, files: &[RpmFileEntry]) -> Result<()> {
+        let w = &mut self.writer;
+        w.write_event(Event::Start(
+            BytesStart::new("package").with_attributes([("type", "rpm")]),
+        ))?;
+        text_element(w, "name", &pkg.name, &[])?;
+        text_element(w, "arch", &pkg.arch, &[])?;
+
+        let epoch = pkg.epoch.unwrap_or(0).to_string();
+        let mut version = BytesStart::new("version").with_attributes([
+            ("epoch", epoch.as_str()),
+            ("ver", pkg.version.as_str()),
+            ("rel", pkg.release.as_str()),
+        ]);
+        if let Some(ref vcs) = pkg.vcs {
+            version.push_attribute(("vcs", vcs.as_str()));
+        }
+        w.write_event(Event::Empty(version))?;
+
+        text_element(w, "summary", &pkg.summary, &[])?;
+        for l10n in &pkg.localized {
+            if let Some(ref summary) = l10n.summary {
+                text_element(w, "summary", summary, &[("lang", &l10n.lang)])?;
+            }
+        }
+        text_element(w, "description", &pkg.description, &[])?;
+        for l10n in &pkg.localized {
+            if let Some(ref description) = l10n.description {
+                text_element(w, "description", description, &[("lang", &l10n.lang)])?;
+            }
+        }
+        if let Some(ref packager) = pkg.packager {
+            text_element(w, "packager", packager, &[])?;
+        }
+        if let Some(ref url) = pkg.url {
+            text_element(w, "url", url, &[])?;
+        }
+        if let Some(build_time) = pkg.build_time {
+            let build_time = build_time.to_string();
+            w.write_event(Event::Empty(BytesStart::new("time").with_attributes([
+                ("file", build_time.as_str()),
+                ("build", build_time.as_str()),
+            ])))?;
+        }
+        if let Some(ref href) = pkg.location_href {
+            w.write_event(Event::Empty(
+                BytesStart::new("location").with_attributes([("href", href.as_str())]),
+            ))?;
+        }
+
+        w.write_event(Event::Start(BytesStart::new("format")))?;
+        if let Some(ref license) = pkg.license {
+            text_element(w, "rpm:license", license, &[])?;
+        }
+        if let Some(ref sourcerpm) = pkg.sourcerpm {
+            text_element(w, "rpm:sourcerpm", sourcerpm, &[])?;
+        }
+        dependency_section(w, "rpm:provides", &pkg.provides)?;
+        dependency_section(w, "rpm:requires", &pkg.requires)?;
+        dependency_section(w, "rpm:conflicts", &pkg.conflicts)?;
+        dependency_section(w, "rpm:obsoletes", &pkg.obsoletes)?;
+        for file in files {
+            match file.file_type {
+                RpmFileType::File => text_element(w, "file", &file.path, &[])?,
+                RpmFileType::Dir => text_element(w, "file", &file.path, &[("type", "dir")])?,
+                RpmFileType::Ghost => text_element(w, "file", &file.path, &[("type", "ghost")])?,
+            }
+        }
+        w.write_event(Event::End(BytesEnd::new("format")))?;
+
+        w.write_event(Event::End(BytesEnd::new("package")))?;
+        Ok(())
+    }
+
+    /// Close the document and return the underlying writer
+    pub fn finish(mut self) -> Result<W> {
+        self.writer
+            .write_event(Event::End(BytesEnd::new("metadata")))?;
+        let mut inner = self.writer.into_inner();
+        inner.write_all(b"\n")?;
+        Ok(inner)
+    }
+}
+
+/// One `<data>` entry of repomd.xml
+#[derive(Debug, Clone)]
+pub struct RepomdRecord {
+    /// Metadata type (e.g. `primary`)
+    pub data_type: String,
+    /// Location relative to the repository root (e.g. `repodata/primary.xml.gz`)
+    pub href: String,
+    /// SHA-256 of the file as stored
+    pub checksum: String,
+    /// SHA-256 of the uncompressed content
+    pub open_checksum: String,
+    pub size: usize,
+    pub open_size: usize,
+    pub timestamp: i64,
+}
+
+impl RepomdRecord {
+    /// Describe a metadata file from its stored (`data`) and uncompressed (`open_data`) bytes
+    pub fn new(data_type: &str, href: &str, data: &[u8], open_data: &[u8], timestamp: i64) -> Self {
+        Self {
+            data_type: data_type.to_string(),
+            href: href.to_string(),
+            checksum: sha256_hex(data),
+            open_checksum: sha256_hex(open_data),
+            size: data.len(),
+            open_size: open_data.len(),
+            timestamp,
+        }
+    }
+}
+
+/// Writer adapter computing the SHA-256 and size of everything written through it
+pub struct ChecksumWriter<W: Write> {
+    inner: W,
+    hasher: Sha256,
+    size: usize,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+            size: 0,
+        }
+    }
+
+    /// Return the underlying writer with the hex SHA-256 and size of the data written
+    pub fn finish(self) -> (W, String, usize) {
+        (self.inner, hex(&self.hasher.finalize()), self.size)
+    }
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.size += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Write repomd.xml listing `records`
+pub fn write_repomd<W: Write>(inner: W, revision: i64, records: &[RepomdRecord]) -> Result<()> {
+    let mut w = Writer::new_with_indent(inner, b' ', 2);
+    w.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    w.write_event(Event::Start(
+        BytesStart::new("repomd").with_attributes([("xmlns", REPO_NS), ("xmlns:rpm", RPM_NS)]),
+    ))?;
+    text_element(&mut w, "revision", &revision.to_string(), &[])?;
+
+    for record in records {
+        w.write_event(Event::Start(
+            BytesStart::new("data").with_attributes([("type", record.data_type.as_str())]),
+        ))?;
+        text_element(&mut w, "checksum", &record.checksum, &[("type", "sha256")])?;
+        text_element(
+            &mut w,
+            "open-checksum",
+            &record.open_checksum,
+            &[("type", "sha256")],
+        )?;
+        w.write_event(Event::Empty(
+            BytesStart::new("location").with_attributes([("href", record.href.as_str())]),
+        ))?;
+        text_element(&mut w, "timestamp", &record.timestamp.to_string(), &[])?;
+        text_element(&mut w, "size", &record.size.to_string(), &[])?;
+        text_element(&mut w, "open-size", &record.open_size.to_string(), &[])?;
+        w.write_event(Event::End(BytesEnd::new("data")))?;
+    }
+
+    w.write_event(Event::End(BytesEnd::new("repomd")))?;
+    w.into_inner().write_all(b"\n")?;
+    Ok(())
+}
+
+fn text_element<W: Write>(
+    w: &mut Writer<W>,
+    name: &str,
+    text: &str,
+    attributes: &[(&str, &str)],
+) -> Result<()> {
+    w.write_event(Event::Start(
+        BytesStart::new(name).with_attributes(attributes.iter().copied()),
+    ))?;
+    w.write_event(Event::Text(BytesText::new(text)))?;
+    w.write_event(Event::End(BytesEnd::new(name)))?;
+    Ok(())
+}
+
+fn dependency_section<W: Write>(
+    w: &mut Writer<W>,
+    name: &str,
+    deps: &[RpmDependency],
+) -> Result<()> {
+    if deps.is_empty() {
+        return Ok(());
+    }
+    w.write_event(Event::Start(BytesStart::new(name)))?;
+    for dep in deps {
+        let mut entry = BytesStart::new("rpm:entry").with_attributes([("name", dep.name.as_str())]);
+        for (key, value) in [
+            ("flags", &dep.flags),
+            ("epoch", &dep.epoch),
+            ("ver", &dep.version),
+            ("rel", &dep.release),
+        ] {
+            if let Some(value) = value {
+                entry.push_attribute((key, value.as_str()));
+            }
+        }
+        if dep.pre {
+            entry.push_attribute(("pre", "1"));
+        }
+        w.write_event(Event::Empty(entry))?;
+    }
+    w.write_event(Event::End(BytesEnd::new(name)))?;
+    Ok(())
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repomd::model::LocalizedText;
+    use crate::repomd::parser::PrimaryXmlParser;
+
+    #[test]
+    fn test_primary_round_trip() {
+        let pkg = RpmPackage {
+            name: "zlib".to_string(),
+            epoch: Some(1),
+            version: "1.3".to_string(),
+            release: "2".to_string(),
+            arch: "x86_64".to_string(),
+            summary: "Compression <library> & tools".to_string(),
+            description: "Deflate compression".to_string(),
+            license: Some("zlib".to_string()),
+            vcs: None,
+            packager: None,
+            url: None,
+            location_href: Some("x86_64/zlib-1.3-2.x86_64.rpm".to_string()),
+            sourcerpm: Some("zlib-1.3-2.src.rpm".to_string()),
+            build_time: None,
+            localized: vec![LocalizedText {
+                lang: "de".to_string(),
+                summary: Some("Kompression".to_string()),
+                description: None,
+            }],
+            requires: vec![
+                RpmDependency::new("glibc".to_string())
+                    .with_version("GE".to_string(), "2.34".to_string()),
+                RpmDependency {
+                    pre: true,
+                    ..RpmDependency::new("/bin/sh".to_string())
+                },
+            ],
+            provides: vec![RpmDependency::new("libz.so.1()(64bit)".to_string())],
+            conflicts: Vec::new(),
+            obsoletes: Vec::new(),
+            files: Vec::new(),
+        };
+        let files = vec![RpmFileEntry {
+            path: "/usr/share/zlib".to_string(),
+            file_type: RpmFileType::Dir,
+        }];
+
+        let mut writer = PrimaryXmlWriter::new(Vec::new(), 1).unwrap();
+        writer.write_package(&pkg, &files).unwrap();
+        let xml = writer.finish().unwrap();
+        let text = String::from_utf8(xml.clone()).unwrap();
+        assert!(text.contains(r#"packages="1""#));
+        assert!(text.contains(r#"<file type="dir">/usr/share/zlib</file>"#));
+
+        let parsed = PrimaryXmlParser::parse(&xml[..]).unwrap();
+        assert_eq!(parsed.len(), 1);
+        let p = &parsed[0];
+        assert_eq!(p.name, "zlib");
+        assert_eq!(p.epoch, Some(1));
+        assert_eq!(p.summary, "Compression <library> & tools");
+        assert_eq!(p.license.as_deref(), Some("zlib"));
+        assert_eq!(p.sourcerpm.as_deref(), Some("zlib-1.3-2.src.rpm"));
+        assert_eq!(p.localized, pkg.localized);
+        assert_eq!(p.requires, pkg.requires);
+        assert_eq!(p.provides, pkg.provides);
+    }
+
+    #[test]
+    fn test_repomd_record() {
+        let record = RepomdRecord::new("primary", "repodata/primary.xml", b"abc", b"abc", 1);
+        assert_eq!(
+            record.checksum,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(record.size, 3);
+
+        let mut out = Vec::new();
+        write_repomd(&mut out, 1, &[record]).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains(r#"<data type="primary">"#));
+        assert!(text.contains(r#"<location href="repodata/primary.xml"/>"#));
+    }
+
+    #[test]
+    fn test_checksum_writer_matches_record() {
+        let mut writer = ChecksumWriter::new(Vec::new());
+        writer.write_all(b"ab").unwrap();
+        writer.write_all(b"c").unwrap();
+        let (data, checksum, size) = writer.finish();
+
+        let record = RepomdRecord::new("primary", "repodata/primary.xml", &data, &data, 1);
+        assert_eq!(checksum, record.checksum);
+        assert_eq!(size, record.size);
+    }
+}
@@ -0,0 +1,332 @@
+use crate::error::Result;
+use crate::normalize::{Dependency, Package};
+use crate::storage::PackageStore;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// A package in the install set of a [`Resolution`]
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedPackage {
+    pub nevra: String,
+    pub repo: String,
+    /// Package whose requirement pulled this one in (None for the requested package)
+    pub required_by: Option<String>,
+    /// The requirement this package satisfies (e.g. `glibc >= 2.34`)
+    pub requirement: Option<String>,
+}
+
+/// A requirement no indexed package satisfies
+#[derive(Debug, Clone, Serialize)]
+pub struct UnresolvedRequirement {
+    /// NEVRA of the requiring package
+    pub package: String,
+    pub requirement: String,
+}
+
+/// A requirement only another version of a package in the install set
+/// satisfies (only one version of a package can be installed)
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionConflict {
+    /// NEVRA of the requiring package
+    pub package: String,
+    pub requirement: String,
+    /// NEVRA of the version already in the install set
+    pub installed: String,
+}
+
+/// Transitive runtime dependency closure of a package
+/// (see [`RpmSearchApi::resolve_dependencies`](crate::api::RpmSearchApi::resolve_dependencies))
+#[derive(Debug, Clone, Serialize)]
+pub struct Resolution {
+    /// Packages to install, the requested package first
+    pub install: Vec<ResolvedPackage>,
+    pub unresolved: Vec<UnresolvedRequirement>,
+    pub conflicts: Vec<VersionConflict>,
+    /// Dependency cycles, each listed as the chain of NEVRAs leading back to its first entry
+    pub cycles: Vec<Vec<String>>,
+}
+
+/// Walks `requires` → providing packages over the indexed repositories.
+///
+/// Each requirement is satisfied by a package already in the install set when
+/// possible; otherwise the best provider is added: one named like the
+/// requirement first, then the latest version. Providers that are another
+/// version of a package already in the set are skipped, and reported as a
+/// [`VersionConflict`] when nothing else satisfies the requirement.
+/// `rpmlib(...)` requirements are provided by rpm itself and skipped.
+pub struct DependencyResolver<'a> {
+    store: &'a PackageStore,
+    /// Acceptable provider architectures (empty = any)
+    arches: Vec<String>,
+    /// Loaded packages by ID
+    packages: HashMap<i64, Package>,
+}
+
+impl<'a> DependencyResolver<'a> {
+    /// Resolve against `store`, accepting providers of the given
+    /// architectures (`noarch` is always accepted; empty = any)
+    pub fn new(store: &'a PackageStore, arches: &[String]) -> Self {
+        let mut arches = arches.to_vec();
+        if !arches.is_empty() && !arches.iter().any(|a| a.eq_ignore_ascii_case("noarch")) {
+            arches.push("noarch".to_string());
+        }
+        Self {
+            store,
+            arches,
+            packages: HashMap::new(),
+        }
+    }
+
+    /// Compute the dependency closure of `root`
+    pub fn resolve(&mut self, root: Package) -> Result<Resolution> {
+        let mut selected: Vec<Package> = Vec::new();
+        let mut selected_ids: HashMap<i64, usize> = HashMap::new();
+        let mut selected_names: HashMap<String, usize> = HashMap::new();
+        let mut resolution = Resolution {
+            install: Vec::new(),
+            unresolved: Vec::new(),
+            conflicts: Vec::new(),
+            cycles: Vec::new(),
+        };
+        let mut seen_cycles: HashSet<Vec<usize>> = HashSet::new();
+
+        resolution.install.push(ResolvedPackage {
+            nevra: nevra(&root),
+            repo: root.repo.clone(),
+            required_by: None,
+            requirement: None,
+        });
+        if let Some(id) = root.pkg_id {
+            selected_ids.insert(id, 0);
+        }
+        selected_names.insert(root.name.clone(), 0);
+        selected.push(root);
+
+        // Depth-first walk: (package index, next requirement to visit)
+        let mut stack: Vec<(usize, usize)> = vec![(0, 0)];
+        while let Some(&mut (node, ref mut next)) = stack.last_mut() {
+            let Some(req) = selected[node].requires.get(*next).cloned() else {
+                stack.pop();
+                continue;
+            };
+            *next += 1;
+
+            if req.name.starts_with("rpmlib(") || selected[node].provides_satisfies(&req) {
+                continue;
+            }
+
+            let providers = self.providers(&req)?;
+            if providers.is_empty() {
+                resolution.unresolved.push(UnresolvedRequirement {
+                    package: nevra(&selected[node]),
+                    requirement: req.to_string(),
+                });
+                continue;
+            }
+
+            let existing = providers
+                .iter()
+                .find_map(|id| selected_ids.get(id).copied());
+            if let Some(idx) = existing {
+                // A provider further up the current path closes a cycle
+                if idx != node {
+                    if let Some(pos) = stack.iter().position(|&(n, _)| n == idx) {
+                        let mut members: Vec<usize> =
+                            stack[pos..].iter().map(|&(n, _)| n).collect();
+                        let mut chain: Vec<String> =
+                            members.iter().map(|&n| nevra(&selected[n])).collect();
+                        chain.push(nevra(&selected[idx]));
+                        members.sort_unstable();
+                        if seen_cycles.insert(members) {
+                            resolution.cycles.push(chain);
+                        }
+                    }
+                }
+                continue;
+            }
+
+            let best = providers
+                .iter()
+                .find(|id| !selected_names.contains_key(&self.packages[id].name));
+            let Some(&best) = best else {
+                let installed = selected_names[&self.packages[&providers[0]].name];
+                resolution.conflicts.push(VersionConflict {
+                    package: nevra(&selected[node]),
+                    requirement: req.to_string(),
+                    installed: nevra(&selected[installed]),
+                });
+                continue;
+            };
+
+            let pkg = self.packages[&best].clone();
+            resolution.install.push(ResolvedPackage {
+                nevra: nevra(&pkg),
+                repo: pkg.repo.clone(),
+                required_by: Some(nevra(&selected[node])),
+                requirement: Some(req.to_string()),
+            });
+            selected_ids.insert(best, selected.len());
+            selected_names.insert(pkg.name.clone(), selected.len());
+            stack.push((selected.len(), 0));
+            selected.push(pkg);
+        }
+
+        Ok(resolution)
+    }
+
+    /// IDs of packages satisfying `req`, best candidate first
+    fn providers(&mut self, req: &Dependency) -> Result<Vec<i64>> {
+        let pkg_ids = self.store.find_providers(&req.name)?;
+        for &pkg_id in &pkg_ids {
+            if !self.packages.contains_key(&pkg_id) {
+                if let Some(pkg) = self.store.get_package(pkg_id)? {
+                    self.packages.insert(pkg_id, pkg);
+                }
+            }
+        }
+
+        let mut candidates: Vec<&Package> = pkg_ids
+            .iter()
+            .filter_map(|id| self.packages.get(id))
+            // File requirements matched through the file list carry no version
+            .filter(|pkg| pkg.provides_satisfies(req) || req.name.starts_with('/'))
+            .filter(|pkg| pkg.matches_arch_repo(&self.arches, &[]))
+            .collect();
+
+        candidates.sort_by(|a, b| {
+            (b.name == req.name)
+                .cmp(&(a.name == req.name))
+                .then_with(|| b.to_rpm_version().cmp(&a.to_rpm_version()))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        Ok(candidates.iter().filter_map(|p| p.pkg_id).collect())
+    }
+}
+
+fn nevra(pkg: &Package) -> String {
+    format!("{}-{}.{}", pkg.name, pkg.full_version(), pkg.arch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalize::test_package;
+    use tempfile::NamedTempFile;
+
+    fn dep(name: &str, flags: Option<&str>, version: Option<&str>) -> Dependency {
+        Dependency::from_evr(name, flags, version)
+    }
+
+    fn package(name: &str, version: &str, requires: Vec<Dependency>) -> Package {
+        Package {
+            requires,
+            ..test_package(name, version)
+        }
+    }
+
+    #[test]
+    fn test_resolve_closure() {
+        let temp = NamedTempFile::new().unwrap();
+        let mut store = PackageStore::new(temp.path()).unwrap();
+
+        let mut glibc_old = package("glibc", "2.30", Vec::new());
+        glibc_old.provides = vec![dep("libc.so.6()(64bit)", None, None)];
+        let mut glibc = package("glibc", "2.34", vec![dep("bash", None, None)]);
+        glibc.provides = glibc_old.provides.clone();
+        let bash = package(
+            "bash",
+            "5.2",
+            vec![
+                dep("libc.so.6()(64bit)", None, None),
+                dep("glibc", Some("GE"), Some("2.32")),
+                dep("rpmlib(CompressedFileNames)", Some("LE"), Some("3.0.4-1")),
+                dep("libmissing.so.1", None, None),
+            ],
+        );
+        store
+            .insert_packages_batch(&[glibc_old, glibc, bash])
+            .unwrap();
+
+        let root = store.get_packages_by_name("bash").unwrap().remove(0);
+        let resolution = DependencyResolver::new(&store, &["x86_64".to_string()])
+            .resolve(root)
+            .unwrap();
+
+        let install: Vec<&str> = resolution
+            .install
+            .iter()
+            .map(|p| p.nevra.as_str())
+            .collect();
+        assert_eq!(install, vec!["bash-5.2-1.x86_64", "glibc-2.34-1.x86_64"]);
+        assert_eq!(
+            resolution.install[1].required_by.as_deref(),
+            Some("bash-5.2-1.x86_64")
+        );
+
+        assert_eq!(resolution.unresolved.len(), 1);
+        assert_eq!(resolution.unresolved[0].requirement, "libmissing.so.1");
+        assert!(resolution.conflicts.is_empty());
+
+        assert_eq!(
+            resolution.cycles,
+            vec![vec![
+                "bash-5.2-1.x86_64".to_string(),
+                "glibc-2.34-1.x86_64".to_string(),
+                "bash-5.2-1.x86_64".to_string(),
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_resolve_version_conflict() {
+        let temp = NamedTempFile::new().unwrap();
+        let mut store = PackageStore::new(temp.path()).unwrap();
+
+        // openssl pulls in the latest openssl-libs, but curl needs the older one
+        let openssl = package(
+            "openssl",
+            "3.2",
+            vec![dep("openssl-libs", Some("GE"), Some("3.0"))],
+        );
+        let curl = package(
+            "curl",
+            "8.0",
+            vec![
+                dep("openssl", None, None),
+                dep("openssl-libs", Some("LT"), Some("3.1")),
+            ],
+        );
+        store
+            .insert_packages_batch(&[
+                curl,
+                openssl,
+                package("openssl-libs", "3.0", Vec::new()),
+                package("openssl-libs", "3.2", Vec::new()),
+            ])
+            .unwrap();
+
+        let root = store.get_packages_by_name("curl").unwrap().remove(0);
+        let resolution = DependencyResolver::new(&store, &[]).resolve(root).unwrap();
+
+        let install: Vec<&str> = resolution
+            .install
+            .iter()
+            .map(|p| p.nevra.as_str())
+            .collect();
+        assert_eq!(
+            install,
+            vec![
+                "curl-8.0-1.x86_64",
+                "openssl-3.2-1.x86_64",
+                "openssl-libs-3.2-1.x86_64"
+            ]
+        );
+        assert_eq!(resolution.conflicts.len(), 1);
+        assert_eq!(resolution.conflicts[0].package, "curl-8.0-1.x86_64");
+        assert_eq!(resolution.conflicts[0].requirement, "openssl-libs < 3.1");
+        assert_eq!(
+            resolution.conflicts[0].installed,
+            "openssl-libs-3.2-1.x86_64"
+        );
+    }
+}
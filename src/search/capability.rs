@@ -0,0 +1,204 @@
+use crate::error::{Result, RpmSearchError};
+use crate::normalize::package::parse_sense;
+use crate::normalize::Dependency;
+
+/// A capability expression such as `libssl.so.3` or `libssl.so.3 >= 3.0`.
+/// The name may contain `*` and `?` wildcards (`libssl.so*`), matched like
+/// `find --provides` (whole name, ASCII case-insensitive).
+#[derive(Debug, Clone)]
+pub struct CapabilityFilter {
+    /// The expression as a dependency; its name may be a wildcard pattern
+    pub requirement: Dependency,
+}
+
+impl CapabilityFilter {
+    /// Parse `name` or `name OP version`.
+    pub fn parse(expr: &str) -> Result<Self> {
+        let tokens: Vec<&str> = expr.split_whitespace().collect();
+        match tokens.as_slice() {
+            [name] => Ok(Self {
+                requirement: Dependency::from_evr(*name, None, None),
+            }),
+            [name, op, version] => {
+                if parse_sense(op).is_none() {
+                    return Err(RpmSearchError::Config(format!(
+                        "Invalid operator '{}' in capability '{}' (expected =, >=, <=, >, <)",
+                        op, expr
+                    )));
+                }
+                Ok(Self {
+                    requirement: Dependency::from_evr(*name, Some(op), Some(version)),
+                })
+            }
+            _ => Err(RpmSearchError::Config(format!(
+                "Invalid capability expression '{}' (expected 'name' or 'name OP version')",
+                expr
+            ))),
+        }
+    }
+
+    /// Check whether any of the dependencies matches this expression.
+    ///
+    /// Versions are compared with [`Dependency::version_matches`], so the
+    /// dependency's own operator counts (`foo >= 2` does not match a provide
+    /// of `foo = 1`) and an unversioned entry matches any constraint.
+    pub fn matches_any(&self, deps: &[Dependency]) -> bool {
+        deps.iter().any(|dep| self.matches(dep))
+    }
+
+    fn matches(&self, dep: &Dependency) -> bool {
+        let req = &self.requirement;
+        let name_matches = if req.name.contains(['*', '?']) {
+            glob_match(&req.name, &dep.name)
+        } else {
+            dep.name.eq_ignore_ascii_case(&req.name)
+        };
+        name_matches && dep.version_matches(req.flags.as_deref(), req.evr().as_deref())
+    }
+}
+
+/// Match `text` against a `*`/`?` wildcard pattern (ASCII case-insensitive,
+/// like SQLite's LIKE used by `find`).
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text index it is currently matched up to
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p].eq_ignore_ascii_case(&text[t])) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // Let the last `*` swallow one more character and retry
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, t));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dep(name: &str, version: Option<&str>) -> Dependency {
+        Dependency::from_evr(name, version.map(|_| "EQ"), version)
+    }
+
+    #[test]
+    fn test_parse_capability() {
+        let plain = CapabilityFilter::parse("libssl.so.3").unwrap();
+        assert_eq!(plain.requirement.name, "libssl.so.3");
+        assert!(plain.requirement.flags.is_none());
+
+        let versioned = CapabilityFilter::parse("openssl-libs >= 1:3.0").unwrap();
+        assert_eq!(versioned.requirement.flags.as_deref(), Some(">="));
+        assert_eq!(versioned.requirement.epoch, Some(1));
+        assert_eq!(versioned.requirement.version.as_deref(), Some("3.0"));
+
+        assert!(CapabilityFilter::parse("foo ~ 1.0").is_err());
+        assert!(CapabilityFilter::parse("foo >=").is_err());
+    }
+
+    #[test]
+    fn test_matches_version() {
+        let deps = vec![dep("openssl-libs", Some("1:3.0.7-1.el9"))];
+
+        assert!(CapabilityFilter::parse("openssl-libs")
+            .unwrap()
+            .matches_any(&deps));
+        assert!(CapabilityFilter::parse("openssl-libs >= 1:3.0")
+            .unwrap()
+            .matches_any(&deps));
+        assert!(!CapabilityFilter::parse("openssl-libs > 1:3.0.7")
+            .unwrap()
+            .matches_any(&deps));
+        assert!(CapabilityFilter::parse("openssl-libs = 1:3.0.7-1.el9")
+            .unwrap()
+            .matches_any(&deps));
+        assert!(!CapabilityFilter::parse("openssl-libs >= 3.0")
+            .unwrap()
+            .matches_any(&[dep("openssl-libs", Some("2.9-1"))]));
+        assert!(!CapabilityFilter::parse("openssl")
+            .unwrap()
+            .matches_any(&deps));
+    }
+
+    #[test]
+    fn test_unversioned_dependency_matches_any_version() {
+        let deps = vec![dep("libssl.so.3()(64bit)", None)];
+        assert!(CapabilityFilter::parse("libssl.so.3()(64bit) >= 3.0")
+            .unwrap()
+            .matches_any(&deps));
+    }
+
+    #[test]
+    fn test_matches_respects_dependency_operator() {
+        let provides = vec![Dependency::from_evr("foo", Some("EQ"), Some("1"))];
+        assert!(!CapabilityFilter::parse("foo >= 2")
+            .unwrap()
+            .matches_any(&provides));
+        assert!(CapabilityFilter::parse("foo <= 2")
+            .unwrap()
+            .matches_any(&provides));
+
+        // `foo >= 3` overlaps `foo >= 2`, as in rpm's range matching
+        let requires = vec![Dependency::from_evr("foo", Some("GE"), Some("2"))];
+        assert!(CapabilityFilter::parse("foo >= 3")
+            .unwrap()
+            .matches_any(&requires));
+        assert!(!CapabilityFilter::parse("foo < 2")
+            .unwrap()
+            .matches_any(&requires));
+    }
+
+    #[test]
+    fn test_matches_name_case_insensitive() {
+        let deps = vec![dep("OpenSSL-libs", Some("3.0.7-1"))];
+        assert!(CapabilityFilter::parse("openssl-libs >= 3.0")
+            .unwrap()
+            .matches_any(&deps));
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("libssl.so*", "libssl.so.3()(64bit)"));
+        assert!(glob_match("libssl.so.?", "libssl.so.3"));
+        assert!(glob_match("*ssl*", "libssl.so.3"));
+        assert!(glob_match("LIBSSL*", "libssl.so.3"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("libssl.so.?", "libssl.so.30"));
+        assert!(!glob_match("libcrypto*", "libssl.so.3"));
+    }
+
+    #[test]
+    fn test_wildcard_capability() {
+        let deps = vec![
+            dep("libssl.so.3()(64bit)", None),
+            dep("openssl-libs", Some("1:3.0.7-1")),
+        ];
+
+        assert!(CapabilityFilter::parse("libssl.so*")
+            .unwrap()
+            .matches_any(&deps));
+        assert!(CapabilityFilter::parse("openssl-* >= 1:3.0")
+            .unwrap()
+            .matches_any(&deps));
+        assert!(!CapabilityFilter::parse("openssl-* >= 1:3.1")
+            .unwrap()
+            .matches_any(&deps));
+        assert!(!CapabilityFilter::parse("libcrypto*")
+            .unwrap()
+            .matches_any(&deps));
+    }
+}
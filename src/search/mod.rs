@@ -1,7 +1,9 @@
+pub mod capability;
 pub mod planner;
 pub mod semantic;
 pub mod structured;
 
+pub use capability::*;
 pub use planner::*;
 pub use semantic::*;
 pub use structured::*;
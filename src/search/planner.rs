@@ -1,8 +1,10 @@
+use crate::config::{RepoWeights, ScoringWeights};
 use crate::error::Result;
 use crate::normalize::Package;
-use crate::search::{SemanticSearch, StructuredSearch};
+use crate::search::{CapabilityFilter, SemanticSearch, StructuredSearch};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchQuery {
@@ -14,56 +16,208 @@ pub struct SearchQuery {
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct SearchFilters {
     pub name: Option<String>,
-    pub arch: Option<String>,
+    /// Architectures to include (empty = all)
+    pub arches: Vec<String>,
     pub repos: Vec<String>,
+    /// Exclude packages requiring this capability (`name` or `name OP version`,
+    /// with optional `*`/`?` wildcards in the name)
     pub not_requiring: Option<String>,
+    /// Include only packages providing this capability (`name` or `name OP version`)
     pub providing: Option<String>,
+    /// Exclude packages providing this capability (`name` or `name OP version`)
+    pub not_providing: Option<String>,
+    /// Retry without the arch/repo prefilter when it leaves no candidates
+    #[serde(default)]
+    pub relax_filters: bool,
+    /// Domain hint prepended to the query for semantic retrieval only
+    /// (e.g. "database" for "client"). Not a filter; see [`semantic_query_text`].
+    #[serde(default)]
+    pub context: Option<String>,
+    /// Fill up to this many results with the best matches below the score
+    /// threshold when too few pass it (reported via `SearchResult::low_confidence`)
+    #[serde(default)]
+    pub min_results: Option<usize>,
+    /// Multiply each candidate's combined score by its repository's weight
+    /// before ranking (unlisted repos keep 1.0). The relevance threshold is
+    /// checked on the unweighted score, so weights never filter results.
+    #[serde(default)]
+    pub repo_weights: RepoWeights,
+}
+
+/// Compose the text that is embedded for semantic search.
+///
+/// The context (if any) is prepended to the query: `"database" + "client"`
+/// becomes `"database client"`. For E5 models the `query: ` prefix is added
+/// by the embedder in front of the combined text (`"query: database client"`).
+pub fn semantic_query_text(query: &str, context: Option<&str>) -> String {
+    match context.map(str::trim).filter(|c| !c.is_empty()) {
+        Some(context) => format!("{} {}", context, query),
+        None => query.to_string(),
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct SearchResult {
     pub packages: Vec<Package>,
     pub scores: Vec<f32>,
+    /// The arch/repo prefilter matched no packages at all
+    pub prefilter_empty: bool,
+    /// Results were produced without the arch/repo filters (see `relax_filters`)
+    pub relaxed: bool,
+    /// The last `low_confidence` results scored below the relevance threshold
+    /// and were only included to satisfy `min_results`
+    pub low_confidence: usize,
+    /// How the planner produced these results (`search --explain-plan`)
+    pub plan: SearchPlan,
+}
+
+/// Decisions taken by [`QueryPlanner::search`], mirroring its `debug!` logs
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SearchPlan {
+    /// Step 1 shortcut: name filter without query text, structured search only
+    pub name_only: bool,
+    /// The query was shorter than the semantic minimum, so only names were searched
+    pub short_query: bool,
+    /// Candidates left by the arch/repo prefilter (None = no prefilter)
+    pub prefilter_candidates: Option<usize>,
+    /// Semantic search ran without the arch/repo filters (`relax_filters`)
+    pub relaxed: bool,
+    /// Number of nearest neighbours requested from semantic search
+    pub semantic_top_k: usize,
+    pub structured_hits: usize,
+    pub semantic_hits: usize,
+    /// Merged candidates dropped by the minimum score threshold
+    pub below_threshold: usize,
+    /// Final results found only by structured search
+    pub from_structured: usize,
+    /// Final results found only by semantic search
+    pub from_semantic: usize,
+    /// Final results found by both sources
+    pub from_both: usize,
 }
 
-/// Weight configuration for hybrid scoring
-const STRUCTURED_WEIGHT: f32 = 0.45;
-const SEMANTIC_WEIGHT: f32 = 0.55;
+impl fmt::Display for SearchPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Search plan:")?;
+        if self.name_only {
+            return write!(
+                f,
+                "  strategy:        structured name lookup (name filter, no query text)\n  \
+                 results:         {}",
+                self.from_structured
+            );
+        }
+        if self.short_query {
+            writeln!(
+                f,
+                "  strategy:        structured only (query too short for semantic search)"
+            )?;
+        } else {
+            writeln!(f, "  strategy:        hybrid (structured + semantic)")?;
+        }
+        match self.prefilter_candidates {
+            Some(candidates) => writeln!(
+                f,
+                "  prefilter:       {} candidates{}",
+                candidates,
+                if self.relaxed { " (relaxed)" } else { "" }
+            )?,
+            None => writeln!(f, "  prefilter:       none")?,
+        }
+        writeln!(f, "  semantic top-k:  {}", self.semantic_top_k)?;
+        writeln!(f, "  structured hits: {}", self.structured_hits)?;
+        writeln!(f, "  semantic hits:   {}", self.semantic_hits)?;
+        writeln!(f, "  below threshold: {}", self.below_threshold)?;
+        write!(
+            f,
+            "  results:         {} structured only, {} semantic only, {} both",
+            self.from_structured, self.from_semantic, self.from_both
+        )
+    }
+}
 
 /// Minimum score threshold - results below this are filtered out
 const MIN_SCORE_THRESHOLD: f32 = 0.15;
 
 pub struct QueryPlanner<'a> {
-    semantic_search: SemanticSearch,
+    semantic_search: SemanticSearch<'a>,
     structured_search: StructuredSearch<'a>,
     default_top_k: usize,
+    weights: ScoringWeights,
+    min_semantic_query_len: usize,
 }
 
 impl<'a> QueryPlanner<'a> {
     pub fn new(
-        semantic_search: SemanticSearch,
+        semantic_search: SemanticSearch<'a>,
         structured_search: StructuredSearch<'a>,
         default_top_k: usize,
+        weights: ScoringWeights,
     ) -> Self {
         Self {
             semantic_search,
             structured_search,
             default_top_k,
+            weights,
+            min_semantic_query_len: 0,
         }
     }
 
+    /// Skip semantic search for queries shorter than `len` characters
+    pub fn with_min_semantic_query_len(mut self, len: usize) -> Self {
+        self.min_semantic_query_len = len;
+        self
+    }
+
+    /// Whether `query_text` is too short to be worth embedding
+    pub fn is_short_query(query_text: &str, min_len: usize) -> bool {
+        query_text.trim().chars().count() < min_len
+    }
+
     /// Execute a search query with hybrid planning (structured + semantic)
     pub fn search(&self, query: SearchQuery) -> Result<SearchResult> {
-        use tracing::{debug, info};
+        use tracing::{debug, info, warn};
 
         let top_k = query.top_k.unwrap_or(self.default_top_k);
 
+        let not_requiring = query
+            .filters
+            .not_requiring
+            .as_deref()
+            .map(CapabilityFilter::parse)
+            .transpose()?;
+        let providing = query
+            .filters
+            .providing
+            .as_deref()
+            .map(CapabilityFilter::parse)
+            .transpose()?;
+        let not_providing = query
+            .filters
+            .not_providing
+            .as_deref()
+            .map(CapabilityFilter::parse)
+            .transpose()?;
+
         // Step 1: If exact name filter is requested, use structured search only
         if let Some(ref name) = query.filters.name {
             if query.query_text.is_empty() {
                 let packages = self.structured_search.search_by_name(name)?;
                 let scores = vec![1.0; packages.len()];
-                return Ok(SearchResult { packages, scores });
+                let plan = SearchPlan {
+                    name_only: true,
+                    structured_hits: packages.len(),
+                    from_structured: packages.len(),
+                    ..Default::default()
+                };
+                return Ok(SearchResult {
+                    packages,
+                    scores,
+                    prefilter_empty: false,
+                    relaxed: false,
+                    low_confidence: 0,
+                    plan,
+                });
             }
         }
 
@@ -79,36 +233,64 @@ impl<'a> QueryPlanner<'a> {
             "Structured search results"
         );
 
-        // 2b: Semantic/vector search
+        // 2b: Semantic/vector search (skipped for very short queries, whose
+        // embeddings carry no meaning)
+        let short_query = Self::is_short_query(&query.query_text, self.min_semantic_query_len);
+        if short_query {
+            info!(
+                query = %query.query_text,
+                min_len = self.min_semantic_query_len,
+                "Query too short for semantic search, matching names only"
+            );
+        }
         // Expand search to get more candidates for merging
-        let semantic_top_k = (top_k * 3).max(30);
-
-        let use_prefilter = query.filters.arch.is_some() || !query.filters.repos.is_empty();
-
-        let vector_results = if use_prefilter {
+        let min_results = query.filters.min_results.unwrap_or(0);
+        let semantic_top_k = (top_k.max(min_results) * 3).max(30);
+        let semantic_text =
+            semantic_query_text(&query.query_text, query.filters.context.as_deref());
+
+        let use_prefilter = !query.filters.arches.is_empty() || !query.filters.repos.is_empty();
+        let mut prefilter_empty = false;
+        let mut relaxed = false;
+        let mut prefilter_candidates = None;
+
+        let vector_results = if short_query {
+            vec![]
+        } else if use_prefilter {
             let candidates = self
                 .structured_search
-                .get_filtered_candidates(query.filters.arch.as_deref(), &query.filters.repos)?;
+                .get_filtered_candidates(&query.filters.arches, &query.filters.repos)?;
 
             debug!(
                 total_candidates = candidates.len(),
-                arch = ?query.filters.arch,
+                arches = ?query.filters.arches,
                 repos = ?query.filters.repos,
                 "Pre-filtered search space"
             );
+            prefilter_candidates = Some(candidates.len());
 
             if candidates.is_empty() {
-                vec![]
+                prefilter_empty = true;
+                warn!(
+                    arches = ?query.filters.arches,
+                    repos = ?query.filters.repos,
+                    "Arch/repo filters eliminated all candidates"
+                );
+                if query.filters.relax_filters {
+                    info!("Retrying semantic search without arch/repo filters");
+                    relaxed = true;
+                    self.semantic_search
+                        .search(&semantic_text, semantic_top_k)?
+                } else {
+                    vec![]
+                }
             } else {
-                self.semantic_search.search_filtered(
-                    &query.query_text,
-                    &candidates,
-                    semantic_top_k,
-                )?
+                self.semantic_search
+                    .search_filtered(&semantic_text, &candidates, semantic_top_k)?
             }
         } else {
             self.semantic_search
-                .search(&query.query_text, semantic_top_k)?
+                .search(&semantic_text, semantic_top_k)?
         };
 
         debug!(
@@ -120,9 +302,15 @@ impl<'a> QueryPlanner<'a> {
         // Use a HashMap to combine scores from both sources
         let mut combined_scores: HashMap<i64, f32> = HashMap::new();
 
-        // Normalize structured scores (already 0-1 from search_by_name_ranked)
+        // Normalize structured scores (already 0-1 from search_by_name_ranked).
+        // Without embeddings the structured score is the whole score.
+        let structured_weight = if self.semantic_search.is_empty() || short_query {
+            1.0
+        } else {
+            self.weights.structured
+        };
         for (pkg_id, score) in &structured_results {
-            let weighted = score * STRUCTURED_WEIGHT;
+            let weighted = score * structured_weight;
             let entry = combined_scores.entry(*pkg_id).or_insert(0.0);
             *entry += weighted;
         }
@@ -130,53 +318,122 @@ impl<'a> QueryPlanner<'a> {
         // Semantic scores are now proper cosine similarity in [0, 1] range
         // Use raw scores directly (no min-max normalization to preserve absolute quality)
         for (pkg_id, cos_sim) in &vector_results {
-            let weighted = cos_sim * SEMANTIC_WEIGHT;
+            let weighted = cos_sim * self.weights.semantic;
             let entry = combined_scores.entry(*pkg_id).or_insert(0.0);
             *entry += weighted;
         }
 
-        // Step 4: Sort by combined score
-        let mut scored_results: Vec<(i64, f32)> = combined_scores.into_iter().collect();
-        scored_results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        // Filter by minimum threshold on the unweighted score, keeping the rest
+        // for --min-results
+        let (mut scored_results, mut below_threshold): (Vec<_>, Vec<_>) = combined_scores
+            .into_iter()
+            .partition(|(_, score)| *score >= MIN_SCORE_THRESHOLD);
+
+        // Step 3b: Per-repo boosts, which need each candidate's repo before
+        // ranking. Applied after the threshold, so they only re-rank.
+        if !query.filters.repo_weights.is_empty() {
+            let ids: Vec<i64> = scored_results
+                .iter()
+                .chain(&below_threshold)
+                .map(|(id, _)| *id)
+                .collect();
+            let repos = self.structured_search.get_repos(&ids)?;
+            for (pkg_id, score) in scored_results.iter_mut().chain(&mut below_threshold) {
+                if let Some(repo) = repos.get(pkg_id) {
+                    *score *= query.filters.repo_weights.weight(repo);
+                }
+            }
+        }
 
-        // Filter by minimum threshold
-        scored_results.retain(|(_, score)| *score >= MIN_SCORE_THRESHOLD);
+        // Step 4: Sort by combined score
+        for results in [&mut scored_results, &mut below_threshold] {
+            results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        }
 
-        // Limit to top_k
-        scored_results.truncate(top_k);
+        // Limit to top_k, or more when --min-results asks for more: good matches
+        // must not be dropped in favor of below-threshold ones
+        let max_results = top_k.max(min_results);
+        scored_results.truncate(max_results);
 
         debug!(
             combined_count = scored_results.len(),
+            below_threshold = below_threshold.len(),
             "Combined hybrid results"
         );
 
         // Step 5: Load package details and apply post-filters
+        let passes_filters = |pkg: &Package| {
+            if !relaxed && !pkg.matches_arch_repo(&query.filters.arches, &query.filters.repos) {
+                return false;
+            }
+            if let Some(ref not_requiring) = not_requiring {
+                if not_requiring.matches_any(&pkg.requires) {
+                    return false;
+                }
+            }
+            if let Some(ref providing) = providing {
+                if !providing.matches_any(&pkg.provides) {
+                    return false;
+                }
+            }
+            if let Some(ref not_providing) = not_providing {
+                if not_providing.matches_any(&pkg.provides) {
+                    return false;
+                }
+            }
+            true
+        };
+
+        let structured_ids: HashSet<i64> = structured_results.iter().map(|(id, _)| *id).collect();
+        let semantic_ids: HashSet<i64> = vector_results.iter().map(|(id, _)| *id).collect();
+        let mut plan = SearchPlan {
+            short_query,
+            prefilter_candidates,
+            relaxed,
+            semantic_top_k,
+            structured_hits: structured_results.len(),
+            semantic_hits: vector_results.len(),
+            below_threshold: below_threshold.len(),
+            ..Default::default()
+        };
+        let mut count_source = |pkg_id: i64| match (
+            structured_ids.contains(&pkg_id),
+            semantic_ids.contains(&pkg_id),
+        ) {
+            (true, true) => plan.from_both += 1,
+            (true, false) => plan.from_structured += 1,
+            _ => plan.from_semantic += 1,
+        };
+
         let mut final_packages: Vec<(Package, f32)> = Vec::new();
 
         for (pkg_id, score) in &scored_results {
             if let Some(pkg) = self.structured_search.get_package(*pkg_id)? {
-                // Apply post-filters
-                if let Some(ref arch) = query.filters.arch {
-                    if pkg.arch != *arch {
-                        continue;
-                    }
-                }
-                if !query.filters.repos.is_empty() && !query.filters.repos.contains(&pkg.repo) {
-                    continue;
+                if passes_filters(&pkg) {
+                    final_packages.push((pkg, *score));
+                    count_source(*pkg_id);
                 }
-                if let Some(ref not_requiring) = query.filters.not_requiring {
-                    if pkg.requires.iter().any(|r| r.name == *not_requiring) {
-                        continue;
-                    }
-                }
-                if let Some(ref providing) = query.filters.providing {
-                    if !pkg.provides.iter().any(|prov| prov.name == *providing) {
-                        continue;
-                    }
+            }
+        }
+
+        // Step 6: --min-results: top up with the best matches below the threshold
+        let mut low_confidence = 0;
+        for (pkg_id, score) in &below_threshold {
+            if final_packages.len() >= min_results {
+                break;
+            }
+            if let Some(pkg) = self.structured_search.get_package(*pkg_id)? {
+                if passes_filters(&pkg) {
+                    final_packages.push((pkg, *score));
+                    count_source(*pkg_id);
+                    low_confidence += 1;
                 }
-                final_packages.push((pkg, *score));
             }
         }
+        if low_confidence > 0 {
+            debug!(low_confidence, "Added results below the score threshold");
+        }
+        final_packages.truncate(max_results);
 
         let packages: Vec<Package> = final_packages.iter().map(|(p, _)| p.clone()).collect();
         let scores: Vec<f32> = final_packages.iter().map(|(_, s)| *s).collect();
@@ -188,7 +445,14 @@ impl<'a> QueryPlanner<'a> {
             "Hybrid search completed"
         );
 
-        Ok(SearchResult { packages, scores })
+        Ok(SearchResult {
+            packages,
+            scores,
+            prefilter_empty,
+            relaxed,
+            low_confidence,
+            plan,
+        })
     }
 
     /// Simple search by name only
@@ -208,6 +472,149 @@ impl<'a> QueryPlanner<'a> {
 
         let packages = self.structured_search.get_packages(&pkg_ids)?;
 
-        Ok(SearchResult { packages, scores })
+        Ok(SearchResult {
+            packages,
+            scores,
+            prefilter_empty: false,
+            relaxed: false,
+            low_confidence: 0,
+            plan: SearchPlan::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalize::test_package;
+
+    #[test]
+    fn test_semantic_query_text() {
+        assert_eq!(semantic_query_text("client", None), "client");
+        assert_eq!(
+            semantic_query_text("client", Some("database")),
+            "database client"
+        );
+        assert_eq!(semantic_query_text("client", Some("  ")), "client");
+    }
+
+    #[test]
+    fn test_structured_only_search() {
+        let temp = tempfile::NamedTempFile::new().unwrap();
+        let mut store = crate::storage::PackageStore::new(temp.path()).unwrap();
+        let pkg = Package {
+            summary: "Cryptography toolkit".to_string(),
+            ..test_package("openssl", "3.0")
+        };
+        store.insert_package(&pkg).unwrap();
+
+        let planner = QueryPlanner::new(
+            SemanticSearch::empty(),
+            StructuredSearch::new(&store),
+            10,
+            ScoringWeights::default(),
+        );
+        let result = planner
+            .search(SearchQuery {
+                query_text: "openssl".to_string(),
+                filters: SearchFilters::default(),
+                top_k: None,
+            })
+            .unwrap();
+        assert_eq!(result.packages.len(), 1);
+        assert_eq!(result.packages[0].name, "openssl");
+        assert!((result.scores[0] - 1.0).abs() < 1e-6);
+        assert!(!result.plan.name_only);
+        assert_eq!(result.plan.prefilter_candidates, None);
+        assert_eq!(result.plan.structured_hits, 1);
+        assert_eq!(result.plan.from_structured, 1);
+    }
+
+    #[test]
+    fn test_min_results_above_top_k_keeps_good_matches() {
+        let temp = tempfile::NamedTempFile::new().unwrap();
+        let mut store = crate::storage::PackageStore::new(temp.path()).unwrap();
+        for name in ["openssl", "openssl-devel", "openssl-libs"] {
+            let pkg = test_package(name, "3.0");
+            store.insert_package(&pkg).unwrap();
+        }
+
+        let planner = QueryPlanner::new(
+            SemanticSearch::empty(),
+            StructuredSearch::new(&store),
+            10,
+            ScoringWeights::default(),
+        );
+        let result = planner
+            .search(SearchQuery {
+                query_text: "openssl".to_string(),
+                filters: SearchFilters {
+                    min_results: Some(3),
+                    ..Default::default()
+                },
+                top_k: Some(1),
+            })
+            .unwrap();
+        assert_eq!(result.packages.len(), 3);
+        assert_eq!(result.low_confidence, 0);
+    }
+
+    #[test]
+    fn test_repo_weights_rerank() {
+        let temp = tempfile::NamedTempFile::new().unwrap();
+        let mut store = crate::storage::PackageStore::new(temp.path()).unwrap();
+        for repo in ["mirror", "curated"] {
+            let pkg = Package {
+                repo: repo.to_string(),
+                ..test_package("openssl", "3.0")
+            };
+            store.insert_package(&pkg).unwrap();
+        }
+
+        let planner = QueryPlanner::new(
+            SemanticSearch::empty(),
+            StructuredSearch::new(&store),
+            10,
+            ScoringWeights::default(),
+        );
+        let result = planner
+            .search(SearchQuery {
+                query_text: "openssl".to_string(),
+                filters: SearchFilters {
+                    repo_weights: "curated=1.2,mirror=0.8".parse().unwrap(),
+                    ..Default::default()
+                },
+                top_k: None,
+            })
+            .unwrap();
+        let repos: Vec<&str> = result.packages.iter().map(|p| p.repo.as_str()).collect();
+        assert_eq!(repos, ["curated", "mirror"]);
+        assert!((result.scores[0] - 1.2).abs() < 1e-6);
+        assert!((result.scores[1] - 0.8).abs() < 1e-6);
+
+        // A weight of 0 ranks the repo last but does not filter it out
+        let result = planner
+            .search(SearchQuery {
+                query_text: "openssl".to_string(),
+                filters: SearchFilters {
+                    repo_weights: "curated=0".parse().unwrap(),
+                    ..Default::default()
+                },
+                top_k: None,
+            })
+            .unwrap();
+        let repos: Vec<&str> = result.packages.iter().map(|p| p.repo.as_str()).collect();
+        assert_eq!(repos, ["mirror", "curated"]);
+        assert_eq!(result.low_confidence, 0);
+    }
+
+    #[test]
+    fn test_short_query_skips_semantic() {
+        assert!(QueryPlanner::is_short_query("gc", 3));
+        assert!(QueryPlanner::is_short_query(" gc ", 3));
+        assert!(!QueryPlanner::is_short_query("gcc", 3));
+        // Counted in characters, not bytes
+        assert!(QueryPlanner::is_short_query("한글", 3));
+        assert!(!QueryPlanner::is_short_query("gc", 0));
     }
 }
@@ -1,30 +1,124 @@
+use crate::config::ModelType;
 use crate::embedding::Embedder;
 use crate::error::Result;
 use crate::storage::VectorStore;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::sync::Arc;
 use tracing::debug;
 
-pub struct SemanticSearch {
-    vector_store: VectorStore,
-    embedder: Embedder,
+/// An embedding model loaded on first use, so a search whose query
+/// embeddings are all cached never loads it
+pub struct LazyEmbedder<'c> {
+    model_type: ModelType,
+    revision: Option<String>,
+    load: Box<dyn Fn() -> Result<Arc<Embedder>> + 'c>,
+    embedder: OnceCell<Arc<Embedder>>,
 }
 
-impl SemanticSearch {
-    pub fn new(vector_store: VectorStore, embedder: Embedder) -> Self {
+impl<'c> LazyEmbedder<'c> {
+    /// `load` must produce `model_type` at `revision` (the query cache key)
+    pub fn new(
+        model_type: ModelType,
+        revision: Option<String>,
+        load: impl Fn() -> Result<Arc<Embedder>> + 'c,
+    ) -> Self {
         Self {
-            vector_store,
-            embedder,
+            model_type,
+            revision,
+            load: Box::new(load),
+            embedder: OnceCell::new(),
         }
     }
 
+    pub fn model_type(&self) -> &ModelType {
+        &self.model_type
+    }
+
+    pub fn revision(&self) -> Option<&str> {
+        self.revision.as_deref()
+    }
+
+    /// The embedder, loading it on the first call
+    pub fn get(&self) -> Result<&Embedder> {
+        if let Some(embedder) = self.embedder.get() {
+            return Ok(embedder);
+        }
+        debug!(model = %self.model_type, "Loading embedding model for query");
+        let embedder = (self.load)()?;
+        Ok(self.embedder.get_or_init(|| embedder).as_ref())
+    }
+}
+
+/// Semantic search over one or more embedding models.
+///
+/// Each backend pairs a vector table with the embedder that produced it, so
+/// repositories embedded with different models can be searched together.
+pub struct SemanticSearch<'c> {
+    backends: Vec<(VectorStore<'c>, LazyEmbedder<'c>)>,
+}
+
+impl<'c> SemanticSearch<'c> {
+    pub fn new(vector_store: VectorStore<'c>, embedder: LazyEmbedder<'c>) -> Self {
+        Self {
+            backends: vec![(vector_store, embedder)],
+        }
+    }
+
+    /// Semantic search without any backend, for databases that have no
+    /// embeddings: every search returns nothing and no model is loaded
+    pub fn empty() -> Self {
+        Self {
+            backends: Vec::new(),
+        }
+    }
+
+    /// Whether there is no backend to search (see [`SemanticSearch::empty`])
+    pub fn is_empty(&self) -> bool {
+        self.backends.is_empty()
+    }
+
+    /// Add another vector table searched with its own embedding model
+    pub fn add_backend(&mut self, vector_store: VectorStore<'c>, embedder: LazyEmbedder<'c>) {
+        self.backends.push((vector_store, embedder));
+    }
+
+    /// Embed a query, reusing the on-disk cache when possible (the model is
+    /// only loaded on a cache miss)
+    fn embed_query_cached(
+        vector_store: &VectorStore,
+        embedder: &LazyEmbedder,
+        query: &str,
+    ) -> Result<Vec<f32>> {
+        let model_type = embedder.model_type();
+        let revision = embedder.revision();
+        if let Some(embedding) =
+            vector_store.get_cached_query_embedding(query, model_type, revision)?
+        {
+            debug!("Using cached query embedding");
+            return Ok(embedding);
+        }
+
+        // Embed the query (auto-adds prefix for E5 models)
+        let embedding = embedder.get()?.embed_query(query)?;
+        if let Err(e) = vector_store.cache_query_embedding(query, model_type, revision, &embedding)
+        {
+            debug!("Failed to cache query embedding: {}", e);
+        }
+        Ok(embedding)
+    }
+
     /// Search for similar packages using vector similarity
     pub fn search(&self, query: &str, top_k: usize) -> Result<Vec<(i64, f32)>> {
-        // Embed the query (auto-adds prefix for E5 models)
-        let query_embedding = self.embedder.embed_query(query)?;
+        let mut results = Vec::new();
+        for (vector_store, embedder) in &self.backends {
+            let query_embedding = Self::embed_query_cached(vector_store, embedder, query)?;
 
-        // Search similar vectors
-        let results = self.vector_store.search_similar(&query_embedding, top_k)?;
+            // Search similar vectors
+            results.push(vector_store.search_similar(&query_embedding, top_k)?);
+        }
 
-        Ok(results)
+        Ok(Self::merge(results, top_k))
     }
 
     /// Search with pre-filtered candidates (optimized for large datasets)
@@ -36,14 +130,74 @@ impl SemanticSearch {
     ) -> Result<Vec<(i64, f32)>> {
         debug!(
             candidates = candidate_ids.len(),
+            backends = self.backends.len(),
             "Performing pre-filtered vector search"
         );
 
-        // Embed the query (auto-adds prefix for E5 models)
-        let query_embedding = self.embedder.embed_query(query)?;
+        let mut results = Vec::new();
+        for (vector_store, embedder) in &self.backends {
+            let query_embedding = Self::embed_query_cached(vector_store, embedder, query)?;
+
+            // Search only within candidate IDs
+            results.push(vector_store.search_similar_filtered(
+                &query_embedding,
+                candidate_ids,
+                top_k,
+            )?);
+        }
+
+        Ok(Self::merge(results, top_k))
+    }
+
+    /// Combine per-backend results into a single ranking.
+    ///
+    /// Scores are already calibrated by each store's
+    /// [`ScoreTransform`](crate::config::ScoreTransform), so they are merged
+    /// unchanged: rescaling per batch would lift a small backend's best hit to
+    /// the top whatever its similarity. A package found by more than one
+    /// backend keeps its best score.
+    fn merge(per_backend: Vec<Vec<(i64, f32)>>, top_k: usize) -> Vec<(i64, f32)> {
+        let mut best: HashMap<i64, f32> = HashMap::new();
+        for (pkg_id, score) in per_backend.into_iter().flatten() {
+            let entry = best.entry(pkg_id).or_insert(score);
+            *entry = entry.max(score);
+        }
+
+        let mut results: Vec<(i64, f32)> = best.into_iter().collect();
+        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        results.truncate(top_k);
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_single_backend_keeps_raw_scores() {
+        let merged = SemanticSearch::merge(vec![vec![(1, 0.4), (2, 0.7)]], 10);
+        assert_eq!(merged, vec![(2, 0.7), (1, 0.4)]);
+    }
+
+    #[test]
+    fn test_merge_keeps_calibrated_scores() {
+        let merged =
+            SemanticSearch::merge(vec![vec![(1, 0.5), (2, 0.3)], vec![(3, 0.9), (4, 0.4)]], 10);
+        assert_eq!(merged, vec![(3, 0.9), (1, 0.5), (4, 0.4), (2, 0.3)]);
+    }
+
+    #[test]
+    fn test_merge_single_hit_backend_is_not_promoted() {
+        // A small override repository's only (weak) hit keeps its score
+        let merged = SemanticSearch::merge(vec![vec![(1, 0.8), (2, 0.6)], vec![(3, 0.2)]], 10);
+        assert_eq!(merged, vec![(1, 0.8), (2, 0.6), (3, 0.2)]);
+    }
 
-        // Search only within candidate IDs
-        self.vector_store
-            .search_similar_filtered(&query_embedding, candidate_ids, top_k)
+    #[test]
+    fn test_merge_dedupes_by_best_score() {
+        let merged = SemanticSearch::merge(vec![vec![(1, 0.8), (2, 0.2)], vec![(1, 0.3)]], 10);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].0, 1);
     }
 }
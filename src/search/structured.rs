@@ -1,14 +1,26 @@
+use crate::config::StructuredWeights;
 use crate::error::Result;
 use crate::normalize::Package;
 use crate::storage::PackageStore;
+use std::collections::HashMap;
 
 pub struct StructuredSearch<'a> {
     store: &'a PackageStore,
+    weights: StructuredWeights,
 }
 
 impl<'a> StructuredSearch<'a> {
     pub fn new(store: &'a PackageStore) -> Self {
-        Self { store }
+        Self {
+            store,
+            weights: StructuredWeights::default(),
+        }
+    }
+
+    /// Use `weights` for the match tiers of [`Self::search_by_name_ranked`]
+    pub fn with_weights(mut self, weights: StructuredWeights) -> Self {
+        self.weights = weights;
+        self
     }
 
     /// Search packages by name
@@ -19,7 +31,7 @@ impl<'a> StructuredSearch<'a> {
     /// Search packages by name with relevance scoring
     /// Returns (pkg_id, score) pairs ordered by relevance
     pub fn search_by_name_ranked(&self, query: &str) -> Result<Vec<(i64, f32)>> {
-        self.store.search_by_name_ranked(query)
+        self.store.search_by_name_ranked(query, &self.weights)
     }
 
     /// Get packages by IDs
@@ -62,11 +74,12 @@ impl<'a> StructuredSearch<'a> {
             .collect()
     }
     /// Get filtered package IDs for pre-filtering vector search
-    pub fn get_filtered_candidates(
-        &self,
-        arch: Option<&str>,
-        repos: &[String],
-    ) -> Result<Vec<i64>> {
-        self.store.get_filtered_pkg_ids(arch, repos)
+    pub fn get_filtered_candidates(&self, arches: &[String], repos: &[String]) -> Result<Vec<i64>> {
+        self.store.get_filtered_pkg_ids(arches, repos)
+    }
+
+    /// Repository of each candidate package
+    pub fn get_repos(&self, pkg_ids: &[i64]) -> Result<HashMap<i64, String>> {
+        self.store.get_repos(pkg_ids)
     }
 }
@@ -1,22 +1,22 @@
+use crate::config::SqlitePragmas;
 use crate::error::Result;
-use rusqlite::Connection;
+use crate::normalize::Dependency;
+use rusqlite::{params, Connection};
+use tracing::{debug, warn};
 
-pub const SCHEMA_VERSION: i32 = 4;
+pub const SCHEMA_VERSION: i32 = 10;
 
 pub struct Schema;
 
 impl Schema {
+    /// Apply journal/sync/cache pragmas to a connection
+    pub fn apply_pragmas(conn: &Connection, pragmas: &SqlitePragmas) -> Result<()> {
+        conn.execute_batch(&pragmas.to_sql())?;
+        Ok(())
+    }
+
     /// Initialize database schema
     pub fn initialize(conn: &Connection) -> Result<()> {
-        // Performance pragmas
-        conn.execute_batch(
-            "PRAGMA journal_mode = WAL;
-             PRAGMA synchronous = NORMAL;
-             PRAGMA cache_size = -64000;
-             PRAGMA temp_store = MEMORY;
-             PRAGMA mmap_size = 268435456;",
-        )?;
-
         // Create packages table
         conn.execute(
             "CREATE TABLE IF NOT EXISTS packages (
@@ -31,7 +31,9 @@ impl Schema {
                 license     TEXT,
                 vcs         TEXT,
                 location_href TEXT,
-                repo        TEXT NOT NULL
+                sourcerpm   TEXT,
+                repo        TEXT NOT NULL,
+                build_time  INTEGER
             )",
             [],
         )?;
@@ -41,6 +43,11 @@ impl Schema {
             "CREATE INDEX IF NOT EXISTS idx_packages_name ON packages(name)",
             [],
         )?;
+        // Case-insensitive exact/prefix name lookups (`name LIKE 'foo%'`) use this index
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_packages_name_nocase ON packages(name COLLATE NOCASE)",
+            [],
+        )?;
         conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_packages_arch ON packages(arch)",
             [],
@@ -49,6 +56,12 @@ impl Schema {
             "CREATE INDEX IF NOT EXISTS idx_packages_repo ON packages(repo)",
             [],
         )?;
+        // Repo/arch filters match case-insensitively (`repo COLLATE NOCASE IN (...)`)
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_packages_repo_arch_nocase
+             ON packages(repo COLLATE NOCASE, arch COLLATE NOCASE)",
+            [],
+        )?;
 
         // Create requires table
         conn.execute(
@@ -57,7 +70,10 @@ impl Schema {
                 pkg_id  INTEGER NOT NULL,
                 name    TEXT NOT NULL,
                 flags   TEXT,
+                epoch   INTEGER,
                 version TEXT,
+                release TEXT,
+                pre     INTEGER NOT NULL DEFAULT 0,
                 FOREIGN KEY(pkg_id) REFERENCES packages(pkg_id)
             )",
             [],
@@ -79,7 +95,9 @@ impl Schema {
                 pkg_id  INTEGER NOT NULL,
                 name    TEXT NOT NULL,
                 flags   TEXT,
+                epoch   INTEGER,
                 version TEXT,
+                release TEXT,
                 FOREIGN KEY(pkg_id) REFERENCES packages(pkg_id)
             )",
             [],
@@ -94,6 +112,24 @@ impl Schema {
             [],
         )?;
 
+        // Conflicts and obsoletes share the provides layout
+        for table in ["conflicts", "obsoletes"] {
+            conn.execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS {table} (
+                    id      INTEGER PRIMARY KEY,
+                    pkg_id  INTEGER NOT NULL,
+                    name    TEXT NOT NULL,
+                    flags   TEXT,
+                    epoch   INTEGER,
+                    version TEXT,
+                    release TEXT,
+                    FOREIGN KEY(pkg_id) REFERENCES packages(pkg_id)
+                );
+                CREATE INDEX IF NOT EXISTS idx_{table}_pkg_id ON {table}(pkg_id);
+                CREATE INDEX IF NOT EXISTS idx_{table}_name ON {table}(name);"
+            ))?;
+        }
+
         // Create directories table (path deduplication for file entries)
         conn.execute(
             "CREATE TABLE IF NOT EXISTS directories (
@@ -130,6 +166,31 @@ impl Schema {
             [],
         )?;
 
+        // Localized summaries/descriptions (the default text stays in packages)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS package_l10n (
+                pkg_id      INTEGER NOT NULL,
+                lang        TEXT NOT NULL,
+                summary     TEXT,
+                description TEXT,
+                PRIMARY KEY (pkg_id, lang)
+            )",
+            [],
+        )?;
+
+        // AppStream data is keyed by package name so it survives package updates
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS appstream (
+                repo        TEXT NOT NULL,
+                pkgname     TEXT NOT NULL,
+                description TEXT NOT NULL,
+                categories  TEXT NOT NULL,
+                keywords    TEXT NOT NULL,
+                PRIMARY KEY (repo, pkgname)
+            )",
+            [],
+        )?;
+
         // Create metadata table for version tracking
         conn.execute(
             "CREATE TABLE IF NOT EXISTS metadata (
@@ -139,6 +200,39 @@ impl Schema {
             [],
         )?;
 
+        // Cache of query embeddings, keyed by query hash and embedding model
+        // (model_revision is '' for the default branch or local model files)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS query_embeddings (
+                query_hash      INTEGER NOT NULL,
+                model_type      TEXT NOT NULL,
+                model_revision  TEXT NOT NULL DEFAULT '',
+                query           TEXT NOT NULL,
+                embedding       TEXT NOT NULL,
+                created_at      TEXT NOT NULL,
+                PRIMARY KEY (query_hash, model_type, model_revision)
+            )",
+            [],
+        )?;
+
+        // Per-repository embedding model overrides (repos not listed use the DB default)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS repo_embedding_models (
+                repo        TEXT PRIMARY KEY,
+                model_type  TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        // Repositories indexed before some dependency data was stored; their
+        // dependencies are rewritten on the next incremental update
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS repo_dependency_refresh (repo TEXT PRIMARY KEY)",
+            [],
+        )?;
+
+        Self::create_name_trigrams(conn);
+
         // Set schema version
         conn.execute(
             "INSERT OR REPLACE INTO metadata (key, value) VALUES ('schema_version', ?)",
@@ -148,11 +242,73 @@ impl Schema {
         Ok(())
     }
 
+    /// Rebuild every SQLite index and the trigram name index from `packages`
+    /// (creating the latter if it is missing), then refresh planner statistics
+    pub fn reindex(conn: &Connection) -> Result<()> {
+        conn.execute_batch("REINDEX;")?;
+        if Self::has_name_trigrams(conn) {
+            conn.execute(
+                "INSERT INTO package_name_trigrams (package_name_trigrams) VALUES ('rebuild')",
+                [],
+            )?;
+        } else {
+            Self::create_name_trigrams(conn);
+        }
+        conn.execute_batch("ANALYZE;")?;
+        Ok(())
+    }
+
+    /// Whether the trigram index over package names exists
+    pub fn has_name_trigrams(conn: &Connection) -> bool {
+        conn.query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'package_name_trigrams'",
+            [],
+            |_| Ok(()),
+        )
+        .is_ok()
+    }
+
+    /// Create the trigram FTS5 index over package names (kept in sync by triggers)
+    /// used for substring name search. It is optional: SQLite builds without FTS5
+    /// or the trigram tokenizer (3.34+) keep using `LIKE '%term%'` scans.
+    fn create_name_trigrams(conn: &Connection) {
+        if Self::has_name_trigrams(conn) {
+            return;
+        }
+
+        let created = conn.unchecked_transaction().and_then(|tx| {
+            tx.execute_batch(
+                "CREATE VIRTUAL TABLE package_name_trigrams USING fts5(
+                     name, content='packages', content_rowid='pkg_id', tokenize='trigram'
+                 );
+                 CREATE TRIGGER IF NOT EXISTS packages_name_trigrams_ai AFTER INSERT ON packages BEGIN
+                     INSERT INTO package_name_trigrams (rowid, name) VALUES (new.pkg_id, new.name);
+                 END;
+                 CREATE TRIGGER IF NOT EXISTS packages_name_trigrams_ad AFTER DELETE ON packages BEGIN
+                     INSERT INTO package_name_trigrams (package_name_trigrams, rowid, name)
+                     VALUES ('delete', old.pkg_id, old.name);
+                 END;
+                 CREATE TRIGGER IF NOT EXISTS packages_name_trigrams_au AFTER UPDATE OF name ON packages BEGIN
+                     INSERT INTO package_name_trigrams (package_name_trigrams, rowid, name)
+                     VALUES ('delete', old.pkg_id, old.name);
+                     INSERT INTO package_name_trigrams (rowid, name) VALUES (new.pkg_id, new.name);
+                 END;
+                 INSERT INTO package_name_trigrams (package_name_trigrams) VALUES ('rebuild');",
+            )?;
+            tx.commit()
+        });
+        if let Err(e) = created {
+            debug!(error = %e, "Trigram name index unavailable, using LIKE scans");
+        }
+    }
+
     /// Migrate database schema from old version to current.
     /// Should be called before initialize() for existing databases.
     pub fn migrate(conn: &Connection) -> Result<()> {
         let current = Self::get_version(conn).unwrap_or(0);
         if current > 0 && current < SCHEMA_VERSION {
+            // Dependency data older versions didn't store
+            let mut missing_dependencies = Vec::new();
             // v1 -> v2: Replace flat files table with normalized directories + files
             if current < 2 {
                 conn.execute_batch(
@@ -184,19 +340,259 @@ impl Schema {
                     }
                 }
             }
+            // v4 -> v5: Add sourcerpm to packages (NULL until the repo is re-indexed)
+            if current < 5 {
+                let has_sourcerpm = conn
+                    .prepare("SELECT sourcerpm FROM packages LIMIT 0")
+                    .is_ok();
+                if !has_sourcerpm {
+                    conn.execute_batch("ALTER TABLE packages ADD COLUMN sourcerpm TEXT;")?;
+                }
+            }
+            // v5 -> v6: Add build_time to packages (NULL until the repo is re-indexed)
+            if current < 6 {
+                let has_build_time = conn
+                    .prepare("SELECT build_time FROM packages LIMIT 0")
+                    .is_ok();
+                if !has_build_time {
+                    conn.execute_batch("ALTER TABLE packages ADD COLUMN build_time INTEGER;")?;
+                }
+            }
+            // v6 -> v7: Keep dependency epoch/version/release in separate columns
+            if current < 7 {
+                for table in ["requires", "provides"] {
+                    Self::split_dependency_evr(conn, table)?;
+                }
+            }
+            // v7 -> v8: Flag prerequisite (`pre="1"`) requires (0 until re-indexed)
+            if current < 8 {
+                let has_pre = conn.prepare("SELECT pre FROM requires LIMIT 0").is_ok();
+                if !has_pre {
+                    conn.execute_batch(
+                        "ALTER TABLE requires ADD COLUMN pre INTEGER NOT NULL DEFAULT 0;",
+                    )?;
+                }
+                missing_dependencies.push("prerequisite (pre) flags");
+            }
+            // v8 -> v9: Key the query embedding cache by model revision too
+            // (it is only a cache, so recreate it empty)
+            if current < 9 {
+                conn.execute_batch("DROP TABLE IF EXISTS query_embeddings;")?;
+            }
+            // v9 -> v10: Conflicts/obsoletes tables (created by initialize()),
+            // empty for packages indexed before they existed
+            if current < 10 {
+                let has_conflicts = conn.prepare("SELECT 1 FROM conflicts LIMIT 0").is_ok();
+                if !has_conflicts {
+                    missing_dependencies.push("conflicts and obsoletes");
+                }
+            }
+
+            if !missing_dependencies.is_empty() {
+                Self::request_dependency_refresh(conn, &missing_dependencies.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Mark every indexed repository for a dependency refresh because the
+    /// stored dependencies lack `missing`, and forget the synced metadata
+    /// checksums so the next sync updates each repository even if unchanged.
+    /// Repositories indexed from files are refreshed by `index repo --update`.
+    pub fn request_dependency_refresh(conn: &Connection, missing: &str) -> Result<()> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS repo_dependency_refresh (repo TEXT PRIMARY KEY);
+             INSERT OR IGNORE INTO repo_dependency_refresh (repo) SELECT DISTINCT repo FROM packages;",
+        )?;
+        let has_sync_state = conn
+            .prepare("SELECT last_checksum FROM repo_sync_state LIMIT 0")
+            .is_ok();
+        if has_sync_state {
+            conn.execute("UPDATE repo_sync_state SET last_checksum = NULL", [])?;
+        }
+
+        let repos: i64 =
+            conn.query_row("SELECT COUNT(*) FROM repo_dependency_refresh", [], |row| {
+                row.get(0)
+            })?;
+        if repos > 0 {
+            warn!(
+                repos,
+                "Indexed dependencies lack {}; each repository is refreshed on its next sync or `index repo --update`",
+                missing
+            );
+        }
+        Ok(())
+    }
+
+    /// Add epoch/release columns to a dependency table and split the combined
+    /// `epoch:version-release` strings stored in `version` before v7
+    fn split_dependency_evr(conn: &Connection, table: &str) -> Result<()> {
+        let has_release = conn
+            .prepare(&format!("SELECT release FROM {} LIMIT 0", table))
+            .is_ok();
+        if has_release {
+            return Ok(());
+        }
+
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(&format!(
+            "ALTER TABLE {table} ADD COLUMN epoch INTEGER;
+             ALTER TABLE {table} ADD COLUMN release TEXT;"
+        ))?;
+        {
+            let mut select = tx.prepare(&format!(
+                "SELECT id, version FROM {} WHERE version LIKE '%:%' OR version LIKE '%-%'",
+                table
+            ))?;
+            let mut update = tx.prepare(&format!(
+                "UPDATE {} SET epoch = ?, version = ?, release = ? WHERE id = ?",
+                table
+            ))?;
+            let rows = select
+                .query_map([], |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            for (id, evr) in rows {
+                let dep = Dependency::from_evr("", None, Some(&evr));
+                update.execute(params![dep.epoch, dep.version, dep.release, id])?;
+            }
         }
+        tx.commit()?;
         Ok(())
     }
 
     /// Get current schema version
     pub fn get_version(conn: &Connection) -> Result<i32> {
-        let version: i32 = conn
+        // The metadata value column is TEXT, so read it as a string and parse
+        let version = conn
             .query_row(
                 "SELECT value FROM metadata WHERE key = 'schema_version'",
                 [],
-                |row| row.get(0),
+                |row| row.get::<_, String>(0),
             )
+            .ok()
+            .and_then(|v| v.parse().ok())
             .unwrap_or(0);
         Ok(version)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_splits_dependency_evr() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE metadata (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             INSERT INTO metadata VALUES ('schema_version', '6');
+             CREATE TABLE packages (pkg_id INTEGER PRIMARY KEY, repo TEXT NOT NULL,
+                                    location_href TEXT, sourcerpm TEXT, build_time INTEGER);
+             CREATE TABLE requires (id INTEGER PRIMARY KEY, pkg_id INTEGER NOT NULL,
+                                    name TEXT NOT NULL, flags TEXT, version TEXT);
+             CREATE TABLE provides (id INTEGER PRIMARY KEY, pkg_id INTEGER NOT NULL,
+                                    name TEXT NOT NULL, flags TEXT, version TEXT);
+             INSERT INTO requires (pkg_id, name, flags, version)
+                 VALUES (1, 'glibc', 'GE', '1:2.34-5.el9'), (1, 'sh', NULL, NULL);
+             INSERT INTO provides (pkg_id, name, flags, version)
+                 VALUES (1, 'libfoo', 'EQ', '2.0');",
+        )
+        .unwrap();
+
+        Schema::migrate(&conn).unwrap();
+
+        let evr = |sql: &str| {
+            conn.query_row(sql, [], |row| {
+                Ok((
+                    row.get::<_, Option<i64>>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            })
+            .unwrap()
+        };
+        assert_eq!(
+            evr("SELECT epoch, version, release FROM requires WHERE name = 'glibc'"),
+            (Some(1), Some("2.34".to_string()), Some("5.el9".to_string()))
+        );
+        assert_eq!(
+            evr("SELECT epoch, version, release FROM requires WHERE name = 'sh'"),
+            (None, None, None)
+        );
+        assert_eq!(
+            evr("SELECT epoch, version, release FROM provides"),
+            (None, Some("2.0".to_string()), None)
+        );
+    }
+
+    #[test]
+    fn test_migrate_refreshes_missing_conflicts() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE metadata (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             INSERT INTO metadata VALUES ('schema_version', '9');
+             CREATE TABLE packages (pkg_id INTEGER PRIMARY KEY, repo TEXT NOT NULL);
+             INSERT INTO packages (repo) VALUES ('base');",
+        )
+        .unwrap();
+        Schema::migrate(&conn).unwrap();
+        let pending: i64 = conn
+            .query_row("SELECT COUNT(*) FROM repo_dependency_refresh", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(pending, 1);
+
+        // Databases that already store conflicts need no refresh
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE metadata (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             INSERT INTO metadata VALUES ('schema_version', '9');
+             CREATE TABLE packages (pkg_id INTEGER PRIMARY KEY, repo TEXT NOT NULL);
+             CREATE TABLE conflicts (id INTEGER PRIMARY KEY);
+             INSERT INTO packages (repo) VALUES ('base');",
+        )
+        .unwrap();
+        Schema::migrate(&conn).unwrap();
+        assert!(conn
+            .prepare("SELECT 1 FROM repo_dependency_refresh LIMIT 0")
+            .is_err());
+    }
+
+    #[test]
+    fn test_migrate_requests_dependency_refresh() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE metadata (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             INSERT INTO metadata VALUES ('schema_version', '7');
+             CREATE TABLE packages (pkg_id INTEGER PRIMARY KEY, repo TEXT NOT NULL);
+             INSERT INTO packages (repo) VALUES ('base'), ('base'), ('updates');
+             CREATE TABLE requires (id INTEGER PRIMARY KEY, pkg_id INTEGER NOT NULL,
+                                    name TEXT NOT NULL, release TEXT);
+             CREATE TABLE provides (id INTEGER PRIMARY KEY, release TEXT);
+             CREATE TABLE repo_sync_state (repo_name TEXT PRIMARY KEY, last_checksum TEXT);
+             INSERT INTO repo_sync_state VALUES ('base', 'abc');",
+        )
+        .unwrap();
+
+        Schema::migrate(&conn).unwrap();
+
+        let repos: Vec<String> = conn
+            .prepare("SELECT repo FROM repo_dependency_refresh ORDER BY repo")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(repos, ["base", "updates"]);
+        let checksum: Option<String> = conn
+            .query_row("SELECT last_checksum FROM repo_sync_state", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(checksum, None);
+    }
+}
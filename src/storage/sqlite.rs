@@ -1,36 +1,117 @@
-use crate::error::Result;
+use crate::config::{SqlitePragmas, StructuredWeights};
+use crate::error::{Result, RpmSearchError};
 use crate::normalize::package::{Dependency, Package};
-use crate::storage::schema::Schema;
-use rusqlite::{params, Connection, OptionalExtension};
+use crate::normalize::version::RpmVersion;
+use crate::normalize::AppstreamInfo;
+use crate::repomd::model::{LocalizedText, RpmFileType};
+use crate::storage::schema::{Schema, SCHEMA_VERSION};
+use regex::Regex;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Upper bound on regex matches: `REGEXP` can't use an index, so a broad
+/// pattern scans and returns the whole table
+pub const REGEX_MAX_RESULTS: usize = 1000;
+
+/// `packages` columns read by [`package_from_row`]
+const PACKAGE_COLUMNS: &str = "pkg_id, name, epoch, version, release, arch, summary, description, license, vcs, location_href, sourcerpm, repo, build_time";
 
 pub struct PackageStore {
     conn: Connection,
+    slow_query_ms: u64,
+    /// Whether the trigram FTS5 index over names is available
+    name_trigrams: bool,
 }
 
 impl PackageStore {
     /// Create a new package store
     pub fn new<P: AsRef<Path>>(db_path: P) -> Result<Self> {
+        Self::open(db_path, &SqlitePragmas::default())
+    }
+
+    /// Open a package store with the given SQLite pragmas
+    pub fn open<P: AsRef<Path>>(db_path: P, pragmas: &SqlitePragmas) -> Result<Self> {
         let conn = Connection::open(db_path)?;
+        register_regexp(&conn)?;
+        Schema::apply_pragmas(&conn, pragmas)?;
         Schema::migrate(&conn)?;
         Schema::initialize(&conn)?;
-        Ok(Self { conn })
+        let name_trigrams = Schema::has_name_trigrams(&conn);
+        Ok(Self {
+            conn,
+            slow_query_ms: 0,
+            name_trigrams,
+        })
+    }
+
+    /// Open an existing database read-only, without migrating or
+    /// initializing it (for previews that must not write). The database must
+    /// already have the current schema.
+    pub fn open_read_only<P: AsRef<Path>>(db_path: P) -> Result<Self> {
+        let conn = Connection::open_with_flags(
+            db_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY
+                | OpenFlags::SQLITE_OPEN_URI
+                | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        register_regexp(&conn)?;
+        let version = Schema::get_version(&conn)?;
+        if version != SCHEMA_VERSION {
+            return Err(RpmSearchError::Storage(format!(
+                "Database schema is version {}, expected {}; \
+                 run a command that writes to the database to migrate it",
+                version, SCHEMA_VERSION
+            )));
+        }
+        let name_trigrams = Schema::has_name_trigrams(&conn);
+        Ok(Self {
+            conn,
+            slow_query_ms: 0,
+            name_trigrams,
+        })
+    }
+
+    /// Log queries slower than `ms` milliseconds as warnings (0 = disabled)
+    pub fn with_slow_query_ms(mut self, ms: u64) -> Self {
+        self.slow_query_ms = ms;
+        self
+    }
+
+    /// Run `f` atomically: its writes are kept only if it returns `Ok`.
+    ///
+    /// Store methods open savepoints rather than transactions, so they nest
+    /// inside `f` and commit only with the outermost call.
+    pub fn in_transaction<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.conn.execute_batch("SAVEPOINT in_transaction")?;
+        let result = f(self);
+        finish_savepoint(&self.conn, "in_transaction", result)
+    }
+
+    /// The underlying connection, for sharing with a [`VectorStore`](crate::storage::VectorStore)
+    pub fn connection(&self) -> &Connection {
+        &self.conn
     }
 
     /// Insert a package and return its pkg_id
     #[allow(dead_code)]
     pub fn insert_package(&mut self, package: &Package) -> Result<i64> {
-        let tx = self.conn.transaction()?;
+        let tx = self.conn.savepoint()?;
         let pkg_id = Self::insert_package_in_tx(&tx, package)?;
         tx.commit()?;
         Ok(pkg_id)
     }
 
     /// Insert a single package within an existing transaction
-    fn insert_package_in_tx(tx: &rusqlite::Transaction, package: &Package) -> Result<i64> {
+    fn insert_package_in_tx(tx: &Connection, package: &Package) -> Result<i64> {
         tx.execute(
-            "INSERT INTO packages (name, epoch, version, release, arch, summary, description, license, vcs, location_href, repo)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            "INSERT INTO packages (name, epoch, version, release, arch, summary, description, license, vcs, location_href, sourcerpm, repo, build_time)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
             params![
                 package.name,
                 package.epoch,
@@ -42,44 +123,74 @@ impl PackageStore {
                 package.license,
                 package.vcs,
                 package.location_href,
+                package.sourcerpm,
                 package.repo,
+                package.build_time,
             ],
         )?;
 
         let pkg_id = tx.last_insert_rowid();
 
+        for l10n in &package.localized {
+            tx.execute(
+                "INSERT OR REPLACE INTO package_l10n (pkg_id, lang, summary, description) VALUES (?, ?, ?, ?)",
+                params![pkg_id, l10n.lang, l10n.summary, l10n.description],
+            )?;
+        }
+
+        Self::insert_dependencies_in_tx(tx, pkg_id, package)?;
+        Ok(pkg_id)
+    }
+
+    /// Store a package's requires, provides, conflicts and obsoletes under `pkg_id`
+    fn insert_dependencies_in_tx(tx: &Connection, pkg_id: i64, package: &Package) -> Result<()> {
         for req in &package.requires {
             tx.execute(
-                "INSERT INTO requires (pkg_id, name, flags, version) VALUES (?, ?, ?, ?)",
-                params![pkg_id, req.name, req.flags, req.version],
+                "INSERT INTO requires (pkg_id, name, flags, epoch, version, release, pre) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                params![pkg_id, req.name, req.flags, req.epoch, req.version, req.release, req.pre],
             )?;
         }
 
         for prov in &package.provides {
             tx.execute(
-                "INSERT INTO provides (pkg_id, name, flags, version) VALUES (?, ?, ?, ?)",
-                params![pkg_id, prov.name, prov.flags, prov.version],
+                "INSERT INTO provides (pkg_id, name, flags, epoch, version, release) VALUES (?, ?, ?, ?, ?, ?)",
+                params![pkg_id, prov.name, prov.flags, prov.epoch, prov.version, prov.release],
             )?;
         }
 
-        Ok(pkg_id)
+        for (table, deps) in [
+            ("conflicts", &package.conflicts),
+            ("obsoletes", &package.obsoletes),
+        ] {
+            for dep in deps {
+                tx.execute(
+                    &format!("INSERT INTO {table} (pkg_id, name, flags, epoch, version, release) VALUES (?, ?, ?, ?, ?, ?)"),
+                    params![pkg_id, dep.name, dep.flags, dep.epoch, dep.version, dep.release],
+                )?;
+            }
+        }
+
+        Ok(())
     }
 
     /// Batch insert packages in a single transaction with prepared statements
     pub fn insert_packages_batch(&mut self, packages: &[Package]) -> Result<Vec<i64>> {
-        let tx = self.conn.transaction()?;
+        let tx = self.conn.savepoint()?;
         let mut pkg_ids = Vec::with_capacity(packages.len());
 
         {
             let mut pkg_stmt = tx.prepare_cached(
-                "INSERT INTO packages (name, epoch, version, release, arch, summary, description, license, vcs, location_href, repo)
-                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                "INSERT INTO packages (name, epoch, version, release, arch, summary, description, license, vcs, location_href, sourcerpm, repo, build_time)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
             )?;
             let mut req_stmt = tx.prepare_cached(
-                "INSERT INTO requires (pkg_id, name, flags, version) VALUES (?, ?, ?, ?)",
+                "INSERT INTO requires (pkg_id, name, flags, epoch, version, release, pre) VALUES (?, ?, ?, ?, ?, ?, ?)",
             )?;
             let mut prov_stmt = tx.prepare_cached(
-                "INSERT INTO provides (pkg_id, name, flags, version) VALUES (?, ?, ?, ?)",
+                "INSERT INTO provides (pkg_id, name, flags, epoch, version, release) VALUES (?, ?, ?, ?, ?, ?)",
+            )?;
+            let mut l10n_stmt = tx.prepare_cached(
+                "INSERT OR REPLACE INTO package_l10n (pkg_id, lang, summary, description) VALUES (?, ?, ?, ?)",
             )?;
 
             for package in packages {
@@ -94,17 +205,62 @@ impl PackageStore {
                     package.license,
                     package.vcs,
                     package.location_href,
+                    package.sourcerpm,
                     package.repo,
+                    package.build_time,
                 ])?;
 
                 let pkg_id = tx.last_insert_rowid();
 
                 for req in &package.requires {
-                    req_stmt.execute(params![pkg_id, req.name, req.flags, req.version])?;
+                    req_stmt.execute(params![
+                        pkg_id,
+                        req.name,
+                        req.flags,
+                        req.epoch,
+                        req.version,
+                        req.release,
+                        req.pre
+                    ])?;
                 }
 
                 for prov in &package.provides {
-                    prov_stmt.execute(params![pkg_id, prov.name, prov.flags, prov.version])?;
+                    prov_stmt.execute(params![
+                        pkg_id,
+                        prov.name,
+                        prov.flags,
+                        prov.epoch,
+                        prov.version,
+                        prov.release
+                    ])?;
+                }
+
+                for (table, deps) in [
+                    ("conflicts", &package.conflicts),
+                    ("obsoletes", &package.obsoletes),
+                ] {
+                    let mut dep_stmt = tx.prepare_cached(&format!(
+                        "INSERT INTO {table} (pkg_id, name, flags, epoch, version, release) VALUES (?, ?, ?, ?, ?, ?)"
+                    ))?;
+                    for dep in deps {
+                        dep_stmt.execute(params![
+                            pkg_id,
+                            dep.name,
+                            dep.flags,
+                            dep.epoch,
+                            dep.version,
+                            dep.release
+                        ])?;
+                    }
+                }
+
+                for l10n in &package.localized {
+                    l10n_stmt.execute(params![
+                        pkg_id,
+                        l10n.lang,
+                        l10n.summary,
+                        l10n.description
+                    ])?;
                 }
 
                 pkg_ids.push(pkg_id);
@@ -117,62 +273,51 @@ impl PackageStore {
 
     /// Get a package by pkg_id
     pub fn get_package(&self, pkg_id: i64) -> Result<Option<Package>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT pkg_id, name, epoch, version, release, arch, summary, description, license, vcs, location_href, repo
-             FROM packages WHERE pkg_id = ?",
-        )?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM packages WHERE pkg_id = ?",
+            PACKAGE_COLUMNS
+        ))?;
 
-        let package = stmt
-            .query_row([pkg_id], |row| {
-                Ok(Package {
-                    pkg_id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    epoch: row.get(2)?,
-                    version: row.get(3)?,
-                    release: row.get(4)?,
-                    arch: row.get(5)?,
-                    summary: row.get(6)?,
-                    description: row.get(7)?,
-                    license: row.get(8)?,
-                    vcs: row.get(9)?,
-                    location_href: row.get(10)?,
-                    repo: row.get(11)?,
-                    requires: Vec::new(),
-                    provides: Vec::new(),
-                })
-            })
-            .optional()?;
+        let package = stmt.query_row([pkg_id], package_from_row).optional()?;
 
         if let Some(mut pkg) = package {
             // Load requires
-            let mut req_stmt = self
-                .conn
-                .prepare("SELECT name, flags, version FROM requires WHERE pkg_id = ?")?;
+            let mut req_stmt = self.conn.prepare(
+                "SELECT name, flags, epoch, version, release, pre FROM requires WHERE pkg_id = ?",
+            )?;
             let requires = req_stmt
                 .query_map([pkg_id], |row| {
                     Ok(Dependency {
                         name: row.get(0)?,
                         flags: row.get(1)?,
-                        version: row.get(2)?,
+                        epoch: row.get(2)?,
+                        version: row.get(3)?,
+                        release: row.get(4)?,
+                        pre: row.get(5)?,
                     })
                 })?
                 .collect::<std::result::Result<Vec<_>, _>>()?;
             pkg.requires = requires;
 
-            // Load provides
-            let mut prov_stmt = self
-                .conn
-                .prepare("SELECT name, flags, version FROM provides WHERE pkg_id = ?")?;
-            let provides = prov_stmt
+            pkg.provides = self.load_dependencies("provides", pkg_id)?;
+            pkg.conflicts = self.load_dependencies("conflicts", pkg_id)?;
+            pkg.obsoletes = self.load_dependencies("obsoletes", pkg_id)?;
+
+            // Load localized summaries/descriptions
+            let mut l10n_stmt = self.conn.prepare(
+                "SELECT lang, summary, description FROM package_l10n WHERE pkg_id = ? ORDER BY lang",
+            )?;
+            pkg.localized = l10n_stmt
                 .query_map([pkg_id], |row| {
-                    Ok(Dependency {
-                        name: row.get(0)?,
-                        flags: row.get(1)?,
-                        version: row.get(2)?,
+                    Ok(LocalizedText {
+                        lang: row.get(0)?,
+                        summary: row.get(1)?,
+                        description: row.get(2)?,
                     })
                 })?
                 .collect::<std::result::Result<Vec<_>, _>>()?;
-            pkg.provides = provides;
+
+            pkg.appstream = self.get_appstream(&pkg.name, &pkg.repo)?;
 
             Ok(Some(pkg))
         } else {
@@ -180,6 +325,96 @@ impl PackageStore {
         }
     }
 
+    /// Load a package's provides, conflicts or obsoletes
+    fn load_dependencies(&self, table: &str, pkg_id: i64) -> Result<Vec<Dependency>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT name, flags, epoch, version, release FROM {} WHERE pkg_id = ?",
+            table
+        ))?;
+        let deps = stmt
+            .query_map([pkg_id], |row| {
+                Ok(Dependency {
+                    name: row.get(0)?,
+                    flags: row.get(1)?,
+                    epoch: row.get(2)?,
+                    version: row.get(3)?,
+                    release: row.get(4)?,
+                    pre: false,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(deps)
+    }
+
+    /// Get the AppStream data indexed for a package name in a repository
+    fn get_appstream(&self, name: &str, repo: &str) -> Result<Option<AppstreamInfo>> {
+        let split = |list: String| -> Vec<String> {
+            list.split(';')
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect()
+        };
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT description, categories, keywords FROM appstream WHERE repo = ? AND pkgname = ?",
+        )?;
+        let info = stmt
+            .query_row(params![repo, name], |row| {
+                Ok(AppstreamInfo {
+                    description: row.get(0)?,
+                    categories: split(row.get(1)?),
+                    keywords: split(row.get(2)?),
+                })
+            })
+            .optional()?;
+        Ok(info)
+    }
+
+    /// Store AppStream data for packages of a repository, replacing earlier data.
+    /// Embeddings of the affected packages are dropped so the next embedding
+    /// build picks up the enriched text. Returns the number of packages matched.
+    pub fn insert_appstream_batch(
+        &mut self,
+        repo: &str,
+        entries: &[(String, AppstreamInfo)],
+    ) -> Result<usize> {
+        let tx = self.conn.savepoint()?;
+        let vec_tables = vector_tables(&tx);
+        let mut matched = 0;
+
+        {
+            let mut id_stmt =
+                tx.prepare_cached("SELECT pkg_id FROM packages WHERE repo = ? AND name = ?")?;
+            let mut insert_stmt = tx.prepare_cached(
+                "INSERT OR REPLACE INTO appstream (repo, pkgname, description, categories, keywords)
+                 VALUES (?, ?, ?, ?, ?)",
+            )?;
+
+            for (name, info) in entries {
+                let pkg_ids: Vec<i64> = id_stmt
+                    .query_map(params![repo, name], |row| row.get(0))?
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                if pkg_ids.is_empty() {
+                    continue;
+                }
+
+                insert_stmt.execute(params![
+                    repo,
+                    name,
+                    info.description,
+                    info.categories.join(";"),
+                    info.keywords.join(";"),
+                ])?;
+                for pkg_id in &pkg_ids {
+                    delete_embeddings_in_tx(&tx, &vec_tables, *pkg_id);
+                }
+                matched += pkg_ids.len();
+            }
+        }
+
+        tx.commit()?;
+        Ok(matched)
+    }
+
     /// Search packages by name
     pub fn search_by_name(&self, name: &str) -> Result<Vec<Package>> {
         // First try exact match
@@ -212,61 +447,113 @@ impl PackageStore {
         Ok(packages)
     }
 
+    /// All packages with exactly this name (every version, arch and repo)
+    pub fn get_packages_by_name(&self, name: &str) -> Result<Vec<Package>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT pkg_id FROM packages WHERE name = ?")?;
+        let pkg_ids: Vec<i64> = stmt
+            .query_map([name], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut packages = Vec::new();
+        for pkg_id in pkg_ids {
+            if let Some(pkg) = self.get_package(pkg_id)? {
+                packages.push(pkg);
+            }
+        }
+        Ok(packages)
+    }
+
+    /// Packages with any of `names`, without dependencies, translations or
+    /// AppStream data (load those with [`get_package`](Self::get_package))
+    pub fn get_bare_packages_by_names(&self, names: &[String]) -> Result<Vec<Package>> {
+        let mut packages = Vec::new();
+        // Stay well under SQLite's bound-parameter limit
+        for chunk in names.chunks(500) {
+            let placeholders = vec!["?"; chunk.len()].join(", ");
+            let mut stmt = self.conn.prepare(&format!(
+                "SELECT {} FROM packages WHERE name IN ({})",
+                PACKAGE_COLUMNS, placeholders
+            ))?;
+            let rows = stmt.query_map(rusqlite::params_from_iter(chunk), package_from_row)?;
+            for row in rows {
+                packages.push(row?);
+            }
+        }
+        Ok(packages)
+    }
+
     /// Search packages by name with relevance scoring
-    /// Returns (pkg_id, score) pairs ordered by relevance
-    pub fn search_by_name_ranked(&self, query: &str) -> Result<Vec<(i64, f32)>> {
+    /// Returns (pkg_id, score) pairs ordered by relevance; each match tier
+    /// scores from `weights`. A package matching several tiers is scored by
+    /// the first in the order below (so a description match wins over a
+    /// provides match, as before the scores were configurable).
+    pub fn search_by_name_ranked(
+        &self,
+        query: &str,
+        weights: &StructuredWeights,
+    ) -> Result<Vec<(i64, f32)>> {
         let lower_query = query.to_lowercase();
         let mut results: Vec<(i64, f32)> = Vec::new();
-        let mut seen_ids = std::collections::HashSet::new();
+        let mut seen_ids: HashSet<i64> = HashSet::new();
+        let mut add = |ids: Vec<i64>, score: f32| {
+            for id in ids {
+                if seen_ids.insert(id) {
+                    results.push((id, score));
+                }
+            }
+        };
 
-        // 1. Exact name match (highest score: 1.0)
+        // 1. Exact name match
         {
             let mut stmt = self
                 .conn
-                .prepare("SELECT pkg_id FROM packages WHERE LOWER(name) = LOWER(?)")?;
+                .prepare("SELECT pkg_id FROM packages WHERE name = ? COLLATE NOCASE")?;
             let ids: Vec<i64> = stmt
                 .query_map([&lower_query], |row| row.get(0))?
                 .collect::<std::result::Result<Vec<_>, _>>()?;
-            for id in ids {
-                if seen_ids.insert(id) {
-                    results.push((id, 1.0));
-                }
-            }
+            add(ids, weights.exact);
         }
 
-        // 2. Prefix match (score: 0.85)
+        // 2. Prefix match; LIKE is case-insensitive and uses the NOCASE index
         {
             let mut stmt = self
                 .conn
-                .prepare("SELECT pkg_id FROM packages WHERE LOWER(name) LIKE ? LIMIT 50")?;
+                .prepare("SELECT pkg_id FROM packages WHERE name LIKE ? LIMIT 50")?;
             let pattern = format!("{}%", lower_query);
             let ids: Vec<i64> = stmt
                 .query_map([&pattern], |row| row.get(0))?
                 .collect::<std::result::Result<Vec<_>, _>>()?;
-            for id in ids {
-                if seen_ids.insert(id) {
-                    results.push((id, 0.85));
-                }
-            }
+            add(ids, weights.prefix);
         }
 
-        // 3. Contains match on name (score: 0.7)
+        // 3. Contains match on name. The trigram index answers this
+        //    without a table scan, but only for terms of at least 3 characters.
         {
-            let mut stmt = self
-                .conn
-                .prepare("SELECT pkg_id FROM packages WHERE LOWER(name) LIKE ? LIMIT 50")?;
-            let pattern = format!("%{}%", lower_query);
-            let ids: Vec<i64> = stmt
-                .query_map([&pattern], |row| row.get(0))?
-                .collect::<std::result::Result<Vec<_>, _>>()?;
-            for id in ids {
-                if seen_ids.insert(id) {
-                    results.push((id, 0.7));
-                }
-            }
+            let ids: Vec<i64> = if self.name_trigrams && query.chars().count() >= 3 {
+                let mut stmt = self.conn.prepare(
+                    "SELECT rowid FROM package_name_trigrams WHERE package_name_trigrams MATCH ? LIMIT 50",
+                )?;
+                let phrase = format!("\"{}\"", query.replace('"', "\"\""));
+                let ids = stmt
+                    .query_map([&phrase], |row| row.get(0))?
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                ids
+            } else {
+                let mut stmt = self
+                    .conn
+                    .prepare("SELECT pkg_id FROM packages WHERE LOWER(name) LIKE ? LIMIT 50")?;
+                let pattern = format!("%{}%", lower_query);
+                let ids = stmt
+                    .query_map([&pattern], |row| row.get(0))?
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                ids
+            };
+            add(ids, weights.contains);
         }
 
-        // 4. Summary keyword match (score: 0.5)
+        // 4. Summary keyword match
         {
             let mut stmt = self
                 .conn
@@ -275,14 +562,10 @@ impl PackageStore {
             let ids: Vec<i64> = stmt
                 .query_map([&pattern], |row| row.get(0))?
                 .collect::<std::result::Result<Vec<_>, _>>()?;
-            for id in ids {
-                if seen_ids.insert(id) {
-                    results.push((id, 0.5));
-                }
-            }
+            add(ids, weights.summary);
         }
 
-        // 5. Description keyword match (score: 0.35)
+        // 5. Description keyword match
         {
             let mut stmt = self
                 .conn
@@ -291,14 +574,10 @@ impl PackageStore {
             let ids: Vec<i64> = stmt
                 .query_map([&pattern], |row| row.get(0))?
                 .collect::<std::result::Result<Vec<_>, _>>()?;
-            for id in ids {
-                if seen_ids.insert(id) {
-                    results.push((id, 0.35));
-                }
-            }
+            add(ids, weights.description);
         }
 
-        // 6. Provides capability match (score: 0.45)
+        // 6. Provides capability match
         {
             let mut stmt = self.conn.prepare(
                 "SELECT DISTINCT pkg_id FROM provides WHERE LOWER(name) LIKE ? LIMIT 50",
@@ -307,11 +586,7 @@ impl PackageStore {
             let ids: Vec<i64> = stmt
                 .query_map([&pattern], |row| row.get(0))?
                 .collect::<std::result::Result<Vec<_>, _>>()?;
-            for id in ids {
-                if seen_ids.insert(id) {
-                    results.push((id, 0.45));
-                }
-            }
+            add(ids, weights.provides);
         }
 
         // Sort by score descending
@@ -337,18 +612,94 @@ impl PackageStore {
         Ok(count as usize)
     }
 
-    /// Get package IDs filtered by arch and/or repos (for pre-filtering vector search)
-    pub fn get_filtered_pkg_ids(&self, arch: Option<&str>, repos: &[String]) -> Result<Vec<i64>> {
+    /// Schema version recorded in the metadata table
+    pub fn schema_version(&self) -> Result<i32> {
+        Schema::get_version(&self.conn)
+    }
+
+    /// Version of the loaded sqlite-vec extension, or None if it is not loaded
+    pub fn sqlite_vec_version(&self) -> Option<String> {
+        self.conn
+            .query_row("SELECT vec_version()", [], |row| row.get(0))
+            .ok()
+    }
+
+    /// Whether any vec table holds at least one embedding.
+    /// Tables that cannot be read (e.g. sqlite-vec not loaded) count as empty.
+    pub fn has_embeddings(&self) -> bool {
+        vector_tables(&self.conn).iter().any(|table| {
+            self.conn
+                .query_row(&format!("SELECT 1 FROM {} LIMIT 1", table), [], |_| Ok(()))
+                .is_ok()
+        })
+    }
+
+    /// pkg_ids that have an embedding in any vec table.
+    /// Tables that cannot be read (e.g. sqlite-vec not loaded) are skipped.
+    pub fn embedded_pkg_ids(&self) -> HashSet<i64> {
+        let mut ids = HashSet::new();
+        for table in vector_tables(&self.conn) {
+            let Ok(mut stmt) = self.conn.prepare(&format!("SELECT pkg_id FROM {}", table)) else {
+                continue;
+            };
+            if let Ok(rows) = stmt.query_map([], |row| row.get::<_, i64>(0)) {
+                ids.extend(rows.flatten());
+            };
+        }
+        ids
+    }
+
+    /// Package IDs of a single repository
+    pub fn pkg_ids_by_repo(&self, repo: &str) -> Result<Vec<i64>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT pkg_id FROM packages WHERE repo = ?")?;
+        let pkg_ids = stmt
+            .query_map([repo], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(pkg_ids)
+    }
+
+    /// Repository of each of the given packages (unknown IDs are left out)
+    pub fn get_repos(&self, pkg_ids: &[i64]) -> Result<HashMap<i64, String>> {
+        let mut repos = HashMap::with_capacity(pkg_ids.len());
+        // Stay well under SQLite's bound-parameter limit
+        for chunk in pkg_ids.chunks(500) {
+            let placeholders = vec!["?"; chunk.len()].join(", ");
+            let mut stmt = self.conn.prepare(&format!(
+                "SELECT pkg_id, repo FROM packages WHERE pkg_id IN ({})",
+                placeholders
+            ))?;
+            let rows = stmt.query_map(rusqlite::params_from_iter(chunk), |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?;
+            for row in rows {
+                let (pkg_id, repo) = row?;
+                repos.insert(pkg_id, repo);
+            }
+        }
+        Ok(repos)
+    }
+
+    /// Get package IDs filtered by arches and/or repos (for pre-filtering vector search)
+    pub fn get_filtered_pkg_ids(&self, arches: &[String], repos: &[String]) -> Result<Vec<i64>> {
         let mut conditions = Vec::new();
         let mut bind_values: Vec<String> = Vec::new();
 
-        if let Some(a) = arch {
-            conditions.push("arch = ?".to_string());
-            bind_values.push(a.to_string());
+        if !arches.is_empty() {
+            let placeholders: Vec<&str> = arches.iter().map(|_| "?").collect();
+            conditions.push(format!(
+                "arch COLLATE NOCASE IN ({})",
+                placeholders.join(", ")
+            ));
+            bind_values.extend(arches.iter().cloned());
         }
         if !repos.is_empty() {
             let placeholders: Vec<&str> = repos.iter().map(|_| "?").collect();
-            conditions.push(format!("repo IN ({})", placeholders.join(", ")));
+            conditions.push(format!(
+                "repo COLLATE NOCASE IN ({})",
+                placeholders.join(", ")
+            ));
             bind_values.extend(repos.iter().cloned());
         }
 
@@ -402,40 +753,76 @@ impl PackageStore {
 
     /// Find a package by name, arch, and repo (for incremental updates)
     pub fn find_package(&self, name: &str, arch: &str, repo: &str) -> Result<Option<Package>> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT pkg_id FROM packages WHERE name = ? AND arch = ? AND repo = ?")?;
-
-        let pkg_id: Option<i64> = stmt
-            .query_row([name, arch, repo], |row| row.get(0))
-            .optional()?;
-
-        if let Some(id) = pkg_id {
+        if let Some(id) = self.find_pkg_id(name, arch, repo)? {
             self.get_package(id)
         } else {
             Ok(None)
         }
     }
 
+    /// ID of the package with this name and arch in `repo`
+    pub fn find_pkg_id(&self, name: &str, arch: &str, repo: &str) -> Result<Option<i64>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT pkg_id FROM packages WHERE name = ? AND arch = ? AND repo = ?",
+        )?;
+        Ok(stmt
+            .query_row([name, arch, repo], |row| row.get(0))
+            .optional()?)
+    }
+
+    /// Whether `repo` was indexed before some dependency data was stored (see
+    /// [`Schema::request_dependency_refresh`](crate::storage::schema::Schema::request_dependency_refresh))
+    pub fn dependency_refresh_pending(&self, repo: &str) -> Result<bool> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT 1 FROM repo_dependency_refresh WHERE repo = ?",
+                [repo],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some())
+    }
+
+    /// Rewrite the dependencies of already stored packages (keeping their
+    /// IDs, files and embeddings) and clear `repo`'s pending refresh
+    pub fn refresh_dependencies(&mut self, repo: &str, packages: &[(i64, Package)]) -> Result<()> {
+        let tx = self.conn.savepoint()?;
+        for (pkg_id, package) in packages {
+            for table in ["requires", "provides", "conflicts", "obsoletes"] {
+                tx.execute(&format!("DELETE FROM {} WHERE pkg_id = ?", table), [pkg_id])?;
+            }
+            Self::insert_dependencies_in_tx(&tx, *pkg_id, package)?;
+        }
+        tx.execute("DELETE FROM repo_dependency_refresh WHERE repo = ?", [repo])?;
+        tx.commit()?;
+        Ok(())
+    }
+
     /// Update an existing package (delete old, insert new)
     #[allow(dead_code)]
     pub fn update_package(&mut self, old_pkg_id: i64, new_package: &Package) -> Result<i64> {
-        let tx = self.conn.transaction()?;
-        let new_pkg_id = Self::update_package_in_tx(&tx, old_pkg_id, new_package)?;
+        let tx = self.conn.savepoint()?;
+        let new_pkg_id =
+            Self::update_package_in_tx(&tx, &vector_tables(&tx), old_pkg_id, new_package)?;
         tx.commit()?;
         Ok(new_pkg_id)
     }
 
     /// Update a package within an existing transaction
     fn update_package_in_tx(
-        tx: &rusqlite::Transaction,
+        tx: &Connection,
+        vec_tables: &[String],
         old_pkg_id: i64,
         new_package: &Package,
     ) -> Result<i64> {
         tx.execute("DELETE FROM requires WHERE pkg_id = ?", [old_pkg_id])?;
         tx.execute("DELETE FROM provides WHERE pkg_id = ?", [old_pkg_id])?;
+        tx.execute("DELETE FROM conflicts WHERE pkg_id = ?", [old_pkg_id])?;
+        tx.execute("DELETE FROM obsoletes WHERE pkg_id = ?", [old_pkg_id])?;
         tx.execute("DELETE FROM files WHERE pkg_id = ?", [old_pkg_id])?;
-        let _ = tx.execute("DELETE FROM embeddings WHERE pkg_id = ?", [old_pkg_id]);
+        tx.execute("DELETE FROM package_l10n WHERE pkg_id = ?", [old_pkg_id])?;
+        delete_embeddings_in_tx(tx, vec_tables, old_pkg_id);
         tx.execute("DELETE FROM packages WHERE pkg_id = ?", [old_pkg_id])?;
 
         let pkg_id = Self::insert_package_in_tx(tx, new_package)?;
@@ -449,7 +836,8 @@ impl PackageStore {
         updates: &[(i64, Package)],
         deletes: &[(String, String, String)],
     ) -> Result<(usize, usize, usize)> {
-        let tx = self.conn.transaction()?;
+        let tx = self.conn.savepoint()?;
+        let vec_tables = vector_tables(&tx);
 
         // Batch inserts
         for package in inserts {
@@ -458,7 +846,7 @@ impl PackageStore {
 
         // Batch updates
         for (old_pkg_id, new_package) in updates {
-            Self::update_package_in_tx(&tx, *old_pkg_id, new_package)?;
+            Self::update_package_in_tx(&tx, &vec_tables, *old_pkg_id, new_package)?;
         }
 
         // Batch deletes
@@ -474,8 +862,11 @@ impl PackageStore {
             if let Some(id) = pkg_id {
                 tx.execute("DELETE FROM requires WHERE pkg_id = ?", [id])?;
                 tx.execute("DELETE FROM provides WHERE pkg_id = ?", [id])?;
+                tx.execute("DELETE FROM conflicts WHERE pkg_id = ?", [id])?;
+                tx.execute("DELETE FROM obsoletes WHERE pkg_id = ?", [id])?;
                 tx.execute("DELETE FROM files WHERE pkg_id = ?", [id])?;
-                let _ = tx.execute("DELETE FROM embeddings WHERE pkg_id = ?", [id]);
+                tx.execute("DELETE FROM package_l10n WHERE pkg_id = ?", [id])?;
+                delete_embeddings_in_tx(&tx, &vec_tables, id);
                 tx.execute("DELETE FROM packages WHERE pkg_id = ?", [id])?;
             }
         }
@@ -510,17 +901,336 @@ impl PackageStore {
         Ok(packages)
     }
 
+    /// Compare two repositories, keyed by (name, arch).
+    /// When a repository contains several versions of the same (name, arch),
+    /// only the highest version is considered.
+    pub fn repo_diff(&self, repo_a: &str, repo_b: &str) -> Result<RepoDiff> {
+        let a = self.latest_versions_in_repo(repo_a)?;
+        let b = self.latest_versions_in_repo(repo_b)?;
+
+        let mut diff = RepoDiff::default();
+
+        for (key, ver_a) in &a {
+            match b.get(key) {
+                None => diff.only_in_a.push(RepoDiffEntry {
+                    name: key.0.clone(),
+                    arch: key.1.clone(),
+                    version: format_evr(ver_a),
+                }),
+                Some(ver_b) if ver_a.cmp(ver_b) != Ordering::Equal => {
+                    diff.changed.push(RepoVersionChange {
+                        name: key.0.clone(),
+                        arch: key.1.clone(),
+                        version_a: format_evr(ver_a),
+                        version_b: format_evr(ver_b),
+                        newer: if ver_a > ver_b {
+                            repo_a.to_string()
+                        } else {
+                            repo_b.to_string()
+                        },
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+
+        for (key, ver_b) in &b {
+            if !a.contains_key(key) {
+                diff.only_in_b.push(RepoDiffEntry {
+                    name: key.0.clone(),
+                    arch: key.1.clone(),
+                    version: format_evr(ver_b),
+                });
+            }
+        }
+
+        Ok(diff)
+    }
+
+    /// Highest version of each (name, arch) in a repository
+    fn latest_versions_in_repo(
+        &self,
+        repo: &str,
+    ) -> Result<BTreeMap<(String, String), RpmVersion>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, arch, epoch, version, release FROM packages WHERE repo = ?")?;
+
+        let rows = stmt.query_map([repo], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                RpmVersion::new(
+                    row.get::<_, Option<i64>>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                ),
+            ))
+        })?;
+
+        let mut latest: BTreeMap<(String, String), RpmVersion> = BTreeMap::new();
+        for row in rows {
+            let (name, arch, ver) = row?;
+            match latest.get(&(name.clone(), arch.clone())) {
+                Some(existing) if *existing >= ver => {}
+                _ => {
+                    latest.insert((name, arch), ver);
+                }
+            }
+        }
+
+        Ok(latest)
+    }
+
+    /// Capabilities provided by packages in more than one distinct repository,
+    /// together with the providing packages (sorted by capability, repo, name)
+    pub fn duplicate_provides(&self) -> Result<Vec<DuplicateProvide>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT pv.name, p.repo, p.name, p.epoch, p.version, p.release, p.arch
+             FROM provides pv
+             JOIN packages p ON p.pkg_id = pv.pkg_id
+             WHERE pv.name IN (
+                 SELECT pv2.name FROM provides pv2
+                 JOIN packages p2 ON p2.pkg_id = pv2.pkg_id
+                 GROUP BY pv2.name
+                 HAVING COUNT(DISTINCT p2.repo) > 1
+             )
+             ORDER BY pv.name, p.repo, p.name, p.arch",
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            let version = RpmVersion::new(
+                row.get::<_, Option<i64>>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+            );
+            Ok((
+                row.get::<_, String>(0)?,
+                CapabilityProvider {
+                    repo: row.get(1)?,
+                    nevra: format!(
+                        "{}-{}.{}",
+                        row.get::<_, String>(2)?,
+                        format_evr(&version),
+                        row.get::<_, String>(6)?
+                    ),
+                },
+            ))
+        })?;
+
+        let mut duplicates: Vec<DuplicateProvide> = Vec::new();
+        for row in rows {
+            let (capability, provider) = row?;
+            match duplicates.last_mut() {
+                Some(last) if last.capability == capability => last.providers.push(provider),
+                _ => duplicates.push(DuplicateProvide {
+                    capability,
+                    providers: vec![provider],
+                }),
+            }
+        }
+
+        Ok(duplicates)
+    }
+
+    /// Regular files of a repository owned by packages with different names.
+    /// Directories and ghost files are excluded, and so are other versions or
+    /// arches of the same package. Owners are listed by name, then arch.
+    pub fn conflicting_files(&self, repo: &str) -> Result<Vec<FileConflict>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT d.path, f.name, p.name, p.epoch, p.version, p.release, p.arch
+             FROM files f
+             JOIN packages p ON p.pkg_id = f.pkg_id
+             JOIN directories d ON d.dir_id = f.dir_id
+             WHERE p.repo = ?1 AND f.file_type = 0 AND (f.dir_id, f.name) IN (
+                 SELECT f2.dir_id, f2.name FROM files f2
+                 JOIN packages p2 ON p2.pkg_id = f2.pkg_id
+                 WHERE p2.repo = ?1 AND f2.file_type = 0
+                 GROUP BY f2.dir_id, f2.name
+                 HAVING COUNT(DISTINCT p2.name) > 1
+             )
+             ORDER BY d.path, f.name, p.name, p.arch",
+        )?;
+
+        let rows = stmt.query_map([repo], |row| {
+            let version = RpmVersion::new(
+                row.get::<_, Option<i64>>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+            );
+            Ok((
+                format!("{}/{}", row.get::<_, String>(0)?, row.get::<_, String>(1)?),
+                CapabilityProvider {
+                    repo: repo.to_string(),
+                    nevra: format!(
+                        "{}-{}.{}",
+                        row.get::<_, String>(2)?,
+                        format_evr(&version),
+                        row.get::<_, String>(6)?
+                    ),
+                },
+            ))
+        })?;
+
+        let mut conflicts: Vec<FileConflict> = Vec::new();
+        for row in rows {
+            let (path, owner) = row?;
+            match conflicts.last_mut() {
+                Some(last) if last.path == path => last.owners.push(owner),
+                _ => conflicts.push(FileConflict {
+                    path,
+                    owners: vec![owner],
+                }),
+            }
+        }
+
+        Ok(conflicts)
+    }
+
+    /// Packages indexed with more than one version of the same (name, arch),
+    /// optionally restricted to the given arches and repositories.
+    /// Versions within a group are listed oldest first.
+    pub fn find_duplicate_versions(
+        &self,
+        arches: &[String],
+        repos: &[String],
+    ) -> Result<Vec<DuplicateVersions>> {
+        let mut conditions = Vec::new();
+        let mut bind_values: Vec<String> = Vec::new();
+
+        if !arches.is_empty() {
+            let placeholders: Vec<&str> = arches.iter().map(|_| "?").collect();
+            conditions.push(format!(
+                "arch COLLATE NOCASE IN ({})",
+                placeholders.join(", ")
+            ));
+            bind_values.extend(arches.iter().cloned());
+        }
+        if !repos.is_empty() {
+            let placeholders: Vec<&str> = repos.iter().map(|_| "?").collect();
+            conditions.push(format!(
+                "repo COLLATE NOCASE IN ({})",
+                placeholders.join(", ")
+            ));
+            bind_values.extend(repos.iter().cloned());
+        }
+
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        // Only (name, arch) groups with several distinct EVR strings are
+        // loaded; RPM version comparison below drops ones that still compare
+        // equal (e.g. `1.0` and `1.00`)
+        let sql = format!(
+            "WITH filtered AS (
+                 SELECT name, arch, repo, epoch, version, release FROM packages {}
+             )
+             SELECT name, arch, repo, epoch, version, release FROM filtered
+             WHERE (name, arch) IN (
+                 SELECT name, arch FROM filtered GROUP BY name, arch
+                 HAVING COUNT(DISTINCT COALESCE(epoch, 0) || ':' || version || '-' || release) > 1
+             )
+             ORDER BY name, arch",
+            where_clause
+        );
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let params: Vec<&dyn rusqlite::types::ToSql> = bind_values
+            .iter()
+            .map(|v| v as &dyn rusqlite::types::ToSql)
+            .collect();
+        let rows = stmt.query_map(params.as_slice(), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                RpmVersion::new(
+                    row.get::<_, Option<i64>>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, String>(5)?,
+                ),
+            ))
+        })?;
+
+        let rows = rows.collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut duplicates = Vec::new();
+        for group in rows.chunk_by(|a, b| a.0 == b.0 && a.1 == b.1) {
+            let (name, arch) = (group[0].0.clone(), group[0].1.clone());
+            let mut versions: Vec<(RpmVersion, String)> = group
+                .iter()
+                .map(|(_, _, repo, version)| (version.clone(), repo.clone()))
+                .collect();
+            versions.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+            let distinct = versions
+                .windows(2)
+                .filter(|w| w[0].0.cmp(&w[1].0) != Ordering::Equal)
+                .count()
+                + 1;
+            if distinct < 2 {
+                continue;
+            }
+            let packages = versions
+                .iter()
+                .map(|(version, repo)| CapabilityProvider {
+                    repo: repo.clone(),
+                    nevra: format!("{}-{}.{}", name, format_evr(version), arch),
+                })
+                .collect();
+            duplicates.push(DuplicateVersions {
+                name,
+                arch,
+                packages,
+            });
+        }
+
+        Ok(duplicates)
+    }
+
+    /// IDs of packages that may provide `capability`: an explicit provide of that
+    /// name, the package name itself, or, for paths, an indexed file.
+    /// Version constraints are left to the caller.
+    pub fn find_providers(&self, capability: &str) -> Result<Vec<i64>> {
+        let _timer = SlowQueryTimer::new(self.slow_query_ms, || {
+            format!("find_providers(capability={:?})", capability)
+        });
+
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT pkg_id FROM provides WHERE name = ?1
+             UNION
+             SELECT pkg_id FROM packages WHERE name = ?1",
+        )?;
+        let mut pkg_ids: Vec<i64> = stmt
+            .query_map([capability], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        if capability.starts_with('/') {
+            for (pkg_id, _, _) in self.search_by_file_path(capability)? {
+                if !pkg_ids.contains(&pkg_id) {
+                    pkg_ids.push(pkg_id);
+                }
+            }
+        }
+
+        Ok(pkg_ids)
+    }
+
     /// Delete a specific package by name, arch, and repo
     #[allow(dead_code)]
     pub fn delete_package(&mut self, name: &str, arch: &str, repo: &str) -> Result<bool> {
         if let Some(pkg) = self.find_package(name, arch, repo)? {
             let pkg_id = pkg.pkg_id.unwrap();
 
-            let tx = self.conn.transaction()?;
+            let tx = self.conn.savepoint()?;
             tx.execute("DELETE FROM requires WHERE pkg_id = ?", [pkg_id])?;
             tx.execute("DELETE FROM provides WHERE pkg_id = ?", [pkg_id])?;
+            tx.execute("DELETE FROM conflicts WHERE pkg_id = ?", [pkg_id])?;
+            tx.execute("DELETE FROM obsoletes WHERE pkg_id = ?", [pkg_id])?;
             tx.execute("DELETE FROM files WHERE pkg_id = ?", [pkg_id])?;
-            let _ = tx.execute("DELETE FROM embeddings WHERE pkg_id = ?", [pkg_id]);
+            tx.execute("DELETE FROM package_l10n WHERE pkg_id = ?", [pkg_id])?;
+            delete_embeddings_in_tx(&tx, &vector_tables(&tx), pkg_id);
             tx.execute("DELETE FROM packages WHERE pkg_id = ?", [pkg_id])?;
             tx.commit()?;
 
@@ -530,9 +1240,16 @@ impl PackageStore {
         }
     }
 
-    /// Delete all packages from a repository
+    /// Rebuild indexes and the trigram name index (see [`Schema::reindex`])
+    pub fn reindex(&mut self) -> Result<()> {
+        Schema::reindex(&self.conn)?;
+        self.name_trigrams = Schema::has_name_trigrams(&self.conn);
+        Ok(())
+    }
+
+    /// Delete all packages from a repository, along with its tags
     pub fn delete_repository(&mut self, repo: &str) -> Result<usize> {
-        let tx = self.conn.transaction()?;
+        let tx = self.conn.savepoint()?;
 
         // Get pkg_ids for this repo
         let mut stmt = tx.prepare("SELECT pkg_id FROM packages WHERE repo = ?")?;
@@ -544,15 +1261,33 @@ impl PackageStore {
         drop(stmt);
 
         // Delete related data
+        let vec_tables = vector_tables(&tx);
         for pkg_id in &pkg_ids {
             tx.execute("DELETE FROM requires WHERE pkg_id = ?", [pkg_id])?;
             tx.execute("DELETE FROM provides WHERE pkg_id = ?", [pkg_id])?;
+            tx.execute("DELETE FROM conflicts WHERE pkg_id = ?", [pkg_id])?;
+            tx.execute("DELETE FROM obsoletes WHERE pkg_id = ?", [pkg_id])?;
             tx.execute("DELETE FROM files WHERE pkg_id = ?", [pkg_id])?;
-            let _ = tx.execute("DELETE FROM embeddings WHERE pkg_id = ?", [pkg_id]);
+            tx.execute("DELETE FROM package_l10n WHERE pkg_id = ?", [pkg_id])?;
+            delete_embeddings_in_tx(&tx, &vec_tables, *pkg_id);
         }
 
         // Delete packages
         let deleted = tx.execute("DELETE FROM packages WHERE repo = ?", [repo])?;
+        tx.execute("DELETE FROM repo_embedding_models WHERE repo = ?", [repo])?;
+        tx.execute("DELETE FROM appstream WHERE repo = ?", [repo])?;
+        tx.execute("DELETE FROM repo_dependency_refresh WHERE repo = ?", [repo])?;
+        // Tags are recorded by sync, whose tables may not exist yet
+        let has_tags = tx
+            .query_row(
+                "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'repo_tags'",
+                [],
+                |_| Ok(()),
+            )
+            .is_ok();
+        if has_tags {
+            tx.execute("DELETE FROM repo_tags WHERE repo_name = ?", [repo])?;
+        }
 
         tx.commit()?;
         Ok(deleted)
@@ -596,7 +1331,7 @@ impl PackageStore {
     ) -> Result<usize> {
         use std::collections::HashMap;
 
-        let tx = self.conn.transaction()?;
+        let tx = self.conn.savepoint()?;
         let mut count = 0;
 
         {
@@ -651,6 +1386,10 @@ impl PackageStore {
     /// If `path` contains '/', splits into dir+name for exact lookup.
     /// Otherwise searches by filename only.
     pub fn search_by_file_path(&self, path: &str) -> Result<Vec<(i64, String, i32)>> {
+        let _timer = SlowQueryTimer::new(self.slow_query_ms, || {
+            format!("search_by_file_path(path={:?})", path)
+        });
+
         if path.contains('/') {
             let (dir_path, file_name) = if path.ends_with('/') {
                 // Directory query
@@ -671,12 +1410,7 @@ impl PackageStore {
                 .query_map(params![dir_path, file_name], |row| {
                     let dir: String = row.get(1)?;
                     let name: String = row.get(2)?;
-                    let full = if name.is_empty() {
-                        dir
-                    } else {
-                        format!("{}/{}", dir, name)
-                    };
-                    Ok((row.get(0)?, full, row.get(3)?))
+                    Ok((row.get(0)?, join_path(&dir, &name), row.get(3)?))
                 })?
                 .collect::<std::result::Result<Vec<_>, _>>()?;
 
@@ -696,8 +1430,7 @@ impl PackageStore {
                 .query_map(params![path], |row| {
                     let dir: String = row.get(1)?;
                     let name: String = row.get(2)?;
-                    let full = format!("{}/{}", dir, name);
-                    Ok((row.get(0)?, full, row.get(3)?))
+                    Ok((row.get(0)?, join_path(&dir, &name), row.get(3)?))
                 })?
                 .collect::<std::result::Result<Vec<_>, _>>()?;
 
@@ -719,12 +1452,7 @@ impl PackageStore {
             .query_map(params![pkg_id], |row| {
                 let dir: String = row.get(0)?;
                 let name: String = row.get(1)?;
-                let full = if name.is_empty() {
-                    dir
-                } else {
-                    format!("{}/{}", dir, name)
-                };
-                Ok((full, row.get(2)?))
+                Ok((join_path(&dir, &name), row.get(2)?))
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
 
@@ -732,7 +1460,6 @@ impl PackageStore {
     }
 
     /// Check if filelists have been indexed for a given repository
-    #[allow(dead_code)]
     pub fn has_filelists(&self, repo: &str) -> Result<bool> {
         let count: i64 = self.conn.query_row(
             "SELECT COUNT(*) FROM files f
@@ -766,40 +1493,78 @@ impl PackageStore {
     /// General-purpose search with multiple optional filters.
     /// All provided filters are ANDed together.
     /// Wildcards: `*` → `%`, `?` → `_`. No wildcards → contains match.
+    /// With `SortField::Version` the IDs come back by name and unlimited
+    /// (regex searches: up to [`REGEX_MAX_RESULTS`]); the caller sorts and
+    /// truncates (see `RpmSearchApi::find_each`).
     pub fn general_search(&self, filter: &FindFilter) -> Result<Vec<i64>> {
+        let _timer = SlowQueryTimer::new(self.slow_query_ms, || {
+            format!("general_search({:?})", filter)
+        });
+
         let mut conditions = Vec::new();
         let mut bind_values: Vec<String> = Vec::new();
 
         // Core filters on packages table
-        if let Some(ref name) = filter.name {
-            conditions.push("p.name LIKE ?".to_string());
-            bind_values.push(wildcard_to_like(name));
-        }
-        if let Some(ref summary) = filter.summary {
-            conditions.push("p.summary LIKE ?".to_string());
-            bind_values.push(wildcard_to_like(summary));
-        }
-        if let Some(ref description) = filter.description {
-            conditions.push("p.description LIKE ?".to_string());
-            bind_values.push(wildcard_to_like(description));
+        for (column, pattern) in [
+            ("name", &filter.name),
+            ("summary", &filter.summary),
+            ("description", &filter.description),
+        ] {
+            let Some(pattern) = pattern else { continue };
+            if column == "name" && filter.name_exact {
+                conditions.push("p.name = ?".to_string());
+                bind_values.push(pattern.clone());
+            } else if filter.regex {
+                // Reject bad patterns here rather than as an opaque SQLite error
+                Regex::new(pattern).map_err(|e| {
+                    RpmSearchError::Config(format!("Invalid regex '{}': {}", pattern, e))
+                })?;
+                conditions.push(format!("p.{} REGEXP ?", column));
+                bind_values.push(pattern.clone());
+            } else {
+                conditions.push(format!("p.{} LIKE ?", column));
+                bind_values.push(wildcard_to_like(pattern));
+            }
         }
-        if let Some(ref arch) = filter.arch {
-            conditions.push("p.arch = ?".to_string());
-            bind_values.push(arch.clone());
+        if !filter.arches.is_empty() {
+            let placeholders: Vec<&str> = filter.arches.iter().map(|_| "?").collect();
+            conditions.push(format!(
+                "p.arch COLLATE NOCASE IN ({})",
+                placeholders.join(", ")
+            ));
+            bind_values.extend(filter.arches.iter().cloned());
         }
         if !filter.repos.is_empty() {
             let placeholders: Vec<&str> = filter.repos.iter().map(|_| "?").collect();
-            conditions.push(format!("p.repo IN ({})", placeholders.join(", ")));
+            conditions.push(format!(
+                "p.repo COLLATE NOCASE IN ({})",
+                placeholders.join(", ")
+            ));
             bind_values.extend(filter.repos.iter().cloned());
         }
 
         // Subquery filters
         if let Some(ref provides) = filter.provides {
-            conditions.push(
-                "EXISTS (SELECT 1 FROM provides pv WHERE pv.pkg_id = p.pkg_id AND pv.name LIKE ?)"
-                    .to_string(),
-            );
-            bind_values.push(wildcard_to_like(provides));
+            let like_pattern = wildcard_to_like(provides);
+            if provides.starts_with('/') {
+                // A file path is provided both by packages declaring it and by
+                // packages that contain the file (as in `dnf repoquery --whatprovides`)
+                let (file_match, file_values) = file_path_condition(provides);
+                conditions.push(format!(
+                    "(EXISTS (SELECT 1 FROM provides pv WHERE pv.pkg_id = p.pkg_id AND pv.name LIKE ?) \
+                     OR p.pkg_id IN (SELECT f.pkg_id FROM files f JOIN directories d ON f.dir_id = d.dir_id \
+                     WHERE {}))",
+                    file_match
+                ));
+                bind_values.push(like_pattern);
+                bind_values.extend(file_values);
+            } else {
+                conditions.push(
+                    "EXISTS (SELECT 1 FROM provides pv WHERE pv.pkg_id = p.pkg_id AND pv.name LIKE ?)"
+                        .to_string(),
+                );
+                bind_values.push(like_pattern);
+            }
         }
         if let Some(ref requires) = filter.requires {
             conditions.push(
@@ -808,15 +1573,34 @@ impl PackageStore {
             );
             bind_values.push(wildcard_to_like(requires));
         }
+        for (table, pattern) in [
+            ("conflicts", &filter.conflicts),
+            ("obsoletes", &filter.obsoletes),
+        ] {
+            if let Some(pattern) = pattern {
+                conditions.push(format!(
+                    "EXISTS (SELECT 1 FROM {table} dp WHERE dp.pkg_id = p.pkg_id AND dp.name LIKE ?)"
+                ));
+                bind_values.push(wildcard_to_like(pattern));
+            }
+        }
         if let Some(ref file) = filter.file {
-            let like_pattern = wildcard_to_like(file);
-            // Use subquery with directory+filename join
-            conditions.push(
-                "EXISTS (SELECT 1 FROM files f JOIN directories d ON f.dir_id = d.dir_id \
-                 WHERE f.pkg_id = p.pkg_id AND (d.path || '/' || f.name) LIKE ?)"
-                    .to_string(),
-            );
-            bind_values.push(like_pattern);
+            // Uncorrelated subquery, so an exact path is looked up once by index
+            let (file_match, file_values) = file_path_condition(file);
+            let type_clause = if filter.file_type.is_some() {
+                " AND f.file_type = ?"
+            } else {
+                ""
+            };
+            conditions.push(format!(
+                "p.pkg_id IN (SELECT f.pkg_id FROM files f JOIN directories d ON f.dir_id = d.dir_id \
+                 WHERE {}{})",
+                file_match, type_clause
+            ));
+            bind_values.extend(file_values);
+            if let Some(file_type) = filter.file_type {
+                bind_values.push(file_type.as_i32().to_string());
+            }
         }
 
         if conditions.is_empty() {
@@ -824,11 +1608,37 @@ impl PackageStore {
         }
 
         let where_clause = conditions.join(" AND ");
-        let sql = format!(
-            "SELECT DISTINCT p.pkg_id FROM packages p WHERE {} ORDER BY p.name LIMIT ?",
-            where_clause
+        let dir = if filter.reverse { " DESC" } else { "" };
+        let order_by = match filter.sort {
+            // Version order is applied by the caller after loading the packages
+            SortField::Version => "p.name".to_string(),
+            SortField::Name => format!("p.name{}", dir),
+            SortField::Repo => format!("p.repo{0}, p.name{0}", dir),
+            SortField::Buildtime => format!("p.build_time{0}, p.name{0}", dir),
+        };
+        let mut sql = format!(
+            "SELECT DISTINCT p.pkg_id FROM packages p WHERE {} ORDER BY {}",
+            where_clause, order_by
         );
-        bind_values.push(filter.limit.to_string());
+        // Whether REGEX_MAX_RESULTS rather than the caller's limit bounds the
+        // query; one extra row is fetched to tell whether it cut results off
+        let mut regex_capped = false;
+        if filter.regex {
+            // Capped even for version sort; the caller applies `filter.limit`
+            // to that after sorting
+            let limit = match filter.limit {
+                n if n > 0 && n <= REGEX_MAX_RESULTS && filter.sort != SortField::Version => n,
+                _ => {
+                    regex_capped = true;
+                    REGEX_MAX_RESULTS + 1
+                }
+            };
+            sql.push_str(" LIMIT ?");
+            bind_values.push(limit.to_string());
+        } else if filter.limit > 0 && filter.sort != SortField::Version {
+            sql.push_str(" LIMIT ?");
+            bind_values.push(filter.limit.to_string());
+        }
 
         let mut stmt = self.conn.prepare(&sql)?;
 
@@ -838,9 +1648,16 @@ impl PackageStore {
             .map(|v| v as &dyn rusqlite::types::ToSql)
             .collect();
 
-        let pkg_ids: Vec<i64> = stmt
+        let mut pkg_ids: Vec<i64> = stmt
             .query_map(params.as_slice(), |row| row.get(0))?
             .collect::<std::result::Result<Vec<_>, _>>()?;
+        if regex_capped && pkg_ids.len() > REGEX_MAX_RESULTS {
+            pkg_ids.truncate(REGEX_MAX_RESULTS);
+            tracing::warn!(
+                limit = REGEX_MAX_RESULTS,
+                "Regex search matched more packages than it returns; narrow the pattern or add filters"
+            );
+        }
 
         Ok(pkg_ids)
     }
@@ -848,10 +1665,15 @@ impl PackageStore {
 
 /// Search filter for general-purpose package search.
 /// All provided fields are ANDed together.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FindFilter {
     /// Package name pattern (supports `*` and `?` wildcards)
     pub name: Option<String>,
+    /// Match `name` literally instead of as a substring/wildcard pattern
+    pub name_exact: bool,
+    /// Treat `name`, `summary` and `description` as regular expressions instead
+    /// of wildcard patterns (full scan; results capped at [`REGEX_MAX_RESULTS`])
+    pub regex: bool,
     /// Summary text pattern
     pub summary: Option<String>,
     /// Description text pattern
@@ -860,32 +1682,259 @@ pub struct FindFilter {
     pub provides: Option<String>,
     /// Requires dependency pattern
     pub requires: Option<String>,
+    /// Conflicts capability pattern
+    pub conflicts: Option<String>,
+    /// Obsoletes capability pattern
+    pub obsoletes: Option<String>,
     /// File path pattern (searches in filelists)
     pub file: Option<String>,
-    /// Exact architecture match
-    pub arch: Option<String>,
+    /// Only match `file` entries of this type (ignored without `file`)
+    pub file_type: Option<RpmFileType>,
+    /// Architecture filter (exact match, any of the given arches; empty = all arches)
+    pub arches: Vec<String>,
     /// Repository filter (multiple repos ANDed as IN clause; empty = all repos)
     pub repos: Vec<String>,
-    /// Maximum results (default 50)
+    /// Maximum results (default 50, 0 = no limit)
     pub limit: usize,
+    /// Result ordering (see [`SortField`] for the cost of `Version`)
+    pub sort: SortField,
+    /// Reverse the ordering
+    pub reverse: bool,
+}
+
+/// Result ordering for [`FindFilter`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortField {
+    /// Package name (then arch and version)
+    #[default]
+    Name,
+    /// RPM version order. SQL cannot compare RPM versions, so every match is
+    /// loaded and sorted in memory before the limit applies; expect this to be
+    /// slow and memory-hungry on broad queries over large repositories.
+    Version,
+    /// Repository, then package name
+    Repo,
+    /// Build time (packages indexed without one sort first)
+    Buildtime,
+}
+
+impl SortField {
+    /// Sort loaded packages in place (used where SQL ordering is unavailable
+    /// or was lost, e.g. after `--latest`)
+    pub fn sort_packages(self, packages: &mut [Package], reverse: bool) {
+        match self {
+            SortField::Name => packages.sort(),
+            SortField::Version => packages.sort_by(|a, b| {
+                a.to_rpm_version()
+                    .cmp(&b.to_rpm_version())
+                    .then_with(|| a.cmp(b))
+            }),
+            SortField::Repo => packages.sort_by(|a, b| a.repo.cmp(&b.repo).then_with(|| a.cmp(b))),
+            SortField::Buildtime => {
+                packages.sort_by(|a, b| a.build_time.cmp(&b.build_time).then_with(|| a.cmp(b)))
+            }
+        }
+        if reverse {
+            packages.reverse();
+        }
+    }
 }
 
 impl Default for FindFilter {
     fn default() -> Self {
         Self {
             name: None,
+            name_exact: false,
+            regex: false,
             summary: None,
             description: None,
             provides: None,
             requires: None,
+            conflicts: None,
+            obsoletes: None,
             file: None,
-            arch: None,
+            file_type: None,
+            arches: Vec::new(),
             repos: Vec::new(),
             limit: 50,
+            sort: SortField::Name,
+            reverse: false,
+        }
+    }
+}
+
+/// Names of all sqlite-vec tables (one per embedding model).
+/// Returns an empty list if none have been created yet.
+pub(crate) fn vector_tables(conn: &Connection) -> Vec<String> {
+    let mut stmt = match conn.prepare(
+        "SELECT name FROM sqlite_master
+         WHERE type = 'table' AND sql LIKE 'CREATE VIRTUAL TABLE%USING vec0%'",
+    ) {
+        Ok(stmt) => stmt,
+        Err(_) => return Vec::new(),
+    };
+    stmt.query_map([], |row| row.get(0))
+        .and_then(|rows| rows.collect())
+        .unwrap_or_default()
+}
+
+/// Release savepoint `name` if `result` is `Ok`, otherwise roll back to it
+pub(crate) fn finish_savepoint<T>(conn: &Connection, name: &str, result: Result<T>) -> Result<T> {
+    match result {
+        Ok(value) => {
+            conn.execute_batch(&format!("RELEASE {}", name))?;
+            Ok(value)
+        }
+        Err(e) => {
+            // Keep the original error; a failed rollback leaves nothing better to report
+            let _ = conn.execute_batch(&format!("ROLLBACK TO {0}; RELEASE {0}", name));
+            Err(e)
+        }
+    }
+}
+
+/// Map a row of [`PACKAGE_COLUMNS`] to a package without dependencies,
+/// translations or AppStream data
+fn package_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Package> {
+    Ok(Package {
+        pkg_id: Some(row.get(0)?),
+        name: row.get(1)?,
+        epoch: row.get(2)?,
+        version: row.get(3)?,
+        release: row.get(4)?,
+        arch: row.get(5)?,
+        summary: row.get(6)?,
+        description: row.get(7)?,
+        license: row.get(8)?,
+        vcs: row.get(9)?,
+        location_href: row.get(10)?,
+        sourcerpm: row.get(11)?,
+        build_time: row.get(13)?,
+        appstream: None,
+        localized: Vec::new(),
+        repo: row.get(12)?,
+        requires: Vec::new(),
+        provides: Vec::new(),
+        conflicts: Vec::new(),
+        obsoletes: Vec::new(),
+    })
+}
+
+/// Delete a package's embeddings from the given vec tables (see
+/// [`vector_tables`]; ignores missing tables)
+fn delete_embeddings_in_tx(conn: &Connection, tables: &[String], pkg_id: i64) {
+    for table in tables {
+        let _ = conn.execute(&format!("DELETE FROM {} WHERE pkg_id = ?", table), [pkg_id]);
+    }
+}
+
+/// Logs a warning on drop if the enclosing query exceeded the slow-query threshold.
+/// The description is only built when the warning is actually emitted.
+pub(crate) struct SlowQueryTimer<F: Fn() -> String> {
+    start: Instant,
+    threshold_ms: u64,
+    describe: F,
+}
+
+impl<F: Fn() -> String> SlowQueryTimer<F> {
+    pub(crate) fn new(threshold_ms: u64, describe: F) -> Self {
+        Self {
+            start: Instant::now(),
+            threshold_ms,
+            describe,
+        }
+    }
+}
+
+impl<F: Fn() -> String> Drop for SlowQueryTimer<F> {
+    fn drop(&mut self) {
+        if self.threshold_ms == 0 {
+            return;
+        }
+        let elapsed_ms = self.start.elapsed().as_millis() as u64;
+        if elapsed_ms > self.threshold_ms {
+            tracing::warn!(
+                elapsed_ms,
+                threshold_ms = self.threshold_ms,
+                query = %(self.describe)(),
+                "Slow query"
+            );
         }
     }
 }
 
+/// Result of comparing two repositories with [`PackageStore::repo_diff`]
+#[derive(Debug, Default, Serialize)]
+pub struct RepoDiff {
+    /// Packages present only in the first repository
+    pub only_in_a: Vec<RepoDiffEntry>,
+    /// Packages present only in the second repository
+    pub only_in_b: Vec<RepoDiffEntry>,
+    /// Packages present in both repositories with differing versions
+    pub changed: Vec<RepoVersionChange>,
+}
+
+/// A package present in only one of the compared repositories
+#[derive(Debug, Clone, Serialize)]
+pub struct RepoDiffEntry {
+    pub name: String,
+    pub arch: String,
+    pub version: String,
+}
+
+/// A package whose version differs between the compared repositories
+#[derive(Debug, Clone, Serialize)]
+pub struct RepoVersionChange {
+    pub name: String,
+    pub arch: String,
+    pub version_a: String,
+    pub version_b: String,
+    /// Name of the repository holding the newer version
+    pub newer: String,
+}
+
+/// A capability provided by packages in several repositories
+/// (see [`PackageStore::duplicate_provides`])
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateProvide {
+    pub capability: String,
+    pub providers: Vec<CapabilityProvider>,
+}
+
+/// A (name, arch) indexed with several versions
+/// (see [`PackageStore::find_duplicate_versions`])
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateVersions {
+    pub name: String,
+    pub arch: String,
+    /// Every indexed copy, oldest version first
+    pub packages: Vec<CapabilityProvider>,
+}
+
+/// A file owned by several packages (see [`PackageStore::conflicting_files`])
+#[derive(Debug, Clone, Serialize)]
+pub struct FileConflict {
+    pub path: String,
+    pub owners: Vec<CapabilityProvider>,
+}
+
+/// An indexed package identified by repository and NEVRA
+/// (a provider of a duplicated capability, one of several versions, or a file owner)
+#[derive(Debug, Clone, Serialize)]
+pub struct CapabilityProvider {
+    pub repo: String,
+    pub nevra: String,
+}
+
+/// Format an RPM version as `[epoch:]version-release`, omitting a zero epoch.
+fn format_evr(ver: &RpmVersion) -> String {
+    if ver.epoch != 0 {
+        format!("{}:{}-{}", ver.epoch, ver.version, ver.release)
+    } else {
+        format!("{}-{}", ver.version, ver.release)
+    }
+}
+
 /// Convert user wildcard pattern to SQL LIKE pattern.
 /// `*` → `%`, `?` → `_`.
 /// If no wildcards present, wraps with `%` for contains match.
@@ -902,6 +1951,75 @@ fn wildcard_to_like(pattern: &str) -> String {
     }
 }
 
+/// Register a `regexp(pattern, text)` SQL function so `text REGEXP pattern`
+/// works. The compiled pattern is cached per statement; NULL never matches.
+fn register_regexp(conn: &Connection) -> rusqlite::Result<()> {
+    type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+    conn.create_scalar_function(
+        "regexp",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let re: Arc<Regex> = ctx
+                .get_or_create_aux(0, |vr| -> std::result::Result<_, BoxError> {
+                    Ok(Regex::new(vr.as_str()?)?)
+                })?;
+            let text = ctx
+                .get_raw(1)
+                .as_str_or_null()
+                .map_err(|e| rusqlite::Error::UserFunctionError(e.into()))?;
+            Ok(text.is_some_and(|t| re.is_match(t)))
+        },
+    )
+}
+
+/// Full path of a `files` row `f` joined with its directory `d`, as built by
+/// [`join_path`]
+const FILE_PATH_SQL: &str = "CASE WHEN f.name = '' THEN d.path \
+     WHEN d.path = '/' THEN '/' || f.name \
+     ELSE d.path || '/' || f.name END";
+
+/// Condition (with its bind values) on a `files` row `f` joined with its
+/// directory `d` for a file filter. An absolute path without wildcards is
+/// looked up exactly through the directory and file name indexes, as a file
+/// or as a directory entry (also when given with a trailing `/`); anything
+/// else is a [`wildcard_to_like`] match on the full path.
+fn file_path_condition(pattern: &str) -> (String, Vec<String>) {
+    let is_literal = !pattern.contains('*') && !pattern.contains('?');
+    if !(is_literal && pattern.starts_with('/')) {
+        return (
+            format!("({}) LIKE ?", FILE_PATH_SQL),
+            vec![wildcard_to_like(pattern)],
+        );
+    }
+
+    let dir_path = match pattern.trim_end_matches('/') {
+        "" => "/",
+        trimmed => trimmed,
+    };
+    if pattern.ends_with('/') {
+        return (
+            "(d.path = ? AND f.name = '')".to_string(),
+            vec![dir_path.to_string()],
+        );
+    }
+    let (dir, name) = split_path(pattern, false);
+    (
+        "((d.path = ? AND f.name = ?) OR (d.path = ? AND f.name = ''))".to_string(),
+        vec![dir.to_string(), name.to_string(), dir_path.to_string()],
+    )
+}
+
+/// Join a directory and file name as stored in `directories`/`files`; an
+/// empty name is the directory entry itself
+fn join_path(dir: &str, name: &str) -> String {
+    match (dir, name) {
+        (_, "") => dir.to_string(),
+        ("/", _) => format!("/{}", name),
+        _ => format!("{}/{}", dir, name),
+    }
+}
+
 /// Split a file path into (directory, filename).
 /// `/usr/bin/bash` -> (`/usr/bin`, `bash`)
 /// `/etc/nginx` with is_dir=true -> (`/etc/nginx`, ``)
@@ -960,4 +2078,739 @@ mod tests {
         assert_eq!(wildcard_to_like("100%"), "%100\\%%");
         assert_eq!(wildcard_to_like("a_b"), "%a\\_b%");
     }
+
+    fn test_package(name: &str, arch: &str, version: &str, release: &str, repo: &str) -> Package {
+        Package {
+            release: release.to_string(),
+            arch: arch.to_string(),
+            repo: repo.to_string(),
+            ..crate::normalize::test_package(name, version)
+        }
+    }
+
+    #[test]
+    fn test_repo_diff() {
+        let mut store = PackageStore::new(":memory:").unwrap();
+        store
+            .insert_packages_batch(&[
+                test_package("bash", "x86_64", "5.1", "1", "staging"),
+                test_package("curl", "x86_64", "8.0", "1", "staging"),
+                test_package("zlib", "x86_64", "1.3", "1", "staging"),
+                test_package("zlib", "x86_64", "1.2", "1", "staging"),
+                test_package("bash", "x86_64", "5.2", "1", "prod"),
+                test_package("vim", "noarch", "9.0", "1", "prod"),
+                test_package("zlib", "x86_64", "1.3", "1", "prod"),
+            ])
+            .unwrap();
+
+        let diff = store.repo_diff("staging", "prod").unwrap();
+
+        assert_eq!(diff.only_in_a.len(), 1);
+        assert_eq!(diff.only_in_a[0].name, "curl");
+        assert_eq!(diff.only_in_b.len(), 1);
+        assert_eq!(diff.only_in_b[0].name, "vim");
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].name, "bash");
+        assert_eq!(diff.changed[0].version_a, "5.1-1");
+        assert_eq!(diff.changed[0].version_b, "5.2-1");
+        assert_eq!(diff.changed[0].newer, "prod");
+    }
+
+    #[test]
+    fn test_open_read_only() {
+        let temp = tempfile::NamedTempFile::new().unwrap();
+        let mut store = PackageStore::new(temp.path()).unwrap();
+        store
+            .insert_packages_batch(&[test_package("bash", "x86_64", "5.2", "1", "base")])
+            .unwrap();
+        drop(store);
+
+        let mut store = PackageStore::open_read_only(temp.path()).unwrap();
+        assert_eq!(store.count_packages().unwrap(), 1);
+        assert!(store
+            .insert_packages_batch(&[test_package("zsh", "x86_64", "5.9", "1", "base")])
+            .is_err());
+
+        // An empty file has no schema and is not initialized
+        let empty = tempfile::NamedTempFile::new().unwrap();
+        assert!(PackageStore::open_read_only(empty.path()).is_err());
+        assert_eq!(std::fs::metadata(empty.path()).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_in_transaction_rolls_back_on_error() {
+        let mut store = PackageStore::new(":memory:").unwrap();
+        let result: Result<()> = store.in_transaction(|store| {
+            let ids = store
+                .insert_packages_batch(&[test_package("bash", "x86_64", "5.2", "1", "base")])?;
+            store.insert_filelists_batch(&[(ids[0], vec![("/usr/bin/bash".to_string(), 0)])])?;
+            Err(RpmSearchError::Parse("truncated".to_string()))
+        });
+        assert!(result.is_err());
+        assert_eq!(store.count_packages().unwrap(), 0);
+        assert_eq!(store.count_files().unwrap(), 0);
+
+        store
+            .in_transaction(|store| {
+                store.insert_packages_batch(&[test_package("bash", "x86_64", "5.2", "1", "base")])
+            })
+            .unwrap();
+        assert_eq!(store.count_packages().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_general_search_sort() {
+        let mut store = PackageStore::new(":memory:").unwrap();
+        let mut old = test_package("zsh", "x86_64", "5.9", "1", "base");
+        old.build_time = Some(200);
+        let mut new = test_package("zsh", "x86_64", "5.10", "1", "updates");
+        new.build_time = Some(100);
+        let bash = test_package("bash", "x86_64", "5.2", "1", "updates");
+        store.insert_packages_batch(&[old, new, bash]).unwrap();
+
+        let names = |filter: &FindFilter| -> Vec<String> {
+            let mut packages: Vec<Package> = store
+                .general_search(filter)
+                .unwrap()
+                .into_iter()
+                .filter_map(|id| store.get_package(id).unwrap())
+                .collect();
+            if filter.sort == SortField::Version {
+                SortField::Version.sort_packages(&mut packages, filter.reverse);
+            }
+            packages
+                .iter()
+                .map(|p| format!("{}-{}", p.name, p.version))
+                .collect()
+        };
+        let filter = |sort, reverse| FindFilter {
+            name: Some("*".to_string()),
+            sort,
+            reverse,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            names(&filter(SortField::Buildtime, false)),
+            ["bash-5.2", "zsh-5.10", "zsh-5.9"]
+        );
+        assert_eq!(names(&filter(SortField::Repo, true))[2], "zsh-5.9");
+        assert_eq!(
+            names(&filter(SortField::Version, true)),
+            ["zsh-5.10", "zsh-5.9", "bash-5.2"]
+        );
+        assert_eq!(names(&filter(SortField::Name, true))[2], "bash-5.2");
+
+        // Regex with version sort: SQL must not apply the limit before sorting
+        let regex = FindFilter {
+            name: Some("^(bash|zsh)$".to_string()),
+            regex: true,
+            sort: SortField::Version,
+            reverse: true,
+            limit: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            names(&regex),
+            ["zsh-5.10", "zsh-5.9", "bash-5.2"],
+            "the caller truncates to the limit after sorting"
+        );
+    }
+
+    #[test]
+    fn test_multi_arch_filter() {
+        let mut store = PackageStore::new(":memory:").unwrap();
+        store
+            .insert_packages_batch(&[
+                test_package("bash", "x86_64", "5.2", "1", "base"),
+                test_package("bash", "aarch64", "5.2", "1", "base"),
+                test_package("bash", "armv7l", "5.2", "1", "base"),
+                test_package("bash-doc", "noarch", "5.2", "1", "base"),
+            ])
+            .unwrap();
+
+        let single = vec!["x86_64".to_string()];
+        let multi = vec!["x86_64".to_string(), "aarch64".to_string()];
+
+        assert_eq!(store.get_filtered_pkg_ids(&single, &[]).unwrap().len(), 1);
+        assert_eq!(store.get_filtered_pkg_ids(&multi, &[]).unwrap().len(), 2);
+        assert_eq!(store.get_filtered_pkg_ids(&[], &[]).unwrap().len(), 4);
+
+        let filter = FindFilter {
+            name: Some("bash*".to_string()),
+            arches: multi,
+            ..Default::default()
+        };
+        assert_eq!(store.general_search(&filter).unwrap().len(), 2);
+
+        // Arch and repo filters ignore case
+        let upper_arch = vec!["X86_64".to_string()];
+        let upper_repo = vec!["Base".to_string()];
+        assert_eq!(
+            store
+                .get_filtered_pkg_ids(&upper_arch, &upper_repo)
+                .unwrap()
+                .len(),
+            1
+        );
+        let filter = FindFilter {
+            name: Some("bash*".to_string()),
+            repos: upper_repo,
+            ..Default::default()
+        };
+        assert_eq!(store.general_search(&filter).unwrap().len(), 4);
+
+        // limit 0 means no limit
+        let limited = FindFilter {
+            name: Some("bash*".to_string()),
+            limit: 1,
+            ..Default::default()
+        };
+        assert_eq!(store.general_search(&limited).unwrap().len(), 1);
+        let unlimited = FindFilter {
+            limit: 0,
+            ..limited
+        };
+        assert_eq!(store.general_search(&unlimited).unwrap().len(), 4);
+
+        // Substring match also finds bash-doc; exact match does not
+        let substring = FindFilter {
+            name: Some("bash".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(store.general_search(&substring).unwrap().len(), 4);
+        let exact = FindFilter {
+            name_exact: true,
+            ..substring.clone()
+        };
+        assert_eq!(store.general_search(&exact).unwrap().len(), 3);
+
+        let regex = FindFilter {
+            name: Some("^bash-d.c$".to_string()),
+            regex: true,
+            ..substring
+        };
+        assert_eq!(store.general_search(&regex).unwrap().len(), 1);
+        let invalid = FindFilter {
+            name: Some("bash(".to_string()),
+            ..regex
+        };
+        assert!(matches!(
+            store.general_search(&invalid),
+            Err(RpmSearchError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_general_search_file_type() {
+        let mut store = PackageStore::new(":memory:").unwrap();
+        let ids = store
+            .insert_packages_batch(&[
+                test_package("filesystem", "x86_64", "3.18", "1", "base"),
+                test_package("bash", "x86_64", "5.2", "1", "base"),
+            ])
+            .unwrap();
+        let (fs_id, bash_id) = (ids[0], ids[1]);
+        store
+            .insert_filelists_batch(&[
+                (fs_id, vec![("/usr/share/bash-completion".to_string(), 1)]),
+                (
+                    bash_id,
+                    vec![("/usr/share/bash-completion/bash".to_string(), 0)],
+                ),
+            ])
+            .unwrap();
+
+        let any = FindFilter {
+            file: Some("/usr/share/bash-completion*".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(store.general_search(&any).unwrap().len(), 2);
+
+        let dirs = FindFilter {
+            file_type: Some(RpmFileType::Dir),
+            ..any.clone()
+        };
+        assert_eq!(store.general_search(&dirs).unwrap(), vec![fs_id]);
+
+        let ghosts = FindFilter {
+            file_type: Some(RpmFileType::Ghost),
+            ..any
+        };
+        assert!(store.general_search(&ghosts).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_general_search_regex_cap() {
+        let mut store = PackageStore::new(":memory:").unwrap();
+        let packages: Vec<Package> = (0..=REGEX_MAX_RESULTS)
+            .map(|i| test_package(&format!("lib{:04}", i), "x86_64", "1.0", "1", "base"))
+            .collect();
+        store.insert_packages_batch(&packages).unwrap();
+        let filter = FindFilter {
+            name: Some("^lib[0-9]+$".to_string()),
+            regex: true,
+            limit: 0,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            store.general_search(&filter).unwrap().len(),
+            REGEX_MAX_RESULTS
+        );
+        let limited = FindFilter {
+            limit: 5,
+            ..filter.clone()
+        };
+        assert_eq!(store.general_search(&limited).unwrap().len(), 5);
+        let by_version = FindFilter {
+            sort: SortField::Version,
+            ..filter
+        };
+        assert_eq!(
+            store.general_search(&by_version).unwrap().len(),
+            REGEX_MAX_RESULTS
+        );
+    }
+
+    #[test]
+    fn test_general_search_file_paths() {
+        let mut store = PackageStore::new(":memory:").unwrap();
+        let ids = store
+            .insert_packages_batch(&[
+                test_package("rootfile", "x86_64", "1.0", "1", "base"),
+                test_package("app", "x86_64", "1.0", "1", "base"),
+                test_package("bash", "x86_64", "5.2", "1", "base"),
+            ])
+            .unwrap();
+        let (root_id, app_id, bash_id) = (ids[0], ids[1], ids[2]);
+        store
+            .insert_filelists_batch(&[
+                (root_id, vec![("/bash".to_string(), 0)]),
+                (app_id, vec![("/opt/app".to_string(), 1)]),
+                (
+                    bash_id,
+                    vec![
+                        ("/usr/bin/bash".to_string(), 0),
+                        ("/usr/bin/bashbug".to_string(), 0),
+                    ],
+                ),
+            ])
+            .unwrap();
+        let find = |file: &str| {
+            let filter = FindFilter {
+                file: Some(file.to_string()),
+                ..Default::default()
+            };
+            let mut found = store.general_search(&filter).unwrap();
+            found.sort();
+            found
+        };
+
+        // Root-level files and directory entries have no doubled or trailing `/`
+        assert_eq!(find("/b*"), vec![root_id]);
+        assert_eq!(find("*/app"), vec![app_id]);
+        assert_eq!(
+            store.get_files_for_package(root_id).unwrap(),
+            vec![("/bash".to_string(), 0)]
+        );
+
+        // Absolute paths without wildcards match exactly
+        assert_eq!(find("/bash"), vec![root_id]);
+        assert_eq!(find("/usr/bin/bash"), vec![bash_id]);
+        assert_eq!(find("/opt/app"), vec![app_id]);
+        assert_eq!(find("/opt/app/"), vec![app_id]);
+        assert!(find("/usr/bin").is_empty());
+        // Other patterns still match anywhere in the path
+        assert_eq!(find("bin/bash"), vec![bash_id]);
+
+        // The exact lookup goes through the (dir_id, name) index
+        let (condition, values) = file_path_condition("/usr/bin/bash");
+        let plan: Vec<String> = store
+            .connection()
+            .prepare(&format!(
+                "EXPLAIN QUERY PLAN SELECT f.pkg_id FROM files f \
+                 JOIN directories d ON f.dir_id = d.dir_id WHERE {}",
+                condition
+            ))
+            .unwrap()
+            .query_map(rusqlite::params_from_iter(values), |row| row.get(3))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert!(
+            plan.iter().any(|step| step.contains("idx_files_dir_name")),
+            "{:?}",
+            plan
+        );
+        assert!(
+            !plan.iter().any(|step| step.starts_with("SCAN")),
+            "{:?}",
+            plan
+        );
+    }
+
+    #[test]
+    fn test_wal_read_while_writing() {
+        let temp = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = PackageStore::new(temp.path()).unwrap();
+        let reader = PackageStore::new(temp.path()).unwrap();
+        let journal_mode: String = reader
+            .connection()
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal_mode, "wal");
+
+        writer
+            .insert_package(&test_package("bash", "x86_64", "5.2", "1", "base"))
+            .unwrap();
+
+        // A long-running read (e.g. a search) doesn't block the sync's commit,
+        // and keeps seeing the snapshot it started with
+        reader.connection().execute_batch("BEGIN").unwrap();
+        assert_eq!(reader.count_packages().unwrap(), 1);
+        writer
+            .insert_package(&test_package("zsh", "x86_64", "5.9", "1", "base"))
+            .unwrap();
+        assert_eq!(reader.count_packages().unwrap(), 1);
+        reader.connection().execute_batch("COMMIT").unwrap();
+        assert_eq!(reader.count_packages().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_general_search_file_provides() {
+        let mut store = PackageStore::new(":memory:").unwrap();
+        let mut bash = test_package("bash", "x86_64", "5.2", "1", "base");
+        bash.provides = vec![Dependency::from_evr("/bin/sh", None, None)];
+        let ids = store
+            .insert_packages_batch(&[
+                bash,
+                test_package("dash", "x86_64", "0.5", "1", "base"),
+                test_package("zsh", "x86_64", "5.9", "1", "base"),
+            ])
+            .unwrap();
+        let (bash_id, dash_id) = (ids[0], ids[1]);
+        store
+            .insert_filelists_batch(&[(dash_id, vec![("/bin/sh".to_string(), 0)])])
+            .unwrap();
+
+        let filter = FindFilter {
+            provides: Some("/bin/sh".to_string()),
+            ..Default::default()
+        };
+        let mut found = store.general_search(&filter).unwrap();
+        found.sort();
+        assert_eq!(found, vec![bash_id, dash_id]);
+
+        // Non-path capabilities only look at declared provides
+        let filter = FindFilter {
+            provides: Some("sh".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(store.general_search(&filter).unwrap(), vec![bash_id]);
+    }
+
+    #[test]
+    fn test_general_search_conflicts_obsoletes() {
+        let mut store = PackageStore::new(":memory:").unwrap();
+        let mut dnf = test_package("dnf", "noarch", "4.14", "1", "base");
+        dnf.obsoletes = vec![Dependency::from_evr("yum", Some("LT"), Some("4.0"))];
+        dnf.conflicts = vec![Dependency::from_evr("python3-dnf-plugins-core", None, None)];
+        let ids = store
+            .insert_packages_batch(&[dnf, test_package("yum", "noarch", "3.4", "1", "base")])
+            .unwrap();
+
+        let filter = FindFilter {
+            obsoletes: Some("yum".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(store.general_search(&filter).unwrap(), vec![ids[0]]);
+        let filter = FindFilter {
+            conflicts: Some("python3-dnf*".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(store.general_search(&filter).unwrap(), vec![ids[0]]);
+
+        let pkg = store.get_package(ids[0]).unwrap().unwrap();
+        assert_eq!(pkg.obsoletes[0].to_string(), "yum < 4.0");
+        assert_eq!(pkg.conflicts.len(), 1);
+        assert!(pkg.requires.is_empty());
+    }
+
+    #[test]
+    fn test_find_duplicate_versions() {
+        let mut store = PackageStore::new(":memory:").unwrap();
+        store
+            .insert_packages_batch(&[
+                test_package("bash", "x86_64", "5.1", "1", "staging"),
+                test_package("bash", "x86_64", "5.2", "1", "prod"),
+                test_package("bash", "aarch64", "5.2", "1", "prod"),
+                test_package("zlib", "x86_64", "1.3", "1", "staging"),
+                test_package("zlib", "x86_64", "1.3", "1", "prod"),
+            ])
+            .unwrap();
+
+        let duplicates = store.find_duplicate_versions(&[], &[]).unwrap();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].name, "bash");
+        assert_eq!(duplicates[0].arch, "x86_64");
+        let nevras: Vec<&str> = duplicates[0]
+            .packages
+            .iter()
+            .map(|p| p.nevra.as_str())
+            .collect();
+        assert_eq!(nevras, ["bash-5.1-1.x86_64", "bash-5.2-1.x86_64"]);
+
+        let prod_only = store
+            .find_duplicate_versions(&[], &["prod".to_string()])
+            .unwrap();
+        assert!(prod_only.is_empty());
+    }
+
+    #[test]
+    fn test_conflicting_files() {
+        let mut store = PackageStore::new(":memory:").unwrap();
+        let ids = store
+            .insert_packages_batch(&[
+                test_package("vim", "x86_64", "9.0", "1", "base"),
+                test_package("vim-minimal", "x86_64", "9.0", "1", "base"),
+                test_package("vim", "x86_64", "9.1", "1", "base"),
+                test_package("filesystem", "noarch", "3.18", "1", "base"),
+            ])
+            .unwrap();
+        let files = |entries: &[(&str, i32)]| -> Vec<(String, i32)> {
+            entries.iter().map(|(p, t)| (p.to_string(), *t)).collect()
+        };
+        store
+            .insert_filelists_batch(&[
+                (
+                    ids[0],
+                    files(&[
+                        ("/usr/bin/vi", 0),
+                        ("/usr/bin/xxd", 0),
+                        ("/usr/share/vim", 1),
+                    ]),
+                ),
+                // A shared directory is not a conflict
+                (ids[1], files(&[("/usr/bin/vi", 0), ("/usr/share/vim", 1)])),
+                // Another version of the same package is not a conflict
+                (ids[2], files(&[("/usr/bin/vim", 0), ("/usr/bin/xxd", 0)])),
+                // Nor is a ghost entry of a regular file
+                (ids[3], files(&[("/usr/bin/vim", 2)])),
+            ])
+            .unwrap();
+
+        let conflicts = store.conflicting_files("base").unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].path, "/usr/bin/vi");
+        let owners: Vec<&str> = conflicts[0]
+            .owners
+            .iter()
+            .map(|o| o.nevra.as_str())
+            .collect();
+        assert_eq!(owners, ["vim-9.0-1.x86_64", "vim-minimal-9.0-1.x86_64"]);
+        assert!(store.conflicting_files("updates").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_provides() {
+        let mut bash_base = test_package("bash", "x86_64", "5.2", "1", "base");
+        bash_base.provides = vec![Dependency::from_evr("/bin/sh", None, None)];
+        let mut bash_updates = test_package("bash", "x86_64", "5.2", "2", "updates");
+        bash_updates.provides = bash_base.provides.clone();
+        let mut dash = test_package("dash", "x86_64", "0.5", "1", "base");
+        dash.provides = bash_base.provides.clone();
+        let mut zsh = test_package("zsh", "x86_64", "5.9", "1", "base");
+        zsh.provides = vec![Dependency::from_evr("/bin/zsh", None, None)];
+
+        let mut store = PackageStore::new(":memory:").unwrap();
+        store
+            .insert_packages_batch(&[bash_base, bash_updates, dash, zsh])
+            .unwrap();
+
+        let duplicates = store.duplicate_provides().unwrap();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].capability, "/bin/sh");
+        let providers: Vec<(&str, &str)> = duplicates[0]
+            .providers
+            .iter()
+            .map(|p| (p.repo.as_str(), p.nevra.as_str()))
+            .collect();
+        assert_eq!(
+            providers,
+            vec![
+                ("base", "bash-5.2-1.x86_64"),
+                ("base", "dash-0.5-1.x86_64"),
+                ("updates", "bash-5.2-2.x86_64"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_schema_version() {
+        let store = PackageStore::new(":memory:").unwrap();
+        assert_eq!(
+            store.schema_version().unwrap(),
+            crate::storage::schema::SCHEMA_VERSION
+        );
+    }
+
+    #[test]
+    fn test_search_by_name_ranked_weights() {
+        let mut store = PackageStore::new(":memory:").unwrap();
+        let mut curl = test_package("curl", "x86_64", "8.0", "1", "base");
+        curl.summary = "Transfer URLs with TLS".to_string();
+        let mut nss = test_package("nss", "x86_64", "3.9", "1", "base");
+        nss.provides
+            .push(Dependency::from_evr("libtls-compat", None, None));
+        let mut wget = test_package("wget", "x86_64", "1.21", "1", "base");
+        wget.description = "Downloads over TLS".to_string();
+        let mut libressl = test_package("libressl", "x86_64", "3.8", "1", "base");
+        libressl.description = "Libraries supporting TLS".to_string();
+        libressl
+            .provides
+            .push(Dependency::from_evr("libtls.so.28", None, None));
+        store
+            .insert_packages_batch(&[
+                wget,
+                libressl,
+                nss,
+                curl,
+                test_package("gnutls", "x86_64", "3.8", "1", "base"),
+                test_package("tls-utils", "x86_64", "1.0", "1", "base"),
+                test_package("tls", "x86_64", "1.0", "1", "base"),
+            ])
+            .unwrap();
+        let ranked_names = |weights: &StructuredWeights| -> Vec<String> {
+            store
+                .search_by_name_ranked("tls", weights)
+                .unwrap()
+                .iter()
+                .map(|(id, _)| store.get_package(*id).unwrap().unwrap().name)
+                .collect()
+        };
+
+        // libressl matches by description first, so scores like wget
+        assert_eq!(
+            ranked_names(&StructuredWeights::default()),
+            [
+                "tls",
+                "tls-utils",
+                "gnutls",
+                "curl",
+                "nss",
+                "wget",
+                "libressl"
+            ]
+        );
+
+        let boosted = StructuredWeights {
+            provides: 0.9,
+            ..Default::default()
+        };
+        assert_eq!(
+            ranked_names(&boosted),
+            [
+                "tls",
+                "nss",
+                "tls-utils",
+                "gnutls",
+                "curl",
+                "wget",
+                "libressl"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search_by_name_ranked_contains() {
+        let weights = StructuredWeights::default();
+        let mut store = PackageStore::new(":memory:").unwrap();
+        assert!(store.name_trigrams);
+        store
+            .insert_packages_batch(&[
+                test_package("openssl-libs", "x86_64", "3.0", "1", "base"),
+                test_package("OpenSSH", "x86_64", "9.0", "1", "base"),
+                test_package("libssh", "x86_64", "0.10", "1", "base"),
+            ])
+            .unwrap();
+
+        // Trigram index and LIKE fallback agree
+        for trigrams in [true, false] {
+            store.name_trigrams = trigrams;
+            let ranked = store.search_by_name_ranked("SSL", &weights).unwrap();
+            let names: Vec<String> = ranked
+                .iter()
+                .map(|(id, _)| store.get_package(*id).unwrap().unwrap().name)
+                .collect();
+            assert_eq!(names, vec!["openssl-libs"]);
+            assert_eq!(ranked[0].1, 0.7);
+
+            let ranked = store.search_by_name_ranked("openssh", &weights).unwrap();
+            assert_eq!(ranked.len(), 1);
+            assert_eq!(ranked[0].1, 1.0);
+        }
+
+        // Deleted packages leave the trigram index too
+        store.name_trigrams = true;
+        store.delete_repository("base").unwrap();
+        assert!(store
+            .search_by_name_ranked("ssl", &weights)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_delete_repository_clears_tags() {
+        let mut store = PackageStore::new(":memory:").unwrap();
+        // Deleting works before sync has created its tables
+        assert_eq!(store.delete_repository("base").unwrap(), 0);
+
+        store
+            .insert_packages_batch(&[test_package("bash", "x86_64", "5.2", "1", "base")])
+            .unwrap();
+        store
+            .connection()
+            .execute_batch(
+                "CREATE TABLE repo_tags (repo_name TEXT NOT NULL, tag TEXT NOT NULL);
+                 INSERT INTO repo_tags VALUES ('base', 'tizen'), ('unified', 'tizen');",
+            )
+            .unwrap();
+
+        assert_eq!(store.delete_repository("base").unwrap(), 1);
+        let tagged: Vec<String> = store
+            .connection()
+            .prepare("SELECT repo_name FROM repo_tags")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(tagged, vec!["unified"]);
+    }
+
+    #[test]
+    fn test_reindex_restores_name_trigrams() {
+        let weights = StructuredWeights::default();
+        let mut store = PackageStore::new(":memory:").unwrap();
+        store
+            .insert_packages_batch(&[test_package("openssl-libs", "x86_64", "3.0", "1", "base")])
+            .unwrap();
+        // A database written without the trigram index (older version or external tool)
+        store
+            .connection()
+            .execute_batch(
+                "DROP TRIGGER packages_name_trigrams_ai;
+                 DROP TRIGGER packages_name_trigrams_ad;
+                 DROP TRIGGER packages_name_trigrams_au;
+                 DROP TABLE package_name_trigrams;",
+            )
+            .unwrap();
+        store.name_trigrams = false;
+
+        store.reindex().unwrap();
+        assert!(store.name_trigrams);
+        let ranked = store.search_by_name_ranked("ssl", &weights).unwrap();
+        assert_eq!(ranked.len(), 1);
+    }
 }
@@ -1,19 +1,198 @@
-use crate::config::ModelType;
+use crate::config::{EmbeddingTextMode, ModelType, ScoreTransform};
 use crate::error::{Result, RpmSearchError};
-use rusqlite::Connection;
-
-pub struct VectorStore {
-    conn: Connection,
+use crate::storage::sqlite::{finish_savepoint, vector_tables, PackageStore, SlowQueryTimer};
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::HashSet;
+
+/// Name of the vec table holding embeddings built with the DB's default model
+pub const DEFAULT_VECTOR_TABLE: &str = "embeddings";
+
+/// Most query embeddings kept in the cache; older ones are dropped first
+pub const QUERY_CACHE_MAX_ENTRIES: usize = 1000;
+
+/// Vector table access borrowing an existing connection, so it can share the
+/// connection of [`PackageStore`](crate::storage::PackageStore) instead of
+/// opening (and loading sqlite-vec into) another one.
+pub struct VectorStore<'c> {
+    conn: &'c Connection,
+    table: String,
+    slow_query_ms: u64,
+    score_transform: ScoreTransform,
 }
 
-impl VectorStore {
+impl<'c> VectorStore<'c> {
     /// Create a new vector store (using the same connection as PackageStore)
-    pub fn new(conn: Connection) -> Result<Self> {
-        Ok(Self { conn })
+    pub fn new(conn: &'c Connection) -> Result<Self> {
+        Ok(Self {
+            conn,
+            table: DEFAULT_VECTOR_TABLE.to_string(),
+            slow_query_ms: 0,
+            score_transform: ScoreTransform::default(),
+        })
+    }
+
+    /// Use a different vec table (see [`VectorStore::table_name_for`])
+    pub fn with_table(mut self, table: String) -> Self {
+        self.table = table;
+        self
+    }
+
+    /// Name of the vec table this store reads and writes
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// Whether the vec table exists (it is created by the first embedding build)
+    pub fn has_table(&self) -> bool {
+        vector_tables(self.conn).contains(&self.table)
+    }
+
+    /// Vec table for embeddings built with `model_type`.
+    ///
+    /// The DB's default model (recorded in metadata) uses the `embeddings`
+    /// table; any other model gets its own `embeddings_<model>` table.
+    pub fn table_name_for(&self, model_type: &ModelType) -> Result<String> {
+        match self.get_embedding_model_type()? {
+            Some(default) if default != model_type.as_db_str() => Ok(format!(
+                "{}_{}",
+                DEFAULT_VECTOR_TABLE,
+                model_type.as_db_str().replace('-', "_")
+            )),
+            _ => Ok(DEFAULT_VECTOR_TABLE.to_string()),
+        }
+    }
+
+    /// Assign an embedding model to a repository, overriding the DB default
+    pub fn set_repo_embedding_model(&self, repo: &str, model_type: &ModelType) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO repo_embedding_models (repo, model_type) VALUES (?, ?)",
+            [repo, model_type.as_db_str()],
+        )?;
+        Ok(())
+    }
+
+    /// Remove a repository's model override so it uses the DB default again
+    pub fn clear_repo_embedding_model(&self, repo: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM repo_embedding_models WHERE repo = ?", [repo])?;
+        Ok(())
+    }
+
+    /// List repositories with a model override as (repo, model_type) pairs
+    pub fn get_repo_embedding_models(&self) -> Result<Vec<(String, String)>> {
+        let mut stmt = match self
+            .conn
+            .prepare("SELECT repo, model_type FROM repo_embedding_models ORDER BY repo")
+        {
+            Ok(stmt) => stmt,
+            Err(e) => {
+                // repo_embedding_models table might not exist in an older database
+                tracing::debug!("Could not read repo embedding models: {}", e);
+                return Ok(Vec::new());
+            }
+        };
+
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
     }
 
-    /// Record embedding model info in the metadata table
-    pub fn set_embedding_model_info(&self, model_type: &ModelType) -> Result<()> {
+    /// Get the embedding model type string used for a repository
+    /// (its override if set, otherwise the DB default)
+    pub fn get_repo_embedding_model_type(&self, repo: &str) -> Result<Option<String>> {
+        let overridden = self
+            .get_repo_embedding_models()?
+            .into_iter()
+            .find(|(r, _)| r == repo)
+            .map(|(_, model)| model);
+        match overridden {
+            Some(model) => Ok(Some(model)),
+            None => self.get_embedding_model_type(),
+        }
+    }
+
+    /// Delete embeddings for the given packages from this store's table
+    pub fn delete_embeddings(&self, pkg_ids: &[i64]) -> Result<usize> {
+        with_savepoint(self.conn, "delete_embeddings", || {
+            let mut deleted = 0;
+            let mut stmt = match self
+                .conn
+                .prepare(&format!("DELETE FROM {} WHERE pkg_id = ?", self.table))
+            {
+                Ok(stmt) => stmt,
+                // Table not created yet: nothing to delete
+                Err(_) => return Ok(0),
+            };
+            for pkg_id in pkg_ids {
+                deleted += stmt.execute([pkg_id])?;
+            }
+            Ok(deleted)
+        })
+    }
+
+    /// Embedded pkg_ids in this store's table with no matching row in `packages`
+    /// (left behind when packages are removed outside `delete_repository`)
+    pub fn orphan_pkg_ids(&self, package_store: &PackageStore) -> Result<Vec<i64>> {
+        if !self.has_table() {
+            return Ok(Vec::new());
+        }
+        let packages: HashSet<i64> = package_store.get_all_pkg_ids()?.into_iter().collect();
+        let mut orphans: Vec<i64> = self
+            .get_embedded_pkg_ids()?
+            .into_iter()
+            .filter(|id| !packages.contains(id))
+            .collect();
+        orphans.sort_unstable();
+        Ok(orphans)
+    }
+
+    /// Delete embeddings for the given packages from every other model's vec table
+    pub fn remove_from_other_tables(&self, pkg_ids: &[i64]) -> Result<usize> {
+        with_savepoint(self.conn, "remove_from_other_tables", || {
+            let mut deleted = 0;
+            for table in vector_tables(self.conn) {
+                if table == self.table {
+                    continue;
+                }
+                let mut stmt = self
+                    .conn
+                    .prepare(&format!("DELETE FROM {} WHERE pkg_id = ?", table))?;
+                for pkg_id in pkg_ids {
+                    deleted += stmt.execute([pkg_id])?;
+                }
+            }
+            Ok(deleted)
+        })
+    }
+
+    /// Drop this store's vec table
+    pub fn drop_table(&self) -> Result<()> {
+        self.conn
+            .execute(&format!("DROP TABLE IF EXISTS {}", self.table), [])?;
+        Ok(())
+    }
+
+    /// Log queries slower than `ms` milliseconds as warnings (0 = disabled)
+    pub fn with_slow_query_ms(mut self, ms: u64) -> Self {
+        self.slow_query_ms = ms;
+        self
+    }
+
+    /// Transform applied to distances to produce search scores
+    /// (default: [`ScoreTransform::Linear`], the plain cosine similarity)
+    pub fn with_score_transform(mut self, transform: ScoreTransform) -> Self {
+        self.score_transform = transform;
+        self
+    }
+
+    /// Record embedding model info (and the HuggingFace revision it was
+    /// loaded from, if pinned) in the metadata table
+    pub fn set_embedding_model_info(
+        &self,
+        model_type: &ModelType,
+        revision: Option<&str>,
+    ) -> Result<()> {
         let now = chrono::Utc::now().to_rfc3339();
         self.conn.execute(
             "INSERT OR REPLACE INTO metadata (key, value) VALUES ('embedding_model_type', ?)",
@@ -27,6 +206,128 @@ impl VectorStore {
             "INSERT OR REPLACE INTO metadata (key, value) VALUES ('embedding_built_at', ?)",
             [&now],
         )?;
+        match revision {
+            Some(revision) => self.conn.execute(
+                "INSERT OR REPLACE INTO metadata (key, value) VALUES ('embedding_model_revision', ?)",
+                [revision],
+            )?,
+            None => self.conn.execute(
+                "DELETE FROM metadata WHERE key = 'embedding_model_revision'",
+                [],
+            )?,
+        };
+        Ok(())
+    }
+
+    /// HuggingFace revision recorded with the DB's default model (None = default branch)
+    pub fn get_embedding_model_revision(&self) -> Result<Option<String>> {
+        match self.conn.query_row(
+            "SELECT value FROM metadata WHERE key = 'embedding_model_revision'",
+            [],
+            |row| row.get(0),
+        ) {
+            Ok(value) => Ok(Some(value)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => {
+                tracing::debug!("Could not read embedding_model_revision: {}", e);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Record which package fields the embeddings were built from
+    pub fn set_embedding_text_mode(&self, mode: EmbeddingTextMode) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES ('embedding_text_mode', ?)",
+            [mode.as_db_str()],
+        )?;
+        Ok(())
+    }
+
+    /// Text mode recorded with the embeddings (None = not recorded; databases
+    /// built before the mode was recorded used [`EmbeddingTextMode::Full`])
+    pub fn get_embedding_text_mode(&self) -> Result<Option<EmbeddingTextMode>> {
+        match self.conn.query_row(
+            "SELECT value FROM metadata WHERE key = 'embedding_text_mode'",
+            [],
+            |row| row.get::<_, String>(0),
+        ) {
+            Ok(value) => Ok(EmbeddingTextMode::from_db_str(&value)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => {
+                tracing::debug!("Could not read embedding_text_mode: {}", e);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Record the boilerplate phrases stripped from descriptions before embedding
+    pub fn set_embedding_boilerplate(&self, phrases: &[String]) -> Result<()> {
+        let value = serde_json::to_string(phrases).map_err(|e| {
+            RpmSearchError::Storage(format!("Failed to serialize boilerplate: {}", e))
+        })?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES ('embedding_boilerplate', ?)",
+            [value],
+        )?;
+        Ok(())
+    }
+
+    /// Boilerplate phrases recorded with the embeddings (None = not recorded)
+    pub fn get_embedding_boilerplate(&self) -> Result<Option<Vec<String>>> {
+        match self.conn.query_row(
+            "SELECT value FROM metadata WHERE key = 'embedding_boilerplate'",
+            [],
+            |row| row.get::<_, String>(0),
+        ) {
+            Ok(value) => Ok(serde_json::from_str(&value).ok()),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => {
+                tracing::debug!("Could not read embedding_boilerplate: {}", e);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Metadata key marking an unfinished `--rebuild` (of the whole DB or one repo)
+    fn rebuild_pending_key(repo: Option<&str>) -> String {
+        match repo {
+            Some(repo) => format!("embedding_rebuild_pending:{}", repo),
+            None => "embedding_rebuild_pending".to_string(),
+        }
+    }
+
+    /// Record that a rebuild with `model_type` has started, so an interrupted
+    /// rebuild can be resumed instead of starting over
+    pub fn mark_rebuild_pending(&self, repo: Option<&str>, model_type: &ModelType) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES (?, ?)",
+            [
+                Self::rebuild_pending_key(repo).as_str(),
+                model_type.as_db_str(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Model of an unfinished rebuild, if one was interrupted
+    pub fn pending_rebuild(&self, repo: Option<&str>) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT value FROM metadata WHERE key = ?",
+                [Self::rebuild_pending_key(repo)],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// Mark a rebuild as finished
+    pub fn clear_rebuild_pending(&self, repo: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM metadata WHERE key = ?",
+            [Self::rebuild_pending_key(repo)],
+        )?;
         Ok(())
     }
 
@@ -52,13 +353,22 @@ impl VectorStore {
         use tracing::{debug, info};
 
         // Try to delete all existing rows first
-        match self.conn.execute("DELETE FROM embeddings", []) {
+        match self
+            .conn
+            .execute(&format!("DELETE FROM {}", self.table), [])
+        {
             Ok(n) => info!(deleted = n, "Cleared existing vector embeddings"),
             Err(e) => debug!("No existing embeddings table to clear: {}", e),
         }
 
+        // Cached query embeddings may come from a different model
+        self.clear_query_cache()?;
+
         // Drop the table completely to get a fresh start
-        match self.conn.execute("DROP TABLE IF EXISTS embeddings", []) {
+        match self
+            .conn
+            .execute(&format!("DROP TABLE IF EXISTS {}", self.table), [])
+        {
             Ok(_) => info!("Dropped embeddings table"),
             Err(e) => debug!("Could not drop embeddings table: {}", e),
         }
@@ -66,11 +376,11 @@ impl VectorStore {
         // Recreate the virtual table with cosine distance metric
         self.conn.execute(
             &format!(
-                "CREATE VIRTUAL TABLE IF NOT EXISTS embeddings USING vec0(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS {} USING vec0(
                         pkg_id INTEGER PRIMARY KEY,
                         embedding FLOAT[{}] distance_metric=cosine
                     )",
-                dimension
+                self.table, dimension
             ),
             [],
         )?;
@@ -86,11 +396,11 @@ impl VectorStore {
     pub fn ensure_table(&self, dimension: usize) -> Result<()> {
         self.conn.execute(
             &format!(
-                "CREATE VIRTUAL TABLE IF NOT EXISTS embeddings USING vec0(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS {} USING vec0(
                         pkg_id INTEGER PRIMARY KEY,
                         embedding FLOAT[{}] distance_metric=cosine
                     )",
-                dimension
+                self.table, dimension
             ),
             [],
         )?;
@@ -101,7 +411,7 @@ impl VectorStore {
     pub fn get_embedded_pkg_ids(&self) -> Result<Vec<i64>> {
         let mut stmt = self
             .conn
-            .prepare("SELECT pkg_id FROM embeddings")
+            .prepare(&format!("SELECT pkg_id FROM {}", self.table))
             .map_err(|e| {
                 RpmSearchError::Storage(format!("Failed to query embeddings table: {}", e))
             })?;
@@ -121,21 +431,24 @@ impl VectorStore {
         })?;
 
         self.conn.execute(
-            "INSERT OR REPLACE INTO embeddings (pkg_id, embedding) VALUES (?, ?)",
+            &format!(
+                "INSERT OR REPLACE INTO {} (pkg_id, embedding) VALUES (?, ?)",
+                self.table
+            ),
             rusqlite::params![pkg_id, embedding_json],
         )?;
 
         Ok(())
     }
 
-    /// Batch insert embeddings in a single transaction
+    /// Batch insert embeddings in a single transaction (nested in the
+    /// caller's when one is open)
     pub fn insert_embeddings_batch(&self, items: &[(i64, Vec<f32>)]) -> Result<()> {
-        let tx = self.conn.unchecked_transaction()?;
-
-        {
-            let mut stmt = tx.prepare_cached(
-                "INSERT OR REPLACE INTO embeddings (pkg_id, embedding) VALUES (?, ?)",
-            )?;
+        with_savepoint(self.conn, "insert_embeddings", || {
+            let mut stmt = self.conn.prepare_cached(&format!(
+                "INSERT OR REPLACE INTO {} (pkg_id, embedding) VALUES (?, ?)",
+                self.table
+            ))?;
 
             for (pkg_id, embedding) in items {
                 let embedding_json = serde_json::to_string(embedding).map_err(|e| {
@@ -143,34 +456,177 @@ impl VectorStore {
                 })?;
                 stmt.execute(rusqlite::params![pkg_id, embedding_json])?;
             }
+            Ok(())
+        })
+    }
+
+    /// Look up a cached query embedding for the given model and revision
+    pub fn get_cached_query_embedding(
+        &self,
+        query: &str,
+        model_type: &ModelType,
+        revision: Option<&str>,
+    ) -> Result<Option<Vec<f32>>> {
+        let cached: Option<(String, String)> = match self.conn.query_row(
+            "SELECT query, embedding FROM query_embeddings
+             WHERE query_hash = ? AND model_type = ? AND model_revision = ?",
+            rusqlite::params![
+                query_hash(query),
+                model_type.as_db_str(),
+                revision.unwrap_or("")
+            ],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ) {
+            Ok(row) => Some(row),
+            Err(rusqlite::Error::QueryReturnedNoRows) => None,
+            Err(e) => {
+                // query_embeddings table might not exist in an older database
+                tracing::debug!("Could not read query embedding cache: {}", e);
+                None
+            }
+        };
+
+        match cached {
+            // Guard against hash collisions
+            Some((cached_query, embedding_json)) if cached_query == query => {
+                let embedding = serde_json::from_str(&embedding_json).map_err(|e| {
+                    RpmSearchError::Storage(format!("Failed to parse cached embedding: {}", e))
+                })?;
+                Ok(Some(embedding))
+            }
+            _ => Ok(None),
         }
+    }
 
-        tx.commit()?;
-        Ok(())
+    /// Store a query embedding in the cache, dropping the oldest entries
+    /// beyond [`QUERY_CACHE_MAX_ENTRIES`]. Nothing is written on a read-only
+    /// connection, and an entry already cached (or a colliding hash) is kept.
+    pub fn cache_query_embedding(
+        &self,
+        query: &str,
+        model_type: &ModelType,
+        revision: Option<&str>,
+        embedding: &[f32],
+    ) -> Result<()> {
+        if self.conn.is_readonly(rusqlite::MAIN_DB)? {
+            return Ok(());
+        }
+
+        let embedding_json = serde_json::to_string(embedding).map_err(|e| {
+            RpmSearchError::Storage(format!("Failed to serialize embedding: {}", e))
+        })?;
+        let now = chrono::Utc::now().to_rfc3339();
+
+        with_savepoint(self.conn, "cache_query_embedding", || {
+            let inserted = self.conn.execute(
+                "INSERT OR IGNORE INTO query_embeddings
+                     (query_hash, model_type, model_revision, query, embedding, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                rusqlite::params![
+                    query_hash(query),
+                    model_type.as_db_str(),
+                    revision.unwrap_or(""),
+                    query,
+                    embedding_json,
+                    now
+                ],
+            )?;
+            if inserted > 0 {
+                self.conn.execute(
+                    "DELETE FROM query_embeddings WHERE rowid NOT IN (
+                         SELECT rowid FROM query_embeddings
+                         ORDER BY created_at DESC, rowid DESC LIMIT ?
+                     )",
+                    [QUERY_CACHE_MAX_ENTRIES as i64],
+                )?;
+            }
+            Ok(())
+        })
     }
 
-    /// Get embedding for a package (fallback only, not used currently)
-    #[allow(dead_code)]
-    pub fn get_embedding(&self, _pkg_id: i64) -> Result<Option<Vec<f32>>> {
-        // sqlite-vec doesn't support reading embeddings directly
-        // This is not typically needed for vector search
-        Ok(None)
+    /// Remove all cached query embeddings
+    pub fn clear_query_cache(&self) -> Result<usize> {
+        match self.conn.execute("DELETE FROM query_embeddings", []) {
+            Ok(n) => Ok(n),
+            Err(e) => {
+                tracing::debug!("No query embedding cache to clear: {}", e);
+                Ok(0)
+            }
+        }
+    }
+
+    /// Get the stored embedding for a package
+    pub fn get_embedding(&self, pkg_id: i64) -> Result<Option<Vec<f32>>> {
+        let blob: Option<Vec<u8>> = self
+            .conn
+            .query_row(
+                &format!("SELECT embedding FROM {} WHERE pkg_id = ?", self.table),
+                [pkg_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(blob.as_deref().map(decode_embedding))
+    }
+
+    /// Read up to `limit` randomly chosen embeddings (e.g. to inspect the
+    /// distribution of similarities over the corpus)
+    pub fn sample_embeddings(&self, limit: usize) -> Result<Vec<(i64, Vec<f32>)>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT pkg_id, embedding FROM {} ORDER BY random() LIMIT ?",
+            self.table
+        ))?;
+        let rows = stmt
+            .query_map([limit as i64], |row| {
+                let blob: Vec<u8> = row.get(1)?;
+                Ok((row.get(0)?, decode_embedding(&blob)))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
     }
 
     /// Perform KNN search (using sqlite-vec if enabled, fallback to full scan)
     pub fn search_similar(&self, query_embedding: &[f32], top_k: usize) -> Result<Vec<(i64, f32)>> {
+        Ok(self
+            .search_similar_with_distance(query_embedding, top_k)?
+            .into_iter()
+            .map(|(id, cos_sim, _)| (id, cos_sim))
+            .collect())
+    }
+
+    /// KNN search returning `(pkg_id, score, l2_distance)` tuples, where the
+    /// score is the cosine similarity passed through the store's [`ScoreTransform`].
+    ///
+    /// The vec0 tables use the cosine metric, so the L2 distance is derived
+    /// from the cosine distance assuming unit-norm embeddings
+    /// (`l2 = sqrt(2 * cosine_distance)`). Useful for threshold tuning.
+    pub fn search_similar_with_distance(
+        &self,
+        query_embedding: &[f32],
+        top_k: usize,
+    ) -> Result<Vec<(i64, f32, f32)>> {
+        let _timer = SlowQueryTimer::new(self.slow_query_ms, || {
+            format!("search_similar(top_k={})", top_k)
+        });
+
+        // No embeddings built yet (e.g. synced with --no-embeddings)
+        if !self.has_table() {
+            return Ok(Vec::new());
+        }
+
         // Use sqlite-vec's efficient KNN search
         let embedding_json = serde_json::to_string(query_embedding).map_err(|e| {
             RpmSearchError::Storage(format!("Failed to serialize query embedding: {}", e))
         })?;
 
-        let mut stmt = self.conn.prepare(
-            "SELECT pkg_id, distance 
-                 FROM embeddings 
-                 WHERE embedding MATCH ? 
-                 ORDER BY distance 
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT pkg_id, distance
+                 FROM {}
+                 WHERE embedding MATCH ?
+                 ORDER BY distance
                  LIMIT ?",
-        )?;
+            self.table
+        ))?;
 
         let results: Vec<(i64, f32)> = stmt
             .query_map(rusqlite::params![embedding_json, top_k as i64], |row| {
@@ -178,14 +634,15 @@ impl VectorStore {
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
 
-        // Convert cosine distance to cosine similarity
+        // Convert cosine distance to a score
         // cosine_distance = 1 - cosine_similarity
-        // Therefore: cosine_similarity = 1 - cosine_distance
-        let similarities: Vec<(i64, f32)> = results
+        // Therefore: cosine_similarity = 1 - cosine_distance (then transformed)
+        let similarities = results
             .into_iter()
             .map(|(id, dist)| {
-                let cos_sim = (1.0 - dist).clamp(0.0, 1.0);
-                (id, cos_sim)
+                let cos_sim = self.score_transform.apply(dist);
+                let l2_distance = (2.0 * dist).max(0.0).sqrt();
+                (id, cos_sim, l2_distance)
             })
             .collect();
 
@@ -201,6 +658,18 @@ impl VectorStore {
     ) -> Result<Vec<(i64, f32)>> {
         use std::collections::HashSet;
 
+        let _timer = SlowQueryTimer::new(self.slow_query_ms, || {
+            format!(
+                "search_similar_filtered(candidates={}, top_k={})",
+                candidate_ids.len(),
+                top_k
+            )
+        });
+
+        if !self.has_table() {
+            return Ok(Vec::new());
+        }
+
         // Convert to HashSet for O(1) lookup
         let candidate_set: HashSet<i64> = candidate_ids.iter().copied().collect();
 
@@ -212,13 +681,14 @@ impl VectorStore {
             RpmSearchError::Storage(format!("Failed to serialize query embedding: {}", e))
         })?;
 
-        let mut stmt = self.conn.prepare(
-            "SELECT pkg_id, distance 
-                 FROM embeddings 
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT pkg_id, distance
+                 FROM {}
                  WHERE embedding MATCH ?
                  ORDER BY distance
                  LIMIT ?",
-        )?;
+            self.table
+        ))?;
 
         let mut results: Vec<(i64, f32)> = stmt
             .query_map(
@@ -232,9 +702,8 @@ impl VectorStore {
             .filter_map(|result| result.ok())
             .filter(|(pkg_id, _)| candidate_set.contains(pkg_id))
             .map(|(id, dist)| {
-                // Convert cosine distance to cosine similarity
-                let cos_sim = (1.0 - dist).clamp(0.0, 1.0);
-                (id, cos_sim)
+                // Convert cosine distance to a similarity score
+                (id, self.score_transform.apply(dist))
             })
             .collect();
 
@@ -245,3 +714,267 @@ impl VectorStore {
         Ok(results)
     }
 }
+
+/// Stable 64-bit FNV-1a hash of a query string, used as the cache key.
+fn query_hash(query: &str) -> i64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in query.as_bytes() {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash as i64
+}
+
+/// vec0 returns vectors as a blob of little-endian f32 values
+fn decode_embedding(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// Run `f` inside `SAVEPOINT name` on a shared connection: a transaction of
+/// its own, or nested in one the caller already has open
+fn with_savepoint<T>(conn: &Connection, name: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    conn.execute_batch(&format!("SAVEPOINT {}", name))?;
+    finish_savepoint(conn, name, f())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::schema::Schema;
+
+    #[test]
+    fn test_query_embedding_cache() {
+        let conn = Connection::open_in_memory().unwrap();
+        Schema::initialize(&conn).unwrap();
+        let store = VectorStore::new(&conn).unwrap();
+        let minilm = &ModelType::Minilm;
+
+        assert!(store
+            .get_cached_query_embedding("web server", minilm, None)
+            .unwrap()
+            .is_none());
+
+        store
+            .cache_query_embedding("web server", minilm, None, &[0.5, -0.25])
+            .unwrap();
+        assert_eq!(
+            store
+                .get_cached_query_embedding("web server", minilm, None)
+                .unwrap(),
+            Some(vec![0.5, -0.25])
+        );
+
+        // Cached vectors are per model and per revision
+        assert!(store
+            .get_cached_query_embedding("web server", &ModelType::E5Multilingual, None)
+            .unwrap()
+            .is_none());
+        assert!(store
+            .get_cached_query_embedding("web server", minilm, Some("abc123"))
+            .unwrap()
+            .is_none());
+
+        assert_eq!(store.clear_query_cache().unwrap(), 1);
+        assert!(store
+            .get_cached_query_embedding("web server", minilm, None)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_query_embedding_cache_is_capped() {
+        let conn = Connection::open_in_memory().unwrap();
+        Schema::initialize(&conn).unwrap();
+        let store = VectorStore::new(&conn).unwrap();
+
+        for i in 0..=QUERY_CACHE_MAX_ENTRIES {
+            store
+                .cache_query_embedding(&format!("query {}", i), &ModelType::Minilm, None, &[0.1])
+                .unwrap();
+        }
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM query_embeddings", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(count as usize, QUERY_CACHE_MAX_ENTRIES);
+        // The oldest entry went first
+        assert!(store
+            .get_cached_query_embedding("query 0", &ModelType::Minilm, None)
+            .unwrap()
+            .is_none());
+        assert!(store
+            .get_cached_query_embedding("query 1", &ModelType::Minilm, None)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_query_embedding_cache_skips_read_only_connection() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        Schema::initialize(&Connection::open(&path).unwrap()).unwrap();
+
+        let conn =
+            Connection::open_with_flags(&path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY).unwrap();
+        let store = VectorStore::new(&conn).unwrap();
+        store
+            .cache_query_embedding("web server", &ModelType::Minilm, None, &[0.5])
+            .unwrap();
+        assert!(store
+            .get_cached_query_embedding("web server", &ModelType::Minilm, None)
+            .unwrap()
+            .is_none());
+    }
+
+    fn register_sqlite_vec() {
+        unsafe {
+            rusqlite::ffi::sqlite3_auto_extension(Some(std::mem::transmute::<
+                *const (),
+                unsafe extern "C" fn(
+                    *mut rusqlite::ffi::sqlite3,
+                    *mut *mut std::os::raw::c_char,
+                    *const rusqlite::ffi::sqlite3_api_routines,
+                ) -> std::os::raw::c_int,
+            >(
+                sqlite_vec::sqlite3_vec_init as *const (),
+            )));
+        }
+    }
+
+    #[test]
+    fn test_search_similar_with_distance() {
+        register_sqlite_vec();
+        let conn = Connection::open_in_memory().unwrap();
+        Schema::initialize(&conn).unwrap();
+        let store = VectorStore::new(&conn).unwrap();
+        store.ensure_table(2).unwrap();
+        store
+            .insert_embeddings_batch(&[(1, vec![1.0, 0.0]), (2, vec![0.0, 1.0])])
+            .unwrap();
+
+        let results = store.search_similar_with_distance(&[1.0, 0.0], 2).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, 1);
+        assert!((results[0].1 - 1.0).abs() < 1e-5);
+        assert!(results[0].2.abs() < 1e-3);
+        // Orthogonal unit vectors are sqrt(2) apart
+        assert_eq!(results[1].0, 2);
+        assert!(results[1].1.abs() < 1e-5);
+        assert!((results[1].2 - std::f32::consts::SQRT_2).abs() < 1e-3);
+
+        let plain = store.search_similar(&[1.0, 0.0], 2).unwrap();
+        assert_eq!(plain[0].0, 1);
+        assert_eq!(plain[1].0, 2);
+    }
+
+    #[test]
+    fn test_search_without_table() {
+        register_sqlite_vec();
+        let conn = Connection::open_in_memory().unwrap();
+        Schema::initialize(&conn).unwrap();
+        let store = VectorStore::new(&conn).unwrap();
+        assert!(!store.has_table());
+        assert!(store.search_similar(&[1.0, 0.0], 5).unwrap().is_empty());
+        assert!(store
+            .search_similar_filtered(&[1.0, 0.0], &[1, 2], 5)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_orphan_pkg_ids() {
+        register_sqlite_vec();
+        let package_store = PackageStore::new(":memory:").unwrap();
+        let conn = package_store.connection();
+        let store = VectorStore::new(conn).unwrap();
+        assert!(store.orphan_pkg_ids(&package_store).unwrap().is_empty());
+
+        conn.execute(
+            "INSERT INTO packages (pkg_id, name, version, release, arch, summary, description, repo)
+             VALUES (2, 'bash', '5.2', '1', 'x86_64', '', '', 'base')",
+            [],
+        )
+        .unwrap();
+        store.ensure_table(2).unwrap();
+        store
+            .insert_embeddings_batch(&[
+                (1, vec![1.0, 0.0]),
+                (2, vec![0.0, 1.0]),
+                (3, vec![1.0, 1.0]),
+            ])
+            .unwrap();
+
+        let orphans = store.orphan_pkg_ids(&package_store).unwrap();
+        assert_eq!(orphans, vec![1, 3]);
+        assert_eq!(store.delete_embeddings(&orphans).unwrap(), 2);
+        assert!(store.orphan_pkg_ids(&package_store).unwrap().is_empty());
+        assert_eq!(store.get_embedded_pkg_ids().unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn test_get_embedding() {
+        register_sqlite_vec();
+        let conn = Connection::open_in_memory().unwrap();
+        Schema::initialize(&conn).unwrap();
+        let store = VectorStore::new(&conn).unwrap();
+        store.ensure_table(3).unwrap();
+        store.insert_embedding(7, &[0.5, -0.25, 1.0]).unwrap();
+
+        assert_eq!(store.get_embedding(7).unwrap(), Some(vec![0.5, -0.25, 1.0]));
+        assert!(store.get_embedding(8).unwrap().is_none());
+
+        store.insert_embedding(8, &[1.0, 0.0, 0.0]).unwrap();
+        let mut sample = store.sample_embeddings(10).unwrap();
+        sample.sort_by_key(|(id, _)| *id);
+        assert_eq!(
+            sample,
+            vec![(7, vec![0.5, -0.25, 1.0]), (8, vec![1.0, 0.0, 0.0])]
+        );
+        assert_eq!(store.sample_embeddings(1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_rebuild_pending_marker() {
+        let conn = Connection::open_in_memory().unwrap();
+        Schema::initialize(&conn).unwrap();
+        let store = VectorStore::new(&conn).unwrap();
+
+        assert!(store.pending_rebuild(None).unwrap().is_none());
+        store
+            .mark_rebuild_pending(None, &ModelType::E5Multilingual)
+            .unwrap();
+        store
+            .mark_rebuild_pending(Some("updates"), &ModelType::Minilm)
+            .unwrap();
+        assert_eq!(
+            store.pending_rebuild(None).unwrap().as_deref(),
+            Some("e5-multilingual")
+        );
+        assert_eq!(
+            store.pending_rebuild(Some("updates")).unwrap().as_deref(),
+            Some("minilm")
+        );
+
+        store.clear_rebuild_pending(None).unwrap();
+        assert!(store.pending_rebuild(None).unwrap().is_none());
+        assert!(store.pending_rebuild(Some("updates")).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_embedding_boilerplate_round_trip() {
+        let conn = Connection::open_in_memory().unwrap();
+        Schema::initialize(&conn).unwrap();
+        let store = VectorStore::new(&conn).unwrap();
+
+        assert!(store.get_embedding_boilerplate().unwrap().is_none());
+        let phrases = vec!["This package contains".to_string()];
+        store.set_embedding_boilerplate(&phrases).unwrap();
+        assert_eq!(store.get_embedding_boilerplate().unwrap(), Some(phrases));
+        store.set_embedding_boilerplate(&[]).unwrap();
+        assert_eq!(store.get_embedding_boilerplate().unwrap(), Some(Vec::new()));
+    }
+}
@@ -1,10 +1,8 @@
 pub mod config;
-#[cfg(feature = "embedding")]
 pub mod scheduler;
 pub mod state;
 pub mod syncer;
 
 pub use config::SyncConfig;
-#[cfg(feature = "embedding")]
 pub use scheduler::SyncScheduler;
 pub use state::SyncStateStore;
@@ -2,10 +2,11 @@ use crate::config::Config;
 use crate::error::Result;
 use crate::sync::config::SyncConfig;
 use crate::sync::state::SyncStateStore;
-use crate::sync::syncer::RepoSyncer;
-use rusqlite::Connection;
-use std::collections::HashMap;
-use std::time::Duration;
+use crate::sync::syncer::{RepoSyncer, SyncPreview, SyncResult};
+use rusqlite::{Connection, OpenFlags};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::time::{Duration, Instant};
 use tokio::time;
 use tracing::{error, info, warn};
 
@@ -13,6 +14,7 @@ pub struct SyncScheduler {
     sync_config: SyncConfig,
     db_config: Config,
     embedding_enabled: bool,
+    progress: bool,
 }
 
 impl SyncScheduler {
@@ -21,6 +23,7 @@ impl SyncScheduler {
             sync_config,
             db_config,
             embedding_enabled: true,
+            progress: false,
         }
     }
 
@@ -29,71 +32,101 @@ impl SyncScheduler {
         self.embedding_enabled = enabled;
     }
 
+    /// Enable or disable download progress bars for one-time syncs
+    /// (the daemon never shows them)
+    pub fn set_progress(&mut self, enabled: bool) {
+        self.progress = enabled;
+    }
+
     /// Run scheduler in daemon mode
+    ///
+    /// Every enabled repository is synced right away and then again each
+    /// `interval_seconds` after its previous sync started, independently of the
+    /// others. Syncs run one at a time, so two repositories never write to the
+    /// database concurrently (SQLite allows a single writer); a repository
+    /// falling due while another syncs waits for it. Ctrl+C stops the daemon once the running sync
+    /// (if any) has finished.
     pub async fn run_daemon(&self) -> Result<()> {
         info!("Starting sync scheduler daemon");
 
-        // Create interval tasks for each repository
-        let mut tasks = Vec::new();
-
-        let embedding_enabled = self.embedding_enabled;
-
-        for repo_config in &self.sync_config.repositories {
-            if !repo_config.enabled {
-                info!(repo = %repo_config.name, "Repository disabled, skipping");
-                continue;
-            }
-
-            let repo_config = repo_config.clone();
-            let db_config = self.db_config.clone();
-            let work_dir = self.sync_config.work_dir.clone();
-
-            let task = tokio::spawn(async move {
-                let mut interval =
-                    time::interval(Duration::from_secs(repo_config.interval_seconds));
-                interval.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
+        let repos: Vec<_> = self
+            .sync_config
+            .repositories
+            .iter()
+            .filter(|repo_config| {
+                if !repo_config.enabled {
+                    info!(repo = %repo_config.name, "Repository disabled, skipping");
+                }
+                repo_config.enabled
+            })
+            .collect();
 
-                info!(
-                    repo = %repo_config.name,
-                    interval_seconds = repo_config.interval_seconds,
-                    "Starting sync task"
-                );
+        if repos.is_empty() {
+            warn!("No repositories enabled for syncing");
+            return Ok(());
+        }
 
-                loop {
-                    interval.tick().await;
+        for repo_config in &repos {
+            info!(
+                repo = %repo_config.name,
+                interval_seconds = repo_config.interval_seconds,
+                "Scheduling sync"
+            );
+        }
+        let mut schedule =
+            RepoSchedule::new(repos.iter().map(|r| r.interval_seconds), Instant::now());
 
-                    info!(repo = %repo_config.name, "Sync tick triggered");
+        let shutdown = async {
+            if let Err(e) = tokio::signal::ctrl_c().await {
+                warn!(error = %e, "Cannot listen for Ctrl+C; stop the daemon with a signal");
+                std::future::pending::<()>().await;
+            }
+        };
+        tokio::pin!(shutdown);
 
-                    // Perform sync
-                    if let Err(e) =
-                        Self::perform_sync(&repo_config, &db_config, &work_dir, embedding_enabled)
-                            .await
-                    {
-                        error!(repo = %repo_config.name, error = %e, "Sync failed");
-                    }
+        while let Some((due, idx)) = schedule.next_due() {
+            tokio::select! {
+                _ = time::sleep_until(due.into()) => {}
+                _ = &mut shutdown => {
+                    info!("Shutdown requested, stopping sync daemon");
+                    return Ok(());
                 }
-            });
+            }
 
-            tasks.push(task);
-        }
+            let repo_config = repos[idx];
+            info!(repo = %repo_config.name, "Sync due");
+            schedule.reschedule(idx, Instant::now());
 
-        if tasks.is_empty() {
-            warn!("No repositories enabled for syncing");
-            return Ok(());
-        }
-
-        // Wait for all tasks (they run indefinitely)
-        for task in tasks {
-            if let Err(e) = task.await {
-                error!(error = %e, "Sync task panicked");
+            let sync = Self::perform_sync(
+                repo_config,
+                &self.db_config,
+                &self.sync_config.work_dir,
+                self.embedding_enabled,
+                false,
+            );
+            tokio::pin!(sync);
+            let mut stopping = false;
+            let result = tokio::select! {
+                result = &mut sync => result,
+                _ = &mut shutdown => {
+                    info!(repo = %repo_config.name, "Shutdown requested, finishing the running sync");
+                    stopping = true;
+                    (&mut sync).await
+                }
+            };
+            if let Err(e) = result {
+                error!(repo = %repo_config.name, error = %e, "Sync failed");
+            }
+            if stopping {
+                info!("Sync daemon stopped");
+                return Ok(());
             }
         }
-
         Ok(())
     }
 
     /// Perform a one-time sync of all enabled repositories
-    pub async fn sync_once(&self) -> Result<HashMap<String, Result<()>>> {
+    pub async fn sync_once(&self) -> Result<HashMap<String, Result<SyncResult>>> {
         info!("Performing one-time sync of all repositories");
 
         let mut results = HashMap::new();
@@ -109,6 +142,7 @@ impl SyncScheduler {
                 &self.db_config,
                 &self.sync_config.work_dir,
                 false, // sync_once: embedding is handled by the caller (main.rs)
+                self.progress,
             )
             .await;
 
@@ -119,12 +153,62 @@ impl SyncScheduler {
         Ok(results)
     }
 
+    /// Preview a one-time sync of all enabled repositories without writing
+    pub async fn dry_run_once(&self) -> Result<HashMap<String, Result<SyncPreview>>> {
+        info!("Performing dry-run sync of all repositories");
+
+        let mut results = HashMap::new();
+
+        for repo_config in &self.sync_config.repositories {
+            if !repo_config.enabled {
+                info!(repo = %repo_config.name, "Repository disabled, skipping");
+                continue;
+            }
+
+            let config = repo_config.clone();
+            let db_config = self.db_config.clone();
+            let work_dir = self.sync_config.work_dir.clone();
+
+            let result = tokio::task::spawn_blocking(move || {
+                // A preview must not create, migrate or initialize the database
+                let (api, state_store) = if db_config.db_path.exists() {
+                    let state_conn = Connection::open_with_flags(
+                        &db_config.db_path,
+                        OpenFlags::SQLITE_OPEN_READ_ONLY,
+                    )?;
+                    (
+                        crate::api::RpmSearchApi::open_read_only(db_config)?,
+                        SyncStateStore::read_only(state_conn)?,
+                    )
+                } else {
+                    // Nothing indexed yet: compare against an empty in-memory database
+                    let mut memory_config = db_config;
+                    memory_config.db_path = ":memory:".into();
+                    (
+                        crate::api::RpmSearchApi::new(memory_config)?,
+                        SyncStateStore::new(Connection::open_in_memory()?)?,
+                    )
+                };
+
+                let mut syncer = RepoSyncer::new(api, state_store, work_dir)?;
+                syncer.preview_repository(&config)
+            })
+            .await
+            .map_err(|e| crate::error::RpmSearchError::Config(format!("Task join error: {}", e)))?;
+
+            results.insert(repo_config.name.clone(), result);
+        }
+
+        Ok(results)
+    }
+
     async fn perform_sync(
         repo_config: &crate::sync::config::RepoSyncConfig,
         db_config: &Config,
         work_dir: &std::path::Path,
         embedding_enabled: bool,
-    ) -> Result<()> {
+        progress: bool,
+    ) -> Result<SyncResult> {
         // Run sync in blocking context (since RpmSearchApi is synchronous)
         let repo_config = repo_config.clone();
         let db_config = db_config.clone();
@@ -137,12 +221,12 @@ impl SyncScheduler {
             let state_conn = Connection::open(&db_config.db_path)?;
             let state_store = SyncStateStore::new(state_conn)?;
 
-            let mut syncer = RepoSyncer::new(api, state_store, work_dir)?;
+            let mut syncer = RepoSyncer::new(api, state_store, work_dir)?.with_progress(progress);
 
             // Perform sync
             let result = syncer.sync_repository(&repo_config)?;
 
-            // Build embeddings incrementally for new packages
+            // Build embeddings incrementally for this repository's new packages
             #[cfg(feature = "embedding")]
             if embedding_enabled && result.changed && result.packages_synced > 0 {
                 info!(
@@ -151,12 +235,11 @@ impl SyncScheduler {
                     "Building embeddings for new packages"
                 );
                 let api = crate::api::RpmSearchApi::new(db_config.clone())?;
-                let embedder = crate::embedding::Embedder::new(
-                    &db_config.model_path,
-                    &db_config.tokenizer_path,
-                    db_config.model_type.clone(),
+                let count = api.build_embeddings_for_repos(
+                    std::slice::from_ref(&repo_config.name),
+                    |model_type| crate::embedding::Embedder::for_config(&db_config, model_type),
+                    false,
                 )?;
-                let count = api.build_embeddings(&embedder, false, false)?;
                 info!(
                     repo = %repo_config.name,
                     new_embeddings = count,
@@ -166,12 +249,76 @@ impl SyncScheduler {
             #[cfg(not(feature = "embedding"))]
             {
                 let _ = embedding_enabled;
-                let _ = &result;
             }
 
-            Ok(())
+            Ok(result)
         })
         .await
         .map_err(|e| crate::error::RpmSearchError::Config(format!("Task join error: {}", e)))?
     }
 }
+
+/// Next-due times of the daemon's repositories, earliest first
+struct RepoSchedule {
+    /// (due time, repository index); `Reverse` turns the max-heap into a min-heap
+    due: BinaryHeap<Reverse<(Instant, usize)>>,
+    intervals: Vec<Duration>,
+}
+
+impl RepoSchedule {
+    /// Every repository is due at `now`; a zero interval is treated as one second
+    fn new(interval_seconds: impl IntoIterator<Item = u64>, now: Instant) -> Self {
+        let intervals: Vec<Duration> = interval_seconds
+            .into_iter()
+            .map(|secs| Duration::from_secs(secs.max(1)))
+            .collect();
+        let due = (0..intervals.len())
+            .map(|idx| Reverse((now, idx)))
+            .collect();
+        Self { due, intervals }
+    }
+
+    /// The earliest due repository (must be followed by [`Self::reschedule`]),
+    /// or `None` when there are no repositories
+    fn next_due(&self) -> Option<(Instant, usize)> {
+        self.due.peek().map(|&Reverse(next)| next)
+    }
+
+    /// Remove the earliest entry and schedule repository `idx` one interval after `started`
+    fn reschedule(&mut self, idx: usize, started: Instant) {
+        self.due.pop();
+        self.due.push(Reverse((started + self.intervals[idx], idx)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repo_schedule_independent_intervals() {
+        let start = Instant::now();
+        let mut schedule = RepoSchedule::new([300, 86400], start);
+        let mut order = Vec::new();
+        // Simulate a day: the fast repo runs every 5 minutes, the slow one once
+        loop {
+            let (due, idx) = schedule.next_due().unwrap();
+            if due >= start + Duration::from_secs(86400) {
+                break;
+            }
+            order.push(idx);
+            schedule.reschedule(idx, due);
+        }
+        assert_eq!(order.iter().filter(|&&i| i == 1).count(), 1);
+        assert_eq!(order.iter().filter(|&&i| i == 0).count(), 86400 / 300);
+        assert_eq!(
+            schedule.next_due(),
+            Some((start + Duration::from_secs(86400), 0))
+        );
+    }
+
+    #[test]
+    fn test_repo_schedule_empty() {
+        assert_eq!(RepoSchedule::new([], Instant::now()).next_due(), None);
+    }
+}
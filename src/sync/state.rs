@@ -12,21 +12,53 @@ pub struct SyncStateStore {
 impl SyncStateStore {
     pub fn new(conn: Connection) -> Result<Self> {
         let store = Self { conn };
-        store.create_schema()?;
+        store.create_schema(false)?;
         Ok(store)
     }
 
-    /// Create sync_state table if it doesn't exist
-    fn create_schema(&self) -> Result<()> {
+    /// Open the state on a read-only connection (for previews). Nothing is
+    /// written to the database; if it has no sync state yet, empty temporary
+    /// tables stand in for it.
+    pub fn read_only(conn: Connection) -> Result<Self> {
+        let has_state: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'repo_sync_state')",
+            [],
+            |row| row.get(0),
+        )?;
+        let store = Self { conn };
+        if !has_state {
+            store.create_schema(true)?;
+        }
+        Ok(store)
+    }
+
+    /// Create the sync state tables if they don't exist, as temporary tables
+    /// with `temporary` (which works on a read-only connection)
+    fn create_schema(&self, temporary: bool) -> Result<()> {
+        let temp = if temporary { "TEMP " } else { "" };
         self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS repo_sync_state (
-                repo_name TEXT PRIMARY KEY,
-                last_sync TEXT,
-                last_checksum TEXT,
-                last_status TEXT NOT NULL,
-                last_error TEXT,
-                base_url TEXT
-            )",
+            &format!(
+                "CREATE {}TABLE IF NOT EXISTS repo_sync_state (
+                    repo_name TEXT PRIMARY KEY,
+                    last_sync TEXT,
+                    last_checksum TEXT,
+                    last_status TEXT NOT NULL,
+                    last_error TEXT,
+                    base_url TEXT
+                )",
+                temp
+            ),
+            [],
+        )?;
+        self.conn.execute(
+            &format!(
+                "CREATE {}TABLE IF NOT EXISTS repo_tags (
+                    repo_name TEXT NOT NULL,
+                    tag TEXT NOT NULL,
+                    PRIMARY KEY (repo_name, tag)
+                )",
+                temp
+            ),
             [],
         )?;
 
@@ -167,6 +199,32 @@ impl SyncStateStore {
         Ok(())
     }
 
+    /// Replace the tags of a repository (populated from the sync config)
+    pub fn set_repo_tags(&mut self, repo_name: &str, tags: &[String]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM repo_tags WHERE repo_name = ?", [repo_name])?;
+        for tag in tags {
+            tx.execute(
+                "INSERT OR IGNORE INTO repo_tags (repo_name, tag) VALUES (?, ?)",
+                rusqlite::params![repo_name, tag],
+            )?;
+        }
+        tx.commit()?;
+        debug!(repo = %repo_name, ?tags, "Set repository tags");
+        Ok(())
+    }
+
+    /// Names of the repositories carrying a tag, sorted
+    pub fn repos_with_tag(&self, tag: &str) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT repo_name FROM repo_tags WHERE tag = ? ORDER BY repo_name")?;
+        let repos = stmt
+            .query_map([tag], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+        Ok(repos)
+    }
+
     /// Delete sync state for a repository
     #[allow(dead_code)]
     pub fn delete_state(&self, repo_name: &str) -> Result<()> {
@@ -179,3 +237,49 @@ impl SyncStateStore {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repo_tags_replace_and_lookup() {
+        let mut store = SyncStateStore::new(Connection::open_in_memory().unwrap()).unwrap();
+        store
+            .set_repo_tags("base", &["tizen".to_string(), "base".to_string()])
+            .unwrap();
+        store
+            .set_repo_tags("unified", &["tizen".to_string()])
+            .unwrap();
+        assert_eq!(store.repos_with_tag("tizen").unwrap(), ["base", "unified"]);
+
+        store.set_repo_tags("base", &[]).unwrap();
+        assert_eq!(store.repos_with_tag("tizen").unwrap(), ["unified"]);
+        assert!(store.repos_with_tag("base").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_only_does_not_create_tables() {
+        let temp = tempfile::NamedTempFile::new().unwrap();
+        Connection::open(temp.path())
+            .unwrap()
+            .execute_batch("CREATE TABLE metadata (key TEXT PRIMARY KEY, value TEXT)")
+            .unwrap();
+
+        let conn =
+            Connection::open_with_flags(temp.path(), rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .unwrap();
+        let store = SyncStateStore::read_only(conn).unwrap();
+        assert!(store.get_state("base").unwrap().is_none());
+
+        let tables: i64 = Connection::open(temp.path())
+            .unwrap()
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'repo_sync_state'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(tables, 0);
+    }
+}
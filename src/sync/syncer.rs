@@ -3,15 +3,25 @@ use crate::error::{Result, RpmSearchError};
 use crate::sync::config::{RepoSyncConfig, RepoSyncState, SyncStatus};
 use crate::sync::state::SyncStateStore;
 use chrono::Utc;
+use indicatif::{ProgressBar, ProgressStyle};
 use std::fs;
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
 
+/// Subdirectory of the work dir holding metadata kept with `keep_metadata`
+const KEPT_METADATA_DIR: &str = "kept";
+
+/// Number of syncs per repository whose kept metadata is retained
+const KEPT_METADATA_SYNCS: usize = 5;
+
 pub struct RepoSyncer {
     api: RpmSearchApi,
     state_store: SyncStateStore,
     work_dir: PathBuf,
     http: reqwest::blocking::Client,
+    progress: bool,
 }
 
 impl RepoSyncer {
@@ -27,9 +37,17 @@ impl RepoSyncer {
             state_store,
             work_dir,
             http,
+            progress: false,
         })
     }
 
+    /// Show a progress bar on stderr for metadata downloads (only when
+    /// stderr is a terminal and the server reports a Content-Length)
+    pub fn with_progress(mut self, enabled: bool) -> Self {
+        self.progress = enabled;
+        self
+    }
+
     /// Sync a single repository
     pub fn sync_repository(&mut self, config: &RepoSyncConfig) -> Result<SyncResult> {
         info!(repo = %config.name, url = %config.base_url, "Starting repository sync");
@@ -49,6 +67,7 @@ impl RepoSyncer {
         state.base_url = Some(config.base_url.clone());
         state.last_status = SyncStatus::InProgress;
         self.state_store.update_state(&state)?;
+        self.state_store.set_repo_tags(&config.name, &config.tags)?;
 
         let result = self.do_sync(config, &state);
 
@@ -82,15 +101,18 @@ impl RepoSyncer {
         result
     }
 
-    fn do_sync(&mut self, config: &RepoSyncConfig, current_state: &RepoSyncState) -> Result<SyncResult> {
-        let repomd_url = format!(
-            "{}/repodata/repomd.xml",
-            config.base_url.trim_end_matches('/')
-        );
-        debug!(url = %repomd_url, "Downloading repomd.xml");
+    fn do_sync(
+        &mut self,
+        config: &RepoSyncConfig,
+        current_state: &RepoSyncState,
+    ) -> Result<SyncResult> {
+        let base_url = config.request_base_url()?;
+        let repomd_url = format!("{}/repodata/repomd.xml", base_url.trim_end_matches('/'));
+        debug!(repo = %config.name, "Downloading repomd.xml");
 
+        let download_started = Instant::now();
         let repomd_content = self.download_file(&repomd_url)?;
-        let repodata_info = self.parse_repomd(&repomd_content)?;
+        let repodata_info = parse_repomd(&repomd_content)?;
 
         let changed = match &current_state.last_checksum {
             Some(last) => last != &repodata_info.primary_checksum,
@@ -103,39 +125,60 @@ impl RepoSyncer {
                 changed: false,
                 checksum: repodata_info.primary_checksum,
                 packages_synced: 0,
+                download_time: download_started.elapsed(),
+                ..Default::default()
             });
         }
 
         let primary_url = format!(
             "{}/{}",
-            config.base_url.trim_end_matches('/'),
+            base_url.trim_end_matches('/'),
             repodata_info.primary_location.trim_start_matches('/')
         );
-        debug!(url = %primary_url, "Downloading primary.xml");
+        debug!(location = %repodata_info.primary_location, "Downloading primary.xml");
 
+        let stamp = Utc::now().format("%Y%m%dT%H%M%S").to_string();
         let primary_file = self.download_to_file(&primary_url, &config.name)?;
+        if let Err(e) = repodata_info.verify_primary(&primary_file) {
+            // Kept like a failed index: a mismatching download is worth inspecting
+            self.release_metadata_file(&primary_file, config, &stamp);
+            return Err(e);
+        }
+        let mut download_time = download_started.elapsed();
 
         info!(repo = %config.name, file = %primary_file.display(), "Performing incremental update");
-        let packages_synced = self
-            .api
-            .index_repository(&primary_file, &config.name, true)?;
-
-        if let Err(e) = fs::remove_file(&primary_file) {
-            warn!(file = %primary_file.display(), error = %e, "Failed to clean up downloaded file");
-        }
+        let indexed = self.api.update_repository(&primary_file, &config.name);
+        // Kept even if indexing failed: the exact input is what's needed to debug it
+        self.release_metadata_file(&primary_file, config, &stamp);
+        let stats = indexed?;
+        let mut insert_time = stats.insert_time;
 
         if config.sync_filelists {
             if let Some(ref fl_location) = repodata_info.filelists_location {
                 let fl_url = format!(
                     "{}/{}",
-                    config.base_url.trim_end_matches('/'),
+                    base_url.trim_end_matches('/'),
                     fl_location.trim_start_matches('/')
                 );
-                debug!(url = %fl_url, "Downloading filelists.xml");
-
-                match self.download_to_file(&fl_url, &config.name) {
+                debug!(location = %fl_location, "Downloading filelists.xml");
+
+                let fl_started = Instant::now();
+                let downloaded = self
+                    .download_to_file(&fl_url, &config.name)
+                    .and_then(|fl_file| match repodata_info.verify_filelists(&fl_file) {
+                        Ok(()) => Ok(fl_file),
+                        Err(e) => {
+                            self.release_metadata_file(&fl_file, config, &stamp);
+                            Err(e)
+                        }
+                    });
+                download_time += fl_started.elapsed();
+                match downloaded {
                     Ok(fl_file) => {
-                        match self.api.index_filelists(&fl_file, &config.name) {
+                        let index_started = Instant::now();
+                        let indexed = self.api.index_filelists(&fl_file, &config.name);
+                        insert_time += index_started.elapsed();
+                        match indexed {
                             Ok(count) => {
                                 info!(files_indexed = count, "Filelists indexed successfully");
                             }
@@ -143,9 +186,7 @@ impl RepoSyncer {
                                 warn!(error = %e, "Failed to index filelists (non-fatal)");
                             }
                         }
-                        if let Err(e) = fs::remove_file(&fl_file) {
-                            warn!(file = %fl_file.display(), error = %e, "Failed to clean up filelists file");
-                        }
+                        self.release_metadata_file(&fl_file, config, &stamp);
                     }
                     Err(e) => {
                         warn!(error = %e, "Failed to download filelists.xml (non-fatal)");
@@ -157,140 +198,566 @@ impl RepoSyncer {
         Ok(SyncResult {
             changed: true,
             checksum: repodata_info.primary_checksum,
-            packages_synced,
+            packages_synced: stats.added + stats.updated,
+            added: stats.added,
+            updated: stats.updated,
+            removed: stats.removed,
+            download_time,
+            parse_time: stats.parse_time,
+            insert_time,
         })
     }
 
+    /// Report what a sync would change without writing to the database or sync state
+    pub fn preview_repository(&mut self, config: &RepoSyncConfig) -> Result<SyncPreview> {
+        info!(repo = %config.name, url = %config.base_url, "Previewing repository sync (dry run)");
+
+        let base_url = config.request_base_url()?;
+        let last_checksum = self
+            .state_store
+            .get_state(&config.name)?
+            .and_then(|state| state.last_checksum);
+
+        let repomd_url = format!("{}/repodata/repomd.xml", base_url.trim_end_matches('/'));
+        debug!(repo = %config.name, "Downloading repomd.xml");
+
+        let repomd_content = self.download_file(&repomd_url)?;
+        let repodata_info = parse_repomd(&repomd_content)?;
+
+        let changed = match &last_checksum {
+            Some(last) => last != &repodata_info.primary_checksum,
+            None => true,
+        };
+
+        if !changed {
+            return Ok(SyncPreview::default());
+        }
+
+        let primary_url = format!(
+            "{}/{}",
+            base_url.trim_end_matches('/'),
+            repodata_info.primary_location.trim_start_matches('/')
+        );
+        debug!(location = %repodata_info.primary_location, "Downloading primary.xml");
+
+        let primary_file = self.download_to_file(&primary_url, &config.name)?;
+        let preview = repodata_info.verify_primary(&primary_file).and_then(|_| {
+            self.api
+                .preview_repository_update(&primary_file, &config.name)
+        });
+
+        if let Err(e) = fs::remove_file(&primary_file) {
+            warn!(file = %primary_file.display(), error = %e, "Failed to clean up downloaded file");
+        }
+
+        let (added, updated, removed) = preview?;
+        Ok(SyncPreview {
+            changed: true,
+            added,
+            updated,
+            removed,
+        })
+    }
+
+    /// Delete a downloaded metadata file after indexing or a failed checksum
+    /// check, or with `keep_metadata` move it to `kept/<repo>/<stamp>_<file>`
+    /// and drop files from older syncs
+    fn release_metadata_file(&self, file: &Path, config: &RepoSyncConfig, stamp: &str) {
+        if !config.keep_metadata {
+            if let Err(e) = fs::remove_file(file) {
+                warn!(file = %file.display(), error = %e, "Failed to clean up downloaded file");
+            }
+            return;
+        }
+
+        let kept_dir = self.work_dir.join(KEPT_METADATA_DIR).join(&config.name);
+        let filename = file
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        let filename = filename
+            .strip_prefix(&format!("{}_", config.name))
+            .unwrap_or(filename);
+        let dest = kept_dir.join(format!("{}_{}", stamp, filename));
+
+        match fs::create_dir_all(&kept_dir).and_then(|_| fs::rename(file, &dest)) {
+            Ok(()) => {
+                info!(repo = %config.name, file = %dest.display(), "Kept downloaded metadata");
+                rotate_kept_metadata(&kept_dir, KEPT_METADATA_SYNCS);
+            }
+            Err(e) => {
+                warn!(file = %file.display(), error = %e, "Failed to keep downloaded metadata");
+                let _ = fs::remove_file(file);
+            }
+        }
+    }
+
     fn download_file(&self, url: &str) -> Result<String> {
-        let body = self
-            .http
-            .get(url)
-            .send()
-            .map_err(|e| RpmSearchError::Fetch(format!("HTTP request failed: {}", e)))?
-            .error_for_status()
-            .map_err(|e| RpmSearchError::Fetch(format!("HTTP status error: {}", e)))?
-            .text()
-            .map_err(|e| RpmSearchError::Fetch(format!("Failed to read response: {}", e)))?;
-
-        Ok(body)
+        download_text(&self.http, url)
     }
 
     fn download_to_file(&self, url: &str, repo_name: &str) -> Result<PathBuf> {
-        let filename = url
-            .split('/')
-            .next_back()
-            .ok_or_else(|| RpmSearchError::Fetch("Invalid URL".to_string()))?;
-
-        let dest_path = self.work_dir.join(format!("{}_{}", repo_name, filename));
-
-        let mut response = self
-            .http
-            .get(url)
-            .send()
-            .map_err(|e| RpmSearchError::Fetch(format!("HTTP request failed: {}", e)))?
-            .error_for_status()
-            .map_err(|e| RpmSearchError::Fetch(format!("HTTP status error: {}", e)))?;
-
-        let mut file = fs::File::create(&dest_path).map_err(RpmSearchError::Io)?;
-        std::io::copy(&mut response, &mut file)
-            .map_err(|e| RpmSearchError::Fetch(format!("Failed to write downloaded file: {}", e)))?;
-
-        Ok(dest_path)
+        download_to_dir(&self.http, url, &self.work_dir, repo_name, self.progress)
     }
+}
 
-    fn parse_repomd(&self, xml: &str) -> Result<RepoDataInfo> {
-        use quick_xml::events::Event;
-        use quick_xml::Reader;
+/// Index a remote repository in one step: fetch `<base_url>/repodata/repomd.xml`,
+/// download the primary.xml it points to (and filelists.xml with `filelists`)
+/// into a private temporary directory, verify them against repomd.xml and
+/// index them as `repo`. Unlike [`RepoSyncer`], no sync state is recorded.
+/// Returns the number of packages and file entries indexed.
+pub fn index_from_url(
+    api: &mut RpmSearchApi,
+    base_url: &str,
+    repo: &str,
+    filelists: bool,
+    update: bool,
+) -> Result<(usize, Option<usize>)> {
+    let http = reqwest::blocking::Client::builder()
+        .build()
+        .map_err(|e| RpmSearchError::Fetch(format!("Failed to build HTTP client: {}", e)))?;
+    let base_url = base_url.trim_end_matches('/');
+    // Owner-only and removed on return, so other users can't swap the files
+    let work_dir = tempfile::tempdir().map_err(RpmSearchError::Io)?;
+    let work_dir = work_dir.path();
+
+    let repomd_url = format!("{}/repodata/repomd.xml", base_url);
+    debug!(url = %repomd_url, "Downloading repomd.xml");
+    let repodata_info = parse_repomd(&download_text(&http, &repomd_url)?)?;
+
+    let primary_url = format!(
+        "{}/{}",
+        base_url,
+        repodata_info.primary_location.trim_start_matches('/')
+    );
+    debug!(url = %primary_url, "Downloading primary.xml");
+    let primary_file = download_to_dir(&http, &primary_url, work_dir, repo, true)?;
+    let indexed = repodata_info
+        .verify_primary(&primary_file)
+        .and_then(|_| api.index_repository(&primary_file, repo, update));
+    let _ = fs::remove_file(&primary_file);
+    let count = indexed?;
+
+    let files = match (filelists, &repodata_info.filelists_location) {
+        (false, _) => None,
+        (true, None) => {
+            warn!(repo = %repo, "repomd.xml lists no filelists.xml; skipping file lists");
+            None
+        }
+        (true, Some(location)) => {
+            let fl_url = format!("{}/{}", base_url, location.trim_start_matches('/'));
+            debug!(url = %fl_url, "Downloading filelists.xml");
+            let fl_file = download_to_dir(&http, &fl_url, work_dir, repo, true)?;
+            let indexed = repodata_info
+                .verify_filelists(&fl_file)
+                .and_then(|_| api.index_filelists(&fl_file, repo));
+            let _ = fs::remove_file(&fl_file);
+            Some(indexed?)
+        }
+    };
 
-        let mut reader = Reader::from_str(xml);
+    Ok((count, files))
+}
 
-        #[derive(PartialEq)]
-        enum Section {
-            None,
-            Primary,
-            Filelists,
-        }
+/// Find the primary (and filelists) metadata locations in a repomd.xml document
+pub fn parse_repomd(xml: &str) -> Result<RepoDataInfo> {
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+
+    let mut reader = Reader::from_str(xml);
+
+    #[derive(PartialEq)]
+    enum Section {
+        None,
+        Primary,
+        Filelists,
+    }
 
-        let mut section = Section::None;
-        let mut primary_location = None;
-        let mut primary_checksum = None;
-        let mut filelists_location = None;
-
-        let mut buf = Vec::new();
-        loop {
-            match reader.read_event_into(&mut buf) {
-                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => match e.name().as_ref() {
-                    b"data" => {
-                        for attr in e.attributes().flatten() {
-                            if attr.key.as_ref() == b"type" {
-                                match &attr.value[..] {
-                                    b"primary" => section = Section::Primary,
-                                    b"filelists" => section = Section::Filelists,
-                                    _ => {}
-                                }
+    let mut section = Section::None;
+    let mut primary_location = None;
+    let mut primary_checksum = None;
+    let mut primary_checksum_type = None;
+    let mut filelists_location = None;
+    let mut filelists_checksum = None;
+
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => match e.name().as_ref() {
+                b"data" => {
+                    for attr in e.attributes().flatten() {
+                        if attr.key.as_ref() == b"type" {
+                            match &attr.value[..] {
+                                b"primary" => section = Section::Primary,
+                                b"filelists" => section = Section::Filelists,
+                                _ => {}
                             }
                         }
                     }
-                    b"location" if section != Section::None => {
-                        for attr in e.attributes().flatten() {
-                            if attr.key.as_ref() == b"href" {
-                                let href = String::from_utf8_lossy(&attr.value).to_string();
-                                match section {
-                                    Section::Primary => primary_location = Some(href),
-                                    Section::Filelists => filelists_location = Some(href),
-                                    Section::None => {}
-                                }
+                }
+                b"location" if section != Section::None => {
+                    for attr in e.attributes().flatten() {
+                        if attr.key.as_ref() == b"href" {
+                            let href = String::from_utf8_lossy(&attr.value).to_string();
+                            match section {
+                                Section::Primary => primary_location = Some(href),
+                                Section::Filelists => filelists_location = Some(href),
+                                Section::None => {}
                             }
                         }
                     }
-                    b"checksum" if section == Section::Primary => {
-                        if let Ok(Event::Text(e)) = reader.read_event_into(&mut buf) {
-                            primary_checksum = Some(
-                                reader
-                                    .decoder()
-                                    .decode(e.as_ref())
-                                    .unwrap_or_default()
-                                    .to_string(),
-                            );
+                }
+                b"checksum" if section != Section::None => {
+                    let mut checksum_type = "sha256".to_string();
+                    for attr in e.attributes().flatten() {
+                        if attr.key.as_ref() == b"type" {
+                            checksum_type = String::from_utf8_lossy(&attr.value).to_string();
                         }
                     }
-                    _ => {}
-                },
-                Ok(Event::End(ref e)) => {
-                    if e.name().as_ref() == b"data" {
-                        section = Section::None;
+                    let mut value = None;
+                    if let Ok(Event::Text(e)) = reader.read_event_into(&mut buf) {
+                        value = Some(
+                            reader
+                                .decoder()
+                                .decode(e.as_ref())
+                                .unwrap_or_default()
+                                .to_string(),
+                        );
+                    }
+                    match section {
+                        Section::Primary => {
+                            primary_checksum_type = Some(checksum_type);
+                            primary_checksum = value;
+                        }
+                        Section::Filelists => {
+                            filelists_checksum = value.map(|value| Checksum {
+                                checksum_type,
+                                value,
+                            });
+                        }
+                        Section::None => {}
                     }
                 }
-                Ok(Event::Eof) => break,
-                Err(e) => return Err(RpmSearchError::Parse(format!("XML parse error: {}", e))),
                 _ => {}
+            },
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"data" => {
+                section = Section::None;
             }
-            buf.clear();
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(RpmSearchError::Parse(format!("XML parse error: {}", e))),
+            _ => {}
         }
+        buf.clear();
+    }
+
+    match (primary_location, primary_checksum) {
+        (Some(loc), Some(sum)) => Ok(RepoDataInfo {
+            primary_location: loc,
+            primary_checksum: sum,
+            primary_checksum_type: primary_checksum_type.unwrap_or_else(|| "sha256".to_string()),
+            filelists_location,
+            filelists_checksum,
+        }),
+        _ => Err(RpmSearchError::Parse(
+            "Could not find primary.xml location or checksum in repomd.xml".to_string(),
+        )),
+    }
+}
+
+/// Download `url` as text
+fn download_text(http: &reqwest::blocking::Client, url: &str) -> Result<String> {
+    let body = http
+        .get(url)
+        .send()
+        .map_err(|e| RpmSearchError::Fetch(format!("HTTP request failed: {}", e)))?
+        .error_for_status()
+        .map_err(|e| RpmSearchError::Fetch(format!("HTTP status error: {}", e)))?
+        .text()
+        .map_err(|e| RpmSearchError::Fetch(format!("Failed to read response: {}", e)))?;
+
+    Ok(body)
+}
+
+/// Download `url` into `dir` as `<repo_name>_<filename>`, with a progress bar
+/// on stderr when `progress` is set
+fn download_to_dir(
+    http: &reqwest::blocking::Client,
+    url: &str,
+    dir: &Path,
+    repo_name: &str,
+    progress: bool,
+) -> Result<PathBuf> {
+    let filename = url
+        .split('/')
+        .next_back()
+        .ok_or_else(|| RpmSearchError::Fetch("Invalid URL".to_string()))?;
+
+    let dest_path = dir.join(format!("{}_{}", repo_name, filename));
+
+    let mut response = http
+        .get(url)
+        .send()
+        .map_err(|e| RpmSearchError::Fetch(format!("HTTP request failed: {}", e)))?
+        .error_for_status()
+        .map_err(|e| RpmSearchError::Fetch(format!("HTTP status error: {}", e)))?;
+
+    let mut file = fs::File::create(&dest_path).map_err(RpmSearchError::Io)?;
+    let bar = response
+        .content_length()
+        .filter(|_| progress && std::io::stderr().is_terminal())
+        .map(|total| download_bar(total, filename));
+    let copied = match bar {
+        Some(ref bar) => std::io::copy(&mut bar.wrap_read(response), &mut file),
+        None => std::io::copy(&mut response, &mut file),
+    };
+    if let Some(bar) = bar {
+        bar.finish_and_clear();
+    }
+    copied.map_err(|e| RpmSearchError::Fetch(format!("Failed to write downloaded file: {}", e)))?;
+
+    Ok(dest_path)
+}
 
-        match (primary_location, primary_checksum) {
-            (Some(loc), Some(sum)) => Ok(RepoDataInfo {
-                primary_location: loc,
-                primary_checksum: sum,
-                filelists_location,
-            }),
-            _ => Err(RpmSearchError::Parse(
-                "Could not find primary.xml location or checksum in repomd.xml".to_string(),
-            )),
+/// Delete kept metadata files from all but the newest `keep` syncs in `dir`.
+/// Files are named `<stamp>_<file>`, so the stamp identifies the sync.
+fn rotate_kept_metadata(dir: &Path, keep: usize) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let files: Vec<(String, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let (stamp, _) = name.split_once('_')?;
+            Some((stamp.to_string(), entry.path()))
+        })
+        .collect();
+
+    let mut stamps: Vec<&str> = files.iter().map(|(stamp, _)| stamp.as_str()).collect();
+    stamps.sort_unstable();
+    stamps.dedup();
+    if stamps.len() <= keep {
+        return;
+    }
+    let oldest_kept = stamps[stamps.len() - keep].to_string();
+
+    for (stamp, path) in &files {
+        if *stamp < oldest_kept {
+            debug!(file = %path.display(), "Removing old kept metadata");
+            if let Err(e) = fs::remove_file(path) {
+                warn!(file = %path.display(), error = %e, "Failed to remove old kept metadata");
+            }
+        }
+    }
+}
+
+/// Byte-count progress bar for a download of `total` bytes (draws to stderr)
+fn download_bar(total: u64, filename: &str) -> ProgressBar {
+    let bar = ProgressBar::new(total);
+    bar.set_style(
+        ProgressStyle::with_template(
+            "{msg} [{bar:30}] {bytes}/{total_bytes} ({percent}%) {bytes_per_sec}",
+        )
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .progress_chars("=> "),
+    );
+    bar.set_message(filename.to_string());
+    bar
+}
+
+/// Verify a downloaded file against the checksum recorded in repomd.xml
+fn verify_checksum(path: &Path, checksum_type: &str, expected: &str) -> Result<()> {
+    use sha2::Digest;
+
+    fn digest_file<D: Digest + std::io::Write>(path: &Path) -> Result<String> {
+        let mut hasher = D::new();
+        let mut file = fs::File::open(path).map_err(RpmSearchError::Io)?;
+        std::io::copy(&mut file, &mut hasher).map_err(RpmSearchError::Io)?;
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect())
+    }
+
+    let actual = match checksum_type {
+        "sha" | "sha1" => digest_file::<sha1::Sha1>(path)?,
+        "sha224" => digest_file::<sha2::Sha224>(path)?,
+        "sha256" => digest_file::<sha2::Sha256>(path)?,
+        "sha384" => digest_file::<sha2::Sha384>(path)?,
+        "sha512" => digest_file::<sha2::Sha512>(path)?,
+        other => {
+            warn!(checksum_type = %other, "Unsupported checksum type, skipping verification");
+            return Ok(());
         }
+    };
+
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        return Err(RpmSearchError::Fetch(format!(
+            "Checksum mismatch for {}: expected {} {}, got {}",
+            path.display(),
+            checksum_type,
+            expected.trim(),
+            actual
+        )));
     }
+
+    debug!(file = %path.display(), checksum_type = %checksum_type, "Checksum verified");
+    Ok(())
 }
 
+/// Metadata locations read from repomd.xml (see [`parse_repomd`])
 #[derive(Debug)]
-struct RepoDataInfo {
-    primary_location: String,
-    primary_checksum: String,
-    filelists_location: Option<String>,
+pub struct RepoDataInfo {
+    pub primary_location: String,
+    pub primary_checksum: String,
+    /// Checksum algorithm from the `type` attribute (e.g. `sha256`, `sha1`)
+    pub primary_checksum_type: String,
+    pub filelists_location: Option<String>,
+    /// Checksum of the filelists file, when repomd.xml lists one
+    pub filelists_checksum: Option<Checksum>,
 }
 
+/// A `<checksum type="...">value</checksum>` entry from repomd.xml
 #[derive(Debug)]
+pub struct Checksum {
+    pub checksum_type: String,
+    pub value: String,
+}
+
+impl RepoDataInfo {
+    /// Check a downloaded primary file against its repomd.xml checksum
+    pub fn verify_primary(&self, path: &Path) -> Result<()> {
+        verify_checksum(path, &self.primary_checksum_type, &self.primary_checksum)
+    }
+
+    /// Check a downloaded filelists file against its repomd.xml checksum
+    /// (accepted unchecked when repomd.xml has none)
+    pub fn verify_filelists(&self, path: &Path) -> Result<()> {
+        match &self.filelists_checksum {
+            Some(checksum) => verify_checksum(path, &checksum.checksum_type, &checksum.value),
+            None => {
+                warn!(file = %path.display(), "repomd.xml has no filelists checksum, skipping verification");
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct SyncResult {
     pub changed: bool,
     pub checksum: String,
     pub packages_synced: usize,
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    /// repomd.xml, primary.xml and (with `sync_filelists`) filelists.xml downloads
+    pub download_time: Duration,
+    /// Decompressing and parsing primary.xml
+    pub parse_time: Duration,
+    /// Storing package changes (and filelists, when synced)
+    pub insert_time: Duration,
+}
+
+/// Changes a sync would apply to a repository (see `RepoSyncer::preview_repository`)
+#[derive(Debug, Default)]
+pub struct SyncPreview {
+    pub changed: bool,
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPOMD: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<repomd xmlns="http://linux.duke.edu/metadata/repo">
+  <data type="primary">
+    <checksum type="sha1">aaaa</checksum>
+    <open-checksum type="sha256">bbbb</open-checksum>
+    <location href="repodata/primary.xml.gz"/>
+  </data>
+  <data type="filelists">
+    <checksum type="sha256">cccc</checksum>
+    <location href="repodata/filelists.xml.gz"/>
+  </data>
+</repomd>"#;
+
+    #[test]
+    fn test_parse_repomd_checksum_type() {
+        let info = parse_repomd(REPOMD).unwrap();
+        assert_eq!(info.primary_location, "repodata/primary.xml.gz");
+        assert_eq!(info.primary_checksum, "aaaa");
+        assert_eq!(info.primary_checksum_type, "sha1");
+        assert_eq!(
+            info.filelists_location.as_deref(),
+            Some("repodata/filelists.xml.gz")
+        );
+    }
+
+    #[test]
+    fn test_rotate_kept_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        for stamp in ["20260101T000000", "20260102T000000", "20260103T000000"] {
+            for file in ["primary.xml.gz", "filelists.xml.gz"] {
+                fs::write(dir.path().join(format!("{}_{}", stamp, file)), "").unwrap();
+            }
+        }
+
+        rotate_kept_metadata(dir.path(), 2);
+
+        let mut left: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(
+            left,
+            [
+                "20260102T000000_filelists.xml.gz",
+                "20260102T000000_primary.xml.gz",
+                "20260103T000000_filelists.xml.gz",
+                "20260103T000000_primary.xml.gz",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("primary.xml");
+        fs::write(&path, b"abc").unwrap();
+
+        let sha256 = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert!(verify_checksum(&path, "sha256", sha256).is_ok());
+
+        let sha1 = "a9993e364706816aba3e25717850c26c9cd0d89d";
+        assert!(verify_checksum(&path, "sha", sha1).is_ok());
+
+        // Truncated download
+        fs::write(&path, b"ab").unwrap();
+        assert!(verify_checksum(&path, "sha", sha1).is_err());
+    }
+
+    #[test]
+    fn test_verify_filelists_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("filelists.xml");
+        fs::write(&path, b"abc").unwrap();
+
+        let mut info = parse_repomd(REPOMD).unwrap();
+        let checksum = info.filelists_checksum.as_ref().unwrap();
+        assert_eq!(checksum.checksum_type, "sha256");
+        assert_eq!(checksum.value, "cccc");
+        assert!(info.verify_filelists(&path).is_err());
+
+        info.filelists_checksum = Some(Checksum {
+            checksum_type: "sha256".to_string(),
+            value: "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".to_string(),
+        });
+        assert!(info.verify_filelists(&path).is_ok());
+    }
 }